@@ -1,3 +1,21 @@
 fn main() {
+    // Embed the git commit and build profile for `get_app_version`.
+    // The commit is omitted when building outside a git checkout.
+    if let Ok(output) = std::process::Command::new("git")
+        .args(["rev-parse", "--short", "HEAD"])
+        .output()
+    {
+        if output.status.success() {
+            let commit = String::from_utf8_lossy(&output.stdout).trim().to_string();
+            println!("cargo:rustc-env=CODEX_DESKTOP_GIT_COMMIT={commit}");
+        }
+    }
+    println!("cargo:rerun-if-changed=../.git/HEAD");
+    println!("cargo:rerun-if-changed=../.git/refs/heads");
+
+    if let Ok(profile) = std::env::var("PROFILE") {
+        println!("cargo:rustc-env=CODEX_DESKTOP_BUILD_PROFILE={profile}");
+    }
+
     tauri_build::build()
 }
@@ -0,0 +1,194 @@
+//! Cache of the signed-in account
+//!
+//! Reading the account is a round trip to the app-server, yet it rarely
+//! changes. The last `account/read` result is kept with the time it was
+//! fetched, and dropped whenever the account may have changed: on login,
+//! logout, and when the server reports a login or account update. Each drop
+//! starts a new generation, and a read that began in an older generation is
+//! not cached, so a refresh racing a logout can't bring the old account back.
+
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use serde::Serialize;
+
+use super::ipc_bridge::AccountInfo;
+
+/// How long cached account info is considered current
+pub const ACCOUNT_INFO_TTL: Duration = Duration::from_secs(5 * 60);
+
+/// Notifications meaning the signed-in account may have changed
+const ACCOUNT_CHANGE_METHODS: &[&str] = &["account/login/completed", "account/updated"];
+
+/// Account info together with when it was read from the app-server
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CachedAccountInfo {
+    #[serde(flatten)]
+    pub info: AccountInfo,
+    /// Unix timestamp of the `account/read` that produced the info
+    pub fetched_at: i64,
+    /// Whether the info is older than [`ACCOUNT_INFO_TTL`]
+    pub is_stale: bool,
+}
+
+struct CacheEntry {
+    info: AccountInfo,
+    fetched: Instant,
+    fetched_at: i64,
+}
+
+#[derive(Default)]
+struct CacheState {
+    entry: Option<CacheEntry>,
+    /// Bumped by every invalidation
+    generation: u64,
+}
+
+/// Shared cache of the last account info read from the app-server
+#[derive(Clone, Default)]
+pub struct AccountCache {
+    inner: Arc<parking_lot::Mutex<CacheState>>,
+}
+
+impl AccountCache {
+    /// Current generation; take it before reading the account, to pass to [`Self::store`]
+    pub fn generation(&self) -> u64 {
+        self.inner.lock().generation
+    }
+
+    /// Remember account info read during `generation`, returning it as cached
+    ///
+    /// If the cache was invalidated since, the info may predate a login or
+    /// logout and is returned without being kept.
+    pub fn store(&self, info: &AccountInfo, generation: u64) -> CachedAccountInfo {
+        let fetched_at = chrono::Utc::now().timestamp();
+        let mut state = self.inner.lock();
+        if state.generation == generation {
+            state.entry = Some(CacheEntry {
+                info: info.clone(),
+                fetched: Instant::now(),
+                fetched_at,
+            });
+        } else {
+            tracing::debug!("Not caching account info read before an invalidation");
+        }
+        CachedAccountInfo {
+            info: info.clone(),
+            fetched_at,
+            is_stale: false,
+        }
+    }
+
+    /// The cached account info, if any, flagged stale once past the TTL
+    pub fn get(&self) -> Option<CachedAccountInfo> {
+        self.get_at(Instant::now())
+    }
+
+    fn get_at(&self, now: Instant) -> Option<CachedAccountInfo> {
+        self.inner
+            .lock()
+            .entry
+            .as_ref()
+            .map(|entry| CachedAccountInfo {
+                info: entry.info.clone(),
+                fetched_at: entry.fetched_at,
+                is_stale: now.saturating_duration_since(entry.fetched) > ACCOUNT_INFO_TTL,
+            })
+    }
+
+    /// Forget the cached account info, and any read still in flight
+    pub fn invalidate(&self) {
+        let mut state = self.inner.lock();
+        state.entry = None;
+        state.generation += 1;
+    }
+
+    /// Invalidate the cache if a server notification means the account may have changed
+    pub fn observe_notification(&self, method: &str) {
+        if ACCOUNT_CHANGE_METHODS.contains(&method) {
+            self.invalidate();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::app_server::ipc_bridge::AccountDetails;
+
+    fn account(email: &str) -> AccountInfo {
+        AccountInfo {
+            account: Some(AccountDetails {
+                account_type: "chatgpt".to_string(),
+                email: Some(email.to_string()),
+                plan_type: None,
+            }),
+            requires_openai_auth: true,
+        }
+    }
+
+    fn email(cached: &CachedAccountInfo) -> Option<&str> {
+        cached.info.account.as_ref()?.email.as_deref()
+    }
+
+    #[test]
+    fn test_cached_info_goes_stale_after_ttl() {
+        let cache = AccountCache::default();
+        assert!(cache.get().is_none());
+
+        let stored = cache.store(&account("a@example.com"), cache.generation());
+        assert!(!stored.is_stale);
+
+        let hit = cache.get().unwrap();
+        assert_eq!(email(&hit), Some("a@example.com"));
+        assert!(!hit.is_stale);
+        assert_eq!(hit.fetched_at, stored.fetched_at);
+
+        let later = Instant::now() + ACCOUNT_INFO_TTL + Duration::from_secs(1);
+        let stale = cache.get_at(later).unwrap();
+        assert!(stale.is_stale);
+        assert_eq!(email(&stale), Some("a@example.com"));
+
+        // Storing again refreshes it
+        cache.store(&account("b@example.com"), cache.generation());
+        let hit = cache.get().unwrap();
+        assert_eq!(email(&hit), Some("b@example.com"));
+        assert!(!hit.is_stale);
+    }
+
+    #[test]
+    fn test_read_racing_an_invalidation_is_not_cached() {
+        let cache = AccountCache::default();
+        cache.store(&account("a@example.com"), cache.generation());
+
+        // A refresh starts, then a logout lands before it finishes
+        let generation = cache.generation();
+        cache.invalidate();
+        let returned = cache.store(&account("a@example.com"), generation);
+        assert_eq!(email(&returned), Some("a@example.com"));
+        assert!(cache.get().is_none());
+
+        // Reads started after the invalidation are cached again
+        cache.store(&account("b@example.com"), cache.generation());
+        assert_eq!(email(&cache.get().unwrap()), Some("b@example.com"));
+    }
+
+    #[test]
+    fn test_logout_and_account_notifications_invalidate() {
+        let cache = AccountCache::default();
+        cache.store(&account("a@example.com"), cache.generation());
+        cache.invalidate();
+        assert!(cache.get().is_none());
+
+        cache.store(&account("a@example.com"), cache.generation());
+        cache.observe_notification("account/rateLimits/updated");
+        assert!(cache.get().is_some());
+        cache.observe_notification("account/login/completed");
+        assert!(cache.get().is_none());
+
+        cache.store(&account("a@example.com"), cache.generation());
+        cache.observe_notification("account/updated");
+        assert!(cache.get().is_none());
+    }
+}
@@ -0,0 +1,161 @@
+//! Tracking of approval requests awaiting a user decision
+//!
+//! The app-server asks for approvals with server-initiated JSON-RPC requests.
+//! They are recorded here when they arrive and removed once we respond, so
+//! the UI can tell which threads are blocked on the user.
+
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use serde::Serialize;
+use serde_json::Value as JsonValue;
+
+/// An approval request the user hasn't answered yet
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PendingApproval {
+    /// JSON-RPC id of the server's request
+    pub request_id: u64,
+    /// Thread the approval belongs to, if the request named one
+    pub thread_id: Option<String>,
+    /// Request method, e.g. `item/commandExecution/requestApproval`
+    pub method: String,
+    /// Command awaiting approval, for command execution requests
+    pub command: Option<String>,
+    /// Unix timestamp when the request arrived
+    pub received_at: i64,
+}
+
+/// Whether a server-initiated request method asks the user for an approval
+///
+/// Covers both `item/*/requestApproval` and the older `execCommandApproval`
+/// style of method names.
+pub fn is_approval_method(method: &str) -> bool {
+    method.ends_with("Approval")
+}
+
+/// The command line an approval request is about, if any
+///
+/// Taken from `command` (a string or argv array), falling back to the
+/// proposed execpolicy amendment's command.
+fn approval_command(params: &JsonValue) -> Option<String> {
+    let command = params.get("command").or_else(|| {
+        params
+            .get("proposedExecpolicyAmendment")
+            .and_then(|a| a.get("command"))
+    })?;
+    match command {
+        JsonValue::String(command) => Some(command.clone()),
+        JsonValue::Array(argv) => {
+            let parts: Vec<&str> = argv.iter().filter_map(JsonValue::as_str).collect();
+            (!parts.is_empty()).then(|| parts.join(" "))
+        }
+        _ => None,
+    }
+}
+
+/// Shared set of unanswered approval requests for one app-server process
+#[derive(Debug, Clone, Default)]
+pub struct PendingApprovals {
+    inner: Arc<parking_lot::Mutex<HashMap<u64, PendingApproval>>>,
+}
+
+impl PendingApprovals {
+    /// Record a server request if it is an approval request
+    pub fn record(&self, request_id: u64, method: &str, params: &JsonValue) {
+        if !is_approval_method(method) {
+            return;
+        }
+        let thread_id = params
+            .get("threadId")
+            .or_else(|| params.get("conversationId"))
+            .and_then(JsonValue::as_str)
+            .map(str::to_string);
+        self.inner.lock().insert(
+            request_id,
+            PendingApproval {
+                request_id,
+                thread_id,
+                method: method.to_string(),
+                command: approval_command(params),
+                received_at: chrono::Utc::now().timestamp(),
+            },
+        );
+    }
+
+    /// Look up an unanswered request
+    pub fn get(&self, request_id: u64) -> Option<PendingApproval> {
+        self.inner.lock().get(&request_id).cloned()
+    }
+
+    /// Forget a request once it has been answered
+    pub fn resolve(&self, request_id: u64) -> bool {
+        self.inner.lock().remove(&request_id).is_some()
+    }
+
+    /// Drop every pending approval, e.g. when the server goes away
+    pub fn clear(&self) {
+        self.inner.lock().clear();
+    }
+
+    /// Unanswered approvals, oldest first
+    pub fn list(&self) -> Vec<PendingApproval> {
+        let mut approvals: Vec<PendingApproval> = self.inner.lock().values().cloned().collect();
+        approvals.sort_by_key(|a| (a.received_at, a.request_id));
+        approvals
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pending_approvals_record_and_resolve() {
+        let approvals = PendingApprovals::default();
+        approvals.record(
+            7,
+            "item/commandExecution/requestApproval",
+            &serde_json::json!({"threadId": "thr_1", "itemId": "item_1"}),
+        );
+        approvals.record(
+            8,
+            "item/tool/requestUserInput",
+            &serde_json::json!({"threadId": "thr_2"}),
+        );
+        approvals.record(
+            9,
+            "execCommandApproval",
+            &serde_json::json!({"conversationId": "thr_3"}),
+        );
+
+        let pending = approvals.list();
+        assert_eq!(pending.len(), 2);
+        assert_eq!(pending[0].thread_id.as_deref(), Some("thr_1"));
+        assert_eq!(pending[1].thread_id.as_deref(), Some("thr_3"));
+
+        assert_eq!(approvals.get(9).unwrap().command, None);
+
+        assert!(approvals.resolve(7));
+        assert!(!approvals.resolve(7));
+        assert_eq!(approvals.list().len(), 1);
+    }
+
+    #[test]
+    fn test_approval_command_from_params() {
+        assert_eq!(
+            approval_command(&serde_json::json!({"command": ["cargo", "test"]})).as_deref(),
+            Some("cargo test")
+        );
+        assert_eq!(
+            approval_command(&serde_json::json!({"command": "ls -la"})).as_deref(),
+            Some("ls -la")
+        );
+        assert_eq!(
+            approval_command(&serde_json::json!({"proposedExecpolicyAmendment": {"command": ["npm", "run", "build"]}}))
+                .as_deref(),
+            Some("npm run build")
+        );
+        assert_eq!(approval_command(&serde_json::json!({"itemId": "x"})), None);
+    }
+}
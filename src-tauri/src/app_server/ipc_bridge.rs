@@ -44,7 +44,7 @@ pub struct ThreadStartParams {
 }
 
 /// Sandbox policy response (tagged union)
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 #[serde(tag = "type", rename_all = "camelCase")]
 pub enum SandboxPolicy {
     ReadOnly,
@@ -69,6 +69,42 @@ fn default_network_access() -> String {
     "restricted".to_string()
 }
 
+/// How much a sandbox policy lets the agent do without asking
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RiskLevel {
+    Low,
+    Medium,
+    High,
+}
+
+impl SandboxPolicy {
+    /// Classify the policy's risk
+    ///
+    /// Read-only is low risk. Writing inside the workspace is medium, or high
+    /// with network access. Full access is always high.
+    pub fn risk_level(&self) -> RiskLevel {
+        match self {
+            SandboxPolicy::ReadOnly => RiskLevel::Low,
+            SandboxPolicy::WorkspaceWrite { network_access, .. } => {
+                if *network_access {
+                    RiskLevel::High
+                } else {
+                    RiskLevel::Medium
+                }
+            }
+            SandboxPolicy::DangerFullAccess => RiskLevel::High,
+            SandboxPolicy::ExternalSandbox { network_access } => {
+                if network_access == "enabled" {
+                    RiskLevel::High
+                } else {
+                    RiskLevel::Medium
+                }
+            }
+        }
+    }
+}
+
 /// Thread start response
 #[derive(Debug, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
@@ -83,6 +119,9 @@ pub struct ThreadStartResponse {
     pub reasoning_effort: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub reasoning_summary: Option<String>,
+    /// Warnings about the requested sandbox, added by the desktop (not sent by the server)
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub sandbox_warnings: Vec<String>,
 }
 
 /// Thread information
@@ -313,13 +352,39 @@ pub struct AccountDetails {
 }
 
 /// Account info response from app-server
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct AccountInfo {
     pub account: Option<AccountDetails>,
     pub requires_openai_auth: bool,
 }
 
+/// A stored auth profile on a multi-account app-server
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AccountProfile {
+    pub id: String,
+    #[serde(rename = "type")]
+    pub account_type: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub email: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub plan_type: Option<String>,
+    #[serde(default)]
+    pub is_active: bool,
+}
+
+/// Account list returned to the frontend
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AccountListResponse {
+    /// False when the app-server has no multi-account support
+    #[serde(default)]
+    pub supported: bool,
+    #[serde(default)]
+    pub accounts: Vec<AccountProfile>,
+}
+
 /// IPC Bridge provides high-level methods for communicating with app-server
 pub struct IpcBridge;
 
@@ -3,8 +3,18 @@
 //! This module handles spawning, monitoring, and communicating with the
 //! Codex app-server subprocess via JSON-RPC 2.0 over stdio.
 
+mod account_cache;
+mod approvals;
 pub mod ipc_bridge;
+mod patch_preview;
 mod process;
+mod recent_events;
+mod stderr_patterns;
 
+pub use account_cache::{AccountCache, CachedAccountInfo, ACCOUNT_INFO_TTL};
+pub use approvals::{PendingApproval, PendingApprovals};
 pub use ipc_bridge::IpcBridge;
-pub use process::AppServerProcess;
+pub use patch_preview::{preview_approval, ApprovalPreview, FileChangeKind, FileDiff};
+pub use process::{AppServerProcess, RequestCanceller};
+pub use recent_events::{RecentEvent, RecentEvents};
+pub use stderr_patterns::{classify_stderr_line, StderrIssue};
@@ -0,0 +1,278 @@
+//! Previews of what an approval request would change
+//!
+//! File-writing approvals carry their patch either as an `apply_patch`
+//! envelope inside the command, or as a map of per-file changes. Both are
+//! turned into a list of file diffs the UI can show before the user decides.
+
+use serde::Serialize;
+use serde_json::Value as JsonValue;
+
+/// How a file is affected by a patch
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum FileChangeKind {
+    Add,
+    Update,
+    Delete,
+}
+
+/// The change a patch makes to one file
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FileDiff {
+    pub path: String,
+    pub change: FileChangeKind,
+    /// New path, when an update also renames the file
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub move_to: Option<String>,
+    pub additions: usize,
+    pub deletions: usize,
+    /// Diff body: `+`, `-` and context lines, plus `@@` hunk headers
+    pub diff: String,
+}
+
+/// What an approval request would do
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "kind", rename_all = "camelCase")]
+pub enum ApprovalPreview {
+    /// The request writes files
+    Patch { files: Vec<FileDiff> },
+    /// The request runs a command whose effect can't be previewed
+    Command { command: String },
+    /// Nothing in the request could be previewed
+    Unknown,
+}
+
+const BEGIN_PATCH: &str = "*** Begin Patch";
+const END_PATCH: &str = "*** End Patch";
+
+/// Count `+`/`-` lines in a diff body, ignoring `+++`/`---` file headers
+fn count_changes(diff: &str) -> (usize, usize) {
+    diff.lines().fold((0, 0), |(added, removed), line| {
+        if line.starts_with('+') && !line.starts_with("+++") {
+            (added + 1, removed)
+        } else if line.starts_with('-') && !line.starts_with("---") {
+            (added, removed + 1)
+        } else {
+            (added, removed)
+        }
+    })
+}
+
+/// Finish the file being parsed and add it to `files`
+fn push_file(
+    files: &mut Vec<FileDiff>,
+    current: Option<(String, FileChangeKind, Option<String>, Vec<&str>)>,
+) {
+    if let Some((path, change, move_to, lines)) = current {
+        let diff = lines.join("\n");
+        let (additions, deletions) = count_changes(&diff);
+        files.push(FileDiff {
+            path,
+            change,
+            move_to,
+            additions,
+            deletions,
+            diff,
+        });
+    }
+}
+
+/// Parse an `apply_patch` envelope (`*** Begin Patch` ... `*** End Patch`)
+///
+/// Text before the envelope, such as a shell heredoc, is skipped. Returns
+/// None if there is no envelope.
+pub fn parse_apply_patch(text: &str) -> Option<Vec<FileDiff>> {
+    let start = text.find(BEGIN_PATCH)? + BEGIN_PATCH.len();
+    let body = &text[start..];
+    let body = body.find(END_PATCH).map_or(body, |end| &body[..end]);
+
+    let mut files = Vec::new();
+    let mut current: Option<(String, FileChangeKind, Option<String>, Vec<&str>)> = None;
+    for line in body.lines() {
+        let header = [
+            ("*** Add File: ", FileChangeKind::Add),
+            ("*** Update File: ", FileChangeKind::Update),
+            ("*** Delete File: ", FileChangeKind::Delete),
+        ]
+        .into_iter()
+        .find_map(|(prefix, kind)| line.strip_prefix(prefix).map(|path| (path.trim(), kind)));
+
+        if let Some((path, kind)) = header {
+            push_file(&mut files, current.take());
+            current = Some((path.to_string(), kind, None, Vec::new()));
+        } else if let Some(dest) = line.strip_prefix("*** Move to: ") {
+            if let Some(file) = current.as_mut() {
+                file.2 = Some(dest.trim().to_string());
+            }
+        } else if let Some(file) = current.as_mut() {
+            if line != "*** End of File" {
+                file.3.push(line);
+            }
+        }
+    }
+    push_file(&mut files, current);
+
+    Some(files)
+}
+
+/// Convert a map of per-file changes (`{path: {type, content | unified_diff}}`) to file diffs
+fn parse_file_changes(changes: &serde_json::Map<String, JsonValue>) -> Vec<FileDiff> {
+    let mut files: Vec<FileDiff> = changes
+        .iter()
+        .filter_map(|(path, change)| {
+            let text = |key: &str| {
+                change
+                    .get(key)
+                    .and_then(JsonValue::as_str)
+                    .unwrap_or_default()
+            };
+            let (kind, diff) = match change.get("type").and_then(JsonValue::as_str)? {
+                "add" => (
+                    FileChangeKind::Add,
+                    text("content")
+                        .lines()
+                        .map(|l| format!("+{l}"))
+                        .collect::<Vec<_>>()
+                        .join("\n"),
+                ),
+                "delete" => (
+                    FileChangeKind::Delete,
+                    text("content")
+                        .lines()
+                        .map(|l| format!("-{l}"))
+                        .collect::<Vec<_>>()
+                        .join("\n"),
+                ),
+                "update" => (FileChangeKind::Update, text("unified_diff").to_string()),
+                _ => return None,
+            };
+            let (additions, deletions) = count_changes(&diff);
+            Some(FileDiff {
+                path: path.clone(),
+                change: kind,
+                move_to: change
+                    .get("move_path")
+                    .and_then(JsonValue::as_str)
+                    .map(str::to_string),
+                additions,
+                deletions,
+                diff,
+            })
+        })
+        .collect();
+    files.sort_by(|a, b| a.path.cmp(&b.path));
+    files
+}
+
+/// Work out what an approval request would do from its params
+pub fn preview_approval(params: &JsonValue) -> ApprovalPreview {
+    for key in ["fileChanges", "changes"] {
+        if let Some(changes) = params.get(key).and_then(JsonValue::as_object) {
+            return ApprovalPreview::Patch {
+                files: parse_file_changes(changes),
+            };
+        }
+    }
+
+    let command = match params.get("command") {
+        Some(JsonValue::String(command)) => Some(command.clone()),
+        Some(JsonValue::Array(argv)) => Some(
+            argv.iter()
+                .filter_map(JsonValue::as_str)
+                .collect::<Vec<_>>()
+                .join(" "),
+        ),
+        _ => None,
+    };
+    match command {
+        Some(command) => match parse_apply_patch(&command) {
+            Some(files) => ApprovalPreview::Patch { files },
+            None => ApprovalPreview::Command { command },
+        },
+        None => ApprovalPreview::Unknown,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PATCH: &str = "*** Begin Patch
+*** Add File: src/new.rs
++pub fn hello() {}
+*** Update File: src/lib.rs
+*** Move to: src/main.rs
+@@ fn main() {
+-    old();
++    new();
++    hello();
+ }
+*** Delete File: README.old
+*** End Patch";
+
+    #[test]
+    fn test_preview_apply_patch_command() {
+        let params = serde_json::json!({
+            "threadId": "thr_1",
+            "command": ["apply_patch", PATCH],
+        });
+
+        let ApprovalPreview::Patch { files } = preview_approval(&params) else {
+            panic!("expected a patch preview");
+        };
+        assert_eq!(files.len(), 3);
+
+        assert_eq!(files[0].path, "src/new.rs");
+        assert_eq!(files[0].change, FileChangeKind::Add);
+        assert_eq!((files[0].additions, files[0].deletions), (1, 0));
+
+        assert_eq!(files[1].path, "src/lib.rs");
+        assert_eq!(files[1].change, FileChangeKind::Update);
+        assert_eq!(files[1].move_to.as_deref(), Some("src/main.rs"));
+        assert_eq!((files[1].additions, files[1].deletions), (2, 1));
+        assert!(files[1].diff.starts_with("@@ fn main() {"));
+
+        assert_eq!(files[2].change, FileChangeKind::Delete);
+    }
+
+    #[test]
+    fn test_preview_patch_in_heredoc() {
+        let command = format!("bash -lc apply_patch <<'EOF'\n{PATCH}\nEOF");
+        let params = serde_json::json!({ "command": command });
+        assert!(
+            matches!(preview_approval(&params), ApprovalPreview::Patch { files } if files.len() == 3)
+        );
+    }
+
+    #[test]
+    fn test_preview_file_changes_map() {
+        let params = serde_json::json!({
+            "fileChanges": {
+                "b.txt": {"type": "update", "unified_diff": "--- a/b.txt\n+++ b/b.txt\n@@ -1 +1 @@\n-x\n+y\n"},
+                "a.txt": {"type": "add", "content": "one\ntwo\n"}
+            }
+        });
+        let ApprovalPreview::Patch { files } = preview_approval(&params) else {
+            panic!("expected a patch preview");
+        };
+        assert_eq!(files[0].path, "a.txt");
+        assert_eq!(files[0].additions, 2);
+        assert_eq!((files[1].additions, files[1].deletions), (1, 1));
+    }
+
+    #[test]
+    fn test_preview_falls_back_to_command() {
+        let params = serde_json::json!({ "command": ["cargo", "test"] });
+        assert_eq!(
+            preview_approval(&params),
+            ApprovalPreview::Command {
+                command: "cargo test".to_string()
+            }
+        );
+        assert_eq!(
+            preview_approval(&serde_json::json!({})),
+            ApprovalPreview::Unknown
+        );
+    }
+}
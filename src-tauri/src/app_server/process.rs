@@ -3,9 +3,9 @@
 //! Spawns and manages the `codex app-server` subprocess, handling health checks,
 //! restarts, and graceful shutdown.
 
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet, VecDeque};
 use std::process::Stdio;
-use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
 use std::sync::Arc;
 use std::time::Instant;
 
@@ -25,13 +25,188 @@ const PENDING_CLEANUP_THRESHOLD: usize = 800;
 /// Maximum age for a pending request before it's considered stale (in seconds)
 const STALE_REQUEST_AGE_SECS: u64 = 60;
 
+/// Number of stderr lines kept for startup error reports
+const STDERR_TAIL_LINES: usize = 20;
+
+/// How long to wait for a failed app-server to exit and flush stderr
+const STARTUP_FAILURE_GRACE_MS: u64 = 500;
+
 /// Pending request entry with timestamp for cleanup
 struct PendingRequest {
     sender: oneshot::Sender<Result<JsonValue>>,
     created_at: Instant,
+    /// Thread the request belongs to (from its `threadId` param), if any
+    thread_id: Option<String>,
+}
+
+/// Cancels in-flight requests without holding the app-server lock
+///
+/// Holds the pending map of the process it was taken from, so it only ever
+/// affects that process's requests.
+#[derive(Clone)]
+pub struct RequestCanceller {
+    pending_requests: Arc<Mutex<HashMap<u64, PendingRequest>>>,
+}
+
+impl RequestCanceller {
+    /// Fail every pending request tagged with `thread_id`, returning how many were cancelled
+    pub async fn cancel_thread(&self, thread_id: &str) -> usize {
+        AppServerProcess::fail_thread_requests(
+            &mut *self.pending_requests.lock().await,
+            thread_id,
+            "cancelled by client",
+        )
+    }
+}
+
+use crate::app_server::{
+    classify_stderr_line, AccountCache, PendingApprovals, RecentEvents, StderrIssue,
+};
+use crate::{AppServerStartupErrorKind, AppServerStartupInfo, Error, Result};
+
+/// Append a stderr line to the tail buffer, dropping the oldest past the limit
+fn push_stderr_line(tail: &parking_lot::Mutex<VecDeque<String>>, line: String) {
+    let mut tail = tail.lock();
+    if tail.len() == STDERR_TAIL_LINES {
+        tail.pop_front();
+    }
+    tail.push_back(line);
+}
+
+/// Build the startup error for a failed spawn of the codex binary
+fn spawn_failure(error: &std::io::Error) -> Error {
+    let kind = if error.kind() == std::io::ErrorKind::NotFound {
+        AppServerStartupErrorKind::BinaryNotFound
+    } else {
+        AppServerStartupErrorKind::SpawnFailed
+    };
+    Error::AppServerStartup(AppServerStartupInfo {
+        kind,
+        message: format!("Failed to spawn app-server: {error}"),
+        stderr_tail: Vec::new(),
+    })
+}
+
+/// Build the startup error for a failed initialize handshake
+///
+/// The error message and stderr are checked for signs of a login requirement
+/// or a CLI too old to understand the app-server protocol.
+fn initialize_failure(error: &Error, stderr_tail: Vec<String>) -> Error {
+    let message = format!("Initialization failed: {error}");
+    let haystack = format!("{message}\n{}", stderr_tail.join("\n")).to_lowercase();
+
+    let kind = if [
+        "not logged in",
+        "unauthorized",
+        "authentication",
+        "login required",
+    ]
+    .iter()
+    .any(|needle| haystack.contains(needle))
+    {
+        AppServerStartupErrorKind::AuthRequired
+    } else if [
+        "-32601",
+        "method not found",
+        "unrecognized subcommand",
+        "unexpected argument",
+        "unknown variant",
+        "unsupported protocol",
+    ]
+    .iter()
+    .any(|needle| haystack.contains(needle))
+    {
+        AppServerStartupErrorKind::VersionMismatch
+    } else {
+        AppServerStartupErrorKind::InitializeFailed
+    };
+
+    Error::AppServerStartup(AppServerStartupInfo {
+        kind,
+        message,
+        stderr_tail,
+    })
+}
+
+/// Raw JSON-RPC line emitted to the debug console when IPC debugging is on
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct IpcDebugEvent {
+    line: String,
+    timestamp_ms: i64,
 }
 
-use crate::{Error, Result};
+/// Stands in for credentials in mirrored IPC lines
+const REDACTED: &str = "[redacted]";
+
+/// Whether a JSON field holds a credential, e.g. `apiKey`, `accessToken`, `client_secret`
+fn is_credential_key(key: &str) -> bool {
+    let key = key.to_ascii_lowercase().replace(['_', '-'], "");
+    key.ends_with("apikey")
+        || key.ends_with("token")
+        || key.ends_with("secret")
+        || key == "password"
+}
+
+/// Replace the string values of credential fields anywhere in `value`, returning whether any were
+fn redact_credentials(value: &mut JsonValue) -> bool {
+    match value {
+        JsonValue::Object(map) => {
+            let mut redacted = false;
+            for (key, field) in map.iter_mut() {
+                if field.is_string() && is_credential_key(key) {
+                    *field = JsonValue::String(REDACTED.to_string());
+                    redacted = true;
+                } else {
+                    redacted |= redact_credentials(field);
+                }
+            }
+            redacted
+        }
+        JsonValue::Array(items) => items
+            .iter_mut()
+            .fold(false, |redacted, item| redact_credentials(item) | redacted),
+        _ => false,
+    }
+}
+
+/// Build a debug event for a raw line, or None when IPC debugging is off
+///
+/// Credentials such as the API key sent with `account/login/start` are
+/// redacted, since the debug console is not a place for secrets.
+fn ipc_debug_event(ipc_debug: &AtomicBool, line: &str) -> Option<IpcDebugEvent> {
+    if !ipc_debug.load(Ordering::Relaxed) {
+        return None;
+    }
+    let line = line.trim_end();
+    let redacted = serde_json::from_str::<JsonValue>(line)
+        .ok()
+        .and_then(|mut value| redact_credentials(&mut value).then(|| value.to_string()));
+    Some(IpcDebugEvent {
+        line: redacted.unwrap_or_else(|| line.to_string()),
+        timestamp_ms: chrono::Utc::now().timestamp_millis(),
+    })
+}
+
+/// Update local state for a server notification before it is emitted as `event_name`
+///
+/// A finished login, successful or not, can no longer be cancelled.
+fn observe_notification(
+    method: &str,
+    event_name: &str,
+    params: &JsonValue,
+    recent_events: &RecentEvents,
+    account_cache: &AccountCache,
+    pending_logins: &parking_lot::Mutex<HashSet<String>>,
+) {
+    account_cache.observe_notification(method);
+    if method == "account/login/completed" {
+        if let Some(login_id) = params.get("loginId").and_then(JsonValue::as_str) {
+            pending_logins.lock().remove(login_id);
+        }
+    }
+    recent_events.record(event_name, params);
+}
 
 /// JSON-RPC request structure (without jsonrpc header as per app-server protocol)
 #[derive(Debug, Serialize)]
@@ -76,25 +251,42 @@ pub struct AppServerProcess {
 
     /// Channel for shutdown signal
     shutdown_tx: Option<mpsc::Sender<()>>,
+
+    /// App handle for emitting outgoing debug events
+    app_handle: AppHandle,
+
+    /// Whether raw traffic is mirrored as `ipc-debug-in`/`ipc-debug-out` events
+    ipc_debug: Arc<AtomicBool>,
+
+    /// Most recent stderr lines, for startup error reports
+    stderr_tail: Arc<parking_lot::Mutex<VecDeque<String>>>,
+
+    /// Approval requests from the server that haven't been answered
+    pending_approvals: PendingApprovals,
+
+    /// Recently emitted notifications, for windows that subscribe late
+    recent_events: RecentEvents,
 }
 
 impl AppServerProcess {
     /// Spawn a new app-server process
-    pub async fn spawn(app_handle: AppHandle) -> Result<Self> {
+    ///
+    /// `ipc_debug` is shared with the caller so debug mode can be toggled at runtime,
+    /// `account_cache` is invalidated when the server reports an account change,
+    /// and logins the server reports as completed are dropped from `pending_logins`.
+    pub async fn spawn(
+        app_handle: AppHandle,
+        ipc_debug: Arc<AtomicBool>,
+        account_cache: AccountCache,
+        pending_logins: Arc<parking_lot::Mutex<HashSet<String>>>,
+    ) -> Result<Self> {
         // Find the codex binary
         let codex_path = Self::find_codex_binary()?;
 
         tracing::info!("Spawning app-server from: {:?}", codex_path);
 
         // Spawn the process
-        let mut child = tokio::process::Command::new(&codex_path)
-            .arg("app-server")
-            .stdin(Stdio::piped())
-            .stdout(Stdio::piped())
-            .stderr(Stdio::inherit())
-            .kill_on_drop(true)
-            .spawn()
-            .map_err(|e| Error::AppServer(format!("Failed to spawn app-server: {e}")))?;
+        let mut child = Self::spawn_child(&codex_path)?;
 
         let stdin = child
             .stdin
@@ -106,14 +298,50 @@ impl AppServerProcess {
             .take()
             .ok_or_else(|| Error::AppServer("Failed to capture stdout".to_string()))?;
 
+        // Keep a tail of stderr for startup error reports, logging every line
+        // and emitting an event for each recognized error
+        let stderr_tail = Arc::new(parking_lot::Mutex::new(VecDeque::with_capacity(
+            STDERR_TAIL_LINES,
+        )));
+        if let Some(stderr) = child.stderr.take() {
+            let stderr_tail = stderr_tail.clone();
+            let app_handle = app_handle.clone();
+            tokio::spawn(async move {
+                let mut lines = BufReader::new(stderr).lines();
+                let mut last_issue: Option<StderrIssue> = None;
+                while let Ok(Some(line)) = lines.next_line().await {
+                    tracing::debug!("app-server stderr: {}", line);
+                    if let Some(issue) = classify_stderr_line(&line) {
+                        // Servers often repeat the same error; only report it once in a row
+                        if last_issue.as_ref() != Some(&issue) {
+                            tracing::warn!(
+                                "app-server reported {}: {:?}",
+                                issue.event_name(),
+                                issue
+                            );
+                            let _ = app_handle.emit(issue.event_name(), &issue);
+                            last_issue = Some(issue);
+                        }
+                    }
+                    push_stderr_line(&stderr_tail, line);
+                }
+            });
+        }
+
         let pending_requests: Arc<Mutex<HashMap<u64, PendingRequest>>> =
             Arc::new(Mutex::new(HashMap::with_capacity(128)));
 
+        let pending_approvals = PendingApprovals::default();
+        let recent_events = RecentEvents::default();
+
         let (shutdown_tx, mut shutdown_rx) = mpsc::channel::<()>(1);
 
         // Spawn stdout reader task
         let pending_clone = pending_requests.clone();
+        let approvals_clone = pending_approvals.clone();
+        let recent_events_clone = recent_events.clone();
         let app_handle_clone = app_handle.clone();
+        let ipc_debug_clone = ipc_debug.clone();
         tokio::spawn(async move {
             let reader = BufReader::new(stdout);
             let mut lines = reader.lines();
@@ -130,7 +358,17 @@ impl AppServerProcess {
                     line = lines.next_line() => {
                         match line {
                             Ok(Some(line)) => {
-                                Self::handle_message(&line, &pending_clone, &app_handle_clone).await;
+                                Self::handle_message(
+                                    &line,
+                                    &pending_clone,
+                                    &approvals_clone,
+                                    &recent_events_clone,
+                                    &account_cache,
+                                    &pending_logins,
+                                    &app_handle_clone,
+                                    &ipc_debug_clone,
+                                )
+                                .await;
                             }
                             Ok(None) => {
                                 tracing::info!("App server stdout closed (EOF)");
@@ -149,16 +387,11 @@ impl AppServerProcess {
 
             // Clean up all pending requests with error
             if let Some(reason) = &disconnect_reason {
-                let mut pending = pending_clone.lock().await;
-                let count = pending.len();
+                let count = Self::fail_pending_requests(&mut *pending_clone.lock().await, reason);
                 if count > 0 {
-                    tracing::warn!("Cleaning up {} pending requests due to disconnect", count);
-                    for (id, pending_req) in pending.drain() {
-                        let _ = pending_req.sender.send(Err(Error::AppServer(format!(
-                            "Request {id} failed: {reason}"
-                        ))));
-                    }
+                    tracing::warn!("Cleaned up {} pending requests due to disconnect", count);
                 }
+                approvals_clone.clear();
 
                 // Emit disconnected event
                 let _ = app_handle_clone.emit("app-server-disconnected", ());
@@ -171,14 +404,41 @@ impl AppServerProcess {
             request_counter: AtomicU64::new(1),
             pending_requests,
             shutdown_tx: Some(shutdown_tx),
+            app_handle,
+            ipc_debug,
+            stderr_tail,
+            pending_approvals,
+            recent_events,
         };
 
         // Initialize the app-server (required before any other requests)
-        process.initialize().await?;
+        if let Err(e) = process.initialize().await {
+            // Give a crashing process a moment to exit so its stderr is captured
+            let grace = std::time::Duration::from_millis(STARTUP_FAILURE_GRACE_MS);
+            let _ = tokio::time::timeout(grace, process.child.wait()).await;
+            return Err(initialize_failure(&e, process.stderr_tail()));
+        }
 
         Ok(process)
     }
 
+    /// Spawn `codex app-server` with piped stdio
+    fn spawn_child(codex_path: &std::path::Path) -> Result<Child> {
+        tokio::process::Command::new(codex_path)
+            .arg("app-server")
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .kill_on_drop(true)
+            .spawn()
+            .map_err(|e| spawn_failure(&e))
+    }
+
+    /// Most recent lines the process wrote to stderr, oldest first
+    pub fn stderr_tail(&self) -> Vec<String> {
+        self.stderr_tail.lock().iter().cloned().collect()
+    }
+
     /// Initialize the app-server with client info
     async fn initialize(&mut self) -> Result<()> {
         #[derive(Serialize)]
@@ -236,17 +496,29 @@ impl AppServerProcess {
             }
         }
 
-        Err(Error::AppServer(
-            "Codex CLI not found. Please install it first.".to_string(),
-        ))
+        Err(Error::AppServerStartup(AppServerStartupInfo {
+            kind: AppServerStartupErrorKind::BinaryNotFound,
+            message: "Codex CLI not found. Please install it first.".to_string(),
+            stderr_tail: Vec::new(),
+        }))
     }
 
     /// Handle an incoming JSON-RPC message
+    #[allow(clippy::too_many_arguments)]
     async fn handle_message(
         line: &str,
         pending_requests: &Arc<Mutex<HashMap<u64, PendingRequest>>>,
+        pending_approvals: &PendingApprovals,
+        recent_events: &RecentEvents,
+        account_cache: &AccountCache,
+        pending_logins: &parking_lot::Mutex<HashSet<String>>,
         app_handle: &AppHandle,
+        ipc_debug: &AtomicBool,
     ) {
+        if let Some(event) = ipc_debug_event(ipc_debug, line) {
+            let _ = app_handle.emit("ipc-debug-in", event);
+        }
+
         let message: JsonRpcMessage = match serde_json::from_str(line) {
             Ok(r) => r,
             Err(e) => {
@@ -256,32 +528,45 @@ impl AppServerProcess {
         };
 
         // Determine message type based on fields present
-        match (message.id, message.method.as_ref(), message.result.as_ref(), message.error.as_ref()) {
+        match (
+            message.id,
+            message.method.as_ref(),
+            message.result.as_ref(),
+            message.error.as_ref(),
+        ) {
             // Response to our request (has id, has result or error, no method)
             (Some(id), None, _, _) => {
-                let mut pending = pending_requests.lock().await;
-                if let Some(pending_req) = pending.remove(&id) {
-                    let result = if let Some(error) = message.error {
-                        Err(Error::AppServer(format!(
-                            "JSON-RPC error {}: {}",
-                            error.code, error.message
-                        )))
-                    } else {
-                        Ok(message.result.unwrap_or(JsonValue::Null))
-                    };
-                    let _ = pending_req.sender.send(result);
+                let result = if let Some(error) = message.error {
+                    Err(Error::AppServer(format!(
+                        "JSON-RPC error {}: {}",
+                        error.code, error.message
+                    )))
+                } else {
+                    Ok(message.result.unwrap_or(JsonValue::Null))
+                };
+                if !Self::route_response(&mut *pending_requests.lock().await, id, result) {
+                    tracing::debug!("Dropping response for unknown request id {}", id);
                 }
             }
             // Server-initiated request (has id AND method) - e.g., approval requests
             (Some(id), Some(method), _, _) => {
                 let event_name = method.replace('/', "-");
+                if let Some(params) = &message.params {
+                    pending_approvals.record(id, method, params);
+                }
                 // Include request ID in params so client can respond
-                let mut params = message.params.unwrap_or(JsonValue::Object(serde_json::Map::new()));
+                let mut params = message
+                    .params
+                    .unwrap_or(JsonValue::Object(serde_json::Map::new()));
                 if let JsonValue::Object(ref mut map) = params {
                     map.insert("_requestId".to_string(), JsonValue::Number(id.into()));
                 }
 
-                tracing::debug!("Emitting server request: {} with params: {:?}", event_name, params);
+                tracing::debug!(
+                    "Emitting server request: {} with params: {:?}",
+                    event_name,
+                    params
+                );
 
                 if let Err(e) = app_handle.emit(&event_name, params) {
                     tracing::warn!("Failed to emit server request {}: {}", event_name, e);
@@ -299,6 +584,14 @@ impl AppServerProcess {
                     tracing::debug!("Emitting event: {} (no threadId)", event_name);
                 }
 
+                observe_notification(
+                    method,
+                    &event_name,
+                    &params,
+                    recent_events,
+                    account_cache,
+                    pending_logins,
+                );
                 if let Err(e) = app_handle.emit(&event_name, params) {
                     tracing::warn!("Failed to emit event {}: {}", event_name, e);
                 }
@@ -317,6 +610,13 @@ impl AppServerProcess {
     {
         let id = self.request_counter.fetch_add(1, Ordering::SeqCst);
 
+        // Serialize params up front so the request can be tagged with its thread
+        let params = serde_json::to_value(params)?;
+        let thread_id = params
+            .get("threadId")
+            .and_then(JsonValue::as_str)
+            .map(str::to_string);
+
         let request = JsonRpcRequest {
             id,
             method: method.to_string(),
@@ -344,22 +644,18 @@ impl AppServerProcess {
                 )));
             }
 
-            pending.insert(id, PendingRequest {
-                sender: tx,
-                created_at: Instant::now(),
-            });
+            pending.insert(
+                id,
+                PendingRequest {
+                    sender: tx,
+                    created_at: Instant::now(),
+                    thread_id,
+                },
+            );
         }
 
         // Send request
-        self.stdin
-            .write_all(json.as_bytes())
-            .await
-            .map_err(|e| Error::AppServer(format!("Failed to write to stdin: {e}")))?;
-
-        self.stdin
-            .flush()
-            .await
-            .map_err(|e| Error::AppServer(format!("Failed to flush stdin: {e}")))?;
+        self.write_line(&json).await?;
 
         // Wait for response with timeout
         let result = tokio::time::timeout(std::time::Duration::from_secs(30), rx).await;
@@ -384,6 +680,75 @@ impl AppServerProcess {
         }
     }
 
+    /// Deliver a response to the pending request with the given id
+    ///
+    /// Returns false if no request with that id is pending on this process.
+    fn route_response(
+        pending: &mut HashMap<u64, PendingRequest>,
+        id: u64,
+        result: Result<JsonValue>,
+    ) -> bool {
+        match pending.remove(&id) {
+            Some(pending_req) => {
+                let _ = pending_req.sender.send(result);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Fail every pending request with the given reason, returning how many were failed
+    fn fail_pending_requests(pending: &mut HashMap<u64, PendingRequest>, reason: &str) -> usize {
+        let count = pending.len();
+        for (id, pending_req) in pending.drain() {
+            let _ = pending_req.sender.send(Err(Error::AppServer(format!(
+                "Request {id} failed: {reason}"
+            ))));
+        }
+        count
+    }
+
+    /// Fail the pending requests tagged with `thread_id`, returning how many were failed
+    ///
+    /// A late response for a cancelled id is dropped by `route_response`.
+    fn fail_thread_requests(
+        pending: &mut HashMap<u64, PendingRequest>,
+        thread_id: &str,
+        reason: &str,
+    ) -> usize {
+        let ids: Vec<u64> = pending
+            .iter()
+            .filter(|(_, req)| req.thread_id.as_deref() == Some(thread_id))
+            .map(|(id, _)| *id)
+            .collect();
+
+        for id in &ids {
+            if let Some(pending_req) = pending.remove(id) {
+                let _ = pending_req.sender.send(Err(Error::AppServer(format!(
+                    "Request {id} failed: {reason}"
+                ))));
+            }
+        }
+        ids.len()
+    }
+
+    /// Get a handle for cancelling this process's requests without the app-server lock
+    pub fn request_canceller(&self) -> RequestCanceller {
+        RequestCanceller {
+            pending_requests: self.pending_requests.clone(),
+        }
+    }
+
+    /// Get a handle to this process's unanswered approval requests
+    pub fn pending_approvals(&self) -> PendingApprovals {
+        self.pending_approvals.clone()
+    }
+
+    /// Buffer of this process's recently emitted notifications
+    pub fn recent_events(&self) -> RecentEvents {
+        self.recent_events.clone()
+    }
+
     /// Clean up stale pending requests that have exceeded the maximum age
     fn cleanup_stale_requests(pending: &mut HashMap<u64, PendingRequest>) {
         let now = Instant::now();
@@ -423,16 +788,8 @@ impl AppServerProcess {
         let mut json = serde_json::to_string(&response)?;
         json.push('\n');
 
-        self.stdin
-            .write_all(json.as_bytes())
-            .await
-            .map_err(|e| Error::AppServer(format!("Failed to write to stdin: {e}")))?;
-
-        self.stdin
-            .flush()
-            .await
-            .map_err(|e| Error::AppServer(format!("Failed to flush stdin: {e}")))?;
-
+        self.write_line(&json).await?;
+        self.pending_approvals.resolve(request_id);
         Ok(())
     }
 
@@ -452,6 +809,15 @@ impl AppServerProcess {
         let mut json = serde_json::to_string(&notification)?;
         json.push('\n');
 
+        self.write_line(&json).await
+    }
+
+    /// Write a newline-terminated JSON line to the app-server's stdin
+    async fn write_line(&mut self, json: &str) -> Result<()> {
+        if let Some(event) = ipc_debug_event(&self.ipc_debug, json) {
+            let _ = self.app_handle.emit("ipc-debug-out", event);
+        }
+
         self.stdin
             .write_all(json.as_bytes())
             .await
@@ -475,12 +841,27 @@ impl AppServerProcess {
     }
 
     /// Gracefully shutdown the app-server
+    ///
+    /// Request ids are only unique per process: every `AppServerProcess` owns its
+    /// own counter (starting at 1) and its own pending map. On shutdown all
+    /// requests still pending on this process are failed immediately, so a
+    /// restarted process reusing the same ids can never have its responses
+    /// routed to a caller from the previous process.
     pub async fn shutdown(&mut self) -> Result<()> {
         // Signal the reader task to stop
         if let Some(tx) = self.shutdown_tx.take() {
             let _ = tx.send(()).await;
         }
 
+        // The reader task skips cleanup on graceful shutdown, so drain here
+        let count = Self::fail_pending_requests(
+            &mut *self.pending_requests.lock().await,
+            "App server was shut down",
+        );
+        if count > 0 {
+            tracing::warn!("Failed {} pending requests during shutdown", count);
+        }
+
         // Try graceful shutdown first
         // The app-server should exit when stdin is closed
         drop(self.stdin.shutdown().await);
@@ -507,3 +888,264 @@ impl Drop for AppServerProcess {
         // The kill_on_drop(true) should handle this
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_account_notifications_invalidate_cached_account() {
+        let recent_events = RecentEvents::default();
+        let cache = AccountCache::default();
+        let pending_logins = parking_lot::Mutex::new(HashSet::new());
+        let account: crate::app_server::ipc_bridge::AccountInfo =
+            serde_json::from_value(serde_json::json!({
+                "account": null,
+                "requiresOpenaiAuth": true
+            }))
+            .unwrap();
+
+        for method in ["account/login/completed", "account/updated"] {
+            cache.store(&account, cache.generation());
+            let params = serde_json::json!({ "success": true });
+            observe_notification(
+                method,
+                &method.replace('/', "-"),
+                &params,
+                &recent_events,
+                &cache,
+                &pending_logins,
+            );
+            assert!(cache.get().is_none(), "{method}");
+        }
+
+        cache.store(&account, cache.generation());
+        let params = serde_json::json!({ "threadId": "t1" });
+        observe_notification(
+            "turn/started",
+            "turn-started",
+            &params,
+            &recent_events,
+            &cache,
+            &pending_logins,
+        );
+        assert!(cache.get().is_some());
+        // Only the non-account notification is kept for replay
+        let names: Vec<String> = recent_events
+            .recent(10)
+            .into_iter()
+            .map(|e| e.name)
+            .collect();
+        assert_eq!(names, vec!["turn-started"]);
+    }
+
+    #[test]
+    fn test_login_completed_forgets_pending_login() {
+        let recent_events = RecentEvents::default();
+        let cache = AccountCache::default();
+        let pending_logins = parking_lot::Mutex::new(HashSet::from([
+            "login-1".to_string(),
+            "login-2".to_string(),
+        ]));
+
+        let params =
+            serde_json::json!({ "loginId": "login-1", "success": false, "error": "denied" });
+        observe_notification(
+            "account/login/completed",
+            "account-login-completed",
+            &params,
+            &recent_events,
+            &cache,
+            &pending_logins,
+        );
+
+        assert_eq!(
+            *pending_logins.lock(),
+            HashSet::from(["login-2".to_string()])
+        );
+    }
+
+    fn pending_entry() -> (PendingRequest, oneshot::Receiver<Result<JsonValue>>) {
+        tagged_entry(None)
+    }
+
+    fn tagged_entry(
+        thread_id: Option<&str>,
+    ) -> (PendingRequest, oneshot::Receiver<Result<JsonValue>>) {
+        let (tx, rx) = oneshot::channel();
+        (
+            PendingRequest {
+                sender: tx,
+                created_at: Instant::now(),
+                thread_id: thread_id.map(str::to_string),
+            },
+            rx,
+        )
+    }
+
+    #[test]
+    fn test_fail_thread_requests_only_cancels_target_thread() {
+        let mut pending = HashMap::new();
+        let (a1, mut a1_rx) = tagged_entry(Some("thread-a"));
+        let (a2, mut a2_rx) = tagged_entry(Some("thread-a"));
+        let (b1, mut b1_rx) = tagged_entry(Some("thread-b"));
+        let (untagged, mut untagged_rx) = tagged_entry(None);
+        pending.insert(1, a1);
+        pending.insert(2, b1);
+        pending.insert(3, a2);
+        pending.insert(4, untagged);
+
+        let cancelled =
+            AppServerProcess::fail_thread_requests(&mut pending, "thread-a", "navigated away");
+
+        assert_eq!(cancelled, 2);
+        assert!(a1_rx.try_recv().unwrap().is_err());
+        assert!(a2_rx.try_recv().unwrap().is_err());
+        assert!(
+            b1_rx.try_recv().is_err(),
+            "thread-b request should still be pending"
+        );
+        assert!(
+            untagged_rx.try_recv().is_err(),
+            "untagged request should still be pending"
+        );
+        assert_eq!(pending.len(), 2);
+
+        // A late response for a cancelled request is dropped
+        assert!(!AppServerProcess::route_response(
+            &mut pending,
+            1,
+            Ok(JsonValue::Null)
+        ));
+    }
+
+    #[test]
+    fn test_ipc_debug_events_only_when_enabled() {
+        let ipc_debug = AtomicBool::new(false);
+        let line = "{\"method\":\"turn/started\",\"params\":{}}\n";
+        assert!(ipc_debug_event(&ipc_debug, line).is_none());
+
+        ipc_debug.store(true, Ordering::Relaxed);
+        let event = ipc_debug_event(&ipc_debug, line).unwrap();
+        assert_eq!(event.line, "{\"method\":\"turn/started\",\"params\":{}}");
+
+        ipc_debug.store(false, Ordering::Relaxed);
+        assert!(ipc_debug_event(&ipc_debug, line).is_none());
+    }
+
+    #[test]
+    fn test_ipc_debug_events_redact_credentials() {
+        let ipc_debug = AtomicBool::new(true);
+        let line = r#"{"id":3,"method":"account/login/start","params":{"type":"apiKey","apiKey":"sk-secret"}}"#;
+        let event = ipc_debug_event(&ipc_debug, line).unwrap();
+        assert!(!event.line.contains("sk-secret"), "{}", event.line);
+        let value: JsonValue = serde_json::from_str(&event.line).unwrap();
+        assert_eq!(value["params"]["apiKey"], REDACTED);
+        assert_eq!(value["params"]["type"], "apiKey");
+
+        let line = r#"{"id":4,"result":{"tokens":[{"access_token":"at","refreshToken":"rt"}],"inputTokens":12}}"#;
+        let value: JsonValue =
+            serde_json::from_str(&ipc_debug_event(&ipc_debug, line).unwrap().line).unwrap();
+        assert_eq!(value["result"]["tokens"][0]["access_token"], REDACTED);
+        assert_eq!(value["result"]["tokens"][0]["refreshToken"], REDACTED);
+        assert_eq!(value["result"]["inputTokens"], 12);
+    }
+
+    #[test]
+    fn test_reconnect_does_not_misroute_reused_ids() {
+        // Old process has request 1 in flight when it is restarted
+        let mut old_pending = HashMap::new();
+        let (old_req, mut old_rx) = pending_entry();
+        old_pending.insert(1, old_req);
+
+        let failed = AppServerProcess::fail_pending_requests(&mut old_pending, "restarting");
+        assert_eq!(failed, 1);
+        assert!(old_pending.is_empty());
+
+        // New process starts its counter at 1 again
+        let mut new_pending = HashMap::new();
+        let (new_req, mut new_rx) = pending_entry();
+        new_pending.insert(1, new_req);
+
+        let routed = AppServerProcess::route_response(
+            &mut new_pending,
+            1,
+            Ok(serde_json::json!({ "from": "new" })),
+        );
+        assert!(routed);
+
+        // The old caller saw a failure, the new caller got the new response
+        assert!(old_rx.try_recv().unwrap().is_err());
+        let value = new_rx.try_recv().unwrap().unwrap();
+        assert_eq!(value["from"], "new");
+
+        // A late response for id 1 cannot reach the drained old map
+        assert!(!AppServerProcess::route_response(
+            &mut old_pending,
+            1,
+            Ok(JsonValue::Null)
+        ));
+    }
+
+    fn startup_kind(error: &Error) -> AppServerStartupErrorKind {
+        match error {
+            Error::AppServerStartup(info) => info.kind,
+            other => panic!("expected a startup error, got {other:?}"),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_spawn_failure_reports_missing_binary() {
+        let error = AppServerProcess::spawn_child(std::path::Path::new("/nonexistent/bin/codex"))
+            .unwrap_err();
+        assert_eq!(
+            startup_kind(&error),
+            AppServerStartupErrorKind::BinaryNotFound
+        );
+
+        let denied = std::io::Error::from(std::io::ErrorKind::PermissionDenied);
+        assert_eq!(
+            startup_kind(&spawn_failure(&denied)),
+            AppServerStartupErrorKind::SpawnFailed
+        );
+    }
+
+    #[test]
+    fn test_initialize_rejection_is_classified() {
+        let rejected = Error::AppServer("JSON-RPC error -32601: Method not found".to_string());
+        assert_eq!(
+            startup_kind(&initialize_failure(&rejected, Vec::new())),
+            AppServerStartupErrorKind::VersionMismatch
+        );
+
+        let exited = Error::AppServer("Request 1 failed: App server process exited".to_string());
+        let error = initialize_failure(
+            &exited,
+            vec!["Error: Not logged in. Run `codex login`.".to_string()],
+        );
+        assert_eq!(
+            startup_kind(&error),
+            AppServerStartupErrorKind::AuthRequired
+        );
+        let Error::AppServerStartup(info) = error else {
+            unreachable!()
+        };
+        assert_eq!(info.stderr_tail.len(), 1);
+
+        assert_eq!(
+            startup_kind(&initialize_failure(&exited, Vec::new())),
+            AppServerStartupErrorKind::InitializeFailed
+        );
+    }
+
+    #[test]
+    fn test_stderr_tail_keeps_last_lines() {
+        let tail = parking_lot::Mutex::new(VecDeque::new());
+        for i in 0..STDERR_TAIL_LINES + 5 {
+            push_stderr_line(&tail, format!("line {i}"));
+        }
+        let tail = tail.lock();
+        assert_eq!(tail.len(), STDERR_TAIL_LINES);
+        assert_eq!(tail.front().unwrap(), "line 5");
+    }
+}
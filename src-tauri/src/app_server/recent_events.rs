@@ -0,0 +1,113 @@
+//! Buffer of recently emitted app-server notifications
+//!
+//! A window that subscribes late (e.g. after a reload mid-turn) has missed
+//! whatever was emitted before it listened. The last few notifications are
+//! kept here so they can be emitted again for it to rebuild its state.
+//! Server requests are never buffered, since an approval that has already
+//! been answered must not be shown again, and neither are account or login
+//! notifications.
+
+use std::collections::VecDeque;
+use std::sync::Arc;
+
+use serde::Serialize;
+use serde_json::Value as JsonValue;
+
+/// Number of notifications kept for replay
+pub const RECENT_EVENTS_CAPACITY: usize = 500;
+
+/// Event name fragments marking notifications that carry account or credential details
+const SENSITIVE_EVENT_MARKERS: &[&str] = &["account", "auth", "login"];
+
+/// A notification as it was emitted to the UI
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RecentEvent {
+    /// Tauri event name, e.g. `item-agentMessage-delta`
+    pub name: String,
+    pub payload: JsonValue,
+}
+
+/// Whether an event must not be kept for replay
+fn is_sensitive_event(name: &str) -> bool {
+    let name = name.to_ascii_lowercase();
+    SENSITIVE_EVENT_MARKERS
+        .iter()
+        .any(|marker| name.contains(marker))
+}
+
+/// Bounded, shared buffer of the most recent notifications
+#[derive(Clone, Default)]
+pub struct RecentEvents {
+    inner: Arc<parking_lot::Mutex<VecDeque<RecentEvent>>>,
+}
+
+impl RecentEvents {
+    /// Remember an emitted notification, dropping the oldest past the capacity
+    pub fn record(&self, name: &str, payload: &JsonValue) {
+        if is_sensitive_event(name) {
+            return;
+        }
+        let mut events = self.inner.lock();
+        if events.len() == RECENT_EVENTS_CAPACITY {
+            events.pop_front();
+        }
+        events.push_back(RecentEvent {
+            name: name.to_string(),
+            payload: payload.clone(),
+        });
+    }
+
+    /// The last `count` notifications, oldest first
+    pub fn recent(&self, count: usize) -> Vec<RecentEvent> {
+        let events = self.inner.lock();
+        let skip = events.len().saturating_sub(count);
+        events.iter().skip(skip).cloned().collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_events_are_replayed_in_order() {
+        let events = RecentEvents::default();
+        events.record("turn-started", &json!({ "threadId": "t1" }));
+        events.record("item-agentMessage-delta", &json!({ "delta": "Hel" }));
+        events.record("account-login-completed", &json!({ "success": true }));
+        events.record("item-agentMessage-delta", &json!({ "delta": "lo" }));
+
+        let names: Vec<String> = events.recent(10).into_iter().map(|e| e.name).collect();
+        assert_eq!(
+            names,
+            vec![
+                "turn-started",
+                "item-agentMessage-delta",
+                "item-agentMessage-delta"
+            ]
+        );
+
+        let last = events.recent(2);
+        assert_eq!(last[0].payload, json!({ "delta": "Hel" }));
+        assert_eq!(last[1].payload, json!({ "delta": "lo" }));
+        assert!(events.recent(0).is_empty());
+    }
+
+    #[test]
+    fn test_buffer_is_bounded() {
+        let events = RecentEvents::default();
+        for i in 0..RECENT_EVENTS_CAPACITY + 10 {
+            events.record("item-started", &json!(i));
+        }
+
+        let all = events.recent(usize::MAX);
+        assert_eq!(all.len(), RECENT_EVENTS_CAPACITY);
+        assert_eq!(all[0].payload, json!(10));
+        assert_eq!(
+            all.last().unwrap().payload,
+            json!(RECENT_EVENTS_CAPACITY + 9)
+        );
+    }
+}
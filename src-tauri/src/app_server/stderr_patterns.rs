@@ -0,0 +1,147 @@
+//! Recognition of known error patterns in app-server stderr
+//!
+//! Some failures only show up in the server's logs. Matching them lets the
+//! UI react, e.g. by prompting a re-login when credentials expire.
+
+use serde::Serialize;
+
+/// A recognized problem reported on stderr
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "kind", rename_all = "kebab-case")]
+pub enum StderrIssue {
+    /// Credentials expired or were rejected; the user needs to log in again
+    AuthExpired { detail: String },
+    /// A config file failed to load
+    ConfigError {
+        /// Path of the offending config file, if the line named one
+        path: Option<String>,
+        detail: String,
+    },
+}
+
+impl StderrIssue {
+    /// Name of the Tauri event emitted for this issue
+    pub fn event_name(&self) -> &'static str {
+        match self {
+            StderrIssue::AuthExpired { .. } => "auth-expired",
+            StderrIssue::ConfigError { .. } => "config-error",
+        }
+    }
+}
+
+const AUTH_PATTERNS: &[&str] = &[
+    "token expired",
+    "token has expired",
+    "refresh token",
+    "401 unauthorized",
+    "not logged in",
+    "please log in again",
+    "re-authenticate",
+];
+
+const CONFIG_PATTERNS: &[&str] = &[
+    "error loading config",
+    "failed to load config",
+    "failed to parse config",
+    "invalid config",
+    "config.toml:",
+];
+
+/// Strip ANSI color codes and the log level/target prefix from a line
+fn message_text(line: &str) -> String {
+    let mut text = String::with_capacity(line.len());
+    let mut chars = line.chars();
+    while let Some(c) = chars.next() {
+        if c == '\u{1b}' {
+            // Skip a CSI sequence: ESC [ ... final byte
+            for c in chars.by_ref() {
+                if c.is_ascii_alphabetic() {
+                    break;
+                }
+            }
+        } else {
+            text.push(c);
+        }
+    }
+
+    let trimmed = text.trim();
+    for level in ["ERROR", "WARN", "INFO"] {
+        if let Some((_, rest)) = trimmed.split_once(&format!("{level} ")) {
+            // Drop a `target:` prefix such as `codex_core::config:`
+            let rest = rest.trim_start();
+            return match rest.split_once(": ") {
+                Some((target, message)) if target.contains("::") || !target.contains(' ') => {
+                    message.to_string()
+                }
+                _ => rest.to_string(),
+            };
+        }
+    }
+    trimmed.to_string()
+}
+
+/// Find the first `config.toml` path mentioned in a line
+fn config_path(text: &str) -> Option<String> {
+    text.split_whitespace()
+        .map(|word| word.trim_matches(|c: char| matches!(c, '"' | '\'' | '`' | ',' | '(' | ')')))
+        .find_map(|word| {
+            let end = word.find("config.toml")? + "config.toml".len();
+            Some(word[..end].to_string())
+        })
+}
+
+/// Match a stderr line against the known error patterns
+pub fn classify_stderr_line(line: &str) -> Option<StderrIssue> {
+    let detail = message_text(line);
+    let lower = detail.to_lowercase();
+
+    if AUTH_PATTERNS.iter().any(|p| lower.contains(p)) {
+        return Some(StderrIssue::AuthExpired { detail });
+    }
+    if CONFIG_PATTERNS.iter().any(|p| lower.contains(p)) {
+        return Some(StderrIssue::ConfigError {
+            path: config_path(&detail),
+            detail,
+        });
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_classify_auth_expired() {
+        let line = "\u{1b}[2m2025-01-01T00:00:00Z\u{1b}[0m \u{1b}[31mERROR\u{1b}[0m codex_login: refresh token expired, please log in again";
+        let issue = classify_stderr_line(line).unwrap();
+        assert_eq!(issue.event_name(), "auth-expired");
+        assert_eq!(
+            issue,
+            StderrIssue::AuthExpired {
+                detail: "refresh token expired, please log in again".to_string()
+            }
+        );
+    }
+
+    #[test]
+    fn test_classify_config_error_extracts_path() {
+        let line = "2025-01-01T00:00:00Z ERROR codex_core::config: error loading config from \"/home/u/.codex/config.toml\": unknown field `modle`";
+        match classify_stderr_line(line).unwrap() {
+            StderrIssue::ConfigError { path, detail } => {
+                assert_eq!(path.as_deref(), Some("/home/u/.codex/config.toml"));
+                assert!(detail.starts_with("error loading config"));
+            }
+            other => panic!("unexpected issue: {other:?}"),
+        }
+    }
+
+    #[test]
+    fn test_classify_ignores_ordinary_lines() {
+        assert_eq!(
+            classify_stderr_line("2025-01-01T00:00:00Z INFO codex_core: session started"),
+            None
+        );
+        assert_eq!(classify_stderr_line(""), None);
+    }
+}
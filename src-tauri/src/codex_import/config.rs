@@ -74,6 +74,150 @@ pub fn read_config_from_path(path: &Path) -> Result<CodexConfig> {
     Ok(config)
 }
 
+/// Where an effective project setting came from
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum CodexConfigSource {
+    /// Top-level value in ~/.codex/config.toml
+    Global,
+    /// `[projects."<path>"]` entry in ~/.codex/config.toml
+    GlobalProject,
+    /// The project's own .codex/config.toml
+    Project,
+}
+
+/// Effective Codex settings for a project with per-key origins
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct ProjectCodexConfig {
+    pub model: Option<String>,
+    pub model_reasoning_effort: Option<String>,
+    pub trust_level: Option<String>,
+    pub instructions: Option<String>,
+    pub mcp_servers: HashMap<String, toml::Value>,
+    /// Setting key (e.g. "model", "mcp_servers.github") -> where it was defined
+    pub origins: HashMap<String, CodexConfigSource>,
+    /// Path of the project-local config file, if one was found
+    pub project_config_path: Option<String>,
+}
+
+/// Read the effective Codex config for a project directory
+///
+/// Layers, lowest to highest precedence: global config, the global
+/// `[projects]` entry for this path, then `<project>/.codex/config.toml`.
+pub fn read_project_config(project_path: &Path) -> Result<ProjectCodexConfig> {
+    let global = read_config()?;
+
+    let local_path = project_path.join(".codex").join("config.toml");
+    let local = if local_path.is_file() {
+        Some(read_config_from_path(&local_path)?)
+    } else {
+        None
+    };
+
+    let mut merged = merge_project_config(&global, project_path, local.as_ref());
+    if local.is_some() {
+        merged.project_config_path = Some(local_path.to_string_lossy().to_string());
+    }
+    Ok(merged)
+}
+
+/// Merge global and project-local configs for a project path
+fn merge_project_config(
+    global: &CodexConfig,
+    project_path: &Path,
+    local: Option<&CodexConfig>,
+) -> ProjectCodexConfig {
+    let mut merged = ProjectCodexConfig::default();
+
+    fn set(
+        slot: &mut Option<String>,
+        origins: &mut HashMap<String, CodexConfigSource>,
+        key: &str,
+        value: &Option<String>,
+        source: CodexConfigSource,
+    ) {
+        if let Some(v) = value {
+            *slot = Some(v.clone());
+            origins.insert(key.to_string(), source);
+        }
+    }
+
+    // Layer 1: global top-level settings
+    set(
+        &mut merged.model,
+        &mut merged.origins,
+        "model",
+        &global.model,
+        CodexConfigSource::Global,
+    );
+    set(
+        &mut merged.model_reasoning_effort,
+        &mut merged.origins,
+        "model_reasoning_effort",
+        &global.model_reasoning_effort,
+        CodexConfigSource::Global,
+    );
+    for (name, server) in &global.mcp_servers {
+        merged.mcp_servers.insert(name.clone(), server.clone());
+        merged
+            .origins
+            .insert(format!("mcp_servers.{name}"), CodexConfigSource::Global);
+    }
+
+    // Layer 2: global [projects."<path>"] entry
+    let path_key = project_path.to_string_lossy();
+    if let Some(entry) = global.projects.get(path_key.as_ref()) {
+        set(
+            &mut merged.model,
+            &mut merged.origins,
+            "model",
+            &entry.model,
+            CodexConfigSource::GlobalProject,
+        );
+        set(
+            &mut merged.trust_level,
+            &mut merged.origins,
+            "trust_level",
+            &entry.trust_level,
+            CodexConfigSource::GlobalProject,
+        );
+        set(
+            &mut merged.instructions,
+            &mut merged.origins,
+            "instructions",
+            &entry.instructions,
+            CodexConfigSource::GlobalProject,
+        );
+    }
+
+    // Layer 3: project-local .codex/config.toml
+    if let Some(local) = local {
+        set(
+            &mut merged.model,
+            &mut merged.origins,
+            "model",
+            &local.model,
+            CodexConfigSource::Project,
+        );
+        set(
+            &mut merged.model_reasoning_effort,
+            &mut merged.origins,
+            "model_reasoning_effort",
+            &local.model_reasoning_effort,
+            CodexConfigSource::Project,
+        );
+        for (name, server) in &local.mcp_servers {
+            merged.mcp_servers.insert(name.clone(), server.clone());
+            merged
+                .origins
+                .insert(format!("mcp_servers.{name}"), CodexConfigSource::Project);
+        }
+    }
+
+    merged
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -100,4 +244,74 @@ model = "gpt-4o"
             Some("trusted".to_string())
         );
     }
+
+    #[test]
+    fn test_merge_project_config_layers_and_origins() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let project_path = temp_dir.path();
+
+        let global: CodexConfig = toml::from_str(&format!(
+            r#"
+model = "gpt-5.2-codex"
+model_reasoning_effort = "medium"
+
+[mcp_servers.github]
+command = "github-mcp"
+
+[projects."{}"]
+trust_level = "trusted"
+"#,
+            project_path.display()
+        ))
+        .unwrap();
+
+        let local_dir = project_path.join(".codex");
+        std::fs::create_dir_all(&local_dir).unwrap();
+        std::fs::write(
+            local_dir.join("config.toml"),
+            r#"
+model = "gpt-4o"
+
+[mcp_servers.local-db]
+command = "db-mcp"
+"#,
+        )
+        .unwrap();
+        let local = read_config_from_path(&local_dir.join("config.toml")).unwrap();
+
+        let merged = merge_project_config(&global, project_path, Some(&local));
+
+        assert_eq!(merged.model.as_deref(), Some("gpt-4o"));
+        assert_eq!(merged.origins["model"], CodexConfigSource::Project);
+        assert_eq!(merged.model_reasoning_effort.as_deref(), Some("medium"));
+        assert_eq!(
+            merged.origins["model_reasoning_effort"],
+            CodexConfigSource::Global
+        );
+        assert_eq!(merged.trust_level.as_deref(), Some("trusted"));
+        assert_eq!(
+            merged.origins["trust_level"],
+            CodexConfigSource::GlobalProject
+        );
+        assert_eq!(merged.mcp_servers.len(), 2);
+        assert_eq!(
+            merged.origins["mcp_servers.github"],
+            CodexConfigSource::Global
+        );
+        assert_eq!(
+            merged.origins["mcp_servers.local-db"],
+            CodexConfigSource::Project
+        );
+    }
+
+    #[test]
+    fn test_merge_project_config_without_local_file() {
+        let global: CodexConfig = toml::from_str(r#"model = "gpt-5.2-codex""#).unwrap();
+
+        let merged = merge_project_config(&global, Path::new("/tmp/other"), None);
+
+        assert_eq!(merged.model.as_deref(), Some("gpt-5.2-codex"));
+        assert_eq!(merged.origins["model"], CodexConfigSource::Global);
+        assert!(merged.trust_level.is_none());
+    }
 }
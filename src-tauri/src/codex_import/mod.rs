@@ -6,9 +6,11 @@
 mod config;
 mod session;
 
-pub use config::{CodexConfig, CodexProject};
+pub use config::{CodexConfig, CodexConfigSource, CodexProject, ProjectCodexConfig};
 pub use session::{
-    CodexSession, CodexSessionMeta, CodexSessionSummary, ResponseItem, SessionMessage,
+    CodexSession, CodexSessionMeta, CodexSessionSummary, ResponseItem, SessionItemsCache,
+    SessionItemsPage, SessionMessage, SessionValidation, SessionValidationError,
+    SessionValidationErrorKind,
 };
 
 use crate::Result;
@@ -26,6 +28,11 @@ pub fn read_config() -> Result<CodexConfig> {
     config::read_config()
 }
 
+/// Read the effective Codex config for a project, merging its .codex/config.toml
+pub fn read_project_config(project_path: &std::path::Path) -> Result<ProjectCodexConfig> {
+    config::read_project_config(project_path)
+}
+
 /// List all available sessions from ~/.codex/sessions/
 pub fn list_sessions() -> Result<Vec<CodexSessionSummary>> {
     session::list_sessions()
@@ -36,6 +43,35 @@ pub fn get_session(session_id: &str) -> Result<CodexSession> {
     session::get_session(session_id)
 }
 
+/// Get full session details by ID, reporting `(bytes_read, total_bytes)` while parsing
+pub fn get_session_with_progress<F>(session_id: &str, on_progress: F) -> Result<CodexSession>
+where
+    F: FnMut(u64, u64),
+{
+    session::get_session_with_progress(session_id, on_progress)
+}
+
+/// Get a window of a session's messages for scrollback
+pub fn get_session_items(
+    cache: &SessionItemsCache,
+    session_id: &str,
+    before_cursor: Option<&str>,
+    after_cursor: Option<&str>,
+    limit: usize,
+) -> Result<SessionItemsPage> {
+    session::get_session_items(cache, session_id, before_cursor, after_cursor, limit)
+}
+
+/// Dry-parse a session file and report every problem found
+pub fn validate_session(session_id: &str) -> Result<SessionValidation> {
+    session::validate_session(session_id)
+}
+
+/// Get the raw JSONL line for one message in a session
+pub fn get_session_message_raw(session_id: &str, message_index: usize) -> Result<String> {
+    session::get_session_message_raw(session_id, message_index)
+}
+
 /// Search sessions by keyword
 pub fn search_sessions(query: &str, limit: usize) -> Result<Vec<CodexSessionSummary>> {
     session::search_sessions(query, limit)
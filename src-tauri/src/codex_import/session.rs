@@ -9,6 +9,8 @@ use serde::{Deserialize, Serialize};
 use std::fs;
 use std::io::{BufRead, BufReader};
 use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::SystemTime;
 
 /// Session summary for list views
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -270,11 +272,19 @@ fn extract_user_text(content: &serde_json::Value) -> Option<String> {
 
 /// Get full session details by ID
 pub fn get_session(session_id: &str) -> Result<CodexSession> {
+    get_session_with_progress(session_id, |_, _| {})
+}
+
+/// Get full session details by ID, reporting `(bytes_read, total_bytes)` while parsing
+pub fn get_session_with_progress<F>(session_id: &str, on_progress: F) -> Result<CodexSession>
+where
+    F: FnMut(u64, u64),
+{
     let sessions_dir = super::get_codex_dir().join("sessions");
 
     // Find the session file by ID
     let file_path = find_session_file(&sessions_dir, session_id)?;
-    parse_full_session(&file_path)
+    parse_full_session(&file_path, on_progress)
 }
 
 /// Find session file by ID
@@ -298,24 +308,49 @@ fn find_session_file(dir: &Path, session_id: &str) -> Result<PathBuf> {
         }
     }
 
-    Err(Error::SessionNotFound(format!("Codex CLI session not found: {session_id}")))
+    Err(Error::SessionNotFound(format!(
+        "Codex CLI session not found: {session_id}"
+    )))
 }
 
+/// Bytes parsed between progress reports from `parse_full_session`
+const PARSE_PROGRESS_INTERVAL: u64 = 256 * 1024;
+
 /// Parse full session from file
-fn parse_full_session(path: &Path) -> Result<CodexSession> {
-    let file = fs::File::open(path).map_err(|e| {
-        Error::Other(format!("Failed to open session file: {e}"))
-    })?;
+///
+/// `on_progress` is called with `(bytes_read, total_bytes)` roughly every
+/// `PARSE_PROGRESS_INTERVAL` bytes, and once more when the whole file is read.
+fn parse_full_session<F>(path: &Path, mut on_progress: F) -> Result<CodexSession>
+where
+    F: FnMut(u64, u64),
+{
+    let file = fs::File::open(path)
+        .map_err(|e| Error::Other(format!("Failed to open session file: {e}")))?;
 
     let file_size = file.metadata().map(|m| m.len()).unwrap_or(0);
-    let reader = BufReader::new(file);
+    let mut reader = BufReader::new(file);
 
     let mut meta: Option<CodexSessionMeta> = None;
     let mut messages: Vec<SessionMessage> = Vec::new();
     let mut first_user_message: Option<String> = None;
 
-    for line in reader.lines() {
-        let line = match line {
+    let mut bytes_read: u64 = 0;
+    let mut next_report = PARSE_PROGRESS_INTERVAL;
+    let mut buf = Vec::new();
+
+    loop {
+        buf.clear();
+        match reader.read_until(b'\n', &mut buf) {
+            Ok(0) | Err(_) => break,
+            Ok(n) => bytes_read += n as u64,
+        }
+        if bytes_read >= next_report {
+            on_progress(bytes_read, file_size.max(bytes_read));
+            next_report = bytes_read + PARSE_PROGRESS_INTERVAL;
+        }
+
+        // Lines that aren't valid UTF-8 are skipped like malformed JSON
+        let line = match std::str::from_utf8(&buf) {
             Ok(l) => l,
             Err(_) => continue,
         };
@@ -324,7 +359,7 @@ fn parse_full_session(path: &Path) -> Result<CodexSession> {
             continue;
         }
 
-        let event: RawEvent = match serde_json::from_str(&line) {
+        let event: RawEvent = match serde_json::from_str(line) {
             Ok(e) => e,
             Err(_) => continue,
         };
@@ -356,6 +391,8 @@ fn parse_full_session(path: &Path) -> Result<CodexSession> {
         }
     }
 
+    on_progress(bytes_read, file_size.max(bytes_read));
+
     let meta = meta.ok_or_else(|| Error::Other("Session has no metadata".to_string()))?;
 
     let project_name = Path::new(&meta.cwd)
@@ -385,6 +422,163 @@ fn parse_full_session(path: &Path) -> Result<CodexSession> {
     })
 }
 
+/// A window of session messages for scrollback, ordered oldest to newest
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SessionItemsPage {
+    /// Messages in this window, oldest first
+    pub items: Vec<SessionMessage>,
+    /// Index of the first item in the window
+    pub start_index: usize,
+    /// Total number of messages in the session
+    pub total: usize,
+    /// Pass as `before_cursor` to load older items (None at the start of the session)
+    pub older_cursor: Option<String>,
+    /// Pass as `after_cursor` to load newer items (None at the end of the session)
+    pub newer_cursor: Option<String>,
+}
+
+/// Parse an opaque item cursor (an item index) and bounds-check it
+fn parse_item_cursor(cursor: &str, total: usize) -> Result<usize> {
+    let index: usize = cursor
+        .parse()
+        .map_err(|_| Error::Other(format!("Invalid item cursor: {cursor}")))?;
+    if index > total {
+        return Err(Error::Other(format!("Item cursor out of range: {cursor}")));
+    }
+    Ok(index)
+}
+
+/// Select a window of at most `limit` items
+///
+/// - `before`: return the items immediately preceding this index
+/// - `after`: return the items starting at this index
+/// - neither: return the newest `limit` items
+fn window_items(
+    messages: &[SessionMessage],
+    before: Option<usize>,
+    after: Option<usize>,
+    limit: usize,
+) -> SessionItemsPage {
+    let total = messages.len();
+    let (start, end) = match (before, after) {
+        (_, Some(after)) => (after, (after + limit).min(total)),
+        (Some(before), None) => (before.saturating_sub(limit), before),
+        (None, None) => (total.saturating_sub(limit), total),
+    };
+
+    let items = messages[start..end].to_vec();
+
+    SessionItemsPage {
+        items,
+        start_index: start,
+        total,
+        older_cursor: (start > 0).then(|| start.to_string()),
+        newer_cursor: (end < total).then(|| end.to_string()),
+    }
+}
+
+/// Messages of the session paged through last, kept while its file is unchanged
+struct CachedSessionItems {
+    session_id: String,
+    path: PathBuf,
+    len: u64,
+    modified: Option<SystemTime>,
+    messages: Arc<Vec<SessionMessage>>,
+}
+
+/// Cache of parsed session messages for scrollback
+///
+/// Paging through a long session would otherwise re-read the whole rollout
+/// file for every page. Only the last session is kept; an entry is reused
+/// while the file's size and modification time are the same, so items a
+/// running session appends are picked up.
+#[derive(Clone, Default)]
+pub struct SessionItemsCache {
+    inner: Arc<parking_lot::Mutex<Option<CachedSessionItems>>>,
+}
+
+impl SessionItemsCache {
+    /// Parsed messages of the session file at `path`, from the cache when it is current
+    fn messages(&self, session_id: &str, path: &Path) -> Result<Arc<Vec<SessionMessage>>> {
+        let metadata = fs::metadata(path)?;
+        let modified = metadata.modified().ok();
+        if let Some(cached) = self.inner.lock().as_ref() {
+            if cached.session_id == session_id
+                && cached.path == path
+                && cached.len == metadata.len()
+                && cached.modified == modified
+            {
+                return Ok(cached.messages.clone());
+            }
+        }
+
+        let messages = Arc::new(parse_full_session(path, |_, _| {})?.messages);
+        *self.inner.lock() = Some(CachedSessionItems {
+            session_id: session_id.to_string(),
+            path: path.to_path_buf(),
+            len: metadata.len(),
+            modified,
+            messages: messages.clone(),
+        });
+        Ok(messages)
+    }
+
+    /// Path of the cached session's file, if `session_id` is the cached session
+    fn cached_path(&self, session_id: &str) -> Option<PathBuf> {
+        self.inner
+            .lock()
+            .as_ref()
+            .filter(|cached| cached.session_id == session_id)
+            .map(|cached| cached.path.clone())
+    }
+}
+
+/// Get a window of a session's messages for incremental scrollback
+///
+/// Only one of `before_cursor` and `after_cursor` may be given.
+pub fn get_session_items(
+    cache: &SessionItemsCache,
+    session_id: &str,
+    before_cursor: Option<&str>,
+    after_cursor: Option<&str>,
+    limit: usize,
+) -> Result<SessionItemsPage> {
+    if before_cursor.is_some() && after_cursor.is_some() {
+        return Err(Error::Other(
+            "Pass either before_cursor or after_cursor, not both".to_string(),
+        ));
+    }
+
+    let path = match cache.cached_path(session_id).filter(|path| path.is_file()) {
+        Some(path) => path,
+        None => find_session_file(&super::get_codex_dir().join("sessions"), session_id)?,
+    };
+    session_items_page(cache, session_id, &path, before_cursor, after_cursor, limit)
+}
+
+/// Page through the session file at `path`
+fn session_items_page(
+    cache: &SessionItemsCache,
+    session_id: &str,
+    path: &Path,
+    before_cursor: Option<&str>,
+    after_cursor: Option<&str>,
+    limit: usize,
+) -> Result<SessionItemsPage> {
+    let messages = cache.messages(session_id, path)?;
+    let total = messages.len();
+
+    let before = before_cursor
+        .map(|c| parse_item_cursor(c, total))
+        .transpose()?;
+    let after = after_cursor
+        .map(|c| parse_item_cursor(c, total))
+        .transpose()?;
+
+    Ok(window_items(&messages, before, after, limit))
+}
+
 /// Search sessions by keyword
 pub fn search_sessions(query: &str, limit: usize) -> Result<Vec<CodexSessionSummary>> {
     let all_sessions = list_sessions()?;
@@ -408,6 +602,180 @@ pub fn search_sessions(query: &str, limit: usize) -> Result<Vec<CodexSessionSumm
     Ok(filtered)
 }
 
+/// Kind of problem found while validating a session file
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum SessionValidationErrorKind {
+    /// The file could not be opened or a line could not be read
+    Io,
+    /// A line is not valid JSON
+    InvalidJson,
+    /// A line is JSON but not a well-formed event
+    InvalidEvent,
+    /// The session_meta payload could not be parsed
+    InvalidMeta,
+    /// The file has no session_meta event
+    MissingMeta,
+}
+
+/// A single problem found while validating a session file
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SessionValidationError {
+    /// 1-based line number, if the problem is tied to a line
+    pub line: Option<usize>,
+    pub kind: SessionValidationErrorKind,
+    pub message: String,
+}
+
+/// Result of a dry parse of a session file
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SessionValidation {
+    /// Session file path
+    pub file_path: String,
+    /// Whether the session can be loaded (bad lines are skipped, so this requires metadata)
+    pub parseable: bool,
+    /// Whether a session_meta event was found and parsed
+    pub meta_present: bool,
+    /// Number of response items that parsed
+    pub message_count: usize,
+    /// Problems found, in file order
+    pub errors: Vec<SessionValidationError>,
+}
+
+/// Parse a session file line by line, collecting every problem instead of skipping it
+fn validate_session_file(path: &Path) -> SessionValidation {
+    let mut validation = SessionValidation {
+        file_path: path.to_string_lossy().to_string(),
+        parseable: false,
+        meta_present: false,
+        message_count: 0,
+        errors: Vec::new(),
+    };
+
+    let file = match fs::File::open(path) {
+        Ok(f) => f,
+        Err(e) => {
+            validation.errors.push(SessionValidationError {
+                line: None,
+                kind: SessionValidationErrorKind::Io,
+                message: format!("Failed to open session file: {e}"),
+            });
+            return validation;
+        }
+    };
+
+    for (index, line) in BufReader::new(file).lines().enumerate() {
+        let line_number = index + 1;
+        let mut push = |kind, message: String| {
+            validation.errors.push(SessionValidationError {
+                line: Some(line_number),
+                kind,
+                message,
+            });
+        };
+
+        let line = match line {
+            Ok(l) => l,
+            Err(e) => {
+                push(
+                    SessionValidationErrorKind::Io,
+                    format!("Failed to read line: {e}"),
+                );
+                continue;
+            }
+        };
+
+        if line.trim().is_empty() {
+            continue;
+        }
+
+        let value: serde_json::Value = match serde_json::from_str(&line) {
+            Ok(v) => v,
+            Err(e) => {
+                push(SessionValidationErrorKind::InvalidJson, e.to_string());
+                continue;
+            }
+        };
+
+        let event: RawEvent = match serde_json::from_value(value) {
+            Ok(e) => e,
+            Err(e) => {
+                push(SessionValidationErrorKind::InvalidEvent, e.to_string());
+                continue;
+            }
+        };
+
+        match event.event_type.as_str() {
+            "session_meta" => match serde_json::from_value::<CodexSessionMeta>(event.payload) {
+                Ok(_) => validation.meta_present = true,
+                Err(e) => push(SessionValidationErrorKind::InvalidMeta, e.to_string()),
+            },
+            "response_item" => match serde_json::from_value::<ResponseItem>(event.payload) {
+                Ok(_) => validation.message_count += 1,
+                Err(e) => push(SessionValidationErrorKind::InvalidEvent, e.to_string()),
+            },
+            _ => {}
+        }
+    }
+
+    if !validation.meta_present {
+        validation.errors.push(SessionValidationError {
+            line: None,
+            kind: SessionValidationErrorKind::MissingMeta,
+            message: "Session has no metadata".to_string(),
+        });
+    }
+    validation.parseable = validation.meta_present;
+
+    validation
+}
+
+/// Dry-parse a session by ID and report every problem found
+pub fn validate_session(session_id: &str) -> Result<SessionValidation> {
+    let sessions_dir = super::get_codex_dir().join("sessions");
+    let file_path = find_session_file(&sessions_dir, session_id)?;
+    Ok(validate_session_file(&file_path))
+}
+
+/// Read the raw JSONL line of the `index`th message in a session file
+///
+/// Messages are counted the same way as in `parse_full_session`, so the index
+/// matches the one used by the items API. Reading stops at the target line.
+fn read_message_line(path: &Path, index: usize) -> Result<String> {
+    let file = fs::File::open(path)
+        .map_err(|e| Error::Other(format!("Failed to open session file: {e}")))?;
+
+    let mut count = 0;
+    for line in BufReader::new(file).lines() {
+        let Ok(line) = line else { continue };
+        let Ok(event) = serde_json::from_str::<RawEvent>(&line) else {
+            continue;
+        };
+        if event.event_type != "response_item"
+            || serde_json::from_value::<ResponseItem>(event.payload).is_err()
+        {
+            continue;
+        }
+        if count == index {
+            return Ok(line);
+        }
+        count += 1;
+    }
+
+    Err(Error::Other(format!(
+        "Message index {index} is out of range (session has {count} messages)"
+    )))
+}
+
+/// Get the raw JSONL line for one message in a session
+pub fn get_session_message_raw(session_id: &str, message_index: usize) -> Result<String> {
+    let sessions_dir = super::get_codex_dir().join("sessions");
+    let file_path = find_session_file(&sessions_dir, session_id)?;
+    read_message_line(&file_path, message_index)
+}
+
 /// Delete a session file
 pub fn delete_session(session_id: &str) -> Result<()> {
     let sessions_dir = super::get_codex_dir().join("sessions");
@@ -420,3 +788,198 @@ pub fn delete_session(session_id: &str) -> Result<()> {
     tracing::info!("Deleted Codex CLI session: {}", session_id);
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn messages(count: usize) -> Vec<SessionMessage> {
+        (0..count)
+            .map(|i| SessionMessage {
+                timestamp: format!("t{i}"),
+                message_type: "message".to_string(),
+                role: Some("user".to_string()),
+                content: serde_json::json!(i),
+            })
+            .collect()
+    }
+
+    fn indices(page: &SessionItemsPage) -> Vec<i64> {
+        page.items
+            .iter()
+            .map(|m| m.content.as_i64().unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn test_window_items_defaults_to_newest() {
+        let page = window_items(&messages(10), None, None, 3);
+        assert_eq!(indices(&page), vec![7, 8, 9]);
+        assert_eq!(page.start_index, 7);
+        assert_eq!(page.older_cursor.as_deref(), Some("7"));
+        assert_eq!(page.newer_cursor, None);
+    }
+
+    #[test]
+    fn test_window_items_pages_back_to_the_start() {
+        let page = window_items(&messages(10), Some(7), None, 3);
+        assert_eq!(indices(&page), vec![4, 5, 6]);
+        assert_eq!(page.older_cursor.as_deref(), Some("4"));
+        assert_eq!(page.newer_cursor.as_deref(), Some("7"));
+
+        // Final page is short and has no older cursor
+        let page = window_items(&messages(10), Some(2), None, 3);
+        assert_eq!(indices(&page), vec![0, 1]);
+        assert_eq!(page.older_cursor, None);
+        assert_eq!(page.newer_cursor.as_deref(), Some("2"));
+    }
+
+    #[test]
+    fn test_window_items_pages_forward_to_the_end() {
+        let page = window_items(&messages(10), None, Some(8), 3);
+        assert_eq!(indices(&page), vec![8, 9]);
+        assert_eq!(page.older_cursor.as_deref(), Some("8"));
+        assert_eq!(page.newer_cursor, None);
+    }
+
+    #[test]
+    fn test_parse_item_cursor_bounds() {
+        assert_eq!(parse_item_cursor("5", 10).unwrap(), 5);
+        assert_eq!(parse_item_cursor("10", 10).unwrap(), 10);
+        assert!(parse_item_cursor("11", 10).is_err());
+        assert!(parse_item_cursor("abc", 10).is_err());
+    }
+
+    const META_LINE: &str = r#"{"timestamp":"2025-01-01T00:00:00Z","type":"session_meta","payload":{"id":"abc","timestamp":"2025-01-01T00:00:00Z","cwd":"/tmp/project"}}"#;
+    const USER_LINE: &str = r#"{"timestamp":"2025-01-01T00:00:01Z","type":"response_item","payload":{"type":"message","role":"user","content":[{"type":"input_text","text":"hi"}]}}"#;
+
+    fn write_session(lines: &[&str]) -> (tempfile::TempDir, PathBuf) {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let path = temp_dir
+            .path()
+            .join("rollout-2025-01-01T00-00-00-abc.jsonl");
+        fs::write(&path, lines.join("\n")).unwrap();
+        (temp_dir, path)
+    }
+
+    #[test]
+    fn test_session_items_reuse_parse_until_file_changes() {
+        let (_dir, path) = write_session(&[META_LINE, USER_LINE, USER_LINE]);
+        let cache = SessionItemsCache::default();
+
+        let page = session_items_page(&cache, "abc", &path, None, None, 1).unwrap();
+        assert_eq!((page.total, page.start_index), (2, 1));
+        let first = cache.messages("abc", &path).unwrap();
+        assert!(Arc::ptr_eq(&first, &cache.messages("abc", &path).unwrap()));
+        assert_eq!(cache.cached_path("abc").as_deref(), Some(path.as_path()));
+        assert_eq!(cache.cached_path("other"), None);
+
+        // A running session appends items
+        let mut file = fs::OpenOptions::new().append(true).open(&path).unwrap();
+        std::io::Write::write_all(&mut file, format!("\n{USER_LINE}").as_bytes()).unwrap();
+        drop(file);
+        let page = session_items_page(&cache, "abc", &path, Some("2"), None, 5).unwrap();
+        assert_eq!(page.total, 3);
+        assert_eq!(page.items.len(), 2);
+    }
+
+    #[test]
+    fn test_session_items_reject_both_cursors() {
+        let err = get_session_items(
+            &SessionItemsCache::default(),
+            "abc",
+            Some("1"),
+            Some("2"),
+            5,
+        )
+        .unwrap_err();
+        assert!(err.to_string().contains("not both"), "{err}");
+    }
+
+    #[test]
+    fn test_validate_session_file_reports_malformed_line() {
+        let (_dir, path) = write_session(&[
+            META_LINE,
+            "{not json",
+            USER_LINE,
+            r#"{"type":"response_item"}"#,
+        ]);
+
+        let validation = validate_session_file(&path);
+
+        assert!(validation.parseable);
+        assert!(validation.meta_present);
+        assert_eq!(validation.message_count, 1);
+        assert_eq!(validation.errors.len(), 2);
+        assert_eq!(validation.errors[0].line, Some(2));
+        assert_eq!(
+            validation.errors[0].kind,
+            SessionValidationErrorKind::InvalidJson
+        );
+        assert_eq!(validation.errors[1].line, Some(4));
+        assert_eq!(
+            validation.errors[1].kind,
+            SessionValidationErrorKind::InvalidEvent
+        );
+    }
+
+    #[test]
+    fn test_validate_session_file_reports_missing_meta() {
+        let (_dir, path) = write_session(&[USER_LINE, USER_LINE]);
+
+        let validation = validate_session_file(&path);
+
+        assert!(!validation.parseable);
+        assert!(!validation.meta_present);
+        assert_eq!(validation.message_count, 2);
+        assert_eq!(validation.errors.len(), 1);
+        assert_eq!(
+            validation.errors[0].kind,
+            SessionValidationErrorKind::MissingMeta
+        );
+        assert_eq!(validation.errors[0].line, None);
+    }
+
+    #[test]
+    fn test_validate_session_file_clean() {
+        let (_dir, path) = write_session(&[META_LINE, USER_LINE, ""]);
+
+        let validation = validate_session_file(&path);
+
+        assert!(validation.parseable);
+        assert!(validation.errors.is_empty());
+    }
+
+    #[test]
+    fn test_parse_full_session_reports_progress() {
+        let lines: Vec<&str> = std::iter::once(META_LINE)
+            .chain(std::iter::repeat_n(USER_LINE, 20_000))
+            .collect();
+        let (_dir, path) = write_session(&lines);
+        let total = fs::metadata(&path).unwrap().len();
+        assert!(total > 4 * PARSE_PROGRESS_INTERVAL);
+
+        let mut reports = Vec::new();
+        let session = parse_full_session(&path, |read, of| reports.push((read, of))).unwrap();
+
+        assert_eq!(session.messages.len(), 20_000);
+        assert_eq!(session.summary.message_count, 20_000);
+        assert!(reports.len() > 4, "{} progress reports", reports.len());
+        assert!(reports.windows(2).all(|w| w[0].0 <= w[1].0));
+        assert!(reports.iter().all(|&(_, of)| of == total));
+        assert_eq!(reports.last(), Some(&(total, total)));
+    }
+
+    #[test]
+    fn test_read_message_line_returns_verbatim_line() {
+        let assistant_line = r#"{"timestamp":"2025-01-01T00:00:02Z","type":"response_item","payload":{"type":"message","role":"assistant","content":[{"type":"output_text","text":"hello"}]}}"#;
+        let (_dir, path) = write_session(&[META_LINE, USER_LINE, "{not json", assistant_line]);
+
+        assert_eq!(read_message_line(&path, 0).unwrap(), USER_LINE);
+        assert_eq!(read_message_line(&path, 1).unwrap(), assistant_line);
+
+        let err = read_message_line(&path, 2).unwrap_err().to_string();
+        assert!(err.contains("out of range"));
+        assert!(err.contains("2 messages"));
+    }
+}
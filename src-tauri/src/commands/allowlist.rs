@@ -1,7 +1,10 @@
 //! Command allowlist management commands
 
+use std::collections::HashMap;
+
 use tauri::State;
 
+use crate::database::AllowlistImportReport;
 use crate::state::AppState;
 use crate::Result;
 
@@ -33,3 +36,151 @@ pub async fn remove_from_allowlist(
 ) -> Result<()> {
     state.database.remove_from_allowlist(&project_id, &command_pattern)
 }
+
+/// Whether an allowlist pattern covers a command
+///
+/// `*` matches any run of characters. A pattern without wildcards matches
+/// the command itself or the command with extra arguments, so `cargo test`
+/// covers `cargo test --all` but not `cargo tests`.
+fn pattern_matches(pattern: &str, command: &str) -> bool {
+    let pattern = pattern.trim();
+    let command = command.trim();
+    if !pattern.contains('*') {
+        return command == pattern
+            || command
+                .strip_prefix(pattern)
+                .is_some_and(|rest| rest.starts_with(char::is_whitespace));
+    }
+
+    let parts: Vec<&str> = pattern.split('*').collect();
+    let (first, last) = (parts[0], parts[parts.len() - 1]);
+    let Some(mut rest) = command.strip_prefix(first) else {
+        return false;
+    };
+    for part in &parts[1..parts.len() - 1] {
+        match rest.find(part) {
+            Some(index) => rest = &rest[index + part.len()..],
+            None => return false,
+        }
+    }
+    rest.len() >= last.len() && rest.ends_with(last)
+}
+
+/// Patterns that match none of `commands`, in their original order
+fn unused_patterns(patterns: Vec<String>, commands: &[String]) -> Vec<String> {
+    patterns
+        .into_iter()
+        .filter(|pattern| {
+            !commands
+                .iter()
+                .any(|command| pattern_matches(pattern, command))
+        })
+        .collect()
+}
+
+/// List a project's allowlist patterns that no command has matched in the last `since_days` days
+///
+/// Based on the recorded command history, so commands run before history
+/// was kept don't count. Defaults to 30 days.
+#[tauri::command]
+pub async fn find_unused_allowlist_patterns(
+    state: State<'_, AppState>,
+    project_id: String,
+    since_days: Option<i64>,
+) -> Result<Vec<String>> {
+    crate::commands::projects::validate_id(&project_id, "project_id")?;
+    let days = since_days.unwrap_or(30);
+    if days < 0 {
+        return Err(crate::Error::Other(
+            "since_days must not be negative".to_string(),
+        ));
+    }
+    let since = chrono::Utc::now().timestamp() - days * 86400;
+
+    let patterns = state.database.get_allowlist(&project_id)?;
+    let commands = state
+        .database
+        .get_project_commands_run_since(&project_id, since)?;
+    Ok(unused_patterns(patterns, &commands))
+}
+
+/// Export the allowlists of every project, keyed by project path
+#[tauri::command]
+pub async fn export_all_allowlists(
+    state: State<'_, AppState>,
+) -> Result<HashMap<String, Vec<String>>> {
+    state.database.get_all_allowlists()
+}
+
+/// Restore allowlists exported by `export_all_allowlists`
+///
+/// With `replace`, existing patterns of each imported project are discarded;
+/// otherwise they are merged. Paths with no matching project are reported back.
+#[tauri::command]
+pub async fn import_all_allowlists(
+    state: State<'_, AppState>,
+    allowlists: HashMap<String, Vec<String>>,
+    replace: Option<bool>,
+) -> Result<AllowlistImportReport> {
+    if allowlists.keys().any(|path| path.is_empty()) {
+        return Err(crate::Error::Other(
+            "Allowlist backup has an empty project path".to_string(),
+        ));
+    }
+    let report = state
+        .database
+        .import_allowlists(&allowlists, replace.unwrap_or(false))?;
+
+    tracing::info!(
+        "Imported {} allowlist patterns ({} projects skipped)",
+        report.imported,
+        report.skipped_projects.len()
+    );
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::{Database, Project, SessionMetadata};
+
+    #[test]
+    fn test_pattern_matches() {
+        assert!(pattern_matches("cargo test", "cargo test"));
+        assert!(pattern_matches("cargo test", "cargo test --all"));
+        assert!(!pattern_matches("cargo test", "cargo tests"));
+        assert!(pattern_matches("npm run *", "npm run build"));
+        assert!(pattern_matches(
+            "git * --dry-run",
+            "git clean -fd --dry-run"
+        ));
+        assert!(!pattern_matches("git * --dry-run", "git clean -fd"));
+    }
+
+    #[test]
+    fn test_unused_patterns_from_history() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let db = Database::new(&temp_dir.path().join("test.db")).unwrap();
+        let project = Project::new("/tmp/project");
+        db.insert_project(&project).unwrap();
+        db.upsert_session_metadata(&SessionMetadata::new("s1", &project.id))
+            .unwrap();
+        for pattern in ["cargo test", "npm run *", "make", "rm -rf *"] {
+            db.add_to_allowlist(&project.id, pattern).unwrap();
+        }
+
+        db.record_command("s1", "cargo test --workspace", "accept", 1_000)
+            .unwrap();
+        db.record_command("s1", "npm run lint", "acceptForSession", 1_000)
+            .unwrap();
+        // Too old, and declined, so neither counts
+        db.record_command("s1", "make", "accept", 10).unwrap();
+        db.record_command("s1", "rm -rf build", "decline", 1_000)
+            .unwrap();
+
+        let commands = db.get_project_commands_run_since(&project.id, 500).unwrap();
+        let mut unused = unused_patterns(db.get_allowlist(&project.id).unwrap(), &commands);
+        unused.sort();
+        assert_eq!(unused, vec!["make".to_string(), "rm -rf *".to_string()]);
+    }
+}
@@ -1,9 +1,13 @@
 //! App server management commands
 
+use std::collections::HashSet;
+use std::future::Future;
+
 use serde::Serialize;
-use tauri::State;
+use tauri::{Emitter, State};
 
-use crate::app_server::ipc_bridge::{AccountInfo, TurnStartResponse};
+use crate::app_server::ipc_bridge::{AccountInfo, AccountListResponse, TurnStartResponse};
+use crate::app_server::{AccountCache, CachedAccountInfo};
 use crate::state::AppState;
 use crate::Result;
 
@@ -13,6 +17,8 @@ use crate::Result;
 pub struct ServerStatus {
     pub is_running: bool,
     pub version: Option<String>,
+    /// Why the last start attempt failed, if it did
+    pub startup_error: Option<crate::AppServerStartupInfo>,
 }
 
 /// Get the app server status
@@ -25,6 +31,144 @@ pub async fn get_server_status(state: State<'_, AppState>) -> Result<ServerStatu
     Ok(ServerStatus {
         is_running,
         version: None, // TODO: Get version from app-server
+        startup_error: state.last_startup_error.lock().clone(),
+    })
+}
+
+/// A running process, as reported by the OS process list
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ProcessEntry {
+    pub pid: u32,
+    #[serde(skip)]
+    pub ppid: u32,
+    pub command: String,
+}
+
+/// Other codex app-server processes that may contend for session files
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct InstanceConflictReport {
+    /// Whether the process list could be read on this platform
+    pub supported: bool,
+    pub conflicts: Vec<ProcessEntry>,
+}
+
+/// Read the process list with `ps`, or None where that isn't available
+fn list_processes() -> Option<Vec<ProcessEntry>> {
+    if cfg!(windows) {
+        return None;
+    }
+
+    let output = std::process::Command::new("ps")
+        .args(["-A", "-o", "pid=,ppid=,args="])
+        .output()
+        .ok()
+        .filter(|o| o.status.success())?;
+
+    let processes = String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .filter_map(|line| {
+            let mut fields = line.split_whitespace();
+            let pid = fields.next()?.parse().ok()?;
+            let ppid = fields.next()?.parse().ok()?;
+            let command = fields.collect::<Vec<_>>().join(" ");
+            Some(ProcessEntry { pid, ppid, command })
+        })
+        .collect();
+    Some(processes)
+}
+
+/// Whether a command line is `codex app-server` (possibly behind a wrapper like node)
+fn is_codex_app_server(command: &str) -> bool {
+    let is_codex = |arg: &str| {
+        std::path::Path::new(arg)
+            .file_stem()
+            .and_then(|s| s.to_str())
+            .is_some_and(|name| name == "codex" || name.starts_with("codex-"))
+    };
+    command.split_whitespace().any(is_codex)
+        && command.split_whitespace().any(|arg| arg == "app-server")
+}
+
+/// App-server processes not started by `own_pid` (directly or through a wrapper)
+fn find_conflicting_instances(processes: &[ProcessEntry], own_pid: u32) -> Vec<ProcessEntry> {
+    let parents: std::collections::HashMap<u32, u32> =
+        processes.iter().map(|p| (p.pid, p.ppid)).collect();
+    let is_ours = |mut pid: u32| {
+        // Bounded walk up the parent chain in case the list is inconsistent
+        for _ in 0..processes.len() {
+            if pid == own_pid {
+                return true;
+            }
+            match parents.get(&pid) {
+                Some(&ppid) if ppid != pid && ppid != 0 => pid = ppid,
+                _ => return false,
+            }
+        }
+        false
+    };
+
+    processes
+        .iter()
+        .filter(|p| is_codex_app_server(&p.command) && !is_ours(p.pid))
+        .cloned()
+        .collect()
+}
+
+/// Check for codex app-server processes started outside this app
+///
+/// Running the CLI's app-server alongside the desktop app can cause session
+/// file contention. Reports `supported: false` where the process list can't
+/// be read.
+#[tauri::command]
+pub async fn detect_conflicting_instances() -> Result<InstanceConflictReport> {
+    let processes = tokio::task::spawn_blocking(list_processes)
+        .await
+        .map_err(|e| crate::Error::Other(format!("Task join error: {e}")))?;
+
+    let Some(processes) = processes else {
+        return Ok(InstanceConflictReport {
+            supported: false,
+            conflicts: Vec::new(),
+        });
+    };
+
+    let conflicts = find_conflicting_instances(&processes, std::process::id());
+    if !conflicts.is_empty() {
+        tracing::warn!("Found {} other codex app-server processes", conflicts.len());
+    }
+    Ok(InstanceConflictReport {
+        supported: true,
+        conflicts,
+    })
+}
+
+/// Desktop app version and build metadata
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AppVersion {
+    pub version: String,
+    /// Short git commit the app was built from, if known
+    pub git_commit: Option<String>,
+    /// Cargo build profile ("debug" or "release")
+    pub build_profile: String,
+}
+
+/// Get the desktop app's version (distinct from the codex CLI version)
+#[tauri::command]
+pub async fn get_app_version() -> Result<AppVersion> {
+    let build_profile =
+        option_env!("CODEX_DESKTOP_BUILD_PROFILE").unwrap_or(if cfg!(debug_assertions) {
+            "debug"
+        } else {
+            "release"
+        });
+
+    Ok(AppVersion {
+        version: env!("CARGO_PKG_VERSION").to_string(),
+        git_commit: option_env!("CODEX_DESKTOP_GIT_COMMIT").map(str::to_string),
+        build_profile: build_profile.to_string(),
     })
 }
 
@@ -35,9 +179,73 @@ pub async fn restart_server(state: State<'_, AppState>) -> Result<()> {
     Ok(())
 }
 
-/// Get account information
+/// Recognized errors in the running app server's recent stderr, oldest first
+///
+/// The same issues are emitted as events (`auth-expired`, `config-error`)
+/// as they happen; this lets the UI catch up on ones it missed.
 #[tauri::command]
-pub async fn get_account_info(state: State<'_, AppState>) -> Result<AccountInfo> {
+pub async fn get_stderr_issues(
+    state: State<'_, AppState>,
+) -> Result<Vec<crate::app_server::StderrIssue>> {
+    let server = state.app_server.read().await;
+    let tail = server.as_ref().map(|p| p.stderr_tail()).unwrap_or_default();
+    let mut issues: Vec<crate::app_server::StderrIssue> = Vec::new();
+    for issue in tail
+        .iter()
+        .filter_map(|line| crate::app_server::classify_stderr_line(line))
+    {
+        if !issues.contains(&issue) {
+            issues.push(issue);
+        }
+    }
+    Ok(issues)
+}
+
+/// Enable or disable mirroring of raw JSON-RPC traffic as debug events
+///
+/// Incoming lines are emitted as `ipc-debug-in` and outgoing lines as
+/// `ipc-debug-out`. Off by default since the traffic may contain user content.
+#[tauri::command]
+pub async fn set_ipc_debug(state: State<'_, AppState>, enabled: bool) -> Result<()> {
+    state
+        .ipc_debug
+        .store(enabled, std::sync::atomic::Ordering::Relaxed);
+    tracing::info!(
+        "IPC debug mode {}",
+        if enabled { "enabled" } else { "disabled" }
+    );
+    Ok(())
+}
+
+/// Emit the last `count` app-server notifications again, to the calling window only
+///
+/// Lets a window that subscribed late (e.g. after a reload mid-turn) rebuild
+/// its state. Events keep their original names and payloads and are emitted
+/// oldest first. Approval requests and account or login notifications are
+/// never replayed. Returns the number of events emitted.
+#[tauri::command]
+pub async fn replay_recent_events(
+    state: State<'_, AppState>,
+    window: tauri::Window,
+    count: usize,
+) -> Result<usize> {
+    let events = state
+        .recent_events
+        .lock()
+        .as_ref()
+        .map(|recent| recent.recent(count))
+        .unwrap_or_default();
+
+    for event in &events {
+        if let Err(e) = window.emit_to(window.label(), &event.name, &event.payload) {
+            tracing::warn!("Failed to replay event {}: {}", event.name, e);
+        }
+    }
+    Ok(events.len())
+}
+
+/// Read the account from the app-server and cache it
+async fn read_account_info(state: &AppState) -> Result<CachedAccountInfo> {
     // Ensure app-server is running
     state.start_app_server().await?;
 
@@ -46,11 +254,144 @@ pub async fn get_account_info(state: State<'_, AppState>) -> Result<AccountInfo>
         .as_mut()
         .ok_or_else(|| crate::Error::AppServer("App server not running".to_string()))?;
 
+    // Taken before the request, so a login or logout landing meanwhile wins
+    let generation = state.account_cache.generation();
     // Empty params for account/read
     let response: AccountInfo = server
         .send_request("account/read", serde_json::json!({}))
         .await?;
 
+    Ok(state.account_cache.store(&response, generation))
+}
+
+/// Get account information
+#[tauri::command]
+pub async fn get_account_info(state: State<'_, AppState>) -> Result<AccountInfo> {
+    Ok(read_account_info(&state).await?.info)
+}
+
+/// Get account information, answering from the cache while it is current
+///
+/// Cached info older than the TTL is refreshed. If the refresh fails, the
+/// old info is returned with `isStale` set rather than an error; with
+/// `force_refresh` the cache is bypassed and failures are reported.
+#[tauri::command]
+pub async fn get_account_info_cached(
+    state: State<'_, AppState>,
+    force_refresh: Option<bool>,
+) -> Result<CachedAccountInfo> {
+    let force_refresh = force_refresh.unwrap_or(false);
+    let cached = if force_refresh {
+        None
+    } else {
+        state.account_cache.get()
+    };
+    if let Some(cached) = &cached {
+        if !cached.is_stale {
+            return Ok(cached.clone());
+        }
+    }
+
+    match read_account_info(&state).await {
+        Ok(fresh) => Ok(fresh),
+        Err(e) => match cached {
+            Some(stale) => {
+                tracing::warn!("Failed to refresh account info, returning cached: {}", e);
+                Ok(stale)
+            }
+            None => Err(e),
+        },
+    }
+}
+
+/// JSON-RPC "method not found" error code
+const JSON_RPC_METHOD_NOT_FOUND: i64 = -32601;
+
+/// Whether an app-server error means the method isn't implemented
+fn is_method_not_found(err: &crate::Error) -> bool {
+    matches!(
+        err,
+        crate::Error::AppServer(message)
+            if message.starts_with(&format!("JSON-RPC error {JSON_RPC_METHOD_NOT_FOUND}:"))
+    )
+}
+
+/// Interpret an `account/list` result, treating a missing method as "unsupported"
+fn account_list_from_result(result: Result<serde_json::Value>) -> Result<AccountListResponse> {
+    match result {
+        Ok(value) => {
+            let mut list: AccountListResponse = serde_json::from_value(value)?;
+            list.supported = true;
+            Ok(list)
+        }
+        Err(err) if is_method_not_found(&err) => Ok(AccountListResponse::default()),
+        Err(err) => Err(err),
+    }
+}
+
+/// List the accounts stored by the app-server
+///
+/// Servers without multi-account support report `supported: false`.
+#[tauri::command]
+pub async fn list_accounts(state: State<'_, AppState>) -> Result<AccountListResponse> {
+    // Ensure app-server is running
+    state.start_app_server().await?;
+
+    let mut server = state.app_server.write().await;
+    let server = server
+        .as_mut()
+        .ok_or_else(|| crate::Error::AppServer("App server not running".to_string()))?;
+
+    let result = server
+        .send_request("account/list", serde_json::json!({}))
+        .await;
+
+    account_list_from_result(result)
+}
+
+/// Interpret an `account/switch` result, turning a missing method into a clear error
+fn check_account_switch(result: Result<serde_json::Value>) -> Result<()> {
+    match result {
+        Ok(_) => Ok(()),
+        Err(err) if is_method_not_found(&err) => Err(crate::Error::AppServer(
+            "This app-server does not support multiple accounts".to_string(),
+        )),
+        Err(err) => Err(err),
+    }
+}
+
+/// Interpret the `account/read` result that follows a switch
+fn account_after_switch(result: Result<serde_json::Value>) -> Result<AccountInfo> {
+    Ok(serde_json::from_value(result?)?)
+}
+
+/// Switch the active account and return the resulting account info
+#[tauri::command]
+pub async fn switch_account(state: State<'_, AppState>, id: String) -> Result<AccountInfo> {
+    crate::commands::projects::validate_id(&id, "id")?;
+
+    // Ensure app-server is running
+    state.start_app_server().await?;
+
+    let mut server = state.app_server.write().await;
+    let server = server
+        .as_mut()
+        .ok_or_else(|| crate::Error::AppServer("App server not running".to_string()))?;
+
+    let switched = server
+        .send_request("account/switch", serde_json::json!({ "accountId": id }))
+        .await;
+    check_account_switch(switched)?;
+
+    tracing::info!("Switched active account to {}", id);
+
+    let generation = state.account_cache.generation();
+    let read = server
+        .send_request("account/read", serde_json::json!({}))
+        .await;
+    let response = account_after_switch(read)?;
+    state.account_cache.store(&response, generation);
+
     Ok(response)
 }
 
@@ -96,22 +437,116 @@ pub async fn start_login(
     };
 
     let response: LoginResponse = server.send_request("account/login/start", params).await?;
+    record_started_login(&state.pending_logins, &state.account_cache, &response);
 
     Ok(response)
 }
 
+/// Remember a login the server is still waiting on, so it can be cancelled
+///
+/// The cached account is dropped, since the login replaces it.
+fn record_started_login(
+    pending_logins: &parking_lot::Mutex<HashSet<String>>,
+    account_cache: &AccountCache,
+    response: &LoginResponse,
+) {
+    account_cache.invalidate();
+    if let Some(login_id) = &response.login_id {
+        pending_logins.lock().insert(login_id.clone());
+    }
+}
+
+/// Error for cancelling a login that was never started here or has already finished
+fn unknown_login(login_id: &str) -> crate::Error {
+    crate::Error::Other(format!("No pending login with id {login_id}"))
+}
+
+/// Ask the app-server to abort a pending login via `send`, then forget it locally
+///
+/// Logins that aren't pending are rejected without asking the server. The
+/// local entry is dropped whatever the server answers, so a login the server
+/// has already finished or forgotten can still be cleared.
+async fn cancel_pending_login<F, Fut>(
+    pending_logins: &parking_lot::Mutex<HashSet<String>>,
+    login_id: &str,
+    send: F,
+) -> Result<()>
+where
+    F: FnOnce(&'static str, serde_json::Value) -> Fut,
+    Fut: Future<Output = Result<serde_json::Value>>,
+{
+    if !pending_logins.lock().contains(login_id) {
+        return Err(unknown_login(login_id));
+    }
+    let result = send(
+        "account/login/cancel",
+        serde_json::json!({ "loginId": login_id }),
+    )
+    .await;
+    pending_logins.lock().remove(login_id);
+    match result {
+        Ok(_) => Ok(()),
+        Err(err) if is_method_not_found(&err) => Err(crate::Error::AppServer(
+            "This app-server does not support cancelling a login".to_string(),
+        )),
+        Err(err) => Err(err),
+    }
+}
+
+/// Cancel a login that is still waiting for the browser
+///
+/// The app-server stops its callback listener for the login. Only logins
+/// started here that the server hasn't reported as completed can be cancelled.
+#[tauri::command]
+pub async fn cancel_login(state: State<'_, AppState>, login_id: String) -> Result<()> {
+    crate::commands::projects::validate_id(&login_id, "login_id")?;
+
+    let mut server = state.app_server.write().await;
+    let Some(server) = server.as_mut() else {
+        // Stopping the server already forgot its logins
+        return Err(unknown_login(&login_id));
+    };
+
+    cancel_pending_login(&state.pending_logins, &login_id, |method, params| {
+        server.send_request(method, params)
+    })
+    .await?;
+
+    tracing::info!("Cancelled login {}", login_id);
+    Ok(())
+}
+
+/// Log out via `send`, then drop the cached account
+///
+/// The cache is dropped even if the request fails, since the account's
+/// state on the server is then unknown.
+async fn logout_account<F, Fut>(account_cache: &AccountCache, send: F) -> Result<()>
+where
+    F: FnOnce(&'static str, serde_json::Value) -> Fut,
+    Fut: Future<Output = Result<serde_json::Value>>,
+{
+    let result = send("account/logout", serde_json::json!({})).await;
+    account_cache.invalidate();
+    result.map(|_| ())
+}
+
 /// Logout
 #[tauri::command]
 pub async fn logout(state: State<'_, AppState>) -> Result<()> {
     let mut server = state.app_server.write().await;
 
-    if let Some(server) = server.as_mut() {
-        let _: serde_json::Value = server
-            .send_request("account/logout", serde_json::json!({}))
-            .await?;
+    match server.as_mut() {
+        Some(server) => {
+            logout_account(&state.account_cache, |method, params| {
+                server.send_request(method, params)
+            })
+            .await
+        }
+        None => {
+            state.account_cache.invalidate();
+            Ok(())
+        }
     }
-
-    Ok(())
 }
 
 /// Reasoning effort option
@@ -133,6 +568,9 @@ pub struct Model {
     pub supported_reasoning_efforts: Vec<ReasoningEffortOption>,
     pub default_reasoning_effort: String,
     pub is_default: bool,
+    /// Maximum context length in tokens, when the server reports it
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub context_window: Option<u32>,
 }
 
 /// Model list response
@@ -146,6 +584,11 @@ pub struct ModelListResponse {
 /// Get available models
 #[tauri::command]
 pub async fn get_models(state: State<'_, AppState>) -> Result<ModelListResponse> {
+    fetch_models(&state).await
+}
+
+/// Request the model list from the app-server and refresh the model cache
+async fn fetch_models(state: &AppState) -> Result<ModelListResponse> {
     // Ensure app-server is running
     state.start_app_server().await?;
 
@@ -158,11 +601,211 @@ pub async fn get_models(state: State<'_, AppState>) -> Result<ModelListResponse>
         "limit": 100,
     });
 
+    let generation = state.account_cache.generation();
     let response: ModelListResponse = server.send_request("model/list", params).await?;
+    *state.model_cache.write().await = Some((generation, response.data.clone()));
 
     Ok(response)
 }
 
+/// A model list and the account cache generation it was read in
+pub type CachedModels = (u64, Vec<Model>);
+
+/// The cached model list, unless the account changed since it was read
+fn current_models(cache: Option<&CachedModels>, generation: u64) -> Option<Vec<Model>> {
+    cache
+        .filter(|(cached_generation, _)| *cached_generation == generation)
+        .map(|(_, models)| models.clone())
+}
+
+/// Get the cached model list, fetching it from the app-server on first use
+/// and whenever the account cache has been invalidated since
+///
+/// Must not be called while holding the app-server lock.
+pub(crate) async fn cached_models(state: &AppState) -> Result<Vec<Model>> {
+    let generation = state.account_cache.generation();
+    if let Some(models) = current_models(state.model_cache.read().await.as_ref(), generation) {
+        return Ok(models);
+    }
+    Ok(fetch_models(state).await?.data)
+}
+
+/// Maximum number of "did you mean" suggestions for an unknown model
+const MAX_MODEL_SUGGESTIONS: usize = 3;
+
+/// Levenshtein distance between two strings, compared case-insensitively
+fn edit_distance(a: &str, b: &str) -> usize {
+    let a: Vec<char> = a.to_lowercase().chars().collect();
+    let b: Vec<char> = b.to_lowercase().chars().collect();
+    let mut prev: Vec<usize> = (0..=b.len()).collect();
+
+    for (i, ca) in a.iter().enumerate() {
+        let mut curr = vec![i + 1; b.len() + 1];
+        for (j, cb) in b.iter().enumerate() {
+            let cost = usize::from(ca != cb);
+            curr[j + 1] = (prev[j] + cost).min(prev[j + 1] + 1).min(curr[j] + 1);
+        }
+        prev = curr;
+    }
+
+    prev[b.len()]
+}
+
+/// Check a requested model id against the model list
+///
+/// Unknown models are rejected with the closest matches and the full list of
+/// available models, unless `force` is set (e.g. for experimental models).
+pub(crate) fn validate_model(model: &str, models: &[Model], force: bool) -> Result<()> {
+    if force || models.is_empty() || models.iter().any(|m| m.model == model || m.id == model) {
+        return Ok(());
+    }
+    Err(unknown_model_error(model, models))
+}
+
+/// Build an "unknown model" error with close matches and the available models
+fn unknown_model_error(model: &str, models: &[Model]) -> crate::Error {
+    let mut ranked: Vec<(usize, &str)> = models
+        .iter()
+        .map(|m| (edit_distance(model, &m.model), m.model.as_str()))
+        .collect();
+    ranked.sort();
+    let threshold = (model.chars().count() / 2).max(2);
+    let suggestions: Vec<&str> = ranked
+        .iter()
+        .filter(|(distance, _)| *distance <= threshold)
+        .take(MAX_MODEL_SUGGESTIONS)
+        .map(|(_, name)| *name)
+        .collect();
+
+    let available = models
+        .iter()
+        .map(|m| m.model.as_str())
+        .collect::<Vec<_>>()
+        .join(", ");
+    let hint = if suggestions.is_empty() {
+        String::new()
+    } else {
+        format!(" Did you mean: {}?", suggestions.join(", "))
+    };
+
+    crate::Error::Other(format!(
+        "Unknown model '{model}'.{hint} Available models: {available}"
+    ))
+}
+
+/// Validate a requested model against the cached model list
+///
+/// If the model list can't be fetched the model is allowed through, so a
+/// failing `model/list` never blocks starting a thread.
+/// Must not be called while holding the app-server lock.
+pub(crate) async fn ensure_model_available(
+    state: &AppState,
+    model: &str,
+    force: bool,
+) -> Result<()> {
+    if force {
+        return Ok(());
+    }
+    match cached_models(state).await {
+        Ok(models) => validate_model(model, &models, false),
+        Err(e) => {
+            tracing::warn!("Skipping model validation, model list unavailable: {}", e);
+            Ok(())
+        }
+    }
+}
+
+/// Reasoning efforts supported by a model
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ModelEfforts {
+    pub supported_reasoning_efforts: Vec<ReasoningEffortOption>,
+    pub default_reasoning_effort: String,
+}
+
+/// Look up the reasoning efforts of a model in the model list
+fn model_efforts(models: &[Model], model_id: &str) -> Result<ModelEfforts> {
+    match models
+        .iter()
+        .find(|m| m.id == model_id || m.model == model_id)
+    {
+        Some(model) => Ok(ModelEfforts {
+            supported_reasoning_efforts: model.supported_reasoning_efforts.clone(),
+            default_reasoning_effort: model.default_reasoning_effort.clone(),
+        }),
+        None if models.is_empty() => Err(crate::Error::Other(format!(
+            "Unknown model '{model_id}': no models available"
+        ))),
+        None => Err(unknown_model_error(model_id, models)),
+    }
+}
+
+/// Get the reasoning efforts supported by a model, plus its default effort
+#[tauri::command]
+pub async fn get_model_efforts(
+    state: State<'_, AppState>,
+    model_id: String,
+) -> Result<ModelEfforts> {
+    let models = cached_models(&state).await?;
+    model_efforts(&models, &model_id)
+}
+
+/// Models meeting every given requirement, in list order
+///
+/// `requires_reasoning` matches on whether a model has any reasoning efforts.
+/// With `min_context`, models that don't report a context window are excluded.
+fn filter_models(
+    models: &[Model],
+    requires_reasoning: Option<bool>,
+    min_context: Option<u32>,
+) -> Vec<Model> {
+    models
+        .iter()
+        .filter(|model| {
+            requires_reasoning
+                .is_none_or(|required| required != model.supported_reasoning_efforts.is_empty())
+        })
+        .filter(|model| {
+            min_context.is_none_or(|min| model.context_window.is_some_and(|window| window >= min))
+        })
+        .cloned()
+        .collect()
+}
+
+/// List the available models that meet the given capability requirements
+#[tauri::command]
+pub async fn list_models_filtered(
+    state: State<'_, AppState>,
+    requires_reasoning: Option<bool>,
+    min_context: Option<u32>,
+) -> Result<Vec<Model>> {
+    let models = cached_models(&state).await?;
+    Ok(filter_models(&models, requires_reasoning, min_context))
+}
+
+/// `app_settings` key holding the app-wide default model for new threads
+pub(crate) const DEFAULT_MODEL_SETTING: &str = "default_model";
+
+/// Get the app-wide default model for new threads, if one is set
+#[tauri::command]
+pub async fn get_default_model(state: State<'_, AppState>) -> Result<Option<String>> {
+    state.database.get_setting(DEFAULT_MODEL_SETTING)
+}
+
+/// Set the app-wide default model for new threads, or clear it with `None`
+///
+/// The model must be in the app-server's model list.
+#[tauri::command]
+pub async fn set_default_model(state: State<'_, AppState>, model: Option<String>) -> Result<()> {
+    if let Some(model) = model.as_deref() {
+        let models = cached_models(&state).await?;
+        validate_model(model, &models, false)?;
+    }
+    state
+        .database
+        .set_setting(DEFAULT_MODEL_SETTING, model.as_deref())
+}
+
 // ==================== Skills Commands ====================
 
 #[derive(Debug, Serialize, serde::Deserialize)]
@@ -220,6 +863,182 @@ pub async fn list_skills(
     Ok(response)
 }
 
+/// Skills and errors from several cwds, merged into one list
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MergedSkillsList {
+    pub skills: Vec<SkillMetadata>,
+    pub errors: Vec<SkillErrorInfo>,
+}
+
+/// Whether a skill scope belongs to a project rather than the user
+fn is_project_scope(scope: &str) -> bool {
+    matches!(scope, "repo" | "project")
+}
+
+/// Merge per-cwd skill lists
+///
+/// Skills are de-duplicated by name and scope, keeping the first seen. A
+/// project-scoped skill hides a user-scoped skill with the same name.
+/// Errors are de-duplicated by path and message.
+fn merge_skill_entries(entries: Vec<SkillsListEntry>) -> MergedSkillsList {
+    let mut skills: Vec<SkillMetadata> = Vec::new();
+    let mut errors: Vec<SkillErrorInfo> = Vec::new();
+
+    for entry in entries {
+        for skill in entry.skills {
+            if !skills
+                .iter()
+                .any(|s| s.name == skill.name && s.scope == skill.scope)
+            {
+                skills.push(skill);
+            }
+        }
+        for error in entry.errors {
+            if !errors
+                .iter()
+                .any(|e| e.path == error.path && e.message == error.message)
+            {
+                errors.push(error);
+            }
+        }
+    }
+
+    let project_names: std::collections::HashSet<String> = skills
+        .iter()
+        .filter(|s| is_project_scope(&s.scope))
+        .map(|s| s.name.clone())
+        .collect();
+    skills.retain(|s| s.scope != "user" || !project_names.contains(&s.name));
+
+    MergedSkillsList { skills, errors }
+}
+
+/// List skills for several cwds as one de-duplicated list
+#[tauri::command]
+pub async fn list_skills_merged(
+    state: State<'_, AppState>,
+    cwds: Vec<String>,
+    force_reload: Option<bool>,
+) -> Result<MergedSkillsList> {
+    let response = list_skills(state, cwds, force_reload.unwrap_or(false)).await?;
+    Ok(merge_skill_entries(response.data))
+}
+
+/// Directories skills may be loaded from: the user's skills plus each project's `.codex/skills`
+pub(crate) fn allowed_skill_dirs(
+    db: &crate::database::Database,
+) -> Result<Vec<std::path::PathBuf>> {
+    let mut dirs = vec![crate::codex_import::get_codex_dir().join("skills")];
+    dirs.extend(
+        db.get_all_projects()?
+            .into_iter()
+            .map(|p| std::path::Path::new(&p.path).join(".codex").join("skills")),
+    );
+    Ok(dirs)
+}
+
+/// Canonicalize a skill path and ensure it lies inside one of `allowed_dirs`
+pub(crate) fn contained_skill_path(
+    path: &str,
+    allowed_dirs: &[std::path::PathBuf],
+) -> Result<std::path::PathBuf> {
+    let canonical = crate::utils::validate_and_canonicalize_path(path)?;
+    let allowed = allowed_dirs
+        .iter()
+        .filter_map(|dir| dir.canonicalize().ok())
+        .any(|dir| canonical.starts_with(dir));
+    if !allowed {
+        return Err(crate::Error::InvalidPath(format!(
+            "Skill is not inside a skills directory: {path}"
+        )));
+    }
+    Ok(canonical)
+}
+
+/// Result of checking a SKILL.md file
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SkillValidation {
+    pub path: String,
+    pub valid: bool,
+    pub name: Option<String>,
+    pub description: Option<String>,
+    /// Problems found; empty when the skill is valid
+    pub issues: Vec<String>,
+}
+
+/// Check a SKILL.md's frontmatter for a name and description
+///
+/// Only flat `key: value` lines are read, which is all the two required
+/// fields need.
+fn check_skill_contents(path: &str, contents: &str) -> SkillValidation {
+    let mut validation = SkillValidation {
+        path: path.to_string(),
+        valid: false,
+        name: None,
+        description: None,
+        issues: Vec::new(),
+    };
+
+    let mut lines = contents.lines();
+    if lines.next().map(str::trim_end) != Some("---") {
+        validation
+            .issues
+            .push("Missing frontmatter: file must start with a '---' line".to_string());
+        return validation;
+    }
+
+    let mut closed = false;
+    for line in lines.by_ref() {
+        if line.trim_end() == "---" {
+            closed = true;
+            break;
+        }
+        let Some((key, value)) = line.split_once(':') else {
+            continue;
+        };
+        let value = value.trim().trim_matches(|c| c == '"' || c == '\'');
+        let value = (!value.is_empty()).then(|| value.to_string());
+        match key.trim() {
+            "name" => validation.name = value,
+            "description" => validation.description = value,
+            _ => {}
+        }
+    }
+
+    if !closed {
+        validation
+            .issues
+            .push("Frontmatter is not closed with a '---' line".to_string());
+    }
+    if validation.name.is_none() {
+        validation
+            .issues
+            .push("Frontmatter has no name".to_string());
+    }
+    if validation.description.is_none() {
+        validation
+            .issues
+            .push("Frontmatter has no description".to_string());
+    }
+    validation.valid = validation.issues.is_empty();
+    validation
+}
+
+/// Check a skill's SKILL.md before it is selected for a turn
+#[tauri::command]
+pub async fn validate_skill(state: State<'_, AppState>, path: String) -> Result<SkillValidation> {
+    let allowed_dirs = allowed_skill_dirs(&state.database)?;
+    let canonical = contained_skill_path(&path, &allowed_dirs)?;
+    if !canonical.is_file() {
+        return Err(crate::Error::InvalidPath(format!("Not a file: {path}")));
+    }
+
+    let contents = tokio::fs::read_to_string(&canonical).await?;
+    Ok(check_skill_contents(&path, &contents))
+}
+
 // ==================== MCP Commands ====================
 
 #[derive(Debug, Serialize, serde::Deserialize)]
@@ -291,22 +1110,51 @@ pub struct ReviewStartParams {
     pub target: ReviewTarget,
 }
 
+/// Maximum length (in characters) of custom review instructions
+const MAX_REVIEW_INSTRUCTIONS_CHARS: usize = 10_000;
+
+/// Validate a review target before sending it to the app-server
+///
+/// Custom instructions are trimmed and must be non-empty and within
+/// `MAX_REVIEW_INSTRUCTIONS_CHARS`.
+fn validate_review_target(target: ReviewTarget) -> Result<ReviewTarget> {
+    match target {
+        ReviewTarget::Custom { instructions } => {
+            let trimmed = instructions.trim();
+            if trimmed.is_empty() {
+                return Err(crate::Error::Other(
+                    "Custom review instructions cannot be empty".to_string(),
+                ));
+            }
+            let char_count = trimmed.chars().count();
+            if char_count > MAX_REVIEW_INSTRUCTIONS_CHARS {
+                return Err(crate::Error::Other(format!(
+                    "Custom review instructions are too long ({char_count} characters, maximum is {MAX_REVIEW_INSTRUCTIONS_CHARS})"
+                )));
+            }
+            Ok(ReviewTarget::Custom {
+                instructions: trimmed.to_string(),
+            })
+        }
+        other => Ok(other),
+    }
+}
+
 #[tauri::command]
 pub async fn start_review(
     state: State<'_, AppState>,
     thread_id: String,
     target: Option<ReviewTarget>,
 ) -> Result<ReviewStartResponse> {
+    let target = validate_review_target(target.unwrap_or(ReviewTarget::UncommittedChanges))?;
+
     state.start_app_server().await?;
     let mut server = state.app_server.write().await;
     let server = server
         .as_mut()
         .ok_or_else(|| crate::Error::AppServer("App server not running".to_string()))?;
 
-    let params = ReviewStartParams {
-        thread_id,
-        target: target.unwrap_or(ReviewTarget::UncommittedChanges),
-    };
+    let params = ReviewStartParams { thread_id, target };
     let response: ReviewStartResponse = server.send_request("review/start", params).await?;
     Ok(response)
 }
@@ -333,8 +1181,17 @@ pub async fn run_user_shell_command(
         .as_mut()
         .ok_or_else(|| crate::Error::AppServer("App server not running".to_string()))?;
 
-    let params = UserShellCommandParams { thread_id, command };
+    let params = UserShellCommandParams {
+        thread_id: thread_id.clone(),
+        command: command.clone(),
+    };
     let response: TurnStartResponse = server.send_request("userShellCommand/run", params).await?;
+    crate::commands::sessions::record_command_history(
+        &state.database,
+        &thread_id,
+        &command,
+        "userShell",
+    );
     Ok(response)
 }
 
@@ -392,6 +1249,119 @@ pub async fn read_config(
     Ok(response)
 }
 
+/// A config key's value as defined in one layer
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ConfigKeyLayer {
+    pub layer: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub path: Option<String>,
+    pub value: serde_json::Value,
+}
+
+/// Look up a dotted key path in a config value
+///
+/// Segments are matched greedily so table names that themselves contain dots
+/// (e.g. `projects./home/me/app.v2.trust_level`) still resolve. Numeric
+/// segments index into arrays (e.g. `notify.0`).
+fn lookup_config_key<'a>(
+    config: &'a serde_json::Value,
+    parts: &[&str],
+) -> Option<&'a serde_json::Value> {
+    if parts.is_empty() {
+        return Some(config);
+    }
+    if let Some(items) = config.as_array() {
+        let index: usize = parts[0].parse().ok()?;
+        return lookup_config_key(items.get(index)?, &parts[1..]);
+    }
+    let table = config.as_object()?;
+    (1..=parts.len()).rev().find_map(|split| {
+        let child = table.get(&parts[..split].join("."))?;
+        lookup_config_key(child, &parts[split..])
+    })
+}
+
+/// Collect the layers that define `key`, in the order the server returned them
+fn config_key_layers(layers: &[ConfigLayer], key: &str) -> Vec<ConfigKeyLayer> {
+    let parts: Vec<&str> = key.split('.').collect();
+    layers
+        .iter()
+        .filter_map(|layer| {
+            lookup_config_key(&layer.config, &parts).map(|value| ConfigKeyLayer {
+                layer: layer.name.clone(),
+                path: layer.path.clone(),
+                value: value.clone(),
+            })
+        })
+        .collect()
+}
+
+/// Reject empty keys and keys with empty segments (`a..b`, `.a`)
+fn validate_config_key(key: &str) -> Result<()> {
+    if key.trim().is_empty() || key.split('.').any(str::is_empty) {
+        return Err(crate::Error::Other(format!("Invalid config key: {key:?}")));
+    }
+    Ok(())
+}
+
+/// Get the value of a dotted config key in every layer that defines it
+#[tauri::command]
+pub async fn get_config_key_layers(
+    state: State<'_, AppState>,
+    key: String,
+) -> Result<Vec<ConfigKeyLayer>> {
+    validate_config_key(&key)?;
+
+    let response = read_config(state, Some(true)).await?;
+    let layers = response.layers.unwrap_or_default();
+
+    Ok(config_key_layers(&layers, &key))
+}
+
+/// The merged value of a config key and the layer it came from
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EffectiveConfigValue {
+    pub key: String,
+    pub value: serde_json::Value,
+    /// Origin of the key, or of the closest enclosing table when the server
+    /// only reports origins for whole tables
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub origin: Option<ConfigOrigin>,
+}
+
+/// Resolve `key` against a `config/read` response
+fn effective_config_value(
+    response: &ConfigReadResponse,
+    key: &str,
+) -> Option<EffectiveConfigValue> {
+    let parts: Vec<&str> = key.split('.').collect();
+    let value = lookup_config_key(&response.config, &parts)?;
+    let origin = (1..=parts.len())
+        .rev()
+        .find_map(|len| response.origins.get(&parts[..len].join(".")))
+        .cloned();
+
+    Some(EffectiveConfigValue {
+        key: key.to_string(),
+        value: value.clone(),
+        origin,
+    })
+}
+
+/// Get the effective (merged) value of a dotted config key, or None if it isn't set
+#[tauri::command]
+pub async fn get_effective_config_value(
+    state: State<'_, AppState>,
+    key: String,
+) -> Result<Option<EffectiveConfigValue>> {
+    validate_config_key(&key)?;
+
+    let response = read_config(state, Some(false)).await?;
+    Ok(effective_config_value(&response, &key))
+}
+
 /// Write configuration
 #[tauri::command]
 pub async fn write_config(
@@ -399,6 +1369,11 @@ pub async fn write_config(
     key: String,
     value: serde_json::Value,
 ) -> Result<()> {
+    write_config_value(&state, key, value).await
+}
+
+/// Send one `config/write` request
+async fn write_config_value(state: &AppState, key: String, value: serde_json::Value) -> Result<()> {
     // Ensure app-server is running
     state.start_app_server().await?;
 
@@ -417,6 +1392,97 @@ pub async fn write_config(
     Ok(())
 }
 
+/// One key to set in a batch config write
+#[derive(Debug, Clone, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ConfigWriteEntry {
+    pub key: String,
+    pub value: serde_json::Value,
+}
+
+/// Name of the layer `config/write` writes to (`~/.codex/config.toml`)
+const USER_CONFIG_LAYER: &str = "user";
+
+/// The user layer's own config from a `config/read` with layers included
+///
+/// An absent user layer means nothing is set there yet. Errors if the server
+/// didn't return layers at all, since the merged config can't stand in for it.
+fn user_layer_config(response: &ConfigReadResponse) -> Result<serde_json::Value> {
+    let layers = response.layers.as_ref().ok_or_else(|| {
+        crate::Error::AppServer("config/read didn't return config layers".to_string())
+    })?;
+    Ok(layers
+        .iter()
+        .find(|layer| layer.name == USER_CONFIG_LAYER)
+        .map(|layer| layer.config.clone())
+        .unwrap_or(serde_json::Value::Null))
+}
+
+/// Write `entries` in order, undoing the ones already written if any write fails
+///
+/// Prior values come from `current`, the user layer before the batch, so a
+/// rollback never copies values from other layers into the user's file. Keys
+/// the user layer didn't set are rolled back by writing `null`, which
+/// `config/write` treats as removing the key.
+async fn apply_config_batch<W, Fut>(
+    entries: &[ConfigWriteEntry],
+    current: &serde_json::Value,
+    mut write: W,
+) -> Result<()>
+where
+    W: FnMut(String, serde_json::Value) -> Fut,
+    Fut: std::future::Future<Output = Result<()>>,
+{
+    let mut applied: Vec<(String, serde_json::Value)> = Vec::new();
+
+    for entry in entries {
+        let parts: Vec<&str> = entry.key.split('.').collect();
+        let prior = lookup_config_key(current, &parts)
+            .cloned()
+            .unwrap_or(serde_json::Value::Null);
+
+        if let Err(e) = write(entry.key.clone(), entry.value.clone()).await {
+            for (key, prior) in applied.into_iter().rev() {
+                if let Err(rollback_err) = write(key.clone(), prior).await {
+                    tracing::warn!("Failed to roll back config key {}: {}", key, rollback_err);
+                }
+            }
+            return Err(crate::Error::Other(format!(
+                "Failed to write config key {}: {e}; earlier keys in the batch were rolled back",
+                entry.key
+            )));
+        }
+        applied.push((entry.key.clone(), prior));
+    }
+
+    Ok(())
+}
+
+/// Write several config keys as a unit
+///
+/// All keys are validated before anything is written. The app server has no
+/// batch write, so keys are written one at a time and restored to their
+/// previous values if a later write fails.
+#[tauri::command]
+pub async fn write_config_batch(
+    state: State<'_, AppState>,
+    entries: Vec<ConfigWriteEntry>,
+) -> Result<()> {
+    for entry in &entries {
+        validate_config_key(&entry.key)?;
+    }
+    if entries.is_empty() {
+        return Ok(());
+    }
+
+    let current = user_layer_config(&read_config(state.clone(), Some(true)).await?)?;
+    let app_state: &AppState = &state;
+    apply_config_batch(&entries, &current, |key, value| {
+        write_config_value(app_state, key, value)
+    })
+    .await
+}
+
 // ==================== Account Rate Limits ====================
 
 /// Get account rate limits
@@ -438,3 +1504,722 @@ pub async fn get_account_rate_limits(
 
     Ok(response)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn custom(instructions: &str) -> ReviewTarget {
+        ReviewTarget::Custom {
+            instructions: instructions.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_validate_review_target_rejects_empty_custom() {
+        assert!(validate_review_target(custom("")).is_err());
+        assert!(validate_review_target(custom("   \n\t ")).is_err());
+    }
+
+    #[test]
+    fn test_validate_review_target_rejects_oversized_custom() {
+        let too_long = "a".repeat(MAX_REVIEW_INSTRUCTIONS_CHARS + 1);
+        assert!(validate_review_target(custom(&too_long)).is_err());
+
+        let at_limit = "a".repeat(MAX_REVIEW_INSTRUCTIONS_CHARS);
+        assert!(validate_review_target(custom(&at_limit)).is_ok());
+    }
+
+    #[test]
+    fn test_validate_review_target_trims_valid_custom() {
+        match validate_review_target(custom("  Focus on error handling\n")).unwrap() {
+            ReviewTarget::Custom { instructions } => {
+                assert_eq!(instructions, "Focus on error handling");
+            }
+            other => panic!("unexpected target: {other:?}"),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_login_and_logout_invalidate_cached_account() {
+        let cache = AccountCache::default();
+        let account: AccountInfo = serde_json::from_value(serde_json::json!({
+            "account": { "type": "apiKey" },
+            "requiresOpenaiAuth": true
+        }))
+        .unwrap();
+
+        cache.store(&account, cache.generation());
+        let started: LoginResponse =
+            serde_json::from_value(serde_json::json!({ "type": "apiKey" })).unwrap();
+        record_started_login(&parking_lot::Mutex::new(HashSet::new()), &cache, &started);
+        assert!(cache.get().is_none());
+
+        cache.store(&account, cache.generation());
+        let mut sent = Vec::new();
+        logout_account(&cache, |method, _| {
+            sent.push(method);
+            async { Ok(serde_json::json!({})) }
+        })
+        .await
+        .unwrap();
+        assert_eq!(sent, vec!["account/logout"]);
+        assert!(cache.get().is_none());
+
+        // A refresh in flight during a failed logout can't repopulate the cache
+        cache.store(&account, cache.generation());
+        let in_flight = cache.generation();
+        let failed = logout_account(&cache, |_, _| async {
+            Err(crate::Error::AppServer("connection lost".to_string()))
+        })
+        .await;
+        assert!(failed.is_err());
+        assert!(cache.get().is_none());
+        cache.store(&account, in_flight);
+        assert!(cache.get().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_cancel_login_sends_login_id_to_server() {
+        let pending_logins = parking_lot::Mutex::new(HashSet::new());
+        let started: LoginResponse = serde_json::from_value(serde_json::json!({
+            "type": "chatgpt",
+            "loginId": "login-123",
+            "authUrl": "https://auth.openai.com/oauth/authorize"
+        }))
+        .unwrap();
+        record_started_login(&pending_logins, &AccountCache::default(), &started);
+        assert!(pending_logins.lock().contains("login-123"));
+
+        let mut sent = Vec::new();
+        cancel_pending_login(&pending_logins, "login-123", |method, params| {
+            sent.push((method, params));
+            async { Ok(serde_json::json!({})) }
+        })
+        .await
+        .unwrap();
+
+        assert_eq!(
+            sent,
+            vec![(
+                "account/login/cancel",
+                serde_json::json!({ "loginId": "login-123" })
+            )]
+        );
+        assert!(pending_logins.lock().is_empty());
+
+        // Once cancelled, the login is unknown
+        let err = cancel_pending_login(&pending_logins, "login-123", |_, _| async {
+            panic!("unknown logins shouldn't reach the server")
+        })
+        .await
+        .unwrap_err();
+        assert!(err.to_string().contains("No pending login"), "{err}");
+    }
+
+    #[tokio::test]
+    async fn test_cancel_login_clears_local_state_when_unsupported() {
+        let pending_logins = parking_lot::Mutex::new(HashSet::from(["login-1".to_string()]));
+
+        let err = cancel_pending_login(&pending_logins, "login-1", |_, _| async {
+            Err(crate::Error::AppServer(
+                "JSON-RPC error -32601: Method not found".to_string(),
+            ))
+        })
+        .await
+        .unwrap_err();
+
+        assert!(
+            err.to_string().contains("does not support cancelling"),
+            "{err}"
+        );
+        assert!(pending_logins.lock().is_empty());
+    }
+
+    fn model(name: &str) -> Model {
+        Model {
+            id: name.to_string(),
+            model: name.to_string(),
+            display_name: name.to_uppercase(),
+            description: String::new(),
+            supported_reasoning_efforts: Vec::new(),
+            default_reasoning_effort: "medium".to_string(),
+            is_default: false,
+            context_window: None,
+        }
+    }
+
+    fn model_list() -> Vec<Model> {
+        vec![model("gpt-5-codex"), model("gpt-5"), model("o3")]
+    }
+
+    #[tokio::test]
+    async fn test_model_cache_goes_stale_with_the_account_cache() {
+        let account_cache = AccountCache::default();
+        let cached = (account_cache.generation(), model_list());
+        assert_eq!(
+            current_models(Some(&cached), account_cache.generation())
+                .unwrap()
+                .len(),
+            3
+        );
+
+        logout_account(&account_cache, |_, _| async { Ok(serde_json::json!({})) })
+            .await
+            .unwrap();
+        assert!(current_models(Some(&cached), account_cache.generation()).is_none());
+
+        let cached = (account_cache.generation(), model_list());
+        account_cache.observe_notification("account/updated");
+        assert!(current_models(Some(&cached), account_cache.generation()).is_none());
+        assert!(current_models(None, account_cache.generation()).is_none());
+    }
+
+    #[tokio::test]
+    async fn test_get_app_version_matches_crate_version() {
+        let version = get_app_version().await.unwrap();
+        assert_eq!(version.version, env!("CARGO_PKG_VERSION"));
+        assert!(!version.build_profile.is_empty());
+    }
+
+    #[test]
+    fn test_model_efforts_match_fixture_model() {
+        let mut fixture = model("gpt-5-codex");
+        fixture.supported_reasoning_efforts = ["low", "medium", "high"]
+            .iter()
+            .map(|effort| ReasoningEffortOption {
+                reasoning_effort: effort.to_string(),
+                description: format!("{effort} effort"),
+            })
+            .collect();
+        let models = vec![fixture, model("o3")];
+
+        let efforts = model_efforts(&models, "gpt-5-codex").unwrap();
+        let names: Vec<&str> = efforts
+            .supported_reasoning_efforts
+            .iter()
+            .map(|e| e.reasoning_effort.as_str())
+            .collect();
+        assert_eq!(names, vec!["low", "medium", "high"]);
+        assert_eq!(efforts.default_reasoning_effort, "medium");
+
+        let err = model_efforts(&models, "gpt-5-codx")
+            .unwrap_err()
+            .to_string();
+        assert!(err.contains("Unknown model 'gpt-5-codx'"), "{err}");
+        assert!(model_efforts(&[], "gpt-5-codex").is_err());
+    }
+
+    #[test]
+    fn test_filter_models_by_capability() {
+        let effort = |name: &str| ReasoningEffortOption {
+            reasoning_effort: name.to_string(),
+            description: String::new(),
+        };
+        let mut reasoning_large = model("gpt-5-codex");
+        reasoning_large.supported_reasoning_efforts = vec![effort("low"), effort("high")];
+        reasoning_large.context_window = Some(272_000);
+        let mut plain_small = model("gpt-4.1-mini");
+        plain_small.context_window = Some(32_000);
+        let mut reasoning_unknown = model("o3");
+        reasoning_unknown.supported_reasoning_efforts = vec![effort("medium")];
+        let models = vec![reasoning_large, plain_small, reasoning_unknown];
+
+        let ids = |filtered: Vec<Model>| filtered.into_iter().map(|m| m.id).collect::<Vec<_>>();
+        assert_eq!(
+            ids(filter_models(&models, None, None)),
+            vec!["gpt-5-codex", "gpt-4.1-mini", "o3"]
+        );
+        assert_eq!(
+            ids(filter_models(&models, Some(true), None)),
+            vec!["gpt-5-codex", "o3"]
+        );
+        assert_eq!(
+            ids(filter_models(&models, Some(false), None)),
+            vec!["gpt-4.1-mini"]
+        );
+        assert_eq!(
+            ids(filter_models(&models, None, Some(100_000))),
+            vec!["gpt-5-codex"]
+        );
+        assert_eq!(
+            ids(filter_models(&models, None, Some(32_000))),
+            vec!["gpt-5-codex", "gpt-4.1-mini"]
+        );
+        assert!(filter_models(&models, Some(false), Some(100_000)).is_empty());
+    }
+
+    #[test]
+    fn test_model_context_window_is_optional() {
+        let listed: ModelListResponse = serde_json::from_value(serde_json::json!({
+            "data": [
+                {
+                    "id": "a", "model": "a", "displayName": "A", "description": "",
+                    "supportedReasoningEfforts": [], "defaultReasoningEffort": "medium",
+                    "isDefault": true, "contextWindow": 400000
+                },
+                {
+                    "id": "b", "model": "b", "displayName": "B", "description": "",
+                    "supportedReasoningEfforts": [], "defaultReasoningEffort": "medium",
+                    "isDefault": false
+                }
+            ],
+            "nextCursor": null
+        }))
+        .unwrap();
+        assert_eq!(listed.data[0].context_window, Some(400_000));
+        assert_eq!(listed.data[1].context_window, None);
+    }
+
+    #[test]
+    fn test_validate_model_accepts_listed_model() {
+        assert!(validate_model("gpt-5-codex", &model_list(), false).is_ok());
+        assert!(validate_model("o3", &model_list(), false).is_ok());
+    }
+
+    #[test]
+    fn test_validate_model_rejects_typo_with_suggestions() {
+        let err = validate_model("gpt-5-codx", &model_list(), false).unwrap_err();
+        let message = err.to_string();
+        assert!(message.contains("Unknown model 'gpt-5-codx'"), "{message}");
+        assert!(message.contains("Did you mean: gpt-5-codex"), "{message}");
+        assert!(
+            message.contains("Available models: gpt-5-codex, gpt-5, o3"),
+            "{message}"
+        );
+
+        let message = validate_model("llama-70b-local", &model_list(), false)
+            .unwrap_err()
+            .to_string();
+        assert!(!message.contains("Did you mean"), "{message}");
+    }
+
+    #[test]
+    fn test_validate_model_force_allows_unknown_model() {
+        assert!(validate_model("experimental-model", &model_list(), true).is_ok());
+    }
+
+    #[test]
+    fn test_config_key_layers_reports_each_defining_layer() {
+        let response: ConfigReadResponse = serde_json::from_value(serde_json::json!({
+            "config": { "model": "o3", "sandbox_mode": "workspace-write" },
+            "origins": {},
+            "layers": [
+                { "name": "defaults", "config": { "model": "gpt-5-codex", "tools": { "web_search": false } } },
+                { "name": "user", "path": "/home/me/.codex/config.toml", "config": { "model": "o3", "sandbox_mode": "workspace-write" } },
+                { "name": "project", "path": "/work/app/.codex/config.toml", "config": { "tools": { "web_search": true } } }
+            ]
+        }))
+        .unwrap();
+        let layers = response.layers.unwrap();
+
+        let model = config_key_layers(&layers, "model");
+        assert_eq!(model.len(), 2);
+        assert_eq!(model[0].layer, "defaults");
+        assert_eq!(model[0].value, "gpt-5-codex");
+        assert_eq!(model[1].layer, "user");
+        assert_eq!(
+            model[1].path.as_deref(),
+            Some("/home/me/.codex/config.toml")
+        );
+        assert_eq!(model[1].value, "o3");
+
+        let web_search = config_key_layers(&layers, "tools.web_search");
+        assert_eq!(web_search.len(), 2);
+        assert_eq!(web_search[1].layer, "project");
+        assert_eq!(web_search[1].value, true);
+
+        assert!(config_key_layers(&layers, "tools.missing").is_empty());
+    }
+
+    fn mock_config_response() -> ConfigReadResponse {
+        serde_json::from_value(serde_json::json!({
+            "config": {
+                "model": "o3",
+                "notify": ["say", "done"],
+                "tools": { "web_search": true },
+                "mcp_servers": { "docs": { "command": "docs-server" } }
+            },
+            "origins": {
+                "model": { "layer": "user", "path": "/home/me/.codex/config.toml" },
+                "notify": { "layer": "user", "path": "/home/me/.codex/config.toml" },
+                "tools.web_search": { "layer": "project", "path": "/work/app/.codex/config.toml" },
+                "mcp_servers": { "layer": "defaults" }
+            }
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_effective_config_value_top_level_key() {
+        let effective = effective_config_value(&mock_config_response(), "model").unwrap();
+        assert_eq!(effective.value, "o3");
+        let origin = effective.origin.unwrap();
+        assert_eq!(origin.layer, "user");
+        assert_eq!(origin.path.as_deref(), Some("/home/me/.codex/config.toml"));
+    }
+
+    #[test]
+    fn test_effective_config_value_nested_key() {
+        let response = mock_config_response();
+
+        let web_search = effective_config_value(&response, "tools.web_search").unwrap();
+        assert_eq!(web_search.value, true);
+        assert_eq!(web_search.origin.unwrap().layer, "project");
+
+        // Origin falls back to the enclosing table
+        let command = effective_config_value(&response, "mcp_servers.docs.command").unwrap();
+        assert_eq!(command.value, "docs-server");
+        assert_eq!(command.origin.unwrap().layer, "defaults");
+
+        let notify = effective_config_value(&response, "notify.1").unwrap();
+        assert_eq!(notify.value, "done");
+        assert_eq!(notify.origin.unwrap().layer, "user");
+    }
+
+    #[test]
+    fn test_effective_config_value_undefined_key() {
+        let response = mock_config_response();
+        assert!(effective_config_value(&response, "approval_policy").is_none());
+        assert!(effective_config_value(&response, "tools.missing").is_none());
+        assert!(effective_config_value(&response, "notify.5").is_none());
+        assert!(effective_config_value(&response, "model.name").is_none());
+    }
+
+    fn batch_entry(key: &str, value: serde_json::Value) -> ConfigWriteEntry {
+        ConfigWriteEntry {
+            key: key.to_string(),
+            value,
+        }
+    }
+
+    /// Applies writes to an in-memory config, failing on one key
+    async fn run_batch(
+        config: &std::sync::Mutex<serde_json::Value>,
+        entries: &[ConfigWriteEntry],
+        failing_key: Option<&str>,
+    ) -> Result<()> {
+        let current = config.lock().unwrap().clone();
+        apply_config_batch(entries, &current, |key, value| async move {
+            if Some(key.as_str()) == failing_key {
+                return Err(crate::Error::AppServer(
+                    "JSON-RPC error -32602: invalid value".to_string(),
+                ));
+            }
+            let mut config = config.lock().unwrap();
+            let table = config.as_object_mut().unwrap();
+            if value.is_null() {
+                table.remove(&key);
+            } else {
+                table.insert(key, value);
+            }
+            Ok(())
+        })
+        .await
+    }
+
+    #[tokio::test]
+    async fn test_config_batch_applies_every_entry() {
+        let config = std::sync::Mutex::new(serde_json::json!({ "model": "o3" }));
+        let entries = vec![
+            batch_entry("model", serde_json::json!("gpt-5-codex")),
+            batch_entry("model_reasoning_effort", serde_json::json!("high")),
+        ];
+
+        run_batch(&config, &entries, None).await.unwrap();
+        assert_eq!(
+            *config.lock().unwrap(),
+            serde_json::json!({ "model": "gpt-5-codex", "model_reasoning_effort": "high" })
+        );
+    }
+
+    #[tokio::test]
+    async fn test_config_batch_rolls_back_on_mid_batch_failure() {
+        let original = serde_json::json!({ "model": "o3", "sandbox_mode": "read-only" });
+        let config = std::sync::Mutex::new(original.clone());
+        let entries = vec![
+            batch_entry("model", serde_json::json!("gpt-5-codex")),
+            batch_entry("model_reasoning_effort", serde_json::json!("high")),
+            batch_entry("sandbox_mode", serde_json::json!("danger-full-access")),
+        ];
+
+        let err = run_batch(&config, &entries, Some("sandbox_mode"))
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("sandbox_mode"));
+        // Nothing from the batch remains, including the key that didn't exist before
+        assert_eq!(*config.lock().unwrap(), original);
+    }
+
+    #[tokio::test]
+    async fn test_config_batch_rolls_back_to_user_layer_values() {
+        let response: ConfigReadResponse = serde_json::from_value(serde_json::json!({
+            "config": { "model": "gpt-5-codex", "sandbox_mode": "read-only" },
+            "origins": {},
+            "layers": [
+                { "name": "defaults", "config": { "model": "gpt-5-codex" } },
+                { "name": "user", "path": "/home/me/.codex/config.toml", "config": { "sandbox_mode": "read-only" } }
+            ]
+        }))
+        .unwrap();
+        let user = user_layer_config(&response).unwrap();
+        let config = std::sync::Mutex::new(user.clone());
+        let entries = vec![
+            batch_entry("model", serde_json::json!("o3")),
+            batch_entry("sandbox_mode", serde_json::json!("danger-full-access")),
+        ];
+
+        run_batch(&config, &entries, Some("sandbox_mode"))
+            .await
+            .unwrap_err();
+        // `model` only came from the defaults, so it is cleared rather than
+        // pinned to the default value in the user's file
+        assert_eq!(*config.lock().unwrap(), user);
+    }
+
+    #[test]
+    fn test_user_layer_config_requires_layers() {
+        let mut response = mock_config_response();
+        response.layers = None;
+        assert!(user_layer_config(&response).is_err());
+
+        response.layers = Some(Vec::new());
+        assert_eq!(
+            user_layer_config(&response).unwrap(),
+            serde_json::Value::Null
+        );
+    }
+
+    #[test]
+    fn test_validate_config_key() {
+        assert!(validate_config_key("model").is_ok());
+        assert!(validate_config_key("tools.web_search").is_ok());
+        assert!(validate_config_key("").is_err());
+        assert!(validate_config_key("tools..web_search").is_err());
+        assert!(validate_config_key(".model").is_err());
+    }
+
+    #[test]
+    fn test_lookup_config_key_with_dotted_table_name() {
+        let config = serde_json::json!({
+            "projects": { "/work/app.v2": { "trust_level": "trusted" } }
+        });
+        let parts: Vec<&str> = "projects./work/app.v2.trust_level".split('.').collect();
+        assert_eq!(lookup_config_key(&config, &parts).unwrap(), "trusted");
+    }
+
+    #[test]
+    fn test_account_list_from_result_parses_accounts() {
+        let mock = serde_json::json!({
+            "accounts": [
+                { "id": "acct_work", "type": "chatgpt", "email": "me@work.com", "planType": "team", "isActive": true },
+                { "id": "acct_api", "type": "apiKey" }
+            ]
+        });
+
+        let list = account_list_from_result(Ok(mock)).unwrap();
+        assert!(list.supported);
+        assert_eq!(list.accounts.len(), 2);
+        assert!(list.accounts[0].is_active);
+        assert_eq!(list.accounts[0].email.as_deref(), Some("me@work.com"));
+        assert_eq!(list.accounts[1].account_type, "apiKey");
+        assert!(!list.accounts[1].is_active);
+    }
+
+    #[test]
+    fn test_account_list_from_result_unsupported_server() {
+        let err = crate::Error::AppServer("JSON-RPC error -32601: Method not found".to_string());
+        let list = account_list_from_result(Err(err)).unwrap();
+        assert!(!list.supported);
+        assert!(list.accounts.is_empty());
+
+        let err = crate::Error::AppServer("JSON-RPC error -32603: Internal error".to_string());
+        assert!(account_list_from_result(Err(err)).is_err());
+    }
+
+    #[test]
+    fn test_switch_returns_new_active_account() {
+        let before = account_list_from_result(Ok(serde_json::json!({
+            "accounts": [
+                { "id": "acct_work", "type": "chatgpt", "email": "me@work.com", "isActive": true },
+                { "id": "acct_home", "type": "chatgpt", "email": "me@home.com", "planType": "plus" }
+            ]
+        })))
+        .unwrap();
+        assert!(before.supported);
+        assert!(!before.accounts[1].is_active);
+
+        check_account_switch(Ok(serde_json::json!({}))).unwrap();
+        let account = account_after_switch(Ok(serde_json::json!({
+            "account": { "type": "chatgpt", "email": "me@home.com", "planType": "plus" },
+            "requiresOpenaiAuth": true
+        })))
+        .unwrap()
+        .account
+        .unwrap();
+        assert_eq!(account.email.as_deref(), Some("me@home.com"));
+        assert_eq!(account.plan_type.as_deref(), Some("plus"));
+
+        // A failed read after the switch is reported, not papered over
+        let err = crate::Error::AppServer("JSON-RPC error -32603: Internal error".to_string());
+        assert!(account_after_switch(Err(err)).is_err());
+        assert!(account_after_switch(Ok(serde_json::json!({ "unexpected": true }))).is_err());
+    }
+
+    #[test]
+    fn test_switch_on_server_without_multi_account_support() {
+        let not_found =
+            || crate::Error::AppServer("JSON-RPC error -32601: Method not found".to_string());
+        assert!(
+            !account_list_from_result(Err(not_found()))
+                .unwrap()
+                .supported
+        );
+
+        let err = check_account_switch(Err(not_found())).unwrap_err();
+        assert!(
+            err.to_string()
+                .contains("does not support multiple accounts"),
+            "{err}"
+        );
+
+        // Other failures pass through unchanged
+        let err = crate::Error::AppServer("JSON-RPC error -32000: unknown account".to_string());
+        let err = check_account_switch(Err(err)).unwrap_err();
+        assert!(err.to_string().contains("unknown account"), "{err}");
+    }
+
+    #[test]
+    fn test_validate_review_target_passes_other_targets_through() {
+        assert!(matches!(
+            validate_review_target(ReviewTarget::UncommittedChanges).unwrap(),
+            ReviewTarget::UncommittedChanges
+        ));
+    }
+
+    fn process(pid: u32, ppid: u32, command: &str) -> ProcessEntry {
+        ProcessEntry {
+            pid,
+            ppid,
+            command: command.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_find_conflicting_instances_skips_own_children() {
+        let processes = vec![
+            process(100, 1, "/Applications/Codex Desktop"),
+            // Our own app-server, behind a node wrapper
+            process(101, 100, "node /usr/local/bin/codex app-server"),
+            process(
+                102,
+                101,
+                "/usr/local/lib/codex/codex-aarch64-apple-darwin app-server",
+            ),
+            // A CLI app-server started from a terminal
+            process(200, 50, "/opt/homebrew/bin/codex app-server"),
+            // Not an app-server
+            process(300, 50, "/opt/homebrew/bin/codex exec hello"),
+            process(400, 50, "vim app-server.md"),
+        ];
+
+        let conflicts = find_conflicting_instances(&processes, 100);
+        let pids: Vec<u32> = conflicts.iter().map(|p| p.pid).collect();
+        assert_eq!(pids, vec![200]);
+        assert!(find_conflicting_instances(&processes[..3], 100).is_empty());
+    }
+
+    fn skills_entry(cwd: &str, skills: &[(&str, &str)], errors: &[&str]) -> SkillsListEntry {
+        SkillsListEntry {
+            cwd: cwd.to_string(),
+            skills: skills
+                .iter()
+                .map(|(name, scope)| SkillMetadata {
+                    name: name.to_string(),
+                    description: format!("{name} ({scope}) in {cwd}"),
+                    short_description: None,
+                    path: format!("{cwd}/{name}/SKILL.md"),
+                    scope: scope.to_string(),
+                })
+                .collect(),
+            errors: errors
+                .iter()
+                .map(|path| SkillErrorInfo {
+                    path: path.to_string(),
+                    message: "bad frontmatter".to_string(),
+                })
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn test_merge_skill_entries_dedups_and_prefers_project_scope() {
+        let merged = merge_skill_entries(vec![
+            skills_entry(
+                "/a",
+                &[("lint", "user"), ("deploy", "user")],
+                &["/home/u/.codex/skills/x/SKILL.md"],
+            ),
+            skills_entry(
+                "/b",
+                &[("lint", "user"), ("deploy", "repo"), ("test", "repo")],
+                &[
+                    "/home/u/.codex/skills/x/SKILL.md",
+                    "/b/.codex/skills/y/SKILL.md",
+                ],
+            ),
+        ]);
+
+        let names: Vec<(&str, &str)> = merged
+            .skills
+            .iter()
+            .map(|s| (s.name.as_str(), s.scope.as_str()))
+            .collect();
+        assert_eq!(
+            names,
+            vec![("lint", "user"), ("deploy", "repo"), ("test", "repo")]
+        );
+        // The user-scoped lint came from the first cwd
+        assert_eq!(merged.skills[0].description, "lint (user) in /a");
+        assert_eq!(merged.errors.len(), 2);
+    }
+
+    #[test]
+    fn test_check_skill_contents_valid() {
+        let contents = "---\nname: lint\ndescription: \"Run the linters\"\n---\n\n# Lint\n";
+        let validation = check_skill_contents("SKILL.md", contents);
+        assert!(validation.valid, "{:?}", validation.issues);
+        assert_eq!(validation.name.as_deref(), Some("lint"));
+        assert_eq!(validation.description.as_deref(), Some("Run the linters"));
+    }
+
+    #[test]
+    fn test_check_skill_contents_malformed() {
+        let validation = check_skill_contents("SKILL.md", "---\nname: lint\n\n# Lint\n");
+        assert!(!validation.valid);
+        assert_eq!(validation.issues.len(), 2);
+        assert!(validation.issues.iter().any(|i| i.contains("not closed")));
+        assert!(validation
+            .issues
+            .iter()
+            .any(|i| i.contains("no description")));
+
+        let validation = check_skill_contents("SKILL.md", "# Lint\n");
+        assert!(validation.issues[0].contains("Missing frontmatter"));
+    }
+
+    #[test]
+    fn test_contained_skill_path() {
+        let skills = tempfile::tempdir().unwrap();
+        let outside = tempfile::tempdir().unwrap();
+        std::fs::create_dir(skills.path().join("lint")).unwrap();
+        let inside = skills.path().join("lint").join("SKILL.md");
+        std::fs::write(&inside, "---\n---\n").unwrap();
+        let stray = outside.path().join("SKILL.md");
+        std::fs::write(&stray, "---\n---\n").unwrap();
+
+        let allowed = vec![skills.path().to_path_buf()];
+        assert!(contained_skill_path(inside.to_str().unwrap(), &allowed).is_ok());
+        assert!(contained_skill_path(stray.to_str().unwrap(), &allowed).is_err());
+    }
+}
@@ -2,8 +2,11 @@
 //!
 //! Tauri commands for importing and managing Codex CLI sessions.
 
+use serde::Serialize;
+use tauri::{AppHandle, Emitter};
+
 use crate::codex_import::{
-    CodexConfig, CodexSession, CodexSessionSummary,
+    CodexConfig, CodexSession, CodexSessionSummary, ProjectCodexConfig, SessionValidation,
 };
 use crate::Result;
 
@@ -15,6 +18,17 @@ pub async fn get_codex_config() -> Result<CodexConfig> {
         .map_err(|e| crate::Error::Other(format!("Task join error: {e}")))?
 }
 
+/// Get the effective Codex config for a project, including its .codex/config.toml
+#[tauri::command]
+pub async fn get_project_codex_config(path: String) -> Result<ProjectCodexConfig> {
+    // Security: Canonicalize to prevent symlink attacks and traversal
+    let canonical_path = crate::utils::validate_and_canonicalize_path(&path)?;
+
+    tokio::task::spawn_blocking(move || crate::codex_import::read_project_config(&canonical_path))
+        .await
+        .map_err(|e| crate::Error::Other(format!("Task join error: {e}")))?
+}
+
 /// List all Codex CLI sessions
 #[tauri::command]
 pub async fn list_codex_sessions() -> Result<Vec<CodexSessionSummary>> {
@@ -23,14 +37,81 @@ pub async fn list_codex_sessions() -> Result<Vec<CodexSessionSummary>> {
         .map_err(|e| crate::Error::Other(format!("Task join error: {e}")))?
 }
 
+/// Emitted as `session-parse-progress` while a session file is parsed
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SessionParseProgressEvent {
+    pub session_id: String,
+    pub bytes_read: u64,
+    pub total_bytes: u64,
+}
+
+/// Emitted as `session-parse-complete` once a session file is parsed
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SessionParseCompleteEvent {
+    pub session_id: String,
+    pub message_count: usize,
+}
+
 /// Get full details of a Codex CLI session
+///
+/// Large files take a while, so `session-parse-progress` events report how
+/// far parsing has got, followed by `session-parse-complete` on success.
 #[tauri::command]
-pub async fn get_codex_session(session_id: String) -> Result<CodexSession> {
-    tokio::task::spawn_blocking(move || crate::codex_import::get_session(&session_id))
+pub async fn get_codex_session(app_handle: AppHandle, session_id: String) -> Result<CodexSession> {
+    tokio::task::spawn_blocking(move || {
+        let session = crate::codex_import::get_session_with_progress(
+            &session_id,
+            |bytes_read, total_bytes| {
+                let progress = SessionParseProgressEvent {
+                    session_id: session_id.clone(),
+                    bytes_read,
+                    total_bytes,
+                };
+                if let Err(e) = app_handle.emit("session-parse-progress", progress) {
+                    tracing::warn!("Failed to emit session parse progress: {}", e);
+                }
+            },
+        )?;
+
+        let complete = SessionParseCompleteEvent {
+            session_id: session_id.clone(),
+            message_count: session.messages.len(),
+        };
+        if let Err(e) = app_handle.emit("session-parse-complete", complete) {
+            tracing::warn!("Failed to emit session parse completion: {}", e);
+        }
+        Ok(session)
+    })
+    .await
+    .map_err(|e| crate::Error::Other(format!("Task join error: {e}")))?
+}
+
+/// Dry-parse a Codex CLI session file and report why it may fail to load
+///
+/// Unlike listing, which skips bad lines and unreadable sessions silently,
+/// this collects every problem without failing outright.
+#[tauri::command]
+pub async fn validate_codex_session(session_id: String) -> Result<SessionValidation> {
+    tokio::task::spawn_blocking(move || crate::codex_import::validate_session(&session_id))
         .await
         .map_err(|e| crate::Error::Other(format!("Task join error: {e}")))?
 }
 
+/// Get the exact source line of a session message, for debugging malformed messages
+#[tauri::command]
+pub async fn get_codex_session_message_raw(
+    session_id: String,
+    message_index: usize,
+) -> Result<String> {
+    tokio::task::spawn_blocking(move || {
+        crate::codex_import::get_session_message_raw(&session_id, message_index)
+    })
+    .await
+    .map_err(|e| crate::Error::Other(format!("Task join error: {e}")))?
+}
+
 /// Search Codex CLI sessions by keyword
 #[tauri::command]
 pub async fn search_codex_sessions(
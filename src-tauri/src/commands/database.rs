@@ -0,0 +1,26 @@
+//! Database maintenance commands
+
+use tauri::State;
+
+use crate::database::DatabaseHealth;
+use crate::state::AppState;
+use crate::Result;
+
+/// Check the database for corruption and orphaned rows
+#[tauri::command]
+pub async fn validate_database_health(state: State<'_, AppState>) -> Result<DatabaseHealth> {
+    let db = state.database.clone();
+    let health = tokio::task::spawn_blocking(move || db.check_health())
+        .await
+        .map_err(|e| crate::Error::Other(format!("Task join error: {e}")))??;
+    *state.last_database_health.lock() = Some(health.clone());
+    Ok(health)
+}
+
+/// The report of the most recent health check, or None while the startup check is running
+#[tauri::command]
+pub async fn get_last_database_health(
+    state: State<'_, AppState>,
+) -> Result<Option<DatabaseHealth>> {
+    Ok(state.last_database_health.lock().clone())
+}
@@ -5,7 +5,9 @@
 pub mod allowlist;
 pub mod app_server;
 pub mod codex_import;
+pub mod database;
 pub mod projects;
 pub mod sessions;
 pub mod snapshots;
+pub mod tasks;
 pub mod thread;
@@ -1,11 +1,11 @@
 //! Project management commands
 
-use std::path::Path;
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
 
 use tauri::State;
 
-use crate::database::{Project, ProjectSettings};
+use crate::database::{Database, Project, ProjectBundle, ProjectBundleImport, ProjectSettings};
 use crate::state::AppState;
 use crate::Result;
 
@@ -63,8 +63,7 @@ fn validate_arg_safe(arg: &str) -> Result<()> {
 /// Validate git branch name
 /// Only allows safe characters: alphanumeric, underscore, hyphen, dot, and forward slash
 /// This prevents command injection through malicious branch names
-#[allow(dead_code)]
-fn validate_branch_name(branch: &str) -> Result<()> {
+pub(crate) fn validate_branch_name(branch: &str) -> Result<()> {
     if branch.is_empty() {
         return Err(crate::Error::Other(
             "Branch name cannot be empty".to_string(),
@@ -112,7 +111,6 @@ fn validate_branch_name(branch: &str) -> Result<()> {
 }
 
 /// Validate git commit SHA (hexadecimal string only)
-#[allow(dead_code)]
 fn validate_commit_sha(sha: &str) -> Result<()> {
     // Only allow hexadecimal characters (0-9, a-f, A-F)
     if !sha.chars().all(|c| c.is_ascii_hexdigit()) {
@@ -170,12 +168,136 @@ fn validate_limit(limit: u32) -> Result<u32> {
     Ok(limit)
 }
 
+/// Find the project containing `path`: an exact match, else the nearest ancestor
+///
+/// Both sides are canonicalized when possible so symlinked or
+/// trailing-slash variants still match.
+pub(crate) fn find_project_for_path(projects: &[Project], path: &Path) -> Option<Project> {
+    let target = path.canonicalize().unwrap_or_else(|_| path.to_path_buf());
+
+    projects
+        .iter()
+        .filter_map(|p| {
+            let project_path = Path::new(&p.path);
+            let project_path = project_path
+                .canonicalize()
+                .unwrap_or_else(|_| project_path.to_path_buf());
+            target
+                .starts_with(&project_path)
+                .then(|| (project_path.components().count(), p))
+        })
+        .max_by_key(|(depth, _)| *depth)
+        .map(|(_, p)| p.clone())
+}
+
+/// Find the project a working directory belongs to
+#[tauri::command]
+pub async fn find_project_for_cwd(
+    state: State<'_, AppState>,
+    cwd: String,
+) -> Result<Option<Project>> {
+    if cwd.trim().is_empty() || cwd.contains('\0') {
+        return Err(crate::Error::InvalidPath(format!("Invalid cwd: {cwd:?}")));
+    }
+    let projects = state.database.get_all_projects()?;
+    Ok(find_project_for_path(&projects, Path::new(&cwd)))
+}
+
 /// List all projects
 #[tauri::command]
 pub async fn list_projects(state: State<'_, AppState>) -> Result<Vec<Project>> {
     state.database.get_all_projects()
 }
 
+/// Projects whose path no longer resolves to a directory on disk
+fn projects_with_missing_paths(projects: Vec<Project>) -> Vec<Project> {
+    projects
+        .into_iter()
+        .filter(|p| {
+            !Path::new(&p.path)
+                .canonicalize()
+                .is_ok_and(|path| path.is_dir())
+        })
+        .collect()
+}
+
+/// List projects whose directory was moved or deleted, so they can be updated or removed
+#[tauri::command]
+pub async fn find_projects_with_missing_paths(state: State<'_, AppState>) -> Result<Vec<Project>> {
+    let projects = state.database.get_all_projects()?;
+    tokio::task::spawn_blocking(move || projects_with_missing_paths(projects))
+        .await
+        .map_err(|e| crate::Error::Other(format!("Task join error: {e}")))
+}
+
+/// Canonical form of a project path, falling back to the raw path if it can't be resolved
+fn canonical_project_path(path: &str) -> PathBuf {
+    Path::new(path)
+        .canonicalize()
+        .unwrap_or_else(|_| PathBuf::from(path))
+}
+
+/// Find a project, other than `exclude_id`, whose path resolves to the same directory as `path`
+fn find_project_with_same_path<'a>(
+    projects: &'a [Project],
+    path: &str,
+    exclude_id: Option<&str>,
+) -> Option<&'a Project> {
+    let target = canonical_project_path(path);
+    projects
+        .iter()
+        .filter(|p| Some(p.id.as_str()) != exclude_id)
+        .find(|p| p.path == path || canonical_project_path(&p.path) == target)
+}
+
+/// Projects that resolve to the same directory
+#[derive(Debug, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DuplicateProjectGroup {
+    pub canonical_path: String,
+    /// Oldest first, so the first project is the natural merge target
+    pub projects: Vec<Project>,
+}
+
+/// Group projects by canonical path, keeping only groups with more than one project
+fn group_duplicate_projects(projects: Vec<Project>) -> Vec<DuplicateProjectGroup> {
+    let mut groups: Vec<DuplicateProjectGroup> = Vec::new();
+    for project in projects {
+        let canonical_path = canonical_project_path(&project.path)
+            .to_string_lossy()
+            .to_string();
+        match groups
+            .iter_mut()
+            .find(|g| g.canonical_path == canonical_path)
+        {
+            Some(group) => group.projects.push(project),
+            None => groups.push(DuplicateProjectGroup {
+                canonical_path,
+                projects: vec![project],
+            }),
+        }
+    }
+
+    groups.retain(|g| g.projects.len() > 1);
+    for group in &mut groups {
+        group.projects.sort_by_key(|p| p.created_at);
+    }
+    groups
+}
+
+/// List projects added more than once under different spellings of the same path
+///
+/// Trailing slashes and symlinks both produce duplicates; the UI can offer to merge them.
+#[tauri::command]
+pub async fn find_duplicate_projects(
+    state: State<'_, AppState>,
+) -> Result<Vec<DuplicateProjectGroup>> {
+    let projects = state.database.get_all_projects()?;
+    tokio::task::spawn_blocking(move || group_duplicate_projects(projects))
+        .await
+        .map_err(|e| crate::Error::Other(format!("Task join error: {e}")))
+}
+
 /// Add a new project
 #[tauri::command]
 pub async fn add_project(state: State<'_, AppState>, path: String) -> Result<Project> {
@@ -186,9 +308,9 @@ pub async fn add_project(state: State<'_, AppState>, path: String) -> Result<Pro
         )));
     }
 
-    // Check if already added
+    // Check if already added, under this or another spelling of the path
     let existing = state.database.get_all_projects()?;
-    if existing.iter().any(|p| p.path == path) {
+    if find_project_with_same_path(&existing, &path, None).is_some() {
         return Err(crate::Error::Other("Project already exists".to_string()));
     }
 
@@ -223,8 +345,6 @@ pub async fn update_project(
         .get_project(&id)?
         .ok_or_else(|| crate::Error::ProjectNotFound(id.clone()))?;
 
-    // For now, we'll need to delete and re-insert
-    // A proper UPDATE query would be better
     let mut updated = project;
     if let Some(name) = display_name {
         updated.display_name = Some(name);
@@ -233,12 +353,169 @@ pub async fn update_project(
         updated.settings_json = Some(serde_json::to_string(&s).unwrap_or_default());
     }
 
-    state.database.delete_project(&id)?;
-    state.database.insert_project(&updated)?;
+    state.database.update_project(&updated)?;
 
     Ok(updated)
 }
 
+/// Turn automatic snapshots before every turn on or off for a project
+#[tauri::command]
+pub async fn set_project_auto_snapshot(
+    state: State<'_, AppState>,
+    id: String,
+    enabled: bool,
+) -> Result<Project> {
+    validate_id(&id, "project_id")?;
+    let mut project = state
+        .database
+        .get_project(&id)?
+        .ok_or_else(|| crate::Error::ProjectNotFound(id.clone()))?;
+
+    let settings = ProjectSettings {
+        auto_snapshot: enabled,
+        ..project.settings()
+    };
+    let settings_json = serde_json::to_string(&settings)?;
+    state
+        .database
+        .update_project_settings(&id, &settings_json)?;
+    project.settings_json = Some(settings_json);
+    Ok(project)
+}
+
+/// Validate `new_path` and point the project at it
+fn repoint_project(db: &Database, id: &str, new_path: &str) -> Result<Project> {
+    let canonical = crate::utils::validate_and_canonicalize_path(new_path)?;
+    if !canonical.is_dir() {
+        return Err(crate::Error::InvalidPath(format!(
+            "Not a directory: {new_path}"
+        )));
+    }
+    let canonical = canonical.to_string_lossy().to_string();
+
+    let projects = db.get_all_projects()?;
+    let mut project = projects
+        .iter()
+        .find(|p| p.id == id)
+        .cloned()
+        .ok_or_else(|| crate::Error::ProjectNotFound(id.to_string()))?;
+
+    if find_project_with_same_path(&projects, &canonical, Some(id)).is_some() {
+        return Err(crate::Error::Other(format!(
+            "Another project already uses {canonical}"
+        )));
+    }
+
+    db.update_project_path(id, &canonical)?;
+    project.path = canonical;
+    Ok(project)
+}
+
+/// Update a project's path after its directory was moved
+///
+/// Sessions, snapshots and allowlist entries stay attached to the project.
+#[tauri::command]
+pub async fn update_project_path(
+    state: State<'_, AppState>,
+    id: String,
+    new_path: String,
+) -> Result<Project> {
+    validate_id(&id, "project_id")?;
+    let project = repoint_project(&state.database, &id, &new_path)?;
+    tracing::info!("Moved project {} to {}", id, project.path);
+    Ok(project)
+}
+
+/// Upper bound on the size of a project bundle being imported
+const MAX_BUNDLE_BYTES: u64 = 256 * 1024 * 1024;
+
+/// Write a project, its sessions, allowlist and snapshot list to a JSON bundle
+///
+/// Snapshot contents are only included with `include_snapshot_contents`,
+/// as file backups can hold whole files.
+#[tauri::command]
+pub async fn export_project_bundle(
+    state: State<'_, AppState>,
+    project_id: String,
+    dest_path: String,
+    include_snapshot_contents: Option<bool>,
+) -> Result<()> {
+    validate_id(&project_id, "project_id")?;
+    let dest = crate::utils::resolve_output_file(&dest_path)?;
+    let bundle = state
+        .database
+        .export_project_bundle(&project_id, include_snapshot_contents.unwrap_or(false))?;
+
+    tokio::fs::write(&dest, serde_json::to_vec_pretty(&bundle)?).await?;
+    tracing::info!("Exported project {} to {:?}", project_id, dest);
+    Ok(())
+}
+
+/// Import a bundle written by `export_project_bundle` as a new project
+#[tauri::command]
+pub async fn import_project_bundle(
+    state: State<'_, AppState>,
+    src_path: String,
+) -> Result<ProjectBundleImport> {
+    let src = crate::utils::validate_and_canonicalize_path(&src_path)?;
+    let metadata = tokio::fs::metadata(&src).await?;
+    if !metadata.is_file() {
+        return Err(crate::Error::InvalidPath(format!(
+            "Not a regular file: {src_path}"
+        )));
+    }
+    if metadata.len() > MAX_BUNDLE_BYTES {
+        return Err(crate::Error::Other(
+            "Project bundle is too large".to_string(),
+        ));
+    }
+
+    let bundle: ProjectBundle = serde_json::from_slice(&tokio::fs::read(&src).await?)?;
+    let existing = state.database.get_all_projects()?;
+    if let Some(project) = find_project_with_same_path(&existing, &bundle.project.path, None) {
+        return Err(crate::Error::Other(format!(
+            "A project already exists at {}",
+            project.path
+        )));
+    }
+
+    let report = state.database.import_project_bundle(&bundle)?;
+    tracing::info!("Imported project bundle as {}", report.project.id);
+    Ok(report)
+}
+
+/// Set a project's cap on message text input size
+///
+/// Pass `None` to go back to the default. Other settings are left unchanged.
+#[tauri::command]
+pub async fn set_project_max_input_bytes(
+    state: State<'_, AppState>,
+    id: String,
+    max_input_bytes: Option<u64>,
+) -> Result<Project> {
+    validate_id(&id, "project_id")?;
+    if max_input_bytes == Some(0) {
+        return Err(crate::Error::Other(
+            "max_input_bytes must be greater than 0".to_string(),
+        ));
+    }
+
+    let mut project = state
+        .database
+        .get_project(&id)?
+        .ok_or_else(|| crate::Error::ProjectNotFound(id.clone()))?;
+
+    let mut settings = project.settings();
+    settings.max_input_bytes = max_input_bytes;
+    let settings_json = serde_json::to_string(&settings)?;
+    state
+        .database
+        .update_project_settings(&id, &settings_json)?;
+    project.settings_json = Some(settings_json);
+
+    Ok(project)
+}
+
 /// Get git information for a project
 #[tauri::command]
 pub async fn get_project_git_info(path: String) -> Result<GitInfo> {
@@ -295,9 +572,67 @@ pub async fn get_project_git_info(path: String) -> Result<GitInfo> {
     })
 }
 
+/// `app_settings` key holding the number of context lines shown around diff hunks
+pub(crate) const DIFF_CONTEXT_LINES_SETTING: &str = "diff_context_lines";
+
+/// Largest accepted `diff_context_lines`
+const MAX_DIFF_CONTEXT_LINES: u32 = 100;
+
+fn validate_diff_context_lines(lines: u32) -> Result<()> {
+    if lines > MAX_DIFF_CONTEXT_LINES {
+        return Err(crate::Error::Other(format!(
+            "Diff context lines must be between 0 and {MAX_DIFF_CONTEXT_LINES}, got {lines}"
+        )));
+    }
+    Ok(())
+}
+
+/// The `-U<n>` flag for git diff, or None to use git's default of 3
+fn diff_context_arg(lines: Option<u32>) -> Option<String> {
+    lines.map(|n| format!("-U{n}"))
+}
+
+/// Read the saved `diff_context_lines`, ignoring values that are no longer valid
+pub(crate) fn diff_context_lines(db: &Database) -> Result<Option<u32>> {
+    let Some(value) = db.get_setting(DIFF_CONTEXT_LINES_SETTING)? else {
+        return Ok(None);
+    };
+    match value.parse::<u32>() {
+        Ok(lines) if validate_diff_context_lines(lines).is_ok() => Ok(Some(lines)),
+        _ => {
+            tracing::warn!(
+                "Ignoring invalid {} setting: {:?}",
+                DIFF_CONTEXT_LINES_SETTING,
+                value
+            );
+            Ok(None)
+        }
+    }
+}
+
+/// Get the number of context lines shown around diff hunks, if set
+#[tauri::command]
+pub async fn get_diff_context_lines(state: State<'_, AppState>) -> Result<Option<u32>> {
+    diff_context_lines(&state.database)
+}
+
+/// Set the number of context lines shown around diff hunks, or restore git's default with `None`
+#[tauri::command]
+pub async fn set_diff_context_lines(state: State<'_, AppState>, lines: Option<u32>) -> Result<()> {
+    if let Some(lines) = lines {
+        validate_diff_context_lines(lines)?;
+    }
+    state.database.set_setting(
+        DIFF_CONTEXT_LINES_SETTING,
+        lines.map(|n| n.to_string()).as_deref(),
+    )
+}
+
 /// Get git diff for a project (tracked + untracked)
+///
+/// Hunks use the `diff_context_lines` setting for context, if set.
 #[tauri::command]
-pub async fn get_project_git_diff(path: String) -> Result<GitDiff> {
+pub async fn get_project_git_diff(state: State<'_, AppState>, path: String) -> Result<GitDiff> {
     // Security: Canonicalize to prevent symlink attacks and traversal
     let canonical_path = crate::utils::validate_and_canonicalize_path(&path)?;
 
@@ -308,28 +643,513 @@ pub async fn get_project_git_diff(path: String) -> Result<GitDiff> {
         });
     }
 
-    let tracked_diff = run_git_capture_diff(&canonical_path, &["diff"])?;
-    let untracked_output =
-        run_git_capture_stdout(&canonical_path, &["ls-files", "--others", "--exclude-standard"])?;
+    let context_lines = diff_context_lines(&state.database)?;
+    Ok(GitDiff {
+        is_git_repo: true,
+        diff: collect_project_diff(&canonical_path, true, context_lines)?,
+    })
+}
+
+/// Build the unified diff of the working tree, optionally including untracked files
+///
+/// `context_lines` overrides git's default context for tracked changes;
+/// untracked files are shown whole either way.
+fn collect_project_diff(
+    canonical_path: &Path,
+    include_untracked: bool,
+    context_lines: Option<u32>,
+) -> Result<String> {
+    let context_arg = diff_context_arg(context_lines);
+    let mut args = vec!["diff"];
+    args.extend(context_arg.as_deref());
+    let tracked_diff = run_git_capture_diff(canonical_path, &args)?;
+    if !include_untracked {
+        return Ok(tracked_diff);
+    }
+
+    let untracked_output = run_git_capture_stdout(
+        canonical_path,
+        &["ls-files", "--others", "--exclude-standard"],
+    )?;
 
     let mut untracked_diff = String::new();
     let null_path = if cfg!(windows) { "NUL" } else { "/dev/null" };
 
-    for file in untracked_output.lines().map(str::trim).filter(|s| !s.is_empty()) {
+    for file in untracked_output
+        .lines()
+        .map(str::trim)
+        .filter(|s| !s.is_empty())
+    {
         // Security: Validate file path argument to prevent command injection
         validate_git_file_path(file)?;
 
         // Use safe argument construction with "--" separator to prevent option injection
-        let diff_result = run_git_diff_file(&canonical_path, null_path, file);
+        let diff_result = run_git_diff_file(canonical_path, null_path, file);
         if let Ok(diff) = diff_result {
             untracked_diff.push_str(&diff);
         }
     }
 
+    Ok(format!("{tracked_diff}{untracked_diff}"))
+}
+
+/// Line counts for one changed file
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FileStat {
+    pub path: String,
+    /// None for binary files
+    pub insertions: Option<u64>,
+    /// None for binary files
+    pub deletions: Option<u64>,
+    pub binary: bool,
+    pub untracked: bool,
+}
+
+/// Summary of working tree changes without the diff itself
+#[derive(Debug, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DiffStat {
+    pub is_git_repo: bool,
+    pub files_changed: usize,
+    /// Total inserted lines, excluding binary files
+    pub insertions: u64,
+    /// Total deleted lines, excluding binary files
+    pub deletions: u64,
+    pub per_file: Vec<FileStat>,
+}
+
+/// Parse `git diff --numstat -z` output (without renames)
+///
+/// Each record is `<added>\t<deleted>\t<path>\0`, with `-` counts for binary files.
+fn parse_numstat_z(output: &str) -> Vec<FileStat> {
+    output
+        .split('\0')
+        .filter_map(|record| {
+            let mut fields = record.splitn(3, '\t');
+            let added = fields.next()?;
+            let deleted = fields.next()?;
+            let path = fields.next().filter(|p| !p.is_empty())?;
+            let binary = added == "-" && deleted == "-";
+            Some(FileStat {
+                path: path.to_string(),
+                insertions: added.parse().ok(),
+                deletions: deleted.parse().ok(),
+                binary,
+                untracked: false,
+            })
+        })
+        .collect()
+}
+
+/// Stat an untracked file as all insertions, or binary if it looks like one
+///
+/// Symlinks and other non-regular files are rejected rather than followed.
+/// The file is read in chunks, so large files aren't loaded into memory.
+fn untracked_file_stat(canonical_path: &Path, relative_path: &str) -> Result<FileStat> {
+    use std::io::Read;
+
+    let file_path = canonical_path.join(relative_path);
+    if !std::fs::symlink_metadata(&file_path)?.file_type().is_file() {
+        return Err(crate::Error::InvalidPath(format!(
+            "Not a regular file: {relative_path}"
+        )));
+    }
+
+    let mut file = std::fs::File::open(&file_path)?;
+    let mut prefix = Vec::with_capacity(TEXT_SNIFF_BYTES as usize);
+    (&mut file)
+        .take(TEXT_SNIFF_BYTES)
+        .read_to_end(&mut prefix)?;
+    let binary = !is_text_prefix(&prefix);
+
+    let insertions = if binary {
+        None
+    } else {
+        let mut newlines = prefix.iter().filter(|&&b| b == b'\n').count() as u64;
+        let mut last = prefix.last().copied();
+        let mut buf = vec![0u8; 64 * 1024];
+        loop {
+            let n = file.read(&mut buf)?;
+            if n == 0 {
+                break;
+            }
+            newlines += buf[..n].iter().filter(|&&b| b == b'\n').count() as u64;
+            last = Some(buf[n - 1]);
+        }
+        Some(newlines + u64::from(last.is_some_and(|b| b != b'\n')))
+    };
+
+    Ok(FileStat {
+        path: relative_path.to_string(),
+        insertions,
+        deletions: (!binary).then_some(0),
+        binary,
+        untracked: true,
+    })
+}
+
+/// Get insertion/deletion counts for the working tree, including untracked files
+#[tauri::command]
+pub async fn get_git_diff_stat(path: String) -> Result<DiffStat> {
+    // Security: Canonicalize to prevent symlink attacks and traversal
+    let canonical_path = crate::utils::validate_and_canonicalize_path(&path)?;
+
+    tokio::task::spawn_blocking(move || {
+        if !inside_git_repo(&canonical_path)? {
+            return Ok(DiffStat {
+                is_git_repo: false,
+                files_changed: 0,
+                insertions: 0,
+                deletions: 0,
+                per_file: Vec::new(),
+            });
+        }
+
+        let numstat = run_git_capture_diff(
+            &canonical_path,
+            &["diff", "--numstat", "-z", "--no-renames"],
+        )?;
+        let mut per_file = parse_numstat_z(&numstat);
+
+        let untracked = run_git_capture_stdout(
+            &canonical_path,
+            &["ls-files", "-z", "--others", "--exclude-standard"],
+        )?;
+        for file in untracked.split('\0').filter(|s| !s.is_empty()) {
+            // Security: Validate file path before touching the filesystem
+            validate_git_file_path(file)?;
+            match untracked_file_stat(&canonical_path, file) {
+                Ok(stat) => per_file.push(stat),
+                Err(e) => tracing::debug!("Skipping untracked file {}: {}", file, e),
+            }
+        }
+
+        Ok(DiffStat {
+            is_git_repo: true,
+            files_changed: per_file.len(),
+            insertions: per_file.iter().filter_map(|f| f.insertions).sum(),
+            deletions: per_file.iter().filter_map(|f| f.deletions).sum(),
+            per_file,
+        })
+    })
+    .await
+    .map_err(|e| crate::Error::Other(format!("Task join error: {e}")))?
+}
+
+/// How a file changed relative to HEAD
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum FileChangeStatus {
+    Added,
+    Modified,
+    Deleted,
+    Renamed,
+}
+
+/// Line counts and change kind for one file, without its hunks
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DiffFileSummary {
+    pub path: String,
+    /// Previous path of a renamed file, or the source of a copied one
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub old_path: Option<String>,
+    /// None for binary files
+    pub additions: Option<u64>,
+    /// None for binary files
+    pub deletions: Option<u64>,
+    pub status: FileChangeStatus,
+}
+
+/// Object name of git's empty tree, diffed against in a repo with no commits yet
+const EMPTY_TREE_SHA: &str = "4b825dc642cb6eb9a060e54bf8d69288fbee4904";
+
+/// Parse `git diff --name-status -z` output into (status, old path, path)
+///
+/// Renames and copies carry two paths (`R100\0old\0new\0`); everything else one.
+fn parse_name_status_z(output: &str) -> Vec<(FileChangeStatus, Option<String>, String)> {
+    let mut fields = output.split('\0').filter(|f| !f.is_empty());
+    let mut entries = Vec::new();
+    while let Some(code) = fields.next() {
+        let (status, old_path) = match code.as_bytes()[0] {
+            b'R' | b'C' => {
+                let Some(old_path) = fields.next() else { break };
+                let status = if code.starts_with('R') {
+                    FileChangeStatus::Renamed
+                } else {
+                    FileChangeStatus::Added
+                };
+                (status, Some(old_path.to_string()))
+            }
+            b'A' => (FileChangeStatus::Added, None),
+            b'D' => (FileChangeStatus::Deleted, None),
+            _ => (FileChangeStatus::Modified, None),
+        };
+        let Some(path) = fields.next() else { break };
+        entries.push((status, old_path, path.to_string()));
+    }
+    entries
+}
+
+/// Parse `git diff --numstat -z` output with renames into (path, additions, deletions)
+///
+/// A rename's record has an empty path followed by `old\0new\0`; it is keyed by the new path.
+fn parse_numstat_renames_z(output: &str) -> HashMap<String, (Option<u64>, Option<u64>)> {
+    let mut fields = output.split('\0');
+    let mut counts = HashMap::new();
+    while let Some(record) = fields.next() {
+        let mut parts = record.splitn(3, '\t');
+        let (Some(added), Some(deleted), Some(path)) = (parts.next(), parts.next(), parts.next())
+        else {
+            continue;
+        };
+        let path = if path.is_empty() {
+            let (Some(_old), Some(new)) = (fields.next(), fields.next()) else {
+                break;
+            };
+            new
+        } else {
+            path
+        };
+        counts.insert(path.to_string(), (added.parse().ok(), deleted.parse().ok()));
+    }
+    counts
+}
+
+/// Summarize every change against HEAD (staged or not), plus untracked files as additions
+fn diff_file_summaries(canonical_path: &Path) -> Result<Vec<DiffFileSummary>> {
+    let base = if run_git_capture_stdout(
+        canonical_path,
+        &["rev-parse", "--verify", "--quiet", "HEAD"],
+    )
+    .is_ok()
+    {
+        "HEAD"
+    } else {
+        EMPTY_TREE_SHA
+    };
+    let name_status = run_git_capture_diff(
+        canonical_path,
+        &["diff", "--name-status", "-z", "-M", base, "--"],
+    )?;
+    let numstat = run_git_capture_diff(
+        canonical_path,
+        &["diff", "--numstat", "-z", "-M", base, "--"],
+    )?;
+    let counts = parse_numstat_renames_z(&numstat);
+
+    let mut summaries: Vec<DiffFileSummary> = parse_name_status_z(&name_status)
+        .into_iter()
+        .map(|(status, old_path, path)| {
+            let (additions, deletions) = counts.get(&path).copied().unwrap_or((None, None));
+            DiffFileSummary {
+                path,
+                old_path,
+                additions,
+                deletions,
+                status,
+            }
+        })
+        .collect();
+
+    let untracked = run_git_capture_stdout(
+        canonical_path,
+        &["ls-files", "-z", "--others", "--exclude-standard"],
+    )?;
+    for file in untracked.split('\0').filter(|s| !s.is_empty()) {
+        // Security: Validate file path before touching the filesystem
+        validate_git_file_path(file)?;
+        match untracked_file_stat(canonical_path, file) {
+            Ok(stat) => summaries.push(DiffFileSummary {
+                path: stat.path,
+                old_path: None,
+                additions: stat.insertions,
+                deletions: stat.deletions,
+                status: FileChangeStatus::Added,
+            }),
+            Err(e) => tracing::debug!("Skipping untracked file {}: {}", file, e),
+        }
+    }
+
+    summaries.sort_by(|a, b| a.path.cmp(&b.path));
+    Ok(summaries)
+}
+
+/// Get per-file addition/deletion counts and change status for the working tree
+///
+/// Returns an empty list outside a git repository.
+#[tauri::command]
+pub async fn get_diff_file_summaries(path: String) -> Result<Vec<DiffFileSummary>> {
+    // Security: Canonicalize to prevent symlink attacks and traversal
+    let canonical_path = crate::utils::validate_and_canonicalize_path(&path)?;
+
+    tokio::task::spawn_blocking(move || {
+        if !inside_git_repo(&canonical_path)? {
+            return Ok(Vec::new());
+        }
+        diff_file_summaries(&canonical_path)
+    })
+    .await
+    .map_err(|e| crate::Error::Other(format!("Task join error: {e}")))?
+}
+
+/// Get the diff of HEAD against another branch, from their merge base
+///
+/// Equivalent to `git diff <branch>...HEAD`: only changes made on the current
+/// branch since it diverged from `branch` are included.
+#[tauri::command]
+pub async fn get_branch_diff(path: String, branch: String) -> Result<GitDiff> {
+    // Security: Canonicalize to prevent symlink attacks and traversal
+    let canonical_path = crate::utils::validate_and_canonicalize_path(&path)?;
+    // Security: Validate branch name to prevent option injection
+    validate_branch_name(&branch)?;
+
+    if !inside_git_repo(&canonical_path)? {
+        return Ok(GitDiff {
+            is_git_repo: false,
+            diff: String::new(),
+        });
+    }
+
+    let branch_exists = std::process::Command::new("git")
+        .args(["rev-parse", "--verify", "--quiet"])
+        .arg(format!("{branch}^{{commit}}"))
+        .current_dir(&canonical_path)
+        .output()
+        .map(|o| o.status.success())
+        .unwrap_or(false);
+    if !branch_exists {
+        return Err(crate::Error::Git(format!("Branch not found: {branch}")));
+    }
+
+    let range = format!("{branch}...HEAD");
+    let diff = run_git_capture_diff(&canonical_path, &["diff", &range, "--"])?;
+
     Ok(GitDiff {
         is_git_repo: true,
-        diff: format!("{tracked_diff}{untracked_diff}"),
+        diff,
+    })
+}
+
+/// How a directory has changed since an imported Codex CLI session ran in it
+#[derive(Debug, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SessionDrift {
+    pub session_id: String,
+    /// Working directory recorded by the session
+    pub cwd: String,
+    /// Commit the session recorded, if any
+    pub recorded_commit: Option<String>,
+    pub is_git_repo: bool,
+    /// Whether the recorded commit exists in the repository
+    pub commit_found: bool,
+    /// `git diff --stat <recorded>..HEAD`; empty if nothing changed or the commit is unknown
+    pub stat: String,
+}
+
+/// Diff stat of `cwd` from `commit` to HEAD, as (is_git_repo, commit_found, stat)
+///
+/// A missing directory, non-repository or unknown commit is reported rather
+/// than treated as an error, since the session may predate any of them.
+fn drift_since_commit(cwd: &Path, commit: Option<&str>) -> Result<(bool, bool, String)> {
+    if !cwd.is_dir() || !inside_git_repo(cwd)? {
+        return Ok((false, false, String::new()));
+    }
+    // Security: Only plain hex shas, so the value can't be read as an option
+    let Some(commit) =
+        commit.filter(|c| (4..=64).contains(&c.len()) && c.bytes().all(|b| b.is_ascii_hexdigit()))
+    else {
+        return Ok((true, false, String::new()));
+    };
+
+    let commit_exists = std::process::Command::new("git")
+        .args(["rev-parse", "--verify", "--quiet"])
+        .arg(format!("{commit}^{{commit}}"))
+        .current_dir(cwd)
+        .output()
+        .map(|o| o.status.success())
+        .unwrap_or(false);
+    if !commit_exists {
+        return Ok((true, false, String::new()));
+    }
+
+    let range = format!("{commit}..HEAD");
+    let stat = run_git_capture_diff(cwd, &["diff", "--stat", &range, "--"])?;
+    Ok((true, true, stat))
+}
+
+/// Show what changed since an imported Codex CLI session ran
+///
+/// Uses the commit the session recorded in its metadata and diffs it against
+/// the current HEAD of the session's working directory.
+#[tauri::command]
+pub async fn get_imported_session_drift(session_id: String) -> Result<SessionDrift> {
+    tokio::task::spawn_blocking(move || {
+        let session = crate::codex_import::get_session(&session_id)?;
+        let summary = session.summary;
+        let (is_git_repo, commit_found, stat) =
+            drift_since_commit(Path::new(&summary.cwd), summary.git_commit.as_deref())?;
+
+        Ok(SessionDrift {
+            session_id: summary.id,
+            cwd: summary.cwd,
+            recorded_commit: summary.git_commit,
+            is_git_repo,
+            commit_found,
+            stat,
+        })
     })
+    .await
+    .map_err(|e| crate::Error::Other(format!("Task join error: {e}")))?
+}
+
+/// Resolve the patch destination, refusing paths inside the repository
+fn resolve_patch_dest(repo_root: &Path, dest_path: &str) -> Result<std::path::PathBuf> {
+    let resolved = crate::utils::resolve_output_file(dest_path)?;
+
+    // Security: Writing into the repo could clobber tracked files or end up in the patch itself
+    if resolved.starts_with(repo_root) {
+        return Err(crate::Error::InvalidPath(
+            "Patch destination must be outside the repository".to_string(),
+        ));
+    }
+
+    Ok(resolved)
+}
+
+/// Write the project's current git diff to a patch file outside the repository
+///
+/// Returns the number of bytes written.
+#[tauri::command]
+pub async fn export_git_patch(
+    path: String,
+    dest_path: String,
+    include_untracked: Option<bool>,
+) -> Result<u64> {
+    // Security: Canonicalize to prevent symlink attacks and traversal
+    let canonical_path = crate::utils::validate_and_canonicalize_path(&path)?;
+
+    tokio::task::spawn_blocking(move || {
+        if !inside_git_repo(&canonical_path)? {
+            return Err(crate::Error::Git(format!(
+                "Not a git repository: {}",
+                canonical_path.display()
+            )));
+        }
+
+        let toplevel = run_git_capture_stdout(&canonical_path, &["rev-parse", "--show-toplevel"])?;
+        let repo_root = Path::new(toplevel.trim()).canonicalize()?;
+        let dest = resolve_patch_dest(&repo_root, &dest_path)?;
+
+        let patch = collect_project_diff(&canonical_path, include_untracked.unwrap_or(true), None)?;
+        std::fs::write(&dest, patch.as_bytes())?;
+
+        tracing::info!("Exported {} byte patch to {}", patch.len(), dest.display());
+        Ok(patch.len() as u64)
+    })
+    .await
+    .map_err(|e| crate::Error::Other(format!("Task join error: {e}")))?
 }
 
 /// Git repository information
@@ -531,7 +1351,13 @@ fn collect_files_recursive(
         let path = entry.path();
         let file_name = match path.file_name().and_then(|n| n.to_str()) {
             Some(n) => n.to_string(),
-            None => continue,
+            None => {
+                tracing::warn!(
+                    "Skipping non-UTF-8 path while listing project files: {}",
+                    path.display()
+                );
+                continue;
+            }
         };
 
         // Skip hidden files (except specific ones)
@@ -546,10 +1372,10 @@ fn collect_files_recursive(
             continue;
         }
 
-        // Get relative path
-        let relative_path = match path.strip_prefix(root) {
-            Ok(p) => p.to_string_lossy().to_string(),
-            Err(_) => continue,
+        // Get relative path; every component was checked to be UTF-8 on the way down
+        let relative_path = match path.strip_prefix(root).ok().and_then(|p| p.to_str()) {
+            Some(p) => p.to_string(),
+            None => continue,
         };
 
         // Apply query filter (fuzzy match on path and name)
@@ -601,6 +1427,140 @@ fn fuzzy_match(target: &str, query: &str) -> bool {
     true
 }
 
+/// Number of leading bytes inspected when classifying a file as text
+const TEXT_SNIFF_BYTES: u64 = 8 * 1024;
+
+/// Classify a byte prefix as text: no NUL bytes and valid UTF-8.
+///
+/// A multi-byte character cut off at the end of the prefix is tolerated,
+/// since the prefix may end mid-character.
+fn is_text_prefix(bytes: &[u8]) -> bool {
+    if bytes.contains(&0) {
+        return false;
+    }
+
+    match std::str::from_utf8(bytes) {
+        Ok(_) => true,
+        // error_len() == None means the input ended inside a valid sequence
+        Err(e) => e.error_len().is_none(),
+    }
+}
+
+/// Outcome of validating a project path without acting on it
+#[derive(Debug, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PathCheck {
+    pub valid: bool,
+    pub error: Option<crate::PathValidationError>,
+    pub message: Option<String>,
+}
+
+impl From<Result<std::path::PathBuf>> for PathCheck {
+    fn from(result: Result<std::path::PathBuf>) -> Self {
+        match result {
+            Ok(_) => Self {
+                valid: true,
+                error: None,
+                message: None,
+            },
+            Err(e) => Self {
+                valid: false,
+                error: e.path_validation_kind(),
+                message: Some(e.to_string()),
+            },
+        }
+    }
+}
+
+/// Check a project root, or a file relative to it, and report why it would be rejected
+///
+/// Uses the same rules as the commands that accept these paths, so the
+/// frontend can show specific guidance before submitting.
+#[tauri::command]
+pub async fn check_project_path(path: String, relative_path: Option<String>) -> Result<PathCheck> {
+    let result = match relative_path {
+        Some(relative_path) => crate::utils::safe_join(&path, &relative_path),
+        None => crate::utils::validate_and_canonicalize_path(&path),
+    };
+    Ok(result.into())
+}
+
+/// Check whether a project file is text (safe to send as context) or binary
+///
+/// Only the first 8KB of the file are read.
+#[tauri::command]
+pub async fn is_file_text(path: String, relative_path: String) -> Result<bool> {
+    use std::io::Read;
+
+    // Security: Resolve within the project root to prevent traversal
+    let file_path = crate::utils::safe_join(&path, &relative_path)?;
+
+    if !file_path.is_file() {
+        return Err(crate::Error::InvalidPath(format!(
+            "Not a regular file: {relative_path}"
+        )));
+    }
+
+    let file = std::fs::File::open(&file_path)?;
+    let mut prefix = Vec::with_capacity(TEXT_SNIFF_BYTES as usize);
+    file.take(TEXT_SNIFF_BYTES).read_to_end(&mut prefix)?;
+
+    Ok(is_text_prefix(&prefix))
+}
+
+/// Lockfiles recognized at a project root, and the package manager each implies
+const LOCKFILES: &[(&str, &str)] = &[
+    ("package-lock.json", "npm"),
+    ("npm-shrinkwrap.json", "npm"),
+    ("yarn.lock", "yarn"),
+    ("pnpm-lock.yaml", "pnpm"),
+    ("bun.lockb", "bun"),
+    ("bun.lock", "bun"),
+    ("Cargo.lock", "cargo"),
+    ("poetry.lock", "poetry"),
+    ("Pipfile.lock", "pipenv"),
+    ("uv.lock", "uv"),
+    ("pdm.lock", "pdm"),
+    ("Gemfile.lock", "bundler"),
+    ("composer.lock", "composer"),
+    ("go.sum", "go"),
+    ("mix.lock", "mix"),
+    ("pubspec.lock", "pub"),
+    ("Podfile.lock", "cocoapods"),
+];
+
+/// A package manager whose lockfile is present at the project root
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DetectedPackageManager {
+    /// Package manager name, e.g. `npm` or `cargo`
+    pub manager: String,
+    /// Lockfile name that identified it
+    pub lockfile: String,
+}
+
+/// Lockfiles directly in `root`, in `LOCKFILES` order
+fn detect_lockfiles(root: &Path) -> Vec<DetectedPackageManager> {
+    LOCKFILES
+        .iter()
+        .filter(|(lockfile, _)| root.join(lockfile).is_file())
+        .map(|(lockfile, manager)| DetectedPackageManager {
+            manager: manager.to_string(),
+            lockfile: lockfile.to_string(),
+        })
+        .collect()
+}
+
+/// Detect the package managers a project uses from lockfiles at its root
+///
+/// Only the root is checked, not subdirectories. A project can use several
+/// (e.g. `Cargo.lock` and `pnpm-lock.yaml`); an empty list means none found.
+#[tauri::command]
+pub async fn detect_package_manager(path: String) -> Result<Vec<DetectedPackageManager>> {
+    let root = crate::utils::validate_and_canonicalize_path(&path)?;
+    Ok(detect_lockfiles(&root))
+}
+
 /// Git branch entry
 #[derive(Debug, serde::Serialize)]
 #[serde(rename_all = "camelCase")]
@@ -620,6 +1580,179 @@ pub struct GitCommit {
     pub date: String,
 }
 
+/// A git operation in progress that makes editing the working tree unsafe
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum GitOperation {
+    Merge,
+    Rebase,
+    CherryPick,
+    Revert,
+    Bisect,
+}
+
+/// Detect an in-progress merge, rebase, cherry-pick, revert or bisect
+fn git_operation_in_progress(repo_path: &Path) -> Result<Option<GitOperation>> {
+    let git_dir = run_git_capture_stdout(repo_path, &["rev-parse", "--git-dir"])?;
+    let git_dir = repo_path.join(git_dir.trim());
+
+    let markers = [
+        ("MERGE_HEAD", GitOperation::Merge),
+        ("rebase-merge", GitOperation::Rebase),
+        ("rebase-apply", GitOperation::Rebase),
+        ("CHERRY_PICK_HEAD", GitOperation::CherryPick),
+        ("REVERT_HEAD", GitOperation::Revert),
+        ("BISECT_LOG", GitOperation::Bisect),
+    ];
+
+    Ok(markers
+        .iter()
+        .find(|(marker, _)| git_dir.join(marker).exists())
+        .map(|(_, op)| *op))
+}
+
+/// Check out `relative_path` from `rev` into the working tree
+fn git_restore_file_from(path: &str, relative_path: &str, rev: &str) -> Result<()> {
+    // Security: Canonicalize to prevent symlink attacks and traversal
+    let canonical_path = crate::utils::validate_and_canonicalize_path(path)?;
+
+    validate_git_file_path(relative_path)?;
+    let relative = Path::new(relative_path);
+    if relative.is_absolute()
+        || relative
+            .components()
+            .any(|c| matches!(c, std::path::Component::ParentDir))
+    {
+        return Err(crate::Error::InvalidPath(format!(
+            "Path must be relative to the project: {relative_path}"
+        )));
+    }
+
+    if !inside_git_repo(&canonical_path)? {
+        return Err(crate::Error::Git(format!(
+            "Not a git repository: {}",
+            canonical_path.display()
+        )));
+    }
+    if let Some(operation) = git_operation_in_progress(&canonical_path)? {
+        return Err(crate::Error::Git(format!(
+            "Cannot restore files while a {operation:?} is in progress"
+        )));
+    }
+
+    // --literal-pathspecs keeps glob characters in file names from matching other files
+    let output = std::process::Command::new("git")
+        .arg("--literal-pathspecs")
+        .arg("checkout")
+        .arg(rev)
+        .arg("--")
+        .arg(relative_path)
+        .current_dir(&canonical_path)
+        .output()
+        .map_err(|err| crate::Error::Git(format!("Failed to run git checkout: {err}")))?;
+
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(crate::Error::Git(format!(
+            "Failed to restore '{relative_path}' from {rev}: {}",
+            stderr.trim()
+        )));
+    }
+
+    tracing::info!("Restored {} from {}", relative_path, rev);
+    Ok(())
+}
+
+/// Restore a tracked file to its committed (HEAD) version
+#[tauri::command]
+pub async fn git_restore_file(path: String, relative_path: String) -> Result<()> {
+    git_restore_file_from(&path, &relative_path, "HEAD")
+}
+
+/// Restore a tracked file to its version at a specific commit
+#[tauri::command]
+pub async fn git_restore_file_to_commit(
+    path: String,
+    relative_path: String,
+    sha: String,
+) -> Result<()> {
+    // Security: Validate commit SHA to prevent option injection
+    validate_commit_sha(&sha)?;
+    git_restore_file_from(&path, &relative_path, &sha)
+}
+
+/// File change frequency from git history
+#[derive(Debug, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HotspotFile {
+    pub path: String,
+    pub change_count: u32,
+}
+
+/// Tally `git log --name-only --format=` output into files sorted by change count
+fn tally_hotspots(log_output: &str, limit: usize) -> Vec<HotspotFile> {
+    let mut counts: std::collections::HashMap<&str, u32> = std::collections::HashMap::new();
+    for file in log_output.lines().map(str::trim).filter(|s| !s.is_empty()) {
+        *counts.entry(file).or_default() += 1;
+    }
+
+    let mut hotspots: Vec<HotspotFile> = counts
+        .into_iter()
+        .map(|(path, change_count)| HotspotFile {
+            path: path.to_string(),
+            change_count,
+        })
+        .collect();
+    hotspots.sort_by(|a, b| {
+        b.change_count
+            .cmp(&a.change_count)
+            .then_with(|| a.path.cmp(&b.path))
+    });
+    hotspots.truncate(limit);
+    hotspots
+}
+
+/// Get the files changed most often in recent git history
+#[tauri::command]
+pub async fn get_hotspot_files(
+    path: String,
+    since_days: Option<u32>,
+    limit: Option<u32>,
+) -> Result<Vec<HotspotFile>> {
+    const MAX_SINCE_DAYS: u32 = 3650;
+
+    // Security: Canonicalize to prevent symlink attacks and traversal
+    let canonical_path = crate::utils::validate_and_canonicalize_path(&path)?;
+
+    let since_days = since_days.unwrap_or(30);
+    if since_days == 0 || since_days > MAX_SINCE_DAYS {
+        return Err(crate::Error::Other(format!(
+            "since_days must be between 1 and {MAX_SINCE_DAYS}"
+        )));
+    }
+    // Security: Validate limit parameter to prevent excessive resource usage
+    let limit = validate_limit(limit.unwrap_or(20))?;
+
+    if !inside_git_repo(&canonical_path)? {
+        return Ok(Vec::new());
+    }
+
+    let since = format!("--since={since_days} days ago");
+    let output = std::process::Command::new("git")
+        .args(["log", &since, "--name-only", "--format=", "--no-renames"])
+        .current_dir(&canonical_path)
+        .output()
+        .map_err(|err| crate::Error::Other(format!("Failed to run git: {err}")))?;
+
+    // No commits yet (or another git failure): nothing to report
+    if !output.status.success() {
+        return Ok(Vec::new());
+    }
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    Ok(tally_hotspots(&stdout, limit as usize))
+}
+
 /// Get list of git branches for a project
 #[tauri::command]
 pub async fn get_git_branches(path: String) -> Result<Vec<GitBranch>> {
@@ -971,4 +2104,740 @@ mod tests {
             );
         }
     }
+
+    // ==================== is_file_text tests ====================
+
+    #[tokio::test]
+    async fn test_is_file_text_utf8_file() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        std::fs::write(temp_dir.path().join("notes.md"), "# Notes\nhéllo wörld ✓\n").unwrap();
+        let root = temp_dir.path().to_string_lossy().to_string();
+
+        assert!(is_file_text(root, "notes.md".to_string()).await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_is_file_text_binary_file() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        std::fs::write(
+            temp_dir.path().join("image.bin"),
+            [0x89, b'P', b'N', b'G', 0xFF, 0xFE, 0xFD],
+        )
+        .unwrap();
+        let root = temp_dir.path().to_string_lossy().to_string();
+
+        assert!(!is_file_text(root, "image.bin".to_string()).await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_is_file_text_early_nul_byte() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        std::fs::write(temp_dir.path().join("data.txt"), b"ab\0cdef plain ascii").unwrap();
+        let root = temp_dir.path().to_string_lossy().to_string();
+
+        assert!(!is_file_text(root, "data.txt".to_string()).await.unwrap());
+    }
+
+    #[test]
+    fn test_is_text_prefix_tolerates_truncated_char() {
+        // "é" is 0xC3 0xA9; cut after the first byte
+        assert!(is_text_prefix(&[b'a', 0xC3]));
+        assert!(!is_text_prefix(&[b'a', 0xC3, b'b']));
+    }
+
+    // ==================== detect_package_manager tests ====================
+
+    #[tokio::test]
+    async fn test_detect_package_manager_single_lockfile() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        std::fs::write(temp_dir.path().join("yarn.lock"), "").unwrap();
+        let root = temp_dir.path().to_string_lossy().to_string();
+
+        let detected = detect_package_manager(root).await.unwrap();
+        assert_eq!(
+            detected,
+            vec![DetectedPackageManager {
+                manager: "yarn".to_string(),
+                lockfile: "yarn.lock".to_string(),
+            }]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_detect_package_manager_multiple_lockfiles() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        for lockfile in ["poetry.lock", "Cargo.lock", "pnpm-lock.yaml"] {
+            std::fs::write(temp_dir.path().join(lockfile), "").unwrap();
+        }
+        let root = temp_dir.path().to_string_lossy().to_string();
+
+        let managers: Vec<String> = detect_package_manager(root)
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|d| d.manager)
+            .collect();
+        assert_eq!(managers, vec!["pnpm", "cargo", "poetry"]);
+    }
+
+    #[tokio::test]
+    async fn test_detect_package_manager_reads_only_root() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        std::fs::create_dir(temp_dir.path().join("frontend")).unwrap();
+        std::fs::write(temp_dir.path().join("frontend/package-lock.json"), "{}").unwrap();
+        // A directory named like a lockfile isn't one
+        std::fs::create_dir(temp_dir.path().join("Cargo.lock")).unwrap();
+        let root = temp_dir.path().to_string_lossy().to_string();
+
+        assert!(detect_package_manager(root).await.unwrap().is_empty());
+    }
+
+    // ==================== export_git_patch tests ====================
+
+    fn git(dir: &Path, args: &[&str]) -> bool {
+        std::process::Command::new("git")
+            .args(["-c", "user.name=Test", "-c", "user.email=test@example.com"])
+            .args(args)
+            .current_dir(dir)
+            .output()
+            .map(|o| o.status.success())
+            .unwrap_or(false)
+    }
+
+    /// Create a repo with one committed file, or None when git is unavailable
+    fn init_test_repo() -> Option<tempfile::TempDir> {
+        let repo = tempfile::tempdir().unwrap();
+        if !git(repo.path(), &["init", "-q"]) {
+            return None;
+        }
+        std::fs::write(repo.path().join("main.rs"), "fn main() {}\n").unwrap();
+        assert!(git(repo.path(), &["add", "."]));
+        assert!(git(repo.path(), &["commit", "-q", "-m", "init"]));
+        Some(repo)
+    }
+
+    #[test]
+    fn test_drift_since_recorded_commit() {
+        let Some(repo) = init_test_repo() else { return };
+        let recorded = run_git_capture_stdout(repo.path(), &["rev-parse", "HEAD"]).unwrap();
+        let recorded = recorded.trim();
+
+        std::fs::write(repo.path().join("main.rs"), "fn main() {\n    run();\n}\n").unwrap();
+        assert!(git(repo.path(), &["commit", "-q", "-am", "change"]));
+
+        let (is_git_repo, commit_found, stat) =
+            drift_since_commit(repo.path(), Some(recorded)).unwrap();
+        assert!(is_git_repo && commit_found);
+        assert!(stat.contains("main.rs"), "unexpected stat: {stat}");
+
+        // Nothing has changed since HEAD
+        let head = run_git_capture_stdout(repo.path(), &["rev-parse", "HEAD"]).unwrap();
+        assert_eq!(
+            drift_since_commit(repo.path(), Some(head.trim())).unwrap(),
+            (true, true, String::new())
+        );
+    }
+
+    #[test]
+    fn test_drift_with_missing_recorded_commit() {
+        let Some(repo) = init_test_repo() else { return };
+
+        let unknown = "0123456789abcdef0123456789abcdef01234567";
+        assert_eq!(
+            drift_since_commit(repo.path(), Some(unknown)).unwrap(),
+            (true, false, String::new())
+        );
+        assert_eq!(
+            drift_since_commit(repo.path(), None).unwrap(),
+            (true, false, String::new())
+        );
+        assert_eq!(
+            drift_since_commit(repo.path(), Some("--output=/tmp/x")).unwrap(),
+            (true, false, String::new())
+        );
+
+        let missing = repo.path().join("gone");
+        assert_eq!(
+            drift_since_commit(&missing, Some(unknown)).unwrap(),
+            (false, false, String::new())
+        );
+    }
+
+    #[tokio::test]
+    async fn test_export_git_patch_is_reappliable() {
+        let Some(repo) = init_test_repo() else { return };
+        std::fs::write(
+            repo.path().join("main.rs"),
+            "fn main() {\n    println!(\"hi\");\n}\n",
+        )
+        .unwrap();
+        std::fs::write(repo.path().join("new.txt"), "untracked\n").unwrap();
+
+        let out_dir = tempfile::tempdir().unwrap();
+        let dest = out_dir.path().join("changes.patch");
+        let written = export_git_patch(
+            repo.path().to_string_lossy().to_string(),
+            dest.to_string_lossy().to_string(),
+            None,
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(written, std::fs::metadata(&dest).unwrap().len());
+        assert!(written > 0);
+
+        // Reset the working tree and re-apply the exported patch
+        assert!(git(repo.path(), &["checkout", "--", "."]));
+        std::fs::remove_file(repo.path().join("new.txt")).unwrap();
+        assert!(git(repo.path(), &["apply", dest.to_str().unwrap()]));
+
+        assert!(std::fs::read_to_string(repo.path().join("main.rs"))
+            .unwrap()
+            .contains("println!"));
+        assert_eq!(
+            std::fs::read_to_string(repo.path().join("new.txt")).unwrap(),
+            "untracked\n"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_export_git_patch_rejects_dest_inside_repo() {
+        let Some(repo) = init_test_repo() else { return };
+        std::fs::write(repo.path().join("main.rs"), "changed\n").unwrap();
+
+        let dest = repo.path().join("changes.patch");
+        let result = export_git_patch(
+            repo.path().to_string_lossy().to_string(),
+            dest.to_string_lossy().to_string(),
+            Some(false),
+        )
+        .await;
+
+        assert!(matches!(result, Err(crate::Error::InvalidPath(_))));
+        assert!(!dest.exists());
+    }
+
+    // ==================== get_hotspot_files tests ====================
+
+    #[tokio::test]
+    async fn test_get_hotspot_files_ranks_most_changed_first() {
+        let Some(repo) = init_test_repo() else { return };
+        for i in 0..3 {
+            std::fs::write(repo.path().join("hot.rs"), format!("// revision {i}\n")).unwrap();
+            assert!(git(repo.path(), &["add", "."]));
+            assert!(git(
+                repo.path(),
+                &["commit", "-q", "-m", &format!("hot {i}")]
+            ));
+        }
+        std::fs::write(repo.path().join("cold.rs"), "// once\n").unwrap();
+        assert!(git(repo.path(), &["add", "."]));
+        assert!(git(repo.path(), &["commit", "-q", "-m", "cold"]));
+
+        let root = repo.path().to_string_lossy().to_string();
+        let hotspots = get_hotspot_files(root.clone(), Some(7), None)
+            .await
+            .unwrap();
+
+        assert_eq!(hotspots[0].path, "hot.rs");
+        assert_eq!(hotspots[0].change_count, 3);
+        assert!(hotspots
+            .iter()
+            .all(|h| h.path == "hot.rs" || h.change_count == 1));
+
+        let limited = get_hotspot_files(root, Some(7), Some(1)).await.unwrap();
+        assert_eq!(limited.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_get_hotspot_files_validates_inputs() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let root = temp_dir.path().to_string_lossy().to_string();
+
+        assert!(get_hotspot_files(root.clone(), Some(0), None)
+            .await
+            .is_err());
+        assert!(get_hotspot_files(root.clone(), Some(100_000), None)
+            .await
+            .is_err());
+        assert!(get_hotspot_files(root.clone(), None, Some(0))
+            .await
+            .is_err());
+        assert!(get_hotspot_files(root, None, Some(5000)).await.is_err());
+    }
+
+    // ==================== find_project_for_path tests ====================
+
+    #[test]
+    fn test_find_project_for_path_exact_match() {
+        let root = tempfile::tempdir().unwrap();
+        let project = Project::new(root.path().to_str().unwrap());
+        let other = Project::new("/nonexistent/other");
+
+        let found = find_project_for_path(&[other, project.clone()], root.path()).unwrap();
+        assert_eq!(found.id, project.id);
+    }
+
+    #[test]
+    fn test_find_project_for_path_nearest_ancestor() {
+        let root = tempfile::tempdir().unwrap();
+        let nested = root.path().join("packages").join("web");
+        let deep = nested.join("src").join("components");
+        std::fs::create_dir_all(&deep).unwrap();
+
+        let outer = Project::new(root.path().to_str().unwrap());
+        let inner = Project::new(nested.to_str().unwrap());
+
+        let found = find_project_for_path(&[outer.clone(), inner.clone()], &deep).unwrap();
+        assert_eq!(found.id, inner.id);
+
+        let found =
+            find_project_for_path(&[outer.clone(), inner], &root.path().join("packages")).unwrap();
+        assert_eq!(found.id, outer.id);
+    }
+
+    #[test]
+    fn test_find_project_for_path_no_match() {
+        let root = tempfile::tempdir().unwrap();
+        let elsewhere = tempfile::tempdir().unwrap();
+        let project = Project::new(root.path().to_str().unwrap());
+
+        assert!(find_project_for_path(&[project], elsewhere.path()).is_none());
+        // A sibling sharing a name prefix is not an ancestor
+        let sibling = format!("{}-sibling", root.path().display());
+        let project = Project::new(root.path().to_str().unwrap());
+        assert!(find_project_for_path(&[project], Path::new(&sibling)).is_none());
+    }
+
+    // ==================== git_restore_file tests ====================
+
+    fn head_sha(repo: &Path) -> String {
+        run_git_capture_stdout(repo, &["rev-parse", "HEAD"])
+            .unwrap()
+            .trim()
+            .to_string()
+    }
+
+    #[tokio::test]
+    async fn test_git_restore_file_to_head() {
+        let Some(repo) = init_test_repo() else { return };
+        let root = repo.path().to_string_lossy().to_string();
+        std::fs::write(repo.path().join("main.rs"), "broken\n").unwrap();
+
+        git_restore_file(root, "main.rs".to_string()).await.unwrap();
+
+        assert_eq!(
+            std::fs::read_to_string(repo.path().join("main.rs")).unwrap(),
+            "fn main() {}\n"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_git_restore_file_to_commit() {
+        let Some(repo) = init_test_repo() else { return };
+        let root = repo.path().to_string_lossy().to_string();
+        let first = head_sha(repo.path());
+        std::fs::write(repo.path().join("main.rs"), "fn main() { v2() }\n").unwrap();
+        assert!(git(repo.path(), &["commit", "-q", "-am", "v2"]));
+
+        git_restore_file_to_commit(root.clone(), "main.rs".to_string(), first)
+            .await
+            .unwrap();
+        assert_eq!(
+            std::fs::read_to_string(repo.path().join("main.rs")).unwrap(),
+            "fn main() {}\n"
+        );
+
+        let bad_sha =
+            git_restore_file_to_commit(root.clone(), "main.rs".to_string(), "--orphan".to_string())
+                .await;
+        assert!(bad_sha.is_err());
+        let traversal = git_restore_file(root, "../outside.rs".to_string()).await;
+        assert!(matches!(traversal, Err(crate::Error::InvalidPath(_))));
+    }
+
+    #[tokio::test]
+    async fn test_git_restore_file_refuses_mid_merge() {
+        let Some(repo) = init_test_repo() else { return };
+        let root = repo.path().to_string_lossy().to_string();
+        std::fs::write(
+            repo.path().join(".git").join("MERGE_HEAD"),
+            head_sha(repo.path()),
+        )
+        .unwrap();
+        std::fs::write(repo.path().join("main.rs"), "conflicted\n").unwrap();
+
+        assert_eq!(
+            git_operation_in_progress(repo.path()).unwrap(),
+            Some(GitOperation::Merge)
+        );
+        assert!(git_restore_file(root, "main.rs".to_string()).await.is_err());
+        assert_eq!(
+            std::fs::read_to_string(repo.path().join("main.rs")).unwrap(),
+            "conflicted\n"
+        );
+    }
+
+    // ==================== diff_context_lines tests ====================
+
+    #[test]
+    fn test_diff_context_arg() {
+        assert_eq!(diff_context_arg(None), None);
+        assert_eq!(diff_context_arg(Some(0)).as_deref(), Some("-U0"));
+        assert_eq!(diff_context_arg(Some(10)).as_deref(), Some("-U10"));
+    }
+
+    #[test]
+    fn test_validate_diff_context_lines_range() {
+        assert!(validate_diff_context_lines(0).is_ok());
+        assert!(validate_diff_context_lines(MAX_DIFF_CONTEXT_LINES).is_ok());
+        assert!(validate_diff_context_lines(MAX_DIFF_CONTEXT_LINES + 1).is_err());
+        assert!(validate_diff_context_lines(u32::MAX).is_err());
+    }
+
+    #[test]
+    fn test_diff_context_lines_setting_ignores_invalid_values() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let db = Database::new(&temp_dir.path().join("test.db")).unwrap();
+        assert_eq!(diff_context_lines(&db).unwrap(), None);
+
+        db.set_setting(DIFF_CONTEXT_LINES_SETTING, Some("8"))
+            .unwrap();
+        assert_eq!(diff_context_lines(&db).unwrap(), Some(8));
+
+        db.set_setting(DIFF_CONTEXT_LINES_SETTING, Some("5000"))
+            .unwrap();
+        assert_eq!(diff_context_lines(&db).unwrap(), None);
+        db.set_setting(DIFF_CONTEXT_LINES_SETTING, Some("lots"))
+            .unwrap();
+        assert_eq!(diff_context_lines(&db).unwrap(), None);
+    }
+
+    #[test]
+    fn test_collect_project_diff_uses_context_lines() {
+        let Some(repo) = init_test_repo() else { return };
+        let lines: String = (1..=20).map(|i| format!("line {i}\n")).collect();
+        std::fs::write(repo.path().join("a.txt"), &lines).unwrap();
+        assert!(git(repo.path(), &["add", "."]));
+        assert!(git(repo.path(), &["commit", "-q", "-m", "lines"]));
+        std::fs::write(
+            repo.path().join("a.txt"),
+            lines.replace("line 10\n", "line ten\n"),
+        )
+        .unwrap();
+
+        let default = collect_project_diff(repo.path(), false, None).unwrap();
+        assert!(default.contains("@@ -7,7 +7,7 @@"), "{default}");
+        let none = collect_project_diff(repo.path(), false, Some(0)).unwrap();
+        assert!(none.contains("@@ -10 +10 @@"), "{none}");
+        assert!(!none.contains("\n line 9\n"));
+    }
+
+    // ==================== get_branch_diff tests ====================
+
+    #[tokio::test]
+    async fn test_get_branch_diff_diverged_branch() {
+        let Some(repo) = init_test_repo() else { return };
+        let root = repo.path().to_string_lossy().to_string();
+        let default_branch =
+            run_git_capture_stdout(repo.path(), &["rev-parse", "--abbrev-ref", "HEAD"])
+                .unwrap()
+                .trim()
+                .to_string();
+
+        // Commit on a side branch that must not appear in the diff
+        assert!(git(repo.path(), &["checkout", "-q", "-b", "base"]));
+        std::fs::write(repo.path().join("base_only.txt"), "base\n").unwrap();
+        assert!(git(repo.path(), &["add", "."]));
+        assert!(git(repo.path(), &["commit", "-q", "-m", "base work"]));
+
+        // Commit on the current branch after diverging
+        assert!(git(repo.path(), &["checkout", "-q", &default_branch]));
+        std::fs::write(repo.path().join("feature.txt"), "feature\n").unwrap();
+        assert!(git(repo.path(), &["add", "."]));
+        assert!(git(repo.path(), &["commit", "-q", "-m", "feature work"]));
+
+        let diff = get_branch_diff(root, "base".to_string()).await.unwrap();
+        assert!(diff.is_git_repo);
+        assert!(diff.diff.contains("feature.txt"));
+        assert!(!diff.diff.contains("base_only.txt"));
+    }
+
+    #[tokio::test]
+    async fn test_get_branch_diff_missing_branch() {
+        let Some(repo) = init_test_repo() else { return };
+        let root = repo.path().to_string_lossy().to_string();
+
+        let result = get_branch_diff(root.clone(), "does-not-exist".to_string()).await;
+        assert!(
+            matches!(result, Err(crate::Error::Git(ref msg)) if msg.contains("does-not-exist"))
+        );
+        assert!(get_branch_diff(root, "--output=/tmp/x".to_string())
+            .await
+            .is_err());
+    }
+
+    #[tokio::test]
+    async fn test_check_project_path_reports_kind() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let root = temp_dir.path().to_string_lossy().to_string();
+
+        let ok = check_project_path(root.clone(), None).await.unwrap();
+        assert!(ok.valid);
+        assert!(ok.error.is_none());
+
+        let escape = check_project_path(root, Some("../x".to_string()))
+            .await
+            .unwrap();
+        assert!(!escape.valid);
+        assert_eq!(
+            escape.error,
+            Some(crate::PathValidationError::ParentTraversal)
+        );
+        assert!(escape.message.is_some());
+    }
+
+    #[test]
+    fn test_projects_with_missing_paths_flags_deleted_dir() {
+        let kept = tempfile::tempdir().unwrap();
+        let deleted = tempfile::tempdir().unwrap();
+        let deleted_path = deleted.path().to_string_lossy().to_string();
+        deleted.close().unwrap();
+
+        let projects = vec![
+            Project::new(&kept.path().to_string_lossy()),
+            Project::new(&deleted_path),
+        ];
+
+        let missing = projects_with_missing_paths(projects);
+        assert_eq!(missing.len(), 1);
+        assert_eq!(missing[0].path, deleted_path);
+    }
+
+    #[test]
+    fn test_repoint_project_moves_path_and_keeps_sessions() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let db = Database::new(&temp_dir.path().join("test.db")).unwrap();
+        let old_dir = tempfile::tempdir().unwrap();
+        let new_dir = tempfile::tempdir().unwrap();
+        let taken_dir = tempfile::tempdir().unwrap();
+
+        let project = Project::new(&old_dir.path().to_string_lossy());
+        let other = Project::new(&taken_dir.path().to_string_lossy());
+        db.insert_project(&project).unwrap();
+        db.insert_project(&other).unwrap();
+        db.upsert_session_metadata(&crate::database::SessionMetadata::new("s1", &project.id))
+            .unwrap();
+        db.add_to_allowlist(&project.id, "npm test").unwrap();
+
+        let moved = repoint_project(&db, &project.id, &new_dir.path().to_string_lossy()).unwrap();
+        assert_eq!(
+            Path::new(&moved.path),
+            new_dir.path().canonicalize().unwrap()
+        );
+        assert_eq!(
+            db.get_project(&project.id).unwrap().unwrap().path,
+            moved.path
+        );
+        assert_eq!(db.get_sessions_for_project(&project.id).unwrap().len(), 1);
+        assert_eq!(
+            db.get_allowlist(&project.id).unwrap(),
+            vec!["npm test".to_string()]
+        );
+
+        let taken = repoint_project(&db, &project.id, &taken_dir.path().to_string_lossy());
+        assert!(taken.is_err());
+        let missing = repoint_project(&db, &project.id, "/nonexistent/moved/project");
+        assert!(missing.is_err());
+    }
+
+    #[test]
+    fn test_parse_numstat_z() {
+        let stats = parse_numstat_z(concat!(
+            "3\t1\tsrc/main.rs\0",
+            "-\t-\tlogo.png\0",
+            "5\t0\tdir/with space.txt\0",
+        ));
+        assert_eq!(stats.len(), 3);
+        assert_eq!(stats[0].insertions, Some(3));
+        assert_eq!(stats[0].deletions, Some(1));
+        assert!(stats[1].binary);
+        assert_eq!(stats[1].insertions, None);
+        assert_eq!(stats[2].path, "dir/with space.txt");
+    }
+
+    #[tokio::test]
+    async fn test_get_git_diff_stat_text_and_binary() {
+        let Some(repo) = init_test_repo() else { return };
+        std::fs::write(repo.path().join("logo.bin"), [0u8, 1, 2, 3]).unwrap();
+        assert!(git(repo.path(), &["add", "logo.bin"]));
+        assert!(git(repo.path(), &["commit", "-q", "-m", "add binary"]));
+
+        std::fs::write(repo.path().join("main.rs"), "fn main() {\n    run();\n}\n").unwrap();
+        std::fs::write(repo.path().join("logo.bin"), [0u8, 9, 9]).unwrap();
+        std::fs::write(repo.path().join("notes.txt"), "one\ntwo").unwrap();
+        std::fs::write(repo.path().join("blob.dat"), [0u8, 0, 7]).unwrap();
+
+        let stat = get_git_diff_stat(repo.path().to_string_lossy().to_string())
+            .await
+            .unwrap();
+        assert!(stat.is_git_repo);
+        assert_eq!(stat.files_changed, 4);
+
+        let by_path = |p: &str| stat.per_file.iter().find(|f| f.path == p).unwrap().clone();
+        let main = by_path("main.rs");
+        assert_eq!((main.insertions, main.deletions), (Some(3), Some(1)));
+        assert!(by_path("logo.bin").binary);
+        let notes = by_path("notes.txt");
+        assert!(notes.untracked);
+        assert_eq!(notes.insertions, Some(2));
+        assert!(by_path("blob.dat").binary);
+
+        assert_eq!(stat.insertions, 5);
+        assert_eq!(stat.deletions, 1);
+    }
+
+    #[test]
+    fn test_diff_file_summaries_mixed_changeset() {
+        let Some(repo) = init_test_repo() else { return };
+        let lines: String = (0..20).map(|i| format!("line {i}\n")).collect();
+        std::fs::write(repo.path().join("old.txt"), &lines).unwrap();
+        std::fs::write(repo.path().join("gone.txt"), "bye\n").unwrap();
+        assert!(git(repo.path(), &["add", "."]));
+        assert!(git(repo.path(), &["commit", "-q", "-m", "more"]));
+
+        std::fs::write(repo.path().join("main.rs"), "fn main() {\n    run();\n}\n").unwrap();
+        assert!(git(repo.path(), &["mv", "old.txt", "renamed.txt"]));
+        std::fs::write(repo.path().join("renamed.txt"), format!("{lines}line 20\n")).unwrap();
+        std::fs::remove_file(repo.path().join("gone.txt")).unwrap();
+        std::fs::write(repo.path().join("staged.txt"), "a\nb\n").unwrap();
+        assert!(git(repo.path(), &["add", "staged.txt"]));
+        std::fs::write(repo.path().join("notes.txt"), "one\ntwo").unwrap();
+
+        let summaries = diff_file_summaries(repo.path()).unwrap();
+        let statuses: Vec<(&str, FileChangeStatus)> = summaries
+            .iter()
+            .map(|s| (s.path.as_str(), s.status))
+            .collect();
+        assert_eq!(
+            statuses,
+            vec![
+                ("gone.txt", FileChangeStatus::Deleted),
+                ("main.rs", FileChangeStatus::Modified),
+                ("notes.txt", FileChangeStatus::Added),
+                ("renamed.txt", FileChangeStatus::Renamed),
+                ("staged.txt", FileChangeStatus::Added),
+            ]
+        );
+        let renamed = summaries.iter().find(|s| s.path == "renamed.txt").unwrap();
+        assert_eq!(renamed.old_path.as_deref(), Some("old.txt"));
+
+        // Tracked counts agree with git's own numstat
+        let numstat =
+            run_git_capture_diff(repo.path(), &["diff", "--numstat", "-M", "HEAD"]).unwrap();
+        for line in numstat.lines() {
+            let fields: Vec<&str> = line.split('\t').collect();
+            let path = fields[2]
+                .rsplit(" => ")
+                .next()
+                .unwrap()
+                .trim_end_matches('}');
+            let summary = summaries.iter().find(|s| s.path.ends_with(path)).unwrap();
+            assert_eq!(summary.additions, fields[0].parse().ok(), "{line}");
+            assert_eq!(summary.deletions, fields[1].parse().ok(), "{line}");
+        }
+        let notes = summaries.iter().find(|s| s.path == "notes.txt").unwrap();
+        assert_eq!((notes.additions, notes.deletions), (Some(2), Some(0)));
+    }
+
+    #[test]
+    fn test_parse_name_status_z() {
+        let entries = parse_name_status_z("M\0src/main.rs\0R087\0a.txt\0b.txt\0D\0gone\0");
+        assert_eq!(
+            entries,
+            vec![
+                (FileChangeStatus::Modified, None, "src/main.rs".to_string()),
+                (
+                    FileChangeStatus::Renamed,
+                    Some("a.txt".to_string()),
+                    "b.txt".to_string()
+                ),
+                (FileChangeStatus::Deleted, None, "gone".to_string()),
+            ]
+        );
+        let counts = parse_numstat_renames_z("1\t0\t\0a.txt\0b.txt\0-\t-\tlogo.png\0");
+        assert_eq!(counts["b.txt"], (Some(1), Some(0)));
+        assert_eq!(counts["logo.png"], (None, None));
+    }
+
+    #[test]
+    fn test_untracked_file_stat_counts_lines_past_the_sniffed_prefix() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let line = "x".repeat(99) + "\n";
+        let text = line.repeat(1000) + "last line without newline";
+        std::fs::write(temp_dir.path().join("big.txt"), &text).unwrap();
+        std::fs::write(temp_dir.path().join("blob.bin"), [0u8, 1, 2, b'\n']).unwrap();
+        std::fs::write(temp_dir.path().join("empty.txt"), "").unwrap();
+
+        let stat = untracked_file_stat(temp_dir.path(), "big.txt").unwrap();
+        assert_eq!(stat.insertions, Some(1001));
+        assert!(!stat.binary);
+        let stat = untracked_file_stat(temp_dir.path(), "blob.bin").unwrap();
+        assert!(stat.binary);
+        assert_eq!(stat.insertions, None);
+        let stat = untracked_file_stat(temp_dir.path(), "empty.txt").unwrap();
+        assert_eq!(stat.insertions, Some(0));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_untracked_file_stat_does_not_follow_symlinks() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let outside = tempfile::tempdir().unwrap();
+        std::fs::write(outside.path().join("secret.txt"), "a\nb\n").unwrap();
+        std::os::unix::fs::symlink(
+            outside.path().join("secret.txt"),
+            temp_dir.path().join("link.txt"),
+        )
+        .unwrap();
+        std::fs::create_dir(temp_dir.path().join("dir")).unwrap();
+
+        assert!(untracked_file_stat(temp_dir.path(), "link.txt").is_err());
+        assert!(untracked_file_stat(temp_dir.path(), "dir").is_err());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_symlinked_project_is_a_duplicate() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let real = temp_dir.path().join("proj");
+        std::fs::create_dir(&real).unwrap();
+        let link = temp_dir.path().join("link");
+        std::os::unix::fs::symlink(&real, &link).unwrap();
+
+        let original = Project::new(real.to_str().unwrap());
+        let mut via_link = Project::new(link.to_str().unwrap());
+        via_link.created_at = original.created_at + 1;
+        let other = Project::new(temp_dir.path().to_str().unwrap());
+
+        // add_project's check catches the symlink and the trailing slash
+        let existing = vec![original.clone()];
+        assert!(find_project_with_same_path(&existing, link.to_str().unwrap(), None).is_some());
+        assert!(
+            find_project_with_same_path(&existing, &format!("{}/", real.display()), None).is_some()
+        );
+        assert!(
+            find_project_with_same_path(&existing, real.to_str().unwrap(), Some(&original.id))
+                .is_none()
+        );
+
+        let groups = group_duplicate_projects(vec![via_link.clone(), other, original.clone()]);
+        assert_eq!(groups.len(), 1);
+        let ids: Vec<&str> = groups[0].projects.iter().map(|p| p.id.as_str()).collect();
+        assert_eq!(ids, vec![original.id.as_str(), via_link.id.as_str()]);
+        assert_eq!(
+            groups[0].canonical_path,
+            real.canonicalize().unwrap().to_string_lossy()
+        );
+    }
 }
@@ -1,8 +1,14 @@
 //! Session management commands
 
-use tauri::State;
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
 
-use crate::database::{SessionMetadata, SessionStatus};
+use tauri::{Emitter, State};
+
+use crate::database::{
+    CommandHistoryEntry, Database, DeletedSession, SessionMetadata, SessionPage, SessionStatus,
+};
 use crate::state::AppState;
 use crate::Result;
 
@@ -10,12 +16,33 @@ use crate::Result;
 use crate::commands::projects::validate_id;
 
 /// List sessions for a project
+///
+/// Without `limit` every session is returned; `total` counts them all either way.
 #[tauri::command]
 pub async fn list_sessions(
     state: State<'_, AppState>,
     project_id: String,
+    limit: Option<usize>,
+    offset: Option<usize>,
+) -> Result<SessionPage> {
+    state.database.get_sessions_for_project_paged(
+        &project_id,
+        limit.unwrap_or(usize::MAX),
+        offset.unwrap_or(0),
+    )
+}
+
+/// List a project's archived sessions, most recently accessed first
+///
+/// Sessions are unarchived with `update_session_metadata` or `bulk_archive_sessions`.
+#[tauri::command]
+pub async fn list_archived_sessions(
+    state: State<'_, AppState>,
+    project_id: String,
 ) -> Result<Vec<SessionMetadata>> {
-    state.database.get_sessions_for_project(&project_id)
+    state
+        .database
+        .get_archived_sessions_for_project(&project_id)
 }
 
 /// Get session metadata
@@ -25,7 +52,7 @@ pub async fn get_session(
     session_id: String,
 ) -> Result<Option<SessionMetadata>> {
     validate_id(&session_id, "session_id")?;
-    // Use optimized direct lookup
+    // Direct lookup by id, which unlike the project listing also finds archived sessions
     state.database.get_session_by_id(&session_id)
 }
 
@@ -49,6 +76,12 @@ pub async fn update_session_metadata(
 
     let mut metadata = match existing {
         Some(m) => m,
+        None if state.database.session_exists_any(&session_id)? => {
+            // Soft-deleted: creating fresh metadata would overwrite the row in the trash
+            return Err(crate::Error::SessionNotFound(format!(
+                "Session {session_id} is deleted; restore it before updating"
+            )));
+        }
         None => {
             // If no existing metadata, we need a project_id
             // Try to get from parameter, or try to infer from thread information
@@ -106,13 +139,105 @@ pub async fn update_session_metadata(
 }
 
 /// Delete session metadata
+///
+/// This is a soft delete: the session can be brought back with `restore_session`
+/// until it is removed by `purge_deleted_sessions`.
 #[tauri::command]
 pub async fn delete_session(state: State<'_, AppState>, session_id: String) -> Result<()> {
     validate_id(&session_id, "session_id")?;
-    state.database.delete_session_metadata(&session_id)?;
+    state
+        .database
+        .soft_delete_session(&session_id, chrono::Utc::now().timestamp())?;
+    Ok(())
+}
+
+/// Archive or unarchive several sessions at once
+///
+/// Returns the number of sessions updated; unknown and deleted sessions are skipped.
+#[tauri::command]
+pub async fn bulk_archive_sessions(
+    state: State<'_, AppState>,
+    session_ids: Vec<String>,
+    archived: bool,
+) -> Result<usize> {
+    for session_id in &session_ids {
+        validate_id(session_id, "session_id")?;
+    }
+    state.database.set_sessions_archived(&session_ids, archived)
+}
+
+/// Delete several sessions at once
+///
+/// Like `delete_session` this is a soft delete, unless `permanent` is set, in
+/// which case the sessions and their snapshots are removed immediately.
+/// Returns the number of sessions deleted.
+#[tauri::command]
+pub async fn bulk_delete_sessions(
+    state: State<'_, AppState>,
+    session_ids: Vec<String>,
+    permanent: Option<bool>,
+) -> Result<usize> {
+    for session_id in &session_ids {
+        validate_id(session_id, "session_id")?;
+    }
+    if permanent.unwrap_or(false) {
+        let deleted = state.database.delete_sessions_metadata(&session_ids)?;
+        if deleted > 0 {
+            tracing::info!("Permanently deleted {} sessions", deleted);
+            // Their snapshots went with them; drop the git refs keeping those alive
+            crate::commands::snapshots::prune_snapshot_refs(state.database.clone()).await;
+        }
+        Ok(deleted)
+    } else {
+        state
+            .database
+            .soft_delete_sessions(&session_ids, chrono::Utc::now().timestamp())
+    }
+}
+
+/// List soft-deleted sessions, most recently deleted first
+#[tauri::command]
+pub async fn list_recently_deleted_sessions(
+    state: State<'_, AppState>,
+) -> Result<Vec<DeletedSession>> {
+    state.database.get_deleted_sessions()
+}
+
+/// Undo the deletion of a session
+#[tauri::command]
+pub async fn restore_session(state: State<'_, AppState>, session_id: String) -> Result<()> {
+    validate_id(&session_id, "session_id")?;
+    if !state.database.restore_deleted_session(&session_id)? {
+        return Err(crate::Error::SessionNotFound(format!(
+            "No deleted session with id {session_id}"
+        )));
+    }
     Ok(())
 }
 
+/// Permanently remove sessions deleted more than `older_than_days` days ago
+///
+/// Returns the number of sessions purged. Pass 0 to empty the trash.
+#[tauri::command]
+pub async fn purge_deleted_sessions(
+    state: State<'_, AppState>,
+    older_than_days: Option<i64>,
+) -> Result<usize> {
+    let days = older_than_days.unwrap_or(30);
+    if days < 0 {
+        return Err(crate::Error::Other(
+            "older_than_days must not be negative".to_string(),
+        ));
+    }
+    let cutoff = chrono::Utc::now().timestamp() - days * 24 * 60 * 60;
+    let purged = state.database.purge_deleted_sessions(cutoff)?;
+    if purged > 0 {
+        tracing::info!("Purged {} deleted sessions", purged);
+        crate::commands::snapshots::prune_snapshot_refs(state.database.clone()).await;
+    }
+    Ok(purged)
+}
+
 /// Update session status only (lightweight update)
 #[tauri::command]
 pub async fn update_session_status(
@@ -150,6 +275,511 @@ pub async fn update_session_tasks(
     Ok(())
 }
 
+/// Why a session needs the user's attention
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AttentionReason {
+    /// The session's last turn failed
+    Failed,
+    /// The session is blocked on an unanswered approval request
+    AwaitingApproval,
+}
+
+/// A session that needs attention, with every reason that applies
+#[derive(Debug, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AttentionSession {
+    #[serde(flatten)]
+    pub session: SessionMetadata,
+    pub reasons: Vec<AttentionReason>,
+    /// Number of unanswered approval requests
+    pub pending_approvals: usize,
+}
+
+/// Collect failed sessions and sessions with pending approvals
+///
+/// `approval_thread_ids` holds one entry per pending approval. Sessions
+/// awaiting approval come first, since they block progress.
+fn attention_sessions(
+    db: &Database,
+    approval_thread_ids: &[String],
+) -> Result<Vec<AttentionSession>> {
+    let mut result: Vec<AttentionSession> = Vec::new();
+
+    for thread_id in approval_thread_ids {
+        if let Some(entry) = result
+            .iter_mut()
+            .find(|e| &e.session.session_id == thread_id)
+        {
+            entry.pending_approvals += 1;
+            continue;
+        }
+        if let Some(session) = db.get_session_by_id(thread_id)? {
+            result.push(AttentionSession {
+                session,
+                reasons: vec![AttentionReason::AwaitingApproval],
+                pending_approvals: 1,
+            });
+        }
+    }
+
+    for session in db.get_sessions_with_status(&SessionStatus::Failed)? {
+        match result
+            .iter_mut()
+            .find(|e| e.session.session_id == session.session_id)
+        {
+            Some(entry) => entry.reasons.push(AttentionReason::Failed),
+            None => result.push(AttentionSession {
+                session,
+                reasons: vec![AttentionReason::Failed],
+                pending_approvals: 0,
+            }),
+        }
+    }
+
+    Ok(result)
+}
+
+/// List sessions that failed or are waiting on an approval
+#[tauri::command]
+pub async fn list_attention_sessions(state: State<'_, AppState>) -> Result<Vec<AttentionSession>> {
+    let approvals = state.pending_approvals.lock().clone();
+    let thread_ids: Vec<String> = approvals
+        .map(|a| a.list().into_iter().filter_map(|p| p.thread_id).collect())
+        .unwrap_or_default();
+    attention_sessions(&state.database, &thread_ids)
+}
+
+/// Record a command in the history, logging instead of failing if it can't be stored
+///
+/// History is for auditing; losing an entry shouldn't fail the command itself.
+pub(crate) fn record_command_history(
+    db: &Database,
+    session_id: &str,
+    command: &str,
+    decision: &str,
+) {
+    if let Err(e) = db.record_command(
+        session_id,
+        command,
+        decision,
+        chrono::Utc::now().timestamp(),
+    ) {
+        tracing::warn!("Failed to record command history for {}: {}", session_id, e);
+    }
+}
+
+/// Get the commands recorded for a session, newest first
+#[tauri::command]
+pub async fn get_command_history(
+    state: State<'_, AppState>,
+    session_id: String,
+) -> Result<Vec<CommandHistoryEntry>> {
+    validate_id(&session_id, "session_id")?;
+    state.database.get_command_history(&session_id)
+}
+
+/// Get the commands recorded across a project's sessions, newest first
+#[tauri::command]
+pub async fn get_project_command_history(
+    state: State<'_, AppState>,
+    project_id: String,
+) -> Result<Vec<CommandHistoryEntry>> {
+    validate_id(&project_id, "project_id")?;
+    state.database.get_project_command_history(&project_id)
+}
+
+/// Delete command history for one session, or all sessions when `session_id` is `None`
+///
+/// With `older_than_days`, only entries older than that are removed.
+/// Returns the number of entries deleted.
+#[tauri::command]
+pub async fn clear_command_history(
+    state: State<'_, AppState>,
+    session_id: Option<String>,
+    older_than_days: Option<i64>,
+) -> Result<usize> {
+    if let Some(session_id) = session_id.as_deref() {
+        validate_id(session_id, "session_id")?;
+    }
+    if older_than_days.is_some_and(|days| days < 0) {
+        return Err(crate::Error::Other(
+            "older_than_days must not be negative".to_string(),
+        ));
+    }
+    let before = older_than_days.map(|days| chrono::Utc::now().timestamp() - days * 86400);
+
+    let count = state
+        .database
+        .clear_command_history(session_id.as_deref(), before)?;
+    if count > 0 {
+        tracing::info!("Cleared {} command history entries", count);
+    }
+    Ok(count)
+}
+
+/// Approval policies accepted as a session default, as the app server spells them
+const APPROVAL_POLICIES: &[&str] = &["on-request", "on-failure", "never", "untrusted"];
+
+/// Normalize an approval policy name to its wire value
+///
+/// Accepts camelCase and snake_case spellings (`onRequest`, `on_request`) as
+/// well as the legacy `unless-trusted`, mirroring `normalizeApprovalPolicy` in
+/// the frontend.
+pub(crate) fn normalize_approval_policy(policy: &str) -> Result<String> {
+    let mut normalized = String::with_capacity(policy.len() + 2);
+    let mut prev: Option<char> = None;
+    for c in policy.trim().chars() {
+        if c.is_ascii_uppercase()
+            && prev.is_some_and(|p| p.is_ascii_lowercase() || p.is_ascii_digit())
+        {
+            normalized.push('-');
+        }
+        normalized.push(if c == '_' {
+            '-'
+        } else {
+            c.to_ascii_lowercase()
+        });
+        prev = Some(c);
+    }
+    if normalized == "unless-trusted" {
+        normalized = "untrusted".to_string();
+    }
+
+    if APPROVAL_POLICIES.contains(&normalized.as_str()) {
+        Ok(normalized)
+    } else {
+        Err(crate::Error::Other(format!(
+            "Invalid approval policy '{policy}'. Expected one of: {}",
+            APPROVAL_POLICIES.join(", ")
+        )))
+    }
+}
+
+/// Get a session's default approval policy, if one is set
+#[tauri::command]
+pub async fn get_session_approval_policy(
+    state: State<'_, AppState>,
+    session_id: String,
+) -> Result<Option<String>> {
+    validate_id(&session_id, "session_id")?;
+    let session = state
+        .database
+        .get_session_by_id(&session_id)?
+        .ok_or_else(|| crate::Error::SessionNotFound(session_id.clone()))?;
+    Ok(session.approval_policy)
+}
+
+/// Set a session's default approval policy, or clear it with `None`
+///
+/// `send_message` uses this policy when a turn doesn't pass its own.
+#[tauri::command]
+pub async fn set_session_approval_policy(
+    state: State<'_, AppState>,
+    session_id: String,
+    approval_policy: Option<String>,
+) -> Result<()> {
+    validate_id(&session_id, "session_id")?;
+    let approval_policy = approval_policy
+        .as_deref()
+        .map(normalize_approval_policy)
+        .transpose()?;
+    if !state
+        .database
+        .set_session_approval_policy(&session_id, approval_policy.as_deref())?
+    {
+        return Err(crate::Error::SessionNotFound(session_id));
+    }
+    Ok(())
+}
+
+/// Mark all of a project's sessions as read
+///
+/// Sets `last_accessed_at` to now and returns that timestamp; a session is
+/// unread when its thread changed after it (see `SessionMetadata::is_unread`).
+#[tauri::command]
+pub async fn touch_all_sessions(state: State<'_, AppState>, project_id: String) -> Result<i64> {
+    validate_id(&project_id, "project_id")?;
+    let now = chrono::Utc::now().timestamp();
+    let count = state.database.touch_project_sessions(&project_id, now)?;
+    tracing::debug!(
+        "Marked {} sessions of project {} as read",
+        count,
+        project_id
+    );
+    Ok(now)
+}
+
+/// Maximum length of a generated session title, in characters
+const MAX_GENERATED_TITLE_CHARS: usize = 50;
+
+/// Strip inline markdown (emphasis, code, links, images) from a line
+fn strip_inline_markdown(line: &str) -> String {
+    let mut out = String::with_capacity(line.len());
+    let mut chars = line.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '*' | '_' | '`' | '~' => {}
+            '!' if chars.peek() == Some(&'[') => {}
+            '[' => {}
+            // "](url)" -> drop the link target
+            ']' if chars.peek() == Some(&'(') => {
+                for c in chars.by_ref() {
+                    if c == ')' {
+                        break;
+                    }
+                }
+            }
+            ']' => {}
+            _ => out.push(c),
+        }
+    }
+
+    out
+}
+
+/// Derive a short, deterministic session title from the first user message
+///
+/// Uses the first line of prose (skipping fenced code blocks), strips markdown
+/// and caps the result at a word boundary.
+fn derive_session_title(first_message: &str) -> Option<String> {
+    let mut in_code_block = false;
+    let line = first_message.lines().find_map(|line| {
+        let trimmed = line.trim();
+        if trimmed.starts_with("```") {
+            in_code_block = !in_code_block;
+            return None;
+        }
+        if in_code_block {
+            return None;
+        }
+
+        // Drop block-level markers: headings, quotes, list bullets
+        let body = trimmed.trim_start_matches(['#', '>']).trim_start();
+        let body = body
+            .strip_prefix("- ")
+            .or_else(|| body.strip_prefix("* "))
+            .or_else(|| body.strip_prefix("+ "))
+            .unwrap_or(body);
+        let body = match body.split_once(". ") {
+            Some((num, rest)) if !num.is_empty() && num.chars().all(|c| c.is_ascii_digit()) => rest,
+            _ => body,
+        };
+
+        let text = strip_inline_markdown(body)
+            .split_whitespace()
+            .collect::<Vec<_>>()
+            .join(" ");
+        (!text.is_empty()).then_some(text)
+    })?;
+
+    let line = line.trim_end_matches([':', '.', ',', ';']);
+    if line.chars().count() <= MAX_GENERATED_TITLE_CHARS {
+        return Some(line.to_string());
+    }
+
+    let mut title = String::new();
+    for word in line.split(' ') {
+        let candidate_len =
+            title.chars().count() + word.chars().count() + usize::from(!title.is_empty());
+        if candidate_len > MAX_GENERATED_TITLE_CHARS {
+            break;
+        }
+        if !title.is_empty() {
+            title.push(' ');
+        }
+        title.push_str(word);
+    }
+    if title.is_empty() {
+        // A single overlong word; cut it on a char boundary
+        title = line.chars().take(MAX_GENERATED_TITLE_CHARS).collect();
+    }
+
+    Some(format!("{}…", title.trim_end_matches([':', '.', ',', ';'])))
+}
+
+/// Store a generated title for a session unless it already has one
+///
+/// Returns the session's effective title afterwards.
+fn apply_generated_title(db: &Database, session_id: &str) -> Result<Option<String>> {
+    let metadata = db
+        .get_session_by_id(session_id)?
+        .ok_or_else(|| crate::Error::SessionNotFound(session_id.to_string()))?;
+
+    if let Some(title) = metadata.title.filter(|t| !t.is_empty()) {
+        return Ok(Some(title));
+    }
+
+    let Some(title) = metadata
+        .first_message
+        .as_deref()
+        .and_then(derive_session_title)
+    else {
+        return Ok(None);
+    };
+    db.set_session_title_if_empty(session_id, &title)?;
+
+    Ok(Some(title))
+}
+
+/// Generate a title from the session's first message if no title is set
+#[tauri::command]
+pub async fn generate_session_title(
+    state: State<'_, AppState>,
+    session_id: String,
+) -> Result<Option<String>> {
+    validate_id(&session_id, "session_id")?;
+    apply_generated_title(&state.database, &session_id)
+}
+
+/// Response item types that represent a tool invocation
+const TOOL_CALL_ITEM_TYPES: &[&str] = &[
+    "function_call",
+    "custom_tool_call",
+    "local_shell_call",
+    "web_search_call",
+];
+
+/// Render a high-level Markdown summary of a session that is safe to share
+///
+/// Only the first message, task list, tool call count and final status are
+/// included; tool arguments, command output and file contents never are.
+/// `messages` is the session's rollout, if it could be read.
+fn render_share_summary(
+    metadata: &SessionMetadata,
+    messages: Option<&[crate::codex_import::SessionMessage]>,
+) -> String {
+    let mut out = format!("# {}\n\n", metadata.get_display_name());
+
+    if let Some(first) = metadata.first_message.as_deref().filter(|m| !m.is_empty()) {
+        out.push_str("## Request\n\n");
+        for line in first.lines() {
+            out.push_str(&format!("> {line}\n"));
+        }
+        out.push('\n');
+    }
+
+    let tasks = metadata.get_tasks();
+    if !tasks.is_empty() {
+        out.push_str("## Tasks\n\n");
+        for task in &tasks {
+            let mark = if task.status == "completed" { "x" } else { " " };
+            out.push_str(&format!("- [{mark}] {} ({})\n", task.content, task.status));
+        }
+        out.push('\n');
+    }
+
+    let tool_calls = messages.map_or_else(
+        || "unknown".to_string(),
+        |messages| {
+            messages
+                .iter()
+                .filter(|m| TOOL_CALL_ITEM_TYPES.contains(&m.message_type.as_str()))
+                .count()
+                .to_string()
+        },
+    );
+    out.push_str(&format!("Tool calls: {tool_calls}\n"));
+    out.push_str(&format!("Status: {}\n", metadata.status.as_str()));
+    out
+}
+
+/// Produce a redacted summary of what a session did, for sharing
+#[tauri::command]
+pub async fn summarize_session_for_sharing(
+    state: State<'_, AppState>,
+    session_id: String,
+) -> Result<String> {
+    validate_id(&session_id, "session_id")?;
+    let metadata = state
+        .database
+        .get_session_by_id(&session_id)?
+        .ok_or_else(|| crate::Error::SessionNotFound(session_id.clone()))?;
+
+    tokio::task::spawn_blocking(move || {
+        // The rollout may be gone (e.g. deleted from ~/.codex); summarize without it
+        let session = crate::codex_import::get_session(&session_id).ok();
+        render_share_summary(&metadata, session.as_ref().map(|s| s.messages.as_slice()))
+    })
+    .await
+    .map_err(|e| crate::Error::Other(format!("Task join error: {e}")))
+}
+
+/// Get the number of non-archived sessions for every project
+///
+/// Projects without sessions are absent from the map.
+#[tauri::command]
+pub async fn get_session_counts(state: State<'_, AppState>) -> Result<HashMap<String, usize>> {
+    state.database.get_session_counts_by_project()
+}
+
+/// Find session metadata rows with an empty or dangling project_id
+#[tauri::command]
+pub async fn find_orphaned_metadata(state: State<'_, AppState>) -> Result<Vec<SessionMetadata>> {
+    state.database.find_orphaned_session_metadata()
+}
+
+/// Outcome of an orphaned metadata repair pass
+#[derive(Debug, Default, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct OrphanRepairReport {
+    /// Session IDs that were reassigned to a project
+    pub repaired: Vec<String>,
+    /// Session IDs whose project could not be inferred
+    pub unresolved: Vec<String>,
+}
+
+/// Reassign orphaned sessions to the project whose path matches the thread's cwd
+///
+/// `cwd_for_session` resolves a session ID to the working directory the thread
+/// ran in (e.g. from its Codex rollout file).
+fn repair_orphans<F>(db: &Database, cwd_for_session: F) -> Result<OrphanRepairReport>
+where
+    F: Fn(&str) -> Option<String>,
+{
+    let orphans = db.find_orphaned_session_metadata()?;
+    let projects = db.get_all_projects()?;
+    let mut report = OrphanRepairReport::default();
+
+    for orphan in orphans {
+        let project = cwd_for_session(&orphan.session_id).and_then(|cwd| {
+            crate::commands::projects::find_project_for_path(&projects, Path::new(&cwd))
+        });
+
+        match project {
+            Some(project) => {
+                db.update_session_project(&orphan.session_id, &project.id)?;
+                tracing::info!(
+                    "Repaired orphaned session {} -> project {}",
+                    orphan.session_id,
+                    project.id
+                );
+                report.repaired.push(orphan.session_id);
+            }
+            None => report.unresolved.push(orphan.session_id),
+        }
+    }
+
+    Ok(report)
+}
+
+/// Repair orphaned session metadata by inferring the project from the thread's cwd
+#[tauri::command]
+pub async fn repair_orphaned_metadata(state: State<'_, AppState>) -> Result<OrphanRepairReport> {
+    let db = state.database.clone();
+    // Reading rollout files is blocking I/O
+    tokio::task::spawn_blocking(move || {
+        repair_orphans(&db, |session_id| {
+            crate::codex_import::get_session(session_id)
+                .ok()
+                .map(|session| session.meta.cwd)
+        })
+    })
+    .await
+    .map_err(|e| crate::Error::Other(format!("Task join error: {e}")))?
+}
+
 /// Calculate relevance score for a session based on query match
 /// Scoring:
 /// - Exact title match: 100 points
@@ -212,6 +842,49 @@ fn calculate_relevance_score(session: &SessionMetadata, query_lower: &str) -> i3
     score
 }
 
+/// Apply tag/favorite filters and compute the relevance score for a session
+///
+/// Returns `None` if the session is filtered out or doesn't match the query.
+fn match_session(
+    session: &SessionMetadata,
+    query_lower: &str,
+    tags_filter: Option<&[String]>,
+    favorites_only: bool,
+) -> Option<i32> {
+    // Filter by tags first
+    if let Some(filter_tags) = tags_filter {
+        let session_tags = session.get_tags();
+        if !filter_tags.iter().all(|ft| session_tags.contains(ft)) {
+            return None;
+        }
+    }
+
+    // Filter by favorites
+    if favorites_only && !session.is_favorite {
+        return None;
+    }
+
+    let score = calculate_relevance_score(session, query_lower);
+    if score > 0 {
+        Some(score)
+    } else {
+        None
+    }
+}
+
+/// Load all non-archived sessions across every project
+fn load_all_sessions(state: &AppState) -> Result<Vec<SessionMetadata>> {
+    let projects = state.database.get_all_projects()?;
+    let mut all_sessions = Vec::new();
+
+    for project in projects {
+        let sessions = state.database.get_sessions_for_project(&project.id)?;
+        all_sessions.extend(sessions);
+    }
+
+    Ok(all_sessions)
+}
+
 /// Search sessions across all projects with relevance scoring
 #[tauri::command]
 pub async fn search_sessions(
@@ -220,42 +893,17 @@ pub async fn search_sessions(
     tags_filter: Option<Vec<String>>,
     favorites_only: Option<bool>,
 ) -> Result<Vec<SessionMetadata>> {
-    let projects = state.database.get_all_projects()?;
-    let mut all_sessions = Vec::new();
-
-    for project in projects {
-        let sessions = state.database.get_sessions_for_project(&project.id)?;
-        all_sessions.extend(sessions);
-    }
+    let all_sessions = load_all_sessions(&state)?;
 
     let query_lower = query.to_lowercase();
+    let favorites_only = favorites_only.unwrap_or(false);
 
     // Filter and score sessions - calculate score once per session
     let mut scored_sessions: Vec<(SessionMetadata, i32)> = all_sessions
         .into_iter()
         .filter_map(|s| {
-            // Filter by tags first
-            if let Some(ref filter_tags) = tags_filter {
-                let session_tags = s.get_tags();
-                if !filter_tags.iter().all(|ft| session_tags.contains(ft)) {
-                    return None;
-                }
-            }
-
-            // Filter by favorites
-            if let Some(true) = favorites_only {
-                if !s.is_favorite {
-                    return None;
-                }
-            }
-
-            // Calculate score once and check if matches
-            let score = calculate_relevance_score(&s, &query_lower);
-            if score > 0 {
-                Some((s, score))
-            } else {
-                None
-            }
+            let score = match_session(&s, &query_lower, tags_filter.as_deref(), favorites_only)?;
+            Some((s, score))
         })
         .collect();
 
@@ -277,3 +925,418 @@ pub async fn search_sessions(
 
     Ok(result)
 }
+
+// ==================== Streaming Search ====================
+
+/// A single match emitted as a `search-result` event
+#[derive(Debug, Clone, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SearchResultEvent {
+    pub search_id: String,
+    pub session: SessionMetadata,
+    pub score: i32,
+}
+
+/// Emitted as `search-complete` once a streaming search finishes or is cancelled
+#[derive(Debug, Clone, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SearchCompleteEvent {
+    pub search_id: String,
+    pub match_count: usize,
+    pub cancelled: bool,
+}
+
+/// Walk sessions and hand each match to `emit` as soon as it is found.
+///
+/// Checks `cancelled` before every session so a cancelled search stops
+/// producing results immediately. Returns the completion summary.
+fn stream_search_results<F>(
+    search_id: &str,
+    sessions: Vec<SessionMetadata>,
+    query_lower: &str,
+    tags_filter: Option<&[String]>,
+    favorites_only: bool,
+    cancelled: &AtomicBool,
+    mut emit: F,
+) -> SearchCompleteEvent
+where
+    F: FnMut(SearchResultEvent),
+{
+    let mut match_count = 0;
+
+    for session in sessions {
+        if cancelled.load(Ordering::SeqCst) {
+            return SearchCompleteEvent {
+                search_id: search_id.to_string(),
+                match_count,
+                cancelled: true,
+            };
+        }
+
+        if let Some(score) = match_session(&session, query_lower, tags_filter, favorites_only) {
+            match_count += 1;
+            emit(SearchResultEvent {
+                search_id: search_id.to_string(),
+                session,
+                score,
+            });
+        }
+    }
+
+    SearchCompleteEvent {
+        search_id: search_id.to_string(),
+        match_count,
+        cancelled: false,
+    }
+}
+
+/// Start a streaming session search
+///
+/// The caller picks the `search_id` (e.g. a fresh UUID) so it can match
+/// events that arrive before this command returns. Matches are emitted as
+/// `search-result` events in discovery order (unsorted), followed by a single
+/// `search-complete` event. Use `cancel_search` with the id to stop early.
+/// Returns the id back.
+#[tauri::command]
+pub async fn search_sessions_stream(
+    state: State<'_, AppState>,
+    search_id: String,
+    query: String,
+    tags_filter: Option<Vec<String>>,
+    favorites_only: Option<bool>,
+) -> Result<String> {
+    validate_id(&search_id, "search_id")?;
+    let all_sessions = load_all_sessions(&state)?;
+
+    let task = state
+        .background_tasks
+        .register_with_id(&search_id, "search", Some(query.clone()), true)
+        .ok_or_else(|| crate::Error::Other(format!("Search {search_id} is already running")))?;
+
+    let app_handle = state.app_handle.clone();
+
+    tokio::task::spawn_blocking(move || {
+        let query_lower = query.to_lowercase();
+        let complete = stream_search_results(
+            task.id(),
+            all_sessions,
+            &query_lower,
+            tags_filter.as_deref(),
+            favorites_only.unwrap_or(false),
+            task.cancel_flag(),
+            |result| {
+                if let Err(e) = app_handle.emit("search-result", result) {
+                    tracing::warn!("Failed to emit search result: {}", e);
+                }
+            },
+        );
+
+        drop(task);
+
+        if let Err(e) = app_handle.emit("search-complete", complete) {
+            tracing::warn!("Failed to emit search completion: {}", e);
+        }
+    });
+
+    Ok(search_id)
+}
+
+/// Cancel a streaming search started with `search_sessions_stream`
+///
+/// Returns false if the search already finished or the id is unknown.
+#[tauri::command]
+pub async fn cancel_search(state: State<'_, AppState>, search_id: String) -> Result<bool> {
+    Ok(state.background_tasks.cancel(&search_id))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::Project;
+
+    fn test_db() -> (tempfile::TempDir, Database) {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let db = Database::new(&temp_dir.path().join("test.db")).unwrap();
+        (temp_dir, db)
+    }
+
+    /// Insert a session row bypassing foreign key enforcement
+    fn insert_orphan(temp_dir: &tempfile::TempDir, session_id: &str, project_id: &str) {
+        let conn = rusqlite::Connection::open(temp_dir.path().join("test.db")).unwrap();
+        conn.execute_batch("PRAGMA foreign_keys = OFF").unwrap();
+        conn.execute(
+            "INSERT INTO session_metadata (session_id, project_id) VALUES (?1, ?2)",
+            rusqlite::params![session_id, project_id],
+        )
+        .unwrap();
+    }
+
+    #[test]
+    fn test_derive_session_title_strips_markdown() {
+        assert_eq!(
+            derive_session_title("## Fix the **login** bug in `auth.rs`:\n\nDetails follow")
+                .as_deref(),
+            Some("Fix the login bug in auth.rs")
+        );
+        assert_eq!(
+            derive_session_title(
+                "```\nlet x = 1;\n```\n- See [the docs](https://example.com) first."
+            )
+            .as_deref(),
+            Some("See the docs first")
+        );
+        assert_eq!(derive_session_title("   \n\n"), None);
+    }
+
+    #[test]
+    fn test_derive_session_title_caps_at_word_boundary() {
+        let title = derive_session_title(
+            "Please refactor the session search so that it streams results and supports cancellation",
+        )
+        .unwrap();
+        assert_eq!(title, "Please refactor the session search so that it…");
+        assert!(title.chars().count() <= MAX_GENERATED_TITLE_CHARS + 1);
+
+        let long_word = "x".repeat(80);
+        let title = derive_session_title(&long_word).unwrap();
+        assert_eq!(title.chars().count(), MAX_GENERATED_TITLE_CHARS + 1);
+    }
+
+    #[test]
+    fn test_apply_generated_title_keeps_existing_title() {
+        let (_temp_dir, db) = test_db();
+        let project = Project::new("/tmp/titles");
+        db.insert_project(&project).unwrap();
+
+        let mut untitled = SessionMetadata::new("untitled", &project.id);
+        untitled.first_message = Some("# Add dark mode".to_string());
+        db.upsert_session_metadata(&untitled).unwrap();
+
+        let mut titled = SessionMetadata::new("titled", &project.id);
+        titled.title = Some("My title".to_string());
+        titled.first_message = Some("Something else".to_string());
+        db.upsert_session_metadata(&titled).unwrap();
+
+        assert_eq!(
+            apply_generated_title(&db, "untitled").unwrap().as_deref(),
+            Some("Add dark mode")
+        );
+        assert_eq!(
+            db.get_session_by_id("untitled")
+                .unwrap()
+                .unwrap()
+                .title
+                .as_deref(),
+            Some("Add dark mode")
+        );
+
+        assert_eq!(
+            apply_generated_title(&db, "titled").unwrap().as_deref(),
+            Some("My title")
+        );
+        assert_eq!(
+            db.get_session_by_id("titled")
+                .unwrap()
+                .unwrap()
+                .title
+                .as_deref(),
+            Some("My title")
+        );
+        assert!(!db
+            .set_session_title_if_empty("titled", "Overwrite")
+            .unwrap());
+    }
+
+    #[test]
+    fn test_repair_orphans_reassigns_matching_project() {
+        let (temp_dir, db) = test_db();
+        let project_dir = tempfile::tempdir().unwrap();
+        let project_path = project_dir.path().canonicalize().unwrap();
+        let project = Project::new(project_path.to_str().unwrap());
+        db.insert_project(&project).unwrap();
+
+        insert_orphan(&temp_dir, "orphan-empty", "");
+        insert_orphan(&temp_dir, "orphan-missing", "deleted-project");
+        insert_orphan(&temp_dir, "orphan-unknown", "");
+
+        let orphans = db.find_orphaned_session_metadata().unwrap();
+        assert_eq!(orphans.len(), 3);
+
+        let cwd = project_path.to_string_lossy().to_string();
+        let report = repair_orphans(&db, |id| match id {
+            "orphan-unknown" => Some("/no/such/project".to_string()),
+            _ => Some(cwd.clone()),
+        })
+        .unwrap();
+
+        assert_eq!(report.repaired.len(), 2);
+        assert_eq!(report.unresolved, vec!["orphan-unknown".to_string()]);
+        let repaired = db.get_session_by_id("orphan-empty").unwrap().unwrap();
+        assert_eq!(repaired.project_id, project.id);
+        assert_eq!(db.find_orphaned_session_metadata().unwrap().len(), 1);
+    }
+
+    fn session_with_title(id: &str, title: &str) -> SessionMetadata {
+        let mut session = SessionMetadata::new(id, "project-1");
+        session.title = Some(title.to_string());
+        session
+    }
+
+    #[test]
+    fn test_stream_search_emits_each_match() {
+        let sessions = vec![
+            session_with_title("s1", "Fix login bug"),
+            session_with_title("s2", "Refactor parser"),
+            session_with_title("s3", "Login page styling"),
+        ];
+        let cancelled = AtomicBool::new(false);
+        let mut emitted = Vec::new();
+
+        let complete = stream_search_results(
+            "search-1",
+            sessions,
+            "login",
+            None,
+            false,
+            &cancelled,
+            |r| emitted.push(r),
+        );
+
+        let ids: Vec<&str> = emitted
+            .iter()
+            .map(|r| r.session.session_id.as_str())
+            .collect();
+        assert_eq!(ids, vec!["s1", "s3"]);
+        assert!(emitted.iter().all(|r| r.search_id == "search-1"));
+        assert_eq!(complete.match_count, 2);
+        assert!(!complete.cancelled);
+    }
+
+    #[test]
+    fn test_stream_search_stops_after_cancellation() {
+        let sessions = vec![
+            session_with_title("s1", "login one"),
+            session_with_title("s2", "login two"),
+            session_with_title("s3", "login three"),
+        ];
+        let cancelled = AtomicBool::new(false);
+        let mut emitted = Vec::new();
+
+        let complete = stream_search_results(
+            "search-2",
+            sessions,
+            "login",
+            None,
+            false,
+            &cancelled,
+            |r| {
+                emitted.push(r);
+                // Cancel as soon as the first result arrives
+                cancelled.store(true, Ordering::SeqCst);
+            },
+        );
+
+        assert_eq!(emitted.len(), 1);
+        assert_eq!(complete.match_count, 1);
+        assert!(complete.cancelled);
+    }
+
+    #[test]
+    fn test_normalize_approval_policy() {
+        for policy in APPROVAL_POLICIES {
+            assert_eq!(normalize_approval_policy(policy).unwrap(), *policy);
+        }
+        assert_eq!(
+            normalize_approval_policy("onRequest").unwrap(),
+            "on-request"
+        );
+        assert_eq!(
+            normalize_approval_policy("on_failure").unwrap(),
+            "on-failure"
+        );
+        assert_eq!(
+            normalize_approval_policy("unlessTrusted").unwrap(),
+            "untrusted"
+        );
+        assert_eq!(normalize_approval_policy(" Never ").unwrap(), "never");
+        assert!(normalize_approval_policy("always").is_err());
+        assert!(normalize_approval_policy("").is_err());
+    }
+
+    #[test]
+    fn test_render_share_summary_omits_command_output() {
+        use crate::codex_import::SessionMessage;
+        use crate::database::TaskItem;
+
+        let mut session = session_with_title("s1", "Fix login bug");
+        session.first_message = Some("Please fix the login bug".to_string());
+        session.status = SessionStatus::Completed;
+        session.set_tasks(vec![
+            TaskItem {
+                content: "Reproduce".to_string(),
+                status: "completed".to_string(),
+            },
+            TaskItem {
+                content: "Add test".to_string(),
+                status: "pending".to_string(),
+            },
+        ]);
+
+        let message = |message_type: &str, content: serde_json::Value| SessionMessage {
+            timestamp: "t".to_string(),
+            message_type: message_type.to_string(),
+            role: None,
+            content,
+        };
+        let messages = vec![
+            message(
+                "function_call",
+                serde_json::json!({"arguments": "cat .env"}),
+            ),
+            message(
+                "function_call_output",
+                serde_json::json!({"output": "API_KEY=sk-secret"}),
+            ),
+            message("local_shell_call", serde_json::json!({"command": ["ls"]})),
+        ];
+
+        let summary = render_share_summary(&session, Some(&messages));
+        assert!(summary.starts_with("# Fix login bug"));
+        assert!(summary.contains("> Please fix the login bug"));
+        assert!(summary.contains("- [x] Reproduce (completed)"));
+        assert!(summary.contains("- [ ] Add test (pending)"));
+        assert!(summary.contains("Tool calls: 2"));
+        assert!(summary.contains("Status: completed"));
+        assert!(!summary.contains("sk-secret"));
+        assert!(!summary.contains("cat .env"));
+
+        assert!(render_share_summary(&session, None).contains("Tool calls: unknown"));
+    }
+
+    #[test]
+    fn test_attention_sessions_reports_failed_and_awaiting_approval() {
+        let (_dir, db) = test_db();
+        let project = Project::new("/tmp/project");
+        db.insert_project(&project).unwrap();
+        for id in ["failed", "waiting", "idle"] {
+            db.upsert_session_metadata(&SessionMetadata::new(id, &project.id))
+                .unwrap();
+        }
+        db.update_session_status("failed", &SessionStatus::Failed)
+            .unwrap();
+
+        let approvals = vec![
+            "waiting".to_string(),
+            "waiting".to_string(),
+            "unknown".to_string(),
+        ];
+        let sessions = attention_sessions(&db, &approvals).unwrap();
+
+        assert_eq!(sessions.len(), 2);
+        assert_eq!(sessions[0].session.session_id, "waiting");
+        assert_eq!(sessions[0].reasons, vec![AttentionReason::AwaitingApproval]);
+        assert_eq!(sessions[0].pending_approvals, 2);
+        assert_eq!(sessions[1].session.session_id, "failed");
+        assert_eq!(sessions[1].reasons, vec![AttentionReason::Failed]);
+    }
+}
@@ -1,33 +1,274 @@
 //! Snapshot management commands
 
 use std::path::Path;
+use std::sync::Arc;
 
-use tauri::State;
+use serde::{Deserialize, Serialize};
+use tauri::{Emitter, State};
 
-use crate::database::Snapshot;
+use crate::commands::projects::validate_id;
+use crate::database::{Database, Snapshot};
+use crate::snapshots::{
+    GitRevertMode, LineEndingMode, RevertReport, SnapshotEstimate, SnapshotFileEntry,
+    SnapshotLocks, SnapshotProgress,
+};
 use crate::state::AppState;
 use crate::Result;
 
-/// Create a snapshot for a session
+/// Emit a `snapshot-progress` event
+fn emit_snapshot_progress(app_handle: &tauri::AppHandle, progress: SnapshotProgress) {
+    if let Err(e) = app_handle.emit("snapshot-progress", progress) {
+        tracing::warn!("Failed to emit snapshot progress: {}", e);
+    }
+}
+
+/// Create a snapshot for a session, optionally named with `label`
+///
+/// File backups emit `snapshot-progress` events as files are read. Fails if
+/// a snapshot of the same project is already being created or reverted.
 #[tauri::command]
 pub async fn create_snapshot(
     state: State<'_, AppState>,
     session_id: String,
     project_path: String,
+    label: Option<String>,
 ) -> Result<Snapshot> {
     let path = Path::new(&project_path);
-    crate::snapshots::create_snapshot(&state.database, &session_id, path)
+    let _lock = state.snapshot_locks.try_lock(path)?;
+    crate::snapshots::create_snapshot_with_progress(
+        &state.database,
+        &session_id,
+        path,
+        label.as_deref(),
+        |progress| emit_snapshot_progress(&state.app_handle, progress),
+    )
+}
+
+/// One snapshot to take in `create_snapshots_batch`
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SnapshotBatchEntry {
+    pub session_id: String,
+    pub project_path: String,
+    #[serde(default)]
+    pub label: Option<String>,
+}
+
+/// Outcome of one entry in a batch
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SnapshotBatchResult {
+    pub session_id: String,
+    pub project_path: String,
+    /// The snapshot taken, if it succeeded
+    pub snapshot: Option<Snapshot>,
+    /// Why the snapshot failed, if it did
+    pub error: Option<String>,
+}
+
+/// Summary of a batch of snapshots, in the order the entries were given
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SnapshotBatchSummary {
+    pub succeeded: usize,
+    pub failed: usize,
+    pub results: Vec<SnapshotBatchResult>,
+}
+
+/// Snapshot each entry in turn; a failed entry is recorded and the rest still run
+fn snapshot_entries(
+    db: &Database,
+    locks: &SnapshotLocks,
+    entries: Vec<SnapshotBatchEntry>,
+) -> SnapshotBatchSummary {
+    let results: Vec<SnapshotBatchResult> = entries
+        .into_iter()
+        .map(|entry| {
+            let outcome = validate_id(&entry.session_id, "session_id").and_then(|()| {
+                let _lock = locks.try_lock(Path::new(&entry.project_path))?;
+                crate::snapshots::create_snapshot(
+                    db,
+                    &entry.session_id,
+                    Path::new(&entry.project_path),
+                    entry.label.as_deref(),
+                )
+            });
+            if let Err(e) = &outcome {
+                tracing::warn!(
+                    "Batch snapshot failed for session {} at {}: {}",
+                    entry.session_id,
+                    entry.project_path,
+                    e
+                );
+            }
+            let (snapshot, error) = match outcome {
+                Ok(snapshot) => (Some(snapshot), None),
+                Err(e) => (None, Some(e.to_string())),
+            };
+            SnapshotBatchResult {
+                session_id: entry.session_id,
+                project_path: entry.project_path,
+                snapshot,
+                error,
+            }
+        })
+        .collect();
+
+    let succeeded = results.iter().filter(|r| r.snapshot.is_some()).count();
+    SnapshotBatchSummary {
+        succeeded,
+        failed: results.len() - succeeded,
+        results,
+    }
+}
+
+/// Snapshot several sessions' projects at once, e.g. before a risky operation
+///
+/// Each entry is attempted even if earlier ones fail.
+#[tauri::command]
+pub async fn create_snapshots_batch(
+    state: State<'_, AppState>,
+    entries: Vec<SnapshotBatchEntry>,
+) -> Result<SnapshotBatchSummary> {
+    let db = state.database.clone();
+    let locks = state.snapshot_locks.clone();
+    tokio::task::spawn_blocking(move || snapshot_entries(&db, &locks, entries))
+        .await
+        .map_err(|e| crate::Error::Other(format!("Task join error: {e}")))
 }
 
 /// Revert to a snapshot
+///
+/// `line_endings` controls whether file backups are restored byte-for-byte
+/// (the default) or converted to the platform's line endings. `git_mode`
+/// chooses between hard-resetting the current branch (the default) and
+/// checking the snapshot out on a new branch, whose name is returned in the
+/// report. File backups emit `snapshot-progress` events as files are written.
+/// Fails if a snapshot of the same project is already being created or reverted.
 #[tauri::command]
 pub async fn revert_to_snapshot(
     state: State<'_, AppState>,
     snapshot_id: String,
     project_path: String,
-) -> Result<()> {
+    line_endings: Option<LineEndingMode>,
+    git_mode: Option<GitRevertMode>,
+) -> Result<RevertReport> {
     let path = Path::new(&project_path);
-    crate::snapshots::revert_to_snapshot(&state.database, &snapshot_id, path)
+    let _lock = state.snapshot_locks.try_lock(path)?;
+    crate::snapshots::revert_to_snapshot_with_progress(
+        &state.database,
+        &snapshot_id,
+        path,
+        line_endings.unwrap_or_default(),
+        git_mode.unwrap_or_default(),
+        |progress| emit_snapshot_progress(&state.app_handle, progress),
+    )
+}
+
+/// List the files a snapshot would restore, without reverting
+///
+/// `existsNow` on each entry tells whether the file is currently present, so
+/// the UI can tell restores of deleted files from overwrites.
+#[tauri::command]
+pub async fn preview_snapshot(
+    state: State<'_, AppState>,
+    snapshot_id: String,
+    project_path: String,
+) -> Result<Vec<SnapshotFileEntry>> {
+    let db = state.database.clone();
+    tokio::task::spawn_blocking(move || {
+        crate::snapshots::list_snapshot_files(&db, &snapshot_id, Path::new(&project_path))
+    })
+    .await
+    .map_err(|e| crate::Error::Other(format!("Task join error: {e}")))?
+}
+
+/// Get a unified diff from a snapshot to the current files, i.e. what reverting would undo
+#[tauri::command]
+pub async fn get_snapshot_diff(
+    state: State<'_, AppState>,
+    snapshot_id: String,
+    project_path: String,
+) -> Result<String> {
+    let db = state.database.clone();
+    tokio::task::spawn_blocking(move || {
+        crate::snapshots::diff_snapshot(&db, &snapshot_id, Path::new(&project_path))
+    })
+    .await
+    .map_err(|e| crate::Error::Other(format!("Task join error: {e}")))?
+}
+
+/// Get whether file backups keep files over 1MB in sidecar files instead of skipping them
+#[tauri::command]
+pub async fn get_backup_large_files(state: State<'_, AppState>) -> Result<bool> {
+    crate::snapshots::backup_large_files_enabled(&state.database)
+}
+
+/// Set whether file backups keep files over 1MB in sidecar files next to the database
+#[tauri::command]
+pub async fn set_backup_large_files(state: State<'_, AppState>, enabled: bool) -> Result<()> {
+    state.database.set_setting(
+        crate::snapshots::BACKUP_LARGE_FILES_SETTING,
+        Some(if enabled { "true" } else { "false" }),
+    )
+}
+
+/// Get whether git snapshots also get a `refs/codex-snapshots/<id>` ref
+#[tauri::command]
+pub async fn get_git_snapshot_refs(state: State<'_, AppState>) -> Result<bool> {
+    crate::snapshots::git_snapshot_refs_enabled(&state.database)
+}
+
+/// Set whether git snapshots also get a ref, making them visible to git tooling
+#[tauri::command]
+pub async fn set_git_snapshot_refs(state: State<'_, AppState>, enabled: bool) -> Result<()> {
+    state.database.set_setting(
+        crate::snapshots::GIT_SNAPSHOT_REFS_SETTING,
+        Some(if enabled { "true" } else { "false" }),
+    )
+}
+
+/// Remove git refs left behind by deleted snapshots
+///
+/// Best effort, since the snapshots themselves are already gone.
+pub(crate) async fn prune_snapshot_refs(db: Arc<Database>) {
+    let pruned =
+        tokio::task::spawn_blocking(move || crate::snapshots::prune_all_snapshot_refs(&db)).await;
+    match pruned {
+        Ok(Ok(count)) if count > 0 => tracing::info!("Removed {} stale snapshot refs", count),
+        Ok(Ok(_)) => {}
+        Ok(Err(e)) => tracing::warn!("Failed to prune snapshot refs: {}", e),
+        Err(e) => tracing::warn!("Snapshot ref pruning task failed: {}", e),
+    }
+}
+
+/// Estimate how many files a file backup of a project would store, and how long it would take
+#[tauri::command]
+pub async fn estimate_snapshot_cost(project_path: String) -> Result<SnapshotEstimate> {
+    let canonical_path = crate::utils::validate_and_canonicalize_path(&project_path)?;
+    tokio::task::spawn_blocking(move || crate::snapshots::estimate_snapshot_cost(&canonical_path))
+        .await
+        .map_err(|e| crate::Error::Other(format!("Task join error: {e}")))?
+}
+
+/// List files in a project that file backups skip because their paths aren't valid UTF-8
+///
+/// The paths are decoded lossily and are meant for display.
+#[tauri::command]
+pub async fn find_non_utf8_paths(project_path: String) -> Result<Vec<String>> {
+    let canonical_path = crate::utils::validate_and_canonicalize_path(&project_path)?;
+    tokio::task::spawn_blocking(move || crate::snapshots::find_non_utf8_paths(&canonical_path))
+        .await
+        .map_err(|e| crate::Error::Other(format!("Task join error: {e}")))?
+}
+
+/// List files left with merge conflicts, e.g. after reverting to a git snapshot conflicted
+#[tauri::command]
+pub async fn get_conflicted_files(project_path: String) -> Result<Vec<String>> {
+    let canonical_path = crate::utils::validate_and_canonicalize_path(&project_path)?;
+    tokio::task::spawn_blocking(move || crate::snapshots::conflicted_files(&canonical_path))
+        .await
+        .map_err(|e| crate::Error::Other(format!("Task join error: {e}")))?
 }
 
 /// List snapshots for a session
@@ -39,16 +280,153 @@ pub async fn list_snapshots(
     state.database.get_snapshots_for_session(&session_id)
 }
 
-/// Clean up old snapshots by age
+/// Search snapshots by label and creation time, across all sessions unless `session_id` is given
+#[tauri::command]
+pub async fn search_snapshots(
+    state: State<'_, AppState>,
+    session_id: Option<String>,
+    label_query: Option<String>,
+    since: Option<i64>,
+    until: Option<i64>,
+) -> Result<Vec<Snapshot>> {
+    if let Some(session_id) = &session_id {
+        validate_id(session_id, "session_id")?;
+    }
+    let label_query = label_query.as_deref().map(str::trim);
+    state
+        .database
+        .search_snapshots(session_id.as_deref(), label_query, since, until)
+}
+
+/// Delete snapshots and command history entries older than `days`
+///
+/// Both use the same cutoff. Returns the (snapshots, history entries) counts.
+fn cleanup_by_age(db: &Database, days: i64) -> Result<(usize, usize)> {
+    let cutoff = chrono::Utc::now().timestamp() - days * 86400;
+    let snapshots = db.cleanup_snapshots_created_before(cutoff)?;
+    let history = db.clear_command_history(None, Some(cutoff))?;
+    Ok((snapshots, history))
+}
+
+/// Clean up old snapshots and command history by age
 #[tauri::command]
 pub async fn cleanup_old_snapshots_by_age(
     state: State<'_, AppState>,
     max_age_days: Option<i64>,
 ) -> Result<String> {
     let days = max_age_days.unwrap_or(30); // Default 30 days
-    let count = state.database.cleanup_snapshots_older_than(days)?;
+    let (count, history) = cleanup_by_age(&state.database, days)?;
+    if count > 0 {
+        prune_snapshot_refs(state.database.clone()).await;
+    }
+
+    Ok(format!(
+        "Deleted {count} old snapshots and {history} command history entries (older than {days} days)"
+    ))
+}
+
+/// Move a session's snapshots older than `older_than_days` into a compressed archive file
+///
+/// The snapshots are removed from the database once the archive is written.
+/// Returns the number archived.
+#[tauri::command]
+pub async fn archive_snapshots_to_file(
+    state: State<'_, AppState>,
+    session_id: String,
+    dest_path: String,
+    older_than_days: Option<i64>,
+) -> Result<usize> {
+    validate_id(&session_id, "session_id")?;
+    let days = older_than_days.unwrap_or(30);
+    if days < 0 {
+        return Err(crate::Error::Other(
+            "older_than_days must not be negative".to_string(),
+        ));
+    }
+    let dest = crate::utils::resolve_output_file(&dest_path)?;
+    let cutoff = chrono::Utc::now().timestamp() - days * 86400;
+
+    let db = state.database.clone();
+    let archived = tokio::task::spawn_blocking(move || {
+        crate::snapshots::archive_snapshots(&db, &session_id, cutoff, &dest)
+    })
+    .await
+    .map_err(|e| crate::Error::Other(format!("Task join error: {e}")))??;
+    if archived > 0 {
+        prune_snapshot_refs(state.database.clone()).await;
+    }
+    Ok(archived)
+}
+
+/// Re-import snapshots from an archive written by `archive_snapshots_to_file`
+///
+/// Returns the number of snapshots added.
+#[tauri::command]
+pub async fn restore_snapshots_from_file(
+    state: State<'_, AppState>,
+    src_path: String,
+) -> Result<usize> {
+    let src = crate::utils::validate_and_canonicalize_path(&src_path)?;
+    if !src.is_file() {
+        return Err(crate::Error::InvalidPath(format!(
+            "Not a regular file: {src_path}"
+        )));
+    }
+
+    let db = state.database.clone();
+    tokio::task::spawn_blocking(move || crate::snapshots::restore_snapshots(&db, &src))
+        .await
+        .map_err(|e| crate::Error::Other(format!("Task join error: {e}")))?
+}
+
+/// Export one snapshot, with the files it backed up, to a portable tar file
+///
+/// Returns the number of files written.
+#[tauri::command]
+pub async fn export_snapshot(
+    state: State<'_, AppState>,
+    snapshot_id: String,
+    dest_path: String,
+) -> Result<usize> {
+    let dest = crate::utils::resolve_output_file(&dest_path)?;
+
+    let db = state.database.clone();
+    tokio::task::spawn_blocking(move || crate::snapshots::export_snapshot(&db, &snapshot_id, &dest))
+        .await
+        .map_err(|e| crate::Error::Other(format!("Task join error: {e}")))?
+}
+
+/// Add a snapshot exported by `export_snapshot` to a session
+#[tauri::command]
+pub async fn import_snapshot(
+    state: State<'_, AppState>,
+    session_id: String,
+    archive_path: String,
+) -> Result<Snapshot> {
+    validate_id(&session_id, "session_id")?;
+    let src = crate::utils::validate_and_canonicalize_path(&archive_path)?;
+    if !src.is_file() {
+        return Err(crate::Error::InvalidPath(format!(
+            "Not a regular file: {archive_path}"
+        )));
+    }
 
-    Ok(format!("Deleted {count} old snapshots (older than {days} days)"))
+    let db = state.database.clone();
+    tokio::task::spawn_blocking(move || crate::snapshots::import_snapshot(&db, &session_id, &src))
+        .await
+        .map_err(|e| crate::Error::Other(format!("Task join error: {e}")))?
+}
+
+/// Upgrade file backups written in older metadata formats to the current one
+///
+/// Returns the number of snapshots migrated. Reverting migrates a snapshot
+/// anyway, so this only does the work up front.
+#[tauri::command]
+pub async fn migrate_legacy_snapshots(state: State<'_, AppState>) -> Result<usize> {
+    let db = state.database.clone();
+    tokio::task::spawn_blocking(move || crate::snapshots::migrate_legacy_snapshots(&db))
+        .await
+        .map_err(|e| crate::Error::Other(format!("Task join error: {e}")))?
 }
 
 /// Clean up old snapshots for a specific session
@@ -60,6 +438,103 @@ pub async fn cleanup_session_snapshots(
 ) -> Result<String> {
     let keep = keep_count.unwrap_or(10); // Default keep 10 most recent
     let count = state.database.cleanup_old_snapshots(&session_id, keep)?;
+    if count > 0 {
+        prune_snapshot_refs(state.database.clone()).await;
+    }
+
+    Ok(format!(
+        "Deleted {count} old snapshots for session {session_id} (kept {keep} most recent)"
+    ))
+}
+
+/// Delete snapshots left behind by permanently deleted sessions
+#[tauri::command]
+pub async fn prune_orphaned_snapshots(state: State<'_, AppState>) -> Result<usize> {
+    let count = state.database.prune_orphaned_snapshots()?;
+    if count > 0 {
+        tracing::info!("Pruned {} orphaned snapshots", count);
+        prune_snapshot_refs(state.database.clone()).await;
+    }
+    Ok(count)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cleanup_by_age_clears_old_command_history() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let db = Database::new(&temp_dir.path().join("test.db")).unwrap();
+        let now = chrono::Utc::now().timestamp();
+        let old = now - 40 * 86400;
+
+        let mut old_snapshot = Snapshot::new_git_ghost("s1", "abc123");
+        old_snapshot.created_at = old;
+        db.insert_snapshot(&old_snapshot).unwrap();
+        db.insert_snapshot(&Snapshot::new_git_ghost("s1", "def456"))
+            .unwrap();
+        db.record_command("s1", "cargo test", "accept", old)
+            .unwrap();
+        db.record_command("s2", "ls", "accept", old).unwrap();
+        db.record_command("s1", "cargo build", "accept", now)
+            .unwrap();
+
+        assert_eq!(cleanup_by_age(&db, 30).unwrap(), (1, 2));
+        assert_eq!(db.get_snapshots_for_session("s1").unwrap().len(), 1);
+        let history = db.get_command_history("s1").unwrap();
+        assert_eq!(history.len(), 1);
+        assert_eq!(history[0].command, "cargo build");
+        assert!(db.get_command_history("s2").unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_snapshot_batch_reports_partial_success() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let db = Database::new(&temp_dir.path().join("test.db")).unwrap();
+        let project_a = temp_dir.path().join("a");
+        let project_b = temp_dir.path().join("b");
+        for dir in [&project_a, &project_b] {
+            std::fs::create_dir(dir).unwrap();
+            std::fs::write(dir.join("main.rs"), "fn main() {}").unwrap();
+        }
+
+        let entry = |session_id: &str, path: &Path| SnapshotBatchEntry {
+            session_id: session_id.to_string(),
+            project_path: path.to_string_lossy().to_string(),
+            label: None,
+        };
+        let summary = snapshot_entries(
+            &db,
+            &SnapshotLocks::default(),
+            vec![
+                entry("s1", &project_a),
+                entry("s2", &temp_dir.path().join("missing")),
+                entry("bad id!", &project_b),
+                entry("s3", &project_b),
+            ],
+        );
+
+        assert_eq!((summary.succeeded, summary.failed), (2, 2));
+        let outcomes: Vec<(&str, bool)> = summary
+            .results
+            .iter()
+            .map(|r| (r.session_id.as_str(), r.snapshot.is_some()))
+            .collect();
+        assert_eq!(
+            outcomes,
+            vec![
+                ("s1", true),
+                ("s2", false),
+                ("bad id!", false),
+                ("s3", true)
+            ]
+        );
+        assert!(summary.results[1].error.is_some());
+        assert!(summary.results[0].error.is_none());
 
-    Ok(format!("Deleted {count} old snapshots for session {session_id} (kept {keep} most recent)"))
+        assert_eq!(db.get_snapshots_for_session("s1").unwrap().len(), 1);
+        assert_eq!(db.get_snapshots_for_session("s3").unwrap().len(), 1);
+        assert!(db.get_snapshots_for_session("s2").unwrap().is_empty());
+    }
 }
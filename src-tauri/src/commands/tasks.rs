@@ -0,0 +1,21 @@
+//! Background task commands
+
+use tauri::State;
+
+use crate::state::AppState;
+use crate::tasks::BackgroundTaskInfo;
+use crate::Result;
+
+/// List running background operations, oldest first
+#[tauri::command]
+pub async fn list_background_tasks(state: State<'_, AppState>) -> Result<Vec<BackgroundTaskInfo>> {
+    Ok(state.background_tasks.list())
+}
+
+/// Ask a background operation to stop
+///
+/// Returns false if the task already finished, is unknown, or can't be cancelled.
+#[tauri::command]
+pub async fn cancel_background_task(state: State<'_, AppState>, id: String) -> Result<bool> {
+    Ok(state.background_tasks.cancel(&id))
+}
@@ -3,32 +3,273 @@
 use base64::{engine::general_purpose::STANDARD, Engine as _};
 use serde_json::Value as JsonValue;
 use std::io::Write;
-use tauri::State;
+use tauri::{Emitter, State};
 
 use crate::app_server::ipc_bridge::{
-    ApprovalDecision, ApprovalResponseResult, ThreadListParams, ThreadListResponse,
-    ThreadResumeParams, ThreadResumeResponse, ThreadStartParams, ThreadStartResponse,
-    TurnInterruptParams, TurnStartParams, TurnStartResponse, UserInput, SandboxPolicy,
+    ApprovalDecision, ApprovalResponseResult, RiskLevel, SandboxPolicy, ThreadInfo,
+    ThreadListParams, ThreadListResponse, ThreadResumeParams, ThreadResumeResponse,
+    ThreadStartParams, ThreadStartResponse, ThreadSummary, TurnInterruptParams, TurnStartParams,
+    TurnStartResponse, UserInput,
 };
-use crate::database::SessionMetadata;
+use crate::app_server::PendingApproval;
+use crate::codex_import::SessionItemsPage;
+use crate::commands::app_server::{
+    cached_models, ensure_model_available, Model, DEFAULT_MODEL_SETTING,
+};
+use crate::database::{Database, ProjectSettings, SessionMetadata, SessionSource};
 use crate::state::AppState;
 use crate::{Error, Result};
 
+/// A named sandbox policy offered when starting a thread
+#[derive(Debug, Clone, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SandboxPreset {
+    /// Value accepted as `sandbox`/`sandbox_policy` by the thread commands
+    pub id: &'static str,
+    pub label: &'static str,
+    pub description: &'static str,
+    pub policy: SandboxPolicy,
+    pub risk: RiskLevel,
+}
+
+/// The sandbox presets, from least to most permissive
+fn sandbox_presets() -> Vec<SandboxPreset> {
+    let preset = |id, label, description, policy: SandboxPolicy| SandboxPreset {
+        id,
+        label,
+        description,
+        risk: policy.risk_level(),
+        policy,
+    };
+
+    vec![
+        preset(
+            "read-only",
+            "Read only",
+            "Can read files but not modify them or use the network.",
+            SandboxPolicy::ReadOnly,
+        ),
+        preset(
+            "workspace-write",
+            "Workspace write",
+            "Can edit files in the project and temp directories, without network access.",
+            SandboxPolicy::WorkspaceWrite {
+                writable_roots: Vec::new(),
+                network_access: false,
+                exclude_tmpdir_env_var: false,
+                exclude_slash_tmp: false,
+            },
+        ),
+        preset(
+            "external-sandbox",
+            "External sandbox",
+            "Relies on a sandbox you run yourself, such as a container or VM.",
+            SandboxPolicy::ExternalSandbox {
+                network_access: "restricted".to_string(),
+            },
+        ),
+        preset(
+            "danger-full-access",
+            "Full access",
+            "No sandbox. Can modify any file and use the network.",
+            SandboxPolicy::DangerFullAccess,
+        ),
+    ]
+}
+
 fn parse_sandbox_policy(policy: Option<String>) -> Option<SandboxPolicy> {
-    match policy.as_deref() {
-        Some("read-only") => Some(SandboxPolicy::ReadOnly),
-        Some("workspace-write") => Some(SandboxPolicy::WorkspaceWrite {
-            writable_roots: Vec::new(),
-            network_access: false,
-            exclude_tmpdir_env_var: false,
-            exclude_slash_tmp: false,
-        }),
-        Some("danger-full-access") => Some(SandboxPolicy::DangerFullAccess),
-        Some("external-sandbox") | Some("external_sandbox") => Some(SandboxPolicy::ExternalSandbox {
-            network_access: "restricted".to_string(),
+    let id = match policy.as_deref()? {
+        "external_sandbox" => "external-sandbox",
+        id => id,
+    };
+    sandbox_presets()
+        .into_iter()
+        .find(|preset| preset.id == id)
+        .map(|preset| preset.policy)
+}
+
+/// List the sandbox presets with labels and risk levels
+#[tauri::command]
+pub async fn list_sandbox_presets() -> Result<Vec<SandboxPreset>> {
+    Ok(sandbox_presets())
+}
+
+/// Where a sandbox writable root points relative to the project
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum WritableRootStatus {
+    /// Inside the project directory
+    Inside,
+    /// Exists but lies outside the project, granting wider write access
+    Outside,
+    /// Does not exist, so it grants nothing (likely a typo)
+    Missing,
+}
+
+/// Validation result for one writable root
+#[derive(Debug, Clone, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct WritableRootCheck {
+    pub root: String,
+    /// Canonical path, if the root exists
+    pub resolved: Option<String>,
+    pub status: WritableRootStatus,
+}
+
+/// Check each writable root against the project directory
+///
+/// Relative roots are resolved against the project.
+fn check_writable_roots(
+    project_path: &std::path::Path,
+    roots: &[String],
+) -> Vec<WritableRootCheck> {
+    let project = project_path
+        .canonicalize()
+        .unwrap_or_else(|_| project_path.to_path_buf());
+
+    roots
+        .iter()
+        .map(|root| {
+            let resolved = project.join(root).canonicalize().ok();
+            let status = match &resolved {
+                None => WritableRootStatus::Missing,
+                Some(path) if path.starts_with(&project) => WritableRootStatus::Inside,
+                Some(_) => WritableRootStatus::Outside,
+            };
+            WritableRootCheck {
+                root: root.clone(),
+                resolved: resolved.map(|p| p.to_string_lossy().to_string()),
+                status,
+            }
+        })
+        .collect()
+}
+
+/// Turn writable root checks into an error for missing roots, or warnings for outside ones
+fn writable_root_warnings(checks: &[WritableRootCheck]) -> Result<Vec<String>> {
+    let missing: Vec<&str> = checks
+        .iter()
+        .filter(|c| c.status == WritableRootStatus::Missing)
+        .map(|c| c.root.as_str())
+        .collect();
+    if !missing.is_empty() {
+        return Err(Error::InvalidPath(format!(
+            "Sandbox writable roots do not exist: {}",
+            missing.join(", ")
+        )));
+    }
+
+    Ok(checks
+        .iter()
+        .filter(|c| c.status == WritableRootStatus::Outside)
+        .map(|c| {
+            format!(
+                "Writable root {} is outside the project and grants write access there",
+                c.root
+            )
+        })
+        .collect())
+}
+
+/// Writable roots requested through a thread's config overrides
+///
+/// Accepts both a nested `sandbox_workspace_write` table and the dotted
+/// `sandbox_workspace_write.writable_roots` key.
+fn requested_writable_roots(config: Option<&JsonValue>) -> Vec<String> {
+    let Some(config) = config else {
+        return Vec::new();
+    };
+    config
+        .get("sandbox_workspace_write")
+        .and_then(|table| table.get("writable_roots"))
+        .or_else(|| config.get("sandbox_workspace_write.writable_roots"))
+        .and_then(JsonValue::as_array)
+        .map(|roots| {
+            roots
+                .iter()
+                .filter_map(|r| r.as_str().map(str::to_string))
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// Check a workspace-write sandbox's writable roots against a project
+#[tauri::command]
+pub async fn validate_writable_roots(
+    project_path: String,
+    writable_roots: Vec<String>,
+) -> Result<Vec<WritableRootCheck>> {
+    let project = crate::utils::validate_and_canonicalize_path(&project_path)?;
+    Ok(check_writable_roots(&project, &writable_roots))
+}
+
+/// Model for a new thread: the explicit one, else the project's, else the app-wide default
+///
+/// `None` leaves the choice to the server.
+fn model_for_new_thread(
+    db: &Database,
+    project_id: &str,
+    explicit: Option<String>,
+) -> Result<Option<String>> {
+    if explicit.is_some() {
+        return Ok(explicit);
+    }
+    let project_model = db.get_project(project_id)?.and_then(|p| p.settings().model);
+    if project_model.is_some() {
+        return Ok(project_model);
+    }
+    db.get_setting(DEFAULT_MODEL_SETTING)
+}
+
+/// Input size cap for a thread, taken from its project's settings
+fn max_input_bytes_for_thread(db: &Database, thread_id: &str) -> Result<u64> {
+    let project = match db.get_session_by_id(thread_id)? {
+        Some(metadata) => db.get_project(&metadata.project_id)?,
+        None => None,
+    };
+    Ok(
+        project.map_or(ProjectSettings::DEFAULT_MAX_INPUT_BYTES, |p| {
+            p.settings().effective_max_input_bytes()
         }),
-        _ => None,
+    )
+}
+
+/// Approval policy for a turn: the per-turn value, else the session's stored default
+///
+/// Stored defaults are normalized to the server's spelling, since older ones
+/// were saved in camelCase.
+fn approval_policy_for_turn(
+    db: &Database,
+    thread_id: &str,
+    per_turn: Option<String>,
+) -> Result<Option<String>> {
+    if per_turn.is_some() {
+        return Ok(per_turn);
+    }
+    db.get_session_by_id(thread_id)?
+        .and_then(|s| s.approval_policy)
+        .map(|policy| crate::commands::sessions::normalize_approval_policy(&policy))
+        .transpose()
+}
+
+/// Reject text input larger than `max_bytes`
+///
+/// Only text items count; images are checked separately when they're saved.
+fn check_input_size(input: &[UserInput], max_bytes: u64) -> Result<()> {
+    let total: u64 = input
+        .iter()
+        .map(|item| match item {
+            UserInput::Text { text } => text.len() as u64,
+            _ => 0,
+        })
+        .sum();
+
+    if total > max_bytes {
+        return Err(Error::Other(format!(
+            "Message is too large ({total} bytes, limit is {max_bytes} bytes). Try splitting the prompt into smaller messages."
+        )));
     }
+    Ok(())
 }
 
 /// Start a new thread
@@ -44,10 +285,26 @@ pub async fn start_thread(
     base_instructions: Option<String>,
     developer_instructions: Option<String>,
     config: Option<JsonValue>,
+    force_model: Option<bool>,
 ) -> Result<ThreadStartResponse> {
     // Ensure app-server is running
     state.start_app_server().await?;
 
+    let model = model_for_new_thread(&state.database, &project_id, model)?;
+    if let Some(model) = model.as_deref() {
+        ensure_model_available(&state, model, force_model.unwrap_or(false)).await?;
+    }
+
+    let writable_roots = requested_writable_roots(config.as_ref());
+    let sandbox_warnings = if writable_roots.is_empty() {
+        Vec::new()
+    } else {
+        writable_root_warnings(&check_writable_roots(
+            std::path::Path::new(&cwd),
+            &writable_roots,
+        ))?
+    };
+
     let params = ThreadStartParams {
         cwd: Some(cwd.clone()),
         model,
@@ -63,14 +320,10 @@ pub async fn start_thread(
     let server = guard
         .as_mut()
         .ok_or_else(|| Error::AppServer("App server not running".to_string()))?;
-    let response: ThreadStartResponse = server.send_request("thread/start", params).await?;
-
-    // Create session metadata
-    let metadata = SessionMetadata::new(&response.thread.id, &project_id);
-    state.database.upsert_session_metadata(&metadata)?;
+    let mut response: ThreadStartResponse = server.send_request("thread/start", params).await?;
+    response.sandbox_warnings = sandbox_warnings;
 
-    // Update project last opened time
-    state.database.update_project_last_opened(&project_id)?;
+    record_started_thread(&state.database, &response.thread.id, &project_id)?;
 
     tracing::info!("Started thread: {}", response.thread.id);
 
@@ -88,6 +341,16 @@ pub async fn resume_thread(
     thread_id: String,
     limit: Option<u32>,
     cursor: Option<String>,
+) -> Result<ThreadResumeResponse> {
+    resume_thread_with(&state, thread_id, limit, cursor).await
+}
+
+/// Resume a thread on the app-server, linking it to a project if it has no metadata
+async fn resume_thread_with(
+    state: &AppState,
+    thread_id: String,
+    limit: Option<u32>,
+    cursor: Option<String>,
 ) -> Result<ThreadResumeResponse> {
     // Ensure app-server is running
     state.start_app_server().await?;
@@ -103,6 +366,13 @@ pub async fn resume_thread(
         .as_mut()
         .ok_or_else(|| Error::AppServer("App server not running".to_string()))?;
     let response: ThreadResumeResponse = server.send_request("thread/resume", params).await?;
+    drop(guard);
+
+    link_imported_thread(
+        &state.database,
+        &response.thread.id,
+        std::path::Path::new(&response.thread.cwd),
+    )?;
 
     tracing::info!(
         "Resumed thread: {}, items: {}, has_more: {:?}",
@@ -114,6 +384,136 @@ pub async fn resume_thread(
     Ok(response)
 }
 
+/// Create metadata for a thread started from the desktop and touch its project
+fn record_started_thread(db: &Database, thread_id: &str, project_id: &str) -> Result<()> {
+    let metadata = SessionMetadata::new(thread_id, project_id).with_source(SessionSource::Desktop);
+    db.upsert_session_metadata(&metadata)?;
+    db.update_project_last_opened(project_id)?;
+    Ok(())
+}
+
+/// Link a thread with no metadata to the project containing `cwd`
+///
+/// Threads started outside the desktop (e.g. with the Codex CLI) have no
+/// metadata until they are first resumed here; they are recorded as imported.
+/// A soft-deleted session still has metadata and is left alone in the trash.
+/// Returns the project the thread was linked to, if any.
+fn link_imported_thread(
+    db: &Database,
+    thread_id: &str,
+    cwd: &std::path::Path,
+) -> Result<Option<String>> {
+    if db.session_exists_any(thread_id)? {
+        return Ok(None);
+    }
+    let projects = db.get_all_projects()?;
+    let Some(project) = crate::commands::projects::find_project_for_path(&projects, cwd) else {
+        return Ok(None);
+    };
+
+    let metadata =
+        SessionMetadata::new(thread_id, &project.id).with_source(SessionSource::CliImport);
+    db.upsert_session_metadata(&metadata)?;
+    tracing::info!(
+        "Linked resumed thread {} to project {}",
+        thread_id,
+        project.id
+    );
+    Ok(Some(project.id.clone()))
+}
+
+/// Run `restart`, then `resume`, turning a failed resume into a descriptive error
+async fn restart_then_resume<T, R, F, Fut>(thread_id: &str, restart: R, resume: F) -> Result<T>
+where
+    R: std::future::Future<Output = Result<()>>,
+    F: FnOnce() -> Fut,
+    Fut: std::future::Future<Output = Result<T>>,
+{
+    restart.await?;
+    resume().await.map_err(|e| {
+        Error::AppServer(format!(
+            "App server restarted, but thread {thread_id} could not be resumed: {e}"
+        ))
+    })
+}
+
+/// Restart the app-server and resume `thread_id` on the new process
+///
+/// Useful for recovering from a hung server without losing the open thread.
+#[tauri::command]
+pub async fn restart_server_preserving(
+    state: State<'_, AppState>,
+    thread_id: String,
+) -> Result<ThreadResumeResponse> {
+    crate::commands::projects::validate_id(&thread_id, "thread_id")?;
+
+    restart_then_resume(&thread_id, state.restart_app_server(), || {
+        resume_thread_with(&state, thread_id.clone(), None, None)
+    })
+    .await
+}
+
+/// Model a thread is currently running with
+#[derive(Debug, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ThreadModel {
+    pub thread_id: String,
+    /// Raw model id reported by the app-server
+    pub model: Option<String>,
+    /// Display name from the model list, if the model is listed
+    pub display_name: Option<String>,
+    pub model_provider: Option<String>,
+}
+
+/// Map a thread's raw model id to its entry in the model list
+fn resolve_thread_model(thread: &ThreadInfo, models: &[Model]) -> ThreadModel {
+    let display_name = thread.model.as_deref().and_then(|raw| {
+        models
+            .iter()
+            .find(|m| m.model == raw || m.id == raw)
+            .map(|m| m.display_name.clone())
+    });
+
+    ThreadModel {
+        thread_id: thread.id.clone(),
+        model: thread.model.clone(),
+        display_name,
+        model_provider: thread.model_provider.clone(),
+    }
+}
+
+/// Get the effective model for a thread
+#[tauri::command]
+pub async fn get_thread_model(
+    state: State<'_, AppState>,
+    thread_id: String,
+) -> Result<ThreadModel> {
+    crate::commands::projects::validate_id(&thread_id, "thread_id")?;
+
+    // Ensure app-server is running
+    state.start_app_server().await?;
+
+    // Only the thread info is needed, so keep the item page minimal
+    let params = ThreadResumeParams {
+        thread_id,
+        limit: Some(1),
+        cursor: None,
+    };
+
+    let response: ThreadResumeResponse = {
+        let mut guard = state.app_server.write().await;
+        let server = guard
+            .as_mut()
+            .ok_or_else(|| Error::AppServer("App server not running".to_string()))?;
+        server.send_request("thread/resume", params).await?
+    };
+
+    // The server lock is released above; cached_models may need to take it
+    let models = cached_models(&state).await?;
+
+    Ok(resolve_thread_model(&response.thread, &models))
+}
+
 /// Skill input for send_message
 #[derive(Debug, serde::Deserialize)]
 pub struct SkillInput {
@@ -121,7 +521,99 @@ pub struct SkillInput {
     pub path: String,
 }
 
+/// Approximate number of tokens in `text`, without a tokenizer
+///
+/// Each run of ASCII letters and digits counts as one token plus one per
+/// further 6 characters, while punctuation and non-ASCII characters count as
+/// one token each. This tends to land within ~25% of real BPE counts for English
+/// prose and code, and is only meant for warnings in the compose box.
+fn approximate_token_count(text: &str) -> usize {
+    let run_tokens = |run: usize| if run == 0 { 0 } else { 1 + (run - 1) / 6 };
+
+    let mut tokens = 0;
+    let mut run = 0;
+    for c in text.chars() {
+        if c.is_ascii_alphanumeric() {
+            run += 1;
+            continue;
+        }
+        tokens += run_tokens(run);
+        run = 0;
+        if !c.is_whitespace() {
+            tokens += 1;
+        }
+    }
+    tokens + run_tokens(run)
+}
+
+/// Estimate a message's token count locally (approximate, see `approximate_token_count`)
+#[tauri::command]
+pub fn estimate_tokens(text: String) -> usize {
+    approximate_token_count(&text)
+}
+
+/// Longest stretch of the message used in an automatic snapshot's label
+const AUTO_SNAPSHOT_LABEL_CHARS: usize = 60;
+
+/// Payload of the `auto-snapshot-failed` event
+#[derive(Debug, Clone, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+struct AutoSnapshotFailedEvent {
+    thread_id: String,
+    error: String,
+}
+
+/// Label for the snapshot taken before a turn, from the first line of its message
+fn auto_snapshot_label(text: &str) -> String {
+    let first_line = text
+        .lines()
+        .map(str::trim)
+        .find(|line| !line.is_empty())
+        .unwrap_or("");
+    if first_line.is_empty() {
+        return "Before turn".to_string();
+    }
+    let mut summary: String = first_line.chars().take(AUTO_SNAPSHOT_LABEL_CHARS).collect();
+    if summary.len() < first_line.len() {
+        summary.push('…');
+    }
+    format!("Before turn: {summary}")
+}
+
+/// Snapshot the thread's project before a turn, if the project has `auto_snapshot` on
+///
+/// Returns None when no snapshot was wanted: the thread has no known project
+/// or the project doesn't enable it.
+fn auto_snapshot_before_turn(
+    db: &Database,
+    locks: &crate::snapshots::SnapshotLocks,
+    thread_id: &str,
+    label: &str,
+) -> Option<Result<crate::database::Snapshot>> {
+    let project = match db.get_session_by_id(thread_id) {
+        Ok(Some(metadata)) => db.get_project(&metadata.project_id),
+        Ok(None) => Ok(None),
+        Err(e) => Err(e),
+    };
+    let project = match project {
+        Ok(Some(project)) if project.settings().auto_snapshot => project,
+        Ok(_) => return None,
+        Err(e) => return Some(Err(e)),
+    };
+
+    let path = std::path::Path::new(&project.path);
+    Some(
+        locks
+            .try_lock(path)
+            .and_then(|_lock| crate::snapshots::create_snapshot(db, thread_id, path, Some(label))),
+    )
+}
+
 /// Send a message to start a new turn
+///
+/// If the thread's project has `auto_snapshot` enabled, a snapshot is taken
+/// first. A failed snapshot doesn't stop the turn; it is logged and reported
+/// with an `auto-snapshot-failed` event.
 #[tauri::command]
 #[allow(clippy::too_many_arguments)]
 pub async fn send_message(
@@ -135,8 +627,18 @@ pub async fn send_message(
     model: Option<String>,
     approval_policy: Option<String>,
     sandbox_policy: Option<String>,
+    force_model: Option<bool>,
 ) -> Result<TurnStartResponse> {
+    if let Some(model) = model.as_deref() {
+        ensure_model_available(&state, model, force_model.unwrap_or(false)).await?;
+    }
+
+    let snapshot_label = auto_snapshot_label(&text);
     let mut input: Vec<UserInput> = vec![UserInput::Text { text }];
+    check_input_size(
+        &input,
+        max_input_bytes_for_thread(&state.database, &thread_id)?,
+    )?;
 
     // Add skills if provided (skills should come before images in input)
     if let Some(skill_data) = skills {
@@ -163,6 +665,40 @@ pub async fn send_message(
         }
     }
 
+    let snapshot = {
+        let db = state.database.clone();
+        let locks = state.snapshot_locks.clone();
+        let thread_id = thread_id.clone();
+        tokio::task::spawn_blocking(move || {
+            auto_snapshot_before_turn(&db, &locks, &thread_id, &snapshot_label)
+        })
+        .await
+        .map_err(|e| Error::Other(format!("Task join error: {e}")))?
+    };
+    match snapshot {
+        Some(Ok(snapshot)) => tracing::info!(
+            "Created snapshot {} before turn on {}",
+            snapshot.id,
+            thread_id
+        ),
+        Some(Err(e)) => {
+            tracing::warn!(
+                "Automatic snapshot before turn on {} failed: {}",
+                thread_id,
+                e
+            );
+            let event = AutoSnapshotFailedEvent {
+                thread_id: thread_id.clone(),
+                error: e.to_string(),
+            };
+            if let Err(e) = state.app_handle.emit("auto-snapshot-failed", event) {
+                tracing::warn!("Failed to emit auto-snapshot-failed: {}", e);
+            }
+        }
+        None => {}
+    }
+
+    let approval_policy = approval_policy_for_turn(&state.database, &thread_id, approval_policy)?;
     let params = TurnStartParams {
         thread_id,
         input,
@@ -201,6 +737,15 @@ pub async fn interrupt_turn(state: State<'_, AppState>, thread_id: String) -> Re
     Ok(())
 }
 
+/// Preview what an approval request would change, from the request's params
+///
+/// File-writing approvals are returned as per-file diffs; other commands
+/// come back as the raw command line.
+#[tauri::command]
+pub fn preview_approval(params: JsonValue) -> crate::app_server::ApprovalPreview {
+    crate::app_server::preview_approval(&params)
+}
+
 /// Respond to an approval request
 ///
 /// request_id is the JSON-RPC request ID from the server's approval request.
@@ -215,6 +760,7 @@ pub async fn respond_to_approval(
     request_id: u64,
     execpolicy_amendment: Option<crate::app_server::ipc_bridge::ExecPolicyAmendment>,
 ) -> Result<()> {
+    let decision_name = decision.clone();
     let decision = match decision.as_str() {
         "accept" => ApprovalDecision::Accept,
         "acceptForSession" => ApprovalDecision::AcceptForSession,
@@ -237,14 +783,60 @@ pub async fn respond_to_approval(
     let server = guard
         .as_mut()
         .ok_or_else(|| Error::AppServer("App server not running".to_string()))?;
+    let approval = state
+        .pending_approvals
+        .lock()
+        .as_ref()
+        .and_then(|a| a.get(request_id));
     // Send JSON-RPC response with the original request ID
     server.send_response(request_id, result).await?;
 
+    if let Some(PendingApproval {
+        thread_id: Some(thread_id),
+        command: Some(command),
+        ..
+    }) = approval
+    {
+        crate::commands::sessions::record_command_history(
+            &state.database,
+            &thread_id,
+            &command,
+            &decision_name,
+        );
+    }
+
     tracing::info!("Responded to approval request {}", request_id);
 
     Ok(())
 }
 
+/// Cancel all in-flight app-server requests for a thread
+///
+/// Returns the number of requests cancelled. Doesn't wait for the app-server
+/// lock, so it works while one of those requests is still running.
+#[tauri::command]
+pub async fn cancel_thread_requests(
+    state: State<'_, AppState>,
+    thread_id: String,
+) -> Result<usize> {
+    crate::commands::projects::validate_id(&thread_id, "thread_id")?;
+
+    let canceller = state.request_canceller.lock().clone();
+    let cancelled = match canceller {
+        Some(canceller) => canceller.cancel_thread(&thread_id).await,
+        None => 0,
+    };
+
+    if cancelled > 0 {
+        tracing::info!(
+            "Cancelled {} pending requests for thread {}",
+            cancelled,
+            thread_id
+        );
+    }
+    Ok(cancelled)
+}
+
 /// List threads from codex
 #[tauri::command]
 pub async fn list_threads(
@@ -270,6 +862,94 @@ pub async fn list_threads(
     Ok(response)
 }
 
+/// A thread from `thread/list` together with its desktop metadata, if any
+#[derive(Debug, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EnrichedThreadSummary {
+    #[serde(flatten)]
+    pub thread: ThreadSummary,
+    /// Title, tags and favorite/archive flags kept by the desktop app
+    pub metadata: Option<SessionMetadata>,
+}
+
+/// A page of `list_threads_enriched`
+#[derive(Debug, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EnrichedThreadListResponse {
+    pub data: Vec<EnrichedThreadSummary>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub next_cursor: Option<String>,
+}
+
+/// Attach each listed thread's session metadata, keeping the server's order
+fn enrich_threads(
+    db: &Database,
+    response: ThreadListResponse,
+) -> Result<EnrichedThreadListResponse> {
+    let ids: Vec<String> = response
+        .data
+        .iter()
+        .map(|thread| thread.id.clone())
+        .collect();
+    let mut metadata = db.get_sessions_by_ids(&ids)?;
+    let data = response
+        .data
+        .into_iter()
+        .map(|thread| EnrichedThreadSummary {
+            metadata: metadata.remove(&thread.id),
+            thread,
+        })
+        .collect();
+    Ok(EnrichedThreadListResponse {
+        data,
+        next_cursor: response.next_cursor,
+    })
+}
+
+/// List threads from codex along with their desktop metadata
+#[tauri::command]
+pub async fn list_threads_enriched(
+    state: State<'_, AppState>,
+    limit: Option<u32>,
+    cursor: Option<String>,
+) -> Result<EnrichedThreadListResponse> {
+    let response = list_threads(state.clone(), limit, cursor).await?;
+    enrich_threads(&state.database, response)
+}
+
+/// Fetch a window of a thread's items for scrollback
+///
+/// Items are read from the thread's rollout file and returned oldest to newest.
+/// Without cursors the newest `limit` items are returned; pass the returned
+/// `older_cursor` as `before_cursor` (or `newer_cursor` as `after_cursor`) to page;
+/// passing both is an error. The parsed file is cached between pages.
+#[tauri::command]
+pub async fn get_thread_items(
+    state: State<'_, AppState>,
+    thread_id: String,
+    before_cursor: Option<String>,
+    after_cursor: Option<String>,
+    limit: Option<usize>,
+) -> Result<SessionItemsPage> {
+    const MAX_ITEMS_LIMIT: usize = 500;
+
+    crate::commands::projects::validate_id(&thread_id, "thread_id")?;
+    let limit = limit.unwrap_or(50).clamp(1, MAX_ITEMS_LIMIT);
+
+    let cache = state.session_items_cache.clone();
+    tokio::task::spawn_blocking(move || {
+        crate::codex_import::get_session_items(
+            &cache,
+            &thread_id,
+            before_cursor.as_deref(),
+            after_cursor.as_deref(),
+            limit,
+        )
+    })
+    .await
+    .map_err(|e| Error::Other(format!("Task join error: {e}")))?
+}
+
 /// Save a base64 data URL image to a temporary file and return the path
 fn save_base64_image_to_temp(data_url: &str) -> Result<String> {
     // Parse the data URL: data:image/png;base64,<data>
@@ -319,3 +999,497 @@ fn save_base64_image_to_temp(data_url: &str) -> Result<String> {
 
     Ok(temp_path.to_string_lossy().to_string())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn model_list() -> Vec<Model> {
+        let response: crate::commands::app_server::ModelListResponse =
+            serde_json::from_value(serde_json::json!({
+                "data": [{
+                    "id": "gpt-5-codex",
+                    "model": "gpt-5-codex",
+                    "displayName": "GPT-5 Codex",
+                    "description": "Optimized for coding",
+                    "supportedReasoningEfforts": [],
+                    "defaultReasoningEffort": "medium",
+                    "isDefault": true
+                }],
+                "nextCursor": null
+            }))
+            .unwrap();
+        response.data
+    }
+
+    fn resume_response(model: Option<&str>) -> ThreadResumeResponse {
+        serde_json::from_value(serde_json::json!({
+            "thread": {
+                "id": "thr_123",
+                "cwd": "/tmp/project",
+                "model": model,
+                "modelProvider": "openai"
+            },
+            "items": []
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_resolve_thread_model_maps_display_name() {
+        let response = resume_response(Some("gpt-5-codex"));
+        let resolved = resolve_thread_model(&response.thread, &model_list());
+
+        assert_eq!(resolved.thread_id, "thr_123");
+        assert_eq!(resolved.model.as_deref(), Some("gpt-5-codex"));
+        assert_eq!(resolved.display_name.as_deref(), Some("GPT-5 Codex"));
+        assert_eq!(resolved.model_provider.as_deref(), Some("openai"));
+    }
+
+    #[test]
+    fn test_resolve_thread_model_unlisted_model() {
+        let response = resume_response(Some("custom-local-model"));
+        let resolved = resolve_thread_model(&response.thread, &model_list());
+        assert_eq!(resolved.model.as_deref(), Some("custom-local-model"));
+        assert!(resolved.display_name.is_none());
+
+        let response = resume_response(None);
+        let resolved = resolve_thread_model(&response.thread, &model_list());
+        assert!(resolved.model.is_none());
+        assert!(resolved.display_name.is_none());
+    }
+
+    #[test]
+    fn test_check_input_size_under_cap() {
+        let input = vec![
+            UserInput::Text {
+                text: "a".repeat(10),
+            },
+            UserInput::LocalImage {
+                path: "/tmp/big.png".to_string(),
+            },
+        ];
+        assert!(check_input_size(&input, 10).is_ok());
+    }
+
+    #[test]
+    fn test_check_input_size_over_cap() {
+        let input = vec![UserInput::Text {
+            text: "a".repeat(11),
+        }];
+        let err = check_input_size(&input, 10).unwrap_err();
+        assert!(err.to_string().contains("splitting"));
+    }
+
+    #[test]
+    fn test_max_input_bytes_for_thread_uses_project_setting() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let db = Database::new(&temp_dir.path().join("test.db")).unwrap();
+        let mut project = crate::database::Project::new("/tmp/project");
+        project.settings_json = Some(r#"{"maxInputBytes":64}"#.to_string());
+        db.insert_project(&project).unwrap();
+        db.upsert_session_metadata(&SessionMetadata::new("thr_1", &project.id))
+            .unwrap();
+
+        assert_eq!(max_input_bytes_for_thread(&db, "thr_1").unwrap(), 64);
+        assert_eq!(
+            max_input_bytes_for_thread(&db, "thr_unknown").unwrap(),
+            ProjectSettings::DEFAULT_MAX_INPUT_BYTES
+        );
+    }
+
+    #[test]
+    fn test_model_for_new_thread_precedence() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let db = Database::new(&temp_dir.path().join("test.db")).unwrap();
+        let mut project = crate::database::Project::new("/tmp/project");
+        db.insert_project(&project).unwrap();
+
+        // Nothing set anywhere: the server decides
+        assert_eq!(model_for_new_thread(&db, &project.id, None).unwrap(), None);
+
+        // Only the global default is set
+        db.set_setting(DEFAULT_MODEL_SETTING, Some("gpt-5"))
+            .unwrap();
+        assert_eq!(
+            model_for_new_thread(&db, &project.id, None)
+                .unwrap()
+                .as_deref(),
+            Some("gpt-5")
+        );
+
+        // The project setting beats the global default, and an explicit model beats both
+        project.settings_json = Some(r#"{"model":"gpt-5-codex"}"#.to_string());
+        db.update_project_settings(&project.id, project.settings_json.as_deref().unwrap())
+            .unwrap();
+        assert_eq!(
+            model_for_new_thread(&db, &project.id, None)
+                .unwrap()
+                .as_deref(),
+            Some("gpt-5-codex")
+        );
+        assert_eq!(
+            model_for_new_thread(&db, &project.id, Some("o3".to_string()))
+                .unwrap()
+                .as_deref(),
+            Some("o3")
+        );
+    }
+
+    #[test]
+    fn test_auto_snapshot_before_turn_follows_project_setting() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let db = Database::new(&temp_dir.path().join("test.db")).unwrap();
+        let project_dir = temp_dir.path().join("project");
+        std::fs::create_dir(&project_dir).unwrap();
+        std::fs::write(project_dir.join("main.rs"), "fn main() {}").unwrap();
+        let mut project = crate::database::Project::new(project_dir.to_str().unwrap());
+        db.insert_project(&project).unwrap();
+        db.upsert_session_metadata(&SessionMetadata::new("thr_1", &project.id))
+            .unwrap();
+        let locks = crate::snapshots::SnapshotLocks::default();
+
+        // Off by default
+        assert!(auto_snapshot_before_turn(&db, &locks, "thr_1", "Before turn").is_none());
+        assert!(db.get_snapshots_for_session("thr_1").unwrap().is_empty());
+
+        project.settings_json = Some(r#"{"autoSnapshot":true}"#.to_string());
+        db.update_project_settings(&project.id, project.settings_json.as_deref().unwrap())
+            .unwrap();
+        let label = auto_snapshot_label("Fix the bug\nin parser");
+        let snapshot = auto_snapshot_before_turn(&db, &locks, "thr_1", &label)
+            .unwrap()
+            .unwrap();
+        assert_eq!(snapshot.label.as_deref(), Some("Before turn: Fix the bug"));
+        assert_eq!(db.get_snapshots_for_session("thr_1").unwrap().len(), 1);
+
+        // Threads without a known project are skipped
+        assert!(auto_snapshot_before_turn(&db, &locks, "thr_unknown", "Before turn").is_none());
+
+        // A failure is reported to the caller instead of aborting
+        let _held = locks.try_lock(&project_dir).unwrap();
+        assert!(matches!(
+            auto_snapshot_before_turn(&db, &locks, "thr_1", "Before turn"),
+            Some(Err(_))
+        ));
+    }
+
+    #[test]
+    fn test_enrich_threads_left_joins_metadata() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let db = Database::new(&temp_dir.path().join("test.db")).unwrap();
+        let project = crate::database::Project::new("/tmp/project");
+        db.insert_project(&project).unwrap();
+        let mut metadata = SessionMetadata::new("thr_2", &project.id);
+        metadata.title = Some("Parser fix".to_string());
+        metadata.is_favorite = true;
+        db.upsert_session_metadata(&metadata).unwrap();
+
+        let thread = |id: &str| {
+            serde_json::json!({
+                "id": id,
+                "preview": "hi",
+                "modelProvider": "openai",
+                "createdAt": 1,
+                "cwd": "/tmp/project",
+                "cliVersion": "0.1.0",
+                "source": "cli"
+            })
+        };
+        let response: ThreadListResponse = serde_json::from_value(serde_json::json!({
+            "data": [thread("thr_1"), thread("thr_2")],
+            "nextCursor": "page2"
+        }))
+        .unwrap();
+
+        let enriched = enrich_threads(&db, response).unwrap();
+        assert_eq!(enriched.next_cursor.as_deref(), Some("page2"));
+        let ids: Vec<&str> = enriched.data.iter().map(|t| t.thread.id.as_str()).collect();
+        assert_eq!(ids, vec!["thr_1", "thr_2"]);
+        assert!(enriched.data[0].metadata.is_none());
+        let joined = enriched.data[1].metadata.as_ref().unwrap();
+        assert_eq!(joined.title.as_deref(), Some("Parser fix"));
+        assert!(joined.is_favorite);
+
+        let json = serde_json::to_value(&enriched.data[1]).unwrap();
+        assert_eq!(json["id"], "thr_2");
+        assert_eq!(json["metadata"]["title"], "Parser fix");
+        assert!(serde_json::to_value(&enriched.data[0]).unwrap()["metadata"].is_null());
+    }
+
+    #[test]
+    fn test_auto_snapshot_label() {
+        assert_eq!(auto_snapshot_label("  \n"), "Before turn");
+        let long = "x".repeat(AUTO_SNAPSHOT_LABEL_CHARS + 5);
+        assert_eq!(
+            auto_snapshot_label(&long),
+            format!("Before turn: {}…", "x".repeat(AUTO_SNAPSHOT_LABEL_CHARS))
+        );
+    }
+
+    #[test]
+    fn test_approval_policy_for_turn_falls_back_to_session() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let db = Database::new(&temp_dir.path().join("test.db")).unwrap();
+        let project = crate::database::Project::new("/tmp/project");
+        db.insert_project(&project).unwrap();
+        db.upsert_session_metadata(&SessionMetadata::new("thr_1", &project.id))
+            .unwrap();
+
+        assert_eq!(approval_policy_for_turn(&db, "thr_1", None).unwrap(), None);
+
+        assert!(db
+            .set_session_approval_policy("thr_1", Some("never"))
+            .unwrap());
+        assert_eq!(
+            approval_policy_for_turn(&db, "thr_1", None)
+                .unwrap()
+                .as_deref(),
+            Some("never")
+        );
+        assert_eq!(
+            approval_policy_for_turn(&db, "thr_1", Some("on-request".to_string()))
+                .unwrap()
+                .as_deref(),
+            Some("on-request")
+        );
+
+        // Defaults saved in camelCase go out in the server's spelling
+        assert!(db
+            .set_session_approval_policy("thr_1", Some("onFailure"))
+            .unwrap());
+        assert_eq!(
+            approval_policy_for_turn(&db, "thr_1", None)
+                .unwrap()
+                .as_deref(),
+            Some("on-failure")
+        );
+    }
+
+    #[test]
+    fn test_session_source_started_vs_imported() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let db = Database::new(&temp_dir.path().join("test.db")).unwrap();
+        let project_dir = temp_dir.path().join("project");
+        std::fs::create_dir(&project_dir).unwrap();
+        let project = crate::database::Project::new(project_dir.to_str().unwrap());
+        db.insert_project(&project).unwrap();
+
+        record_started_thread(&db, "thr_desktop", &project.id).unwrap();
+        let linked = link_imported_thread(&db, "thr_cli", &project_dir.join("src")).unwrap();
+        assert_eq!(linked.as_deref(), Some(project.id.as_str()));
+
+        let source = |id: &str| db.get_session_by_id(id).unwrap().unwrap().source;
+        assert_eq!(source("thr_desktop"), SessionSource::Desktop);
+        assert_eq!(source("thr_cli"), SessionSource::CliImport);
+
+        // Already-known threads are left alone
+        assert_eq!(
+            link_imported_thread(&db, "thr_desktop", &project_dir).unwrap(),
+            None
+        );
+        assert_eq!(source("thr_desktop"), SessionSource::Desktop);
+
+        let sessions = db.get_sessions_for_project(&project.id).unwrap();
+        assert!(sessions.iter().all(|s| s.source != SessionSource::Unknown));
+    }
+
+    #[test]
+    fn test_resuming_soft_deleted_thread_keeps_its_metadata() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let db = Database::new(&temp_dir.path().join("test.db")).unwrap();
+        let project_dir = temp_dir.path().join("project");
+        std::fs::create_dir(&project_dir).unwrap();
+        let project = crate::database::Project::new(project_dir.to_str().unwrap());
+        db.insert_project(&project).unwrap();
+
+        let mut metadata =
+            SessionMetadata::new("thr_trashed", &project.id).with_source(SessionSource::Desktop);
+        metadata.title = Some("Fix the parser".to_string());
+        metadata.is_favorite = true;
+        metadata.tasks_json = Some("[]".to_string());
+        db.upsert_session_metadata(&metadata).unwrap();
+        assert!(db.soft_delete_session("thr_trashed", 100).unwrap());
+
+        // Late turn events for the thread don't touch the trashed row
+        db.update_session_status("thr_trashed", &crate::database::SessionStatus::Running)
+            .unwrap();
+        db.update_session_tasks("thr_trashed", r#"[{"step":"late"}]"#)
+            .unwrap();
+
+        assert_eq!(
+            link_imported_thread(&db, "thr_trashed", &project_dir).unwrap(),
+            None
+        );
+
+        // Still in the trash, and restoring it brings back everything
+        assert!(db.get_session_by_id("thr_trashed").unwrap().is_none());
+        assert!(db.restore_deleted_session("thr_trashed").unwrap());
+        let restored = db.get_session_by_id("thr_trashed").unwrap().unwrap();
+        assert_eq!(restored.title.as_deref(), Some("Fix the parser"));
+        assert!(restored.is_favorite);
+        assert_eq!(restored.tasks_json.as_deref(), Some("[]"));
+        assert_eq!(restored.status, crate::database::SessionStatus::Idle);
+        assert_eq!(restored.source, SessionSource::Desktop);
+    }
+
+    #[test]
+    fn test_approximate_token_count_known_inputs() {
+        assert_eq!(approximate_token_count(""), 0);
+        assert_eq!(approximate_token_count("hello world"), 2);
+        assert_eq!(approximate_token_count("你好世界"), 4);
+
+        // ~90 tokens with a real tokenizer
+        let prose = "The quick brown fox jumps over the lazy dog. ".repeat(9);
+        let estimate = approximate_token_count(&prose);
+        assert!(
+            (70..=130).contains(&estimate),
+            "estimate {estimate} out of band"
+        );
+
+        // `fn main() { println!("hi"); }` is ~12 tokens
+        let code = approximate_token_count(r#"fn main() { println!("hi"); }"#);
+        assert!((8..=16).contains(&code), "estimate {code} out of band");
+    }
+
+    #[tokio::test]
+    async fn test_restart_then_resume_resumes_after_restart() {
+        let events = std::sync::Mutex::new(Vec::new());
+
+        let result = restart_then_resume(
+            "thr_1",
+            async {
+                events.lock().unwrap().push("restart");
+                Ok(())
+            },
+            || async {
+                events.lock().unwrap().push("resume");
+                Ok("resumed")
+            },
+        )
+        .await;
+
+        assert_eq!(result.unwrap(), "resumed");
+        assert_eq!(*events.lock().unwrap(), vec!["restart", "resume"]);
+    }
+
+    #[tokio::test]
+    async fn test_restart_then_resume_reports_failed_resume() {
+        let result: Result<()> = restart_then_resume("thr_1", async { Ok(()) }, || async {
+            Err(Error::AppServer(
+                "JSON-RPC error -32600: thread not found".to_string(),
+            ))
+        })
+        .await;
+
+        let message = result.unwrap_err().to_string();
+        assert!(message.contains("could not be resumed"));
+        assert!(message.contains("thr_1"));
+    }
+
+    #[tokio::test]
+    async fn test_restart_then_resume_skips_resume_when_restart_fails() {
+        let resumed = std::sync::atomic::AtomicBool::new(false);
+        let result: Result<()> = restart_then_resume(
+            "thr_1",
+            async { Err(Error::AppServer("spawn failed".to_string())) },
+            || async {
+                resumed.store(true, std::sync::atomic::Ordering::SeqCst);
+                Ok(())
+            },
+        )
+        .await;
+
+        assert!(result.is_err());
+        assert!(!resumed.load(std::sync::atomic::Ordering::SeqCst));
+    }
+
+    #[test]
+    fn test_sandbox_presets_cover_every_policy_with_risk() {
+        let presets = sandbox_presets();
+        let risk_of = |id: &str| presets.iter().find(|p| p.id == id).unwrap().risk;
+
+        // Exhaustive match: adding a SandboxPolicy variant fails to compile until a preset covers
+        // it
+        for preset in &presets {
+            match preset.policy {
+                SandboxPolicy::ReadOnly
+                | SandboxPolicy::WorkspaceWrite { .. }
+                | SandboxPolicy::ExternalSandbox { .. }
+                | SandboxPolicy::DangerFullAccess => {}
+            }
+        }
+        assert!(presets
+            .iter()
+            .any(|p| matches!(p.policy, SandboxPolicy::ReadOnly)));
+        assert!(presets
+            .iter()
+            .any(|p| matches!(p.policy, SandboxPolicy::WorkspaceWrite { .. })));
+        assert!(presets
+            .iter()
+            .any(|p| matches!(p.policy, SandboxPolicy::ExternalSandbox { .. })));
+        assert!(presets
+            .iter()
+            .any(|p| matches!(p.policy, SandboxPolicy::DangerFullAccess)));
+
+        assert_eq!(risk_of("read-only"), RiskLevel::Low);
+        assert_eq!(risk_of("workspace-write"), RiskLevel::Medium);
+        assert_eq!(risk_of("external-sandbox"), RiskLevel::Medium);
+        assert_eq!(risk_of("danger-full-access"), RiskLevel::High);
+    }
+
+    #[test]
+    fn test_parse_sandbox_policy_uses_presets() {
+        assert_eq!(
+            parse_sandbox_policy(Some("read-only".to_string())),
+            Some(SandboxPolicy::ReadOnly)
+        );
+        assert!(matches!(
+            parse_sandbox_policy(Some("external_sandbox".to_string())),
+            Some(SandboxPolicy::ExternalSandbox { .. })
+        ));
+        assert_eq!(parse_sandbox_policy(Some("bogus".to_string())), None);
+        assert_eq!(parse_sandbox_policy(None), None);
+    }
+
+    #[test]
+    fn test_check_writable_roots() {
+        let project = tempfile::tempdir().unwrap();
+        let outside = tempfile::tempdir().unwrap();
+        std::fs::create_dir(project.path().join("build")).unwrap();
+
+        let roots = vec![
+            "build".to_string(),
+            outside.path().to_string_lossy().to_string(),
+            project.path().join("buidl").to_string_lossy().to_string(),
+        ];
+        let checks = check_writable_roots(project.path(), &roots);
+
+        assert_eq!(checks[0].status, WritableRootStatus::Inside);
+        assert_eq!(checks[1].status, WritableRootStatus::Outside);
+        assert_eq!(checks[2].status, WritableRootStatus::Missing);
+        assert!(checks[2].resolved.is_none());
+
+        // A missing root is an error, an outside root only a warning
+        assert!(writable_root_warnings(&checks).is_err());
+        let warnings = writable_root_warnings(&checks[..2]).unwrap();
+        assert_eq!(warnings.len(), 1);
+        assert!(warnings[0].contains("outside the project"));
+    }
+
+    #[test]
+    fn test_requested_writable_roots_from_config() {
+        let nested = serde_json::json!({"sandbox_workspace_write": {"writable_roots": ["/tmp/a"]}});
+        let dotted = serde_json::json!({"sandbox_workspace_write.writable_roots": ["/tmp/b"]});
+
+        assert_eq!(
+            requested_writable_roots(Some(&nested)),
+            vec!["/tmp/a".to_string()]
+        );
+        assert_eq!(
+            requested_writable_roots(Some(&dotted)),
+            vec!["/tmp/b".to_string()]
+        );
+        assert!(requested_writable_roots(None).is_empty());
+    }
+}
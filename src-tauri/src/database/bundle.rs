@@ -0,0 +1,395 @@
+//! Portable project bundles
+//!
+//! A bundle holds everything the database knows about one project: the
+//! project row, its sessions, its allowlist and its snapshot rows (with the
+//! file contents their backups reference). Importing
+//! a bundle creates a new project, so ids are remapped where they would
+//! collide with existing rows.
+
+use std::collections::{HashMap, HashSet};
+
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
+use rusqlite::params;
+use serde::{Deserialize, Serialize};
+
+use super::{session_from_row, Database, Project, SessionMetadata, Snapshot, SESSION_COLUMNS};
+use crate::{Error, Result};
+
+/// Current bundle format version
+pub const BUNDLE_VERSION: u32 = 1;
+
+/// Everything stored about a project, in a form that can be written to a file
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ProjectBundle {
+    pub version: u32,
+    pub exported_at: i64,
+    pub project: Project,
+    pub sessions: Vec<SessionMetadata>,
+    pub allowlist: Vec<String>,
+    /// Snapshot rows; `metadata_json` is omitted unless contents were included
+    pub snapshots: Vec<Snapshot>,
+    /// Base64 contents of the blobs the file backups reference, by hash
+    #[serde(default)]
+    pub blobs: HashMap<String, String>,
+}
+
+/// Result of importing a bundle
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ProjectBundleImport {
+    pub project: Project,
+    pub sessions_imported: usize,
+    /// Sessions left out because their id is already in use here
+    pub sessions_skipped: Vec<String>,
+    pub allowlist_imported: usize,
+    pub snapshots_imported: usize,
+    /// Snapshots exported without their contents, which can't be reverted to,
+    /// and snapshots of skipped sessions
+    pub snapshots_skipped: usize,
+}
+
+impl Database {
+    /// Collect a project's rows into a bundle
+    ///
+    /// Snapshot contents (file backups can hold whole files) are left out
+    /// unless `include_snapshot_contents` is set. Deleted sessions are skipped.
+    pub fn export_project_bundle(
+        &self,
+        project_id: &str,
+        include_snapshot_contents: bool,
+    ) -> Result<ProjectBundle> {
+        let project = self
+            .get_project(project_id)?
+            .ok_or_else(|| Error::ProjectNotFound(project_id.to_string()))?;
+        let allowlist = self.get_allowlist(project_id)?;
+
+        let conn = self.conn.lock();
+        let sessions = conn
+            .prepare(&format!(
+                r#"SELECT {SESSION_COLUMNS}
+                   FROM session_metadata
+                   WHERE project_id = ?1 AND deleted_at IS NULL
+                   ORDER BY created_at"#
+            ))?
+            .query_map(params![project_id], session_from_row)?
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+
+        let snapshots = conn
+            .prepare(
+                r#"SELECT s.id, s.session_id, s.created_at, s.snapshot_type, s.metadata_json, s.label
+                   FROM snapshots s
+                   JOIN session_metadata m ON m.session_id = s.session_id
+                   WHERE m.project_id = ?1 AND m.deleted_at IS NULL
+                   ORDER BY s.created_at"#,
+            )?
+            .query_map(params![project_id], |row| {
+                Ok(Snapshot {
+                    id: row.get(0)?,
+                    session_id: row.get(1)?,
+                    created_at: row.get(2)?,
+                    snapshot_type: row.get(3)?,
+                    metadata_json: if include_snapshot_contents { row.get(4)? } else { None },
+                    label: row.get(5)?,
+                })
+            })?
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+        drop(conn);
+
+        let blobs = if include_snapshot_contents {
+            let ids: Vec<String> = snapshots.iter().map(|s| s.id.clone()).collect();
+            self.get_blobs_for_snapshots(&ids)?
+                .into_iter()
+                .map(|(hash, content)| (hash, BASE64.encode(content)))
+                .collect()
+        } else {
+            HashMap::new()
+        };
+
+        Ok(ProjectBundle {
+            version: BUNDLE_VERSION,
+            exported_at: chrono::Utc::now().timestamp(),
+            project,
+            sessions,
+            allowlist,
+            snapshots,
+            blobs,
+        })
+    }
+
+    /// Restore a bundle as a new project in one transaction
+    ///
+    /// The project and snapshots always get fresh ids. Sessions keep theirs,
+    /// since they name Codex threads; a session whose id is already in use is
+    /// skipped along with its snapshots and reported.
+    pub fn import_project_bundle(&self, bundle: &ProjectBundle) -> Result<ProjectBundleImport> {
+        if bundle.version > BUNDLE_VERSION {
+            return Err(Error::Other(format!(
+                "Unsupported project bundle version {}",
+                bundle.version
+            )));
+        }
+
+        let blobs = bundle
+            .blobs
+            .iter()
+            .map(|(hash, content)| {
+                let content = BASE64.decode(content).map_err(|e| {
+                    Error::Other(format!("Invalid blob {hash} in project bundle: {e}"))
+                })?;
+                // Blobs are shared by hash across projects, so a mislabeled one
+                // would be restored in place of some other file's contents
+                if crate::snapshots::blob_hash(&content) != *hash {
+                    return Err(Error::Other(format!(
+                        "Blob {hash} in project bundle doesn't match its contents"
+                    )));
+                }
+                Ok((hash.clone(), content))
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        let project = Project {
+            id: uuid::Uuid::new_v4().to_string(),
+            ..bundle.project.clone()
+        };
+
+        self.with_transaction(|conn| {
+            conn.execute(
+                r#"INSERT INTO projects (id, path, display_name, created_at, last_opened_at, settings_json)
+                   VALUES (?1, ?2, ?3, ?4, ?5, ?6)"#,
+                params![
+                    project.id,
+                    project.path,
+                    project.display_name,
+                    project.created_at,
+                    project.last_opened_at,
+                    project.settings_json,
+                ],
+            )?;
+
+            let mut session_taken = conn.prepare("SELECT 1 FROM session_metadata WHERE session_id = ?1")?;
+            let mut insert_session = conn.prepare(
+                r#"INSERT INTO session_metadata
+                   (session_id, project_id, title, tags, is_favorite, is_archived, last_accessed_at,
+                    created_at, status, first_message, tasks_json, approval_policy, source)
+                   VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13)"#,
+            )?;
+            let mut imported_sessions: HashSet<&str> = HashSet::new();
+            let mut sessions_skipped = Vec::new();
+            for session in &bundle.sessions {
+                if session_taken.exists(params![session.session_id])? {
+                    sessions_skipped.push(session.session_id.clone());
+                    continue;
+                }
+                insert_session.execute(params![
+                    session.session_id,
+                    project.id,
+                    session.title,
+                    session.tags,
+                    session.is_favorite,
+                    session.is_archived,
+                    session.last_accessed_at,
+                    session.created_at,
+                    session.status.as_str(),
+                    session.first_message,
+                    session.tasks_json,
+                    session.approval_policy,
+                    session.source.as_str(),
+                ])?;
+                imported_sessions.insert(session.session_id.as_str());
+            }
+
+            let mut insert_pattern = conn.prepare(
+                "INSERT OR IGNORE INTO command_allowlist (project_id, command_pattern) VALUES (?1, ?2)",
+            )?;
+            let mut allowlist_imported = 0;
+            for pattern in &bundle.allowlist {
+                allowlist_imported += insert_pattern.execute(params![project.id, pattern])?;
+            }
+
+            Self::insert_blobs(conn, &blobs)?;
+            let mut insert_snapshot = conn.prepare(
+                r#"INSERT INTO snapshots (id, session_id, created_at, snapshot_type, metadata_json, label)
+                   VALUES (?1, ?2, ?3, ?4, ?5, ?6)"#,
+            )?;
+            let mut snapshots_imported = 0;
+            let mut snapshots_skipped = 0;
+            for snapshot in &bundle.snapshots {
+                let Some(metadata) = snapshot
+                    .metadata_json
+                    .as_ref()
+                    .filter(|_| imported_sessions.contains(snapshot.session_id.as_str()))
+                else {
+                    snapshots_skipped += 1;
+                    continue;
+                };
+                insert_snapshot.execute(params![
+                    uuid::Uuid::new_v4().to_string(),
+                    snapshot.session_id,
+                    snapshot.created_at,
+                    snapshot.snapshot_type,
+                    metadata,
+                    snapshot.label,
+                ])?;
+                snapshots_imported += 1;
+            }
+            Self::delete_unreferenced_blobs(conn, &blobs)?;
+
+            Ok(ProjectBundleImport {
+                project: project.clone(),
+                sessions_imported: imported_sessions.len(),
+                sessions_skipped,
+                allowlist_imported,
+                snapshots_imported,
+                snapshots_skipped,
+            })
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_project_bundle_round_trip() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let source = Database::new(&temp_dir.path().join("source.db")).unwrap();
+        let target = Database::new(&temp_dir.path().join("target.db")).unwrap();
+
+        let project = Project::new("/tmp/project");
+        source.insert_project(&project).unwrap();
+        let mut session = SessionMetadata::new("thr_1", &project.id);
+        session.title = Some("Fix the build".to_string());
+        source.upsert_session_metadata(&session).unwrap();
+        source
+            .upsert_session_metadata(&SessionMetadata::new("thr_2", &project.id))
+            .unwrap();
+        source.add_to_allowlist(&project.id, "cargo test").unwrap();
+        source
+            .insert_snapshot(&Snapshot::new_git_ghost("thr_1", "abc123"))
+            .unwrap();
+        source
+            .insert_snapshot(&Snapshot::new_git_ghost("thr_2", "def456"))
+            .unwrap();
+
+        // A session with the same id already exists in the target
+        let existing = Project::new("/tmp/other");
+        target.insert_project(&existing).unwrap();
+        target
+            .upsert_session_metadata(&SessionMetadata::new("thr_1", &existing.id))
+            .unwrap();
+
+        let bundle = source.export_project_bundle(&project.id, true).unwrap();
+        let json = serde_json::to_string(&bundle).unwrap();
+        let bundle: ProjectBundle = serde_json::from_str(&json).unwrap();
+        let report = target.import_project_bundle(&bundle).unwrap();
+
+        assert_ne!(report.project.id, project.id);
+        assert_eq!(report.project.path, "/tmp/project");
+        assert_eq!(report.sessions_imported, 1);
+        assert_eq!(report.sessions_skipped, vec!["thr_1".to_string()]);
+        assert_eq!(report.snapshots_imported, 1);
+        assert_eq!(report.snapshots_skipped, 1);
+
+        // The colliding session is left alone
+        let kept = target.get_session_by_id("thr_1").unwrap().unwrap();
+        assert_eq!(kept.project_id, existing.id);
+        assert!(kept.title.is_none());
+        assert!(target
+            .get_snapshots_for_session("thr_1")
+            .unwrap()
+            .is_empty());
+
+        let sessions = target.get_sessions_for_project(&report.project.id).unwrap();
+        assert_eq!(sessions.len(), 1);
+        assert_eq!(sessions[0].session_id, "thr_2");
+        assert_eq!(
+            target.get_allowlist(&report.project.id).unwrap(),
+            vec!["cargo test".to_string()]
+        );
+        assert_eq!(
+            target
+                .get_snapshots_for_session(&sessions[0].session_id)
+                .unwrap()
+                .len(),
+            1
+        );
+    }
+
+    #[test]
+    fn test_project_bundle_rejects_mislabeled_blobs() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let db = Database::new(&temp_dir.path().join("test.db")).unwrap();
+        let project = Project::new("/tmp/project");
+        db.insert_project(&project).unwrap();
+        let mut bundle = db.export_project_bundle(&project.id, true).unwrap();
+
+        // Planted contents under the hash of some other file
+        let victim = crate::snapshots::blob_hash(b"fn main() {}\n");
+        bundle
+            .blobs
+            .insert(victim.clone(), BASE64.encode(b"malicious contents"));
+
+        let target = Database::new(&temp_dir.path().join("target.db")).unwrap();
+        let err = target.import_project_bundle(&bundle).unwrap_err();
+        assert!(err.to_string().contains("doesn't match"), "{err}");
+        assert_eq!(target.get_snapshot_blob_refcount(&victim).unwrap(), None);
+        assert!(target.get_all_projects().unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_project_bundle_keeps_no_blobs_of_skipped_sessions() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let source = Database::new(&temp_dir.path().join("source.db")).unwrap();
+        let project = Project::new("/tmp/project");
+        source.insert_project(&project).unwrap();
+        source
+            .upsert_session_metadata(&SessionMetadata::new("thr_1", &project.id))
+            .unwrap();
+        source
+            .insert_snapshot(&Snapshot::new_git_ghost("thr_1", "abc123"))
+            .unwrap();
+        let mut bundle = source.export_project_bundle(&project.id, true).unwrap();
+        let hash = crate::snapshots::blob_hash(b"contents");
+        bundle
+            .blobs
+            .insert(hash.clone(), BASE64.encode(b"contents"));
+
+        let target = Database::new(&temp_dir.path().join("target.db")).unwrap();
+        let existing = Project::new("/tmp/other");
+        target.insert_project(&existing).unwrap();
+        target
+            .upsert_session_metadata(&SessionMetadata::new("thr_1", &existing.id))
+            .unwrap();
+
+        let report = target.import_project_bundle(&bundle).unwrap();
+        assert_eq!(report.sessions_skipped, vec!["thr_1".to_string()]);
+        assert_eq!(report.snapshots_skipped, 1);
+        assert_eq!(target.get_snapshot_blob_refcount(&hash).unwrap(), None);
+    }
+
+    #[test]
+    fn test_project_bundle_omits_snapshot_contents_by_default() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let db = Database::new(&temp_dir.path().join("test.db")).unwrap();
+        let project = Project::new("/tmp/project");
+        db.insert_project(&project).unwrap();
+        db.upsert_session_metadata(&SessionMetadata::new("thr_1", &project.id))
+            .unwrap();
+        db.insert_snapshot(&Snapshot::new_file_backup(
+            "thr_1",
+            r#"{"files":{"a.txt":"secret"}}"#,
+        ))
+        .unwrap();
+
+        let bundle = db.export_project_bundle(&project.id, false).unwrap();
+        assert_eq!(bundle.snapshots.len(), 1);
+        assert!(bundle.snapshots[0].metadata_json.is_none());
+
+        let target = Database::new(&temp_dir.path().join("target.db")).unwrap();
+        let report = target.import_project_bundle(&bundle).unwrap();
+        assert_eq!(report.snapshots_imported, 0);
+        assert_eq!(report.snapshots_skipped, 1);
+    }
+}
@@ -0,0 +1,155 @@
+//! Database integrity checks
+//!
+//! Run after startup so a crash that left the database damaged, or rows
+//! pointing at data that no longer exists, is reported instead of surfacing
+//! later as confusing errors.
+
+use serde::Serialize;
+
+use super::{Database, ORPHANED_SNAPSHOT_FILTER};
+use crate::Result;
+
+/// A row whose foreign key points at a missing parent, from `PRAGMA foreign_key_check`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ForeignKeyViolation {
+    pub table: String,
+    pub rowid: Option<i64>,
+    pub parent: String,
+}
+
+/// Result of checking the database
+#[derive(Debug, Clone, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DatabaseHealth {
+    /// Problems reported by `PRAGMA integrity_check`; empty when it passes
+    pub integrity_errors: Vec<String>,
+    pub foreign_key_violations: Vec<ForeignKeyViolation>,
+    /// Snapshots of permanently deleted sessions, as `prune_orphaned_snapshots` removes
+    pub orphaned_snapshots: usize,
+    /// Session metadata whose project no longer exists
+    pub orphaned_sessions: usize,
+    /// Command history entries whose session has no metadata row
+    pub orphaned_command_history: usize,
+    pub checked_at: i64,
+}
+
+impl DatabaseHealth {
+    /// Whether no problems were found
+    pub fn is_healthy(&self) -> bool {
+        self.integrity_errors.is_empty()
+            && self.foreign_key_violations.is_empty()
+            && self.orphaned_snapshots == 0
+            && self.orphaned_sessions == 0
+            && self.orphaned_command_history == 0
+    }
+}
+
+impl Database {
+    /// Run integrity and foreign key checks and count orphaned rows
+    pub fn check_health(&self) -> Result<DatabaseHealth> {
+        let conn = self.conn.lock();
+
+        let integrity_errors = conn
+            .prepare("PRAGMA integrity_check")?
+            .query_map([], |row| row.get::<_, String>(0))?
+            .collect::<std::result::Result<Vec<_>, _>>()?
+            .into_iter()
+            .filter(|line| line != "ok")
+            .collect();
+
+        let foreign_key_violations = conn
+            .prepare("PRAGMA foreign_key_check")?
+            .query_map([], |row| {
+                Ok(ForeignKeyViolation {
+                    table: row.get(0)?,
+                    rowid: row.get(1)?,
+                    parent: row.get(2)?,
+                })
+            })?
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+
+        let count = |sql: &str| -> Result<usize> {
+            Ok(conn.query_row(sql, [], |row| row.get::<_, i64>(0))? as usize)
+        };
+        let orphaned_snapshots = count(&format!(
+            "SELECT COUNT(*) FROM snapshots WHERE {ORPHANED_SNAPSHOT_FILTER}"
+        ))?;
+        let orphaned_sessions =
+            count("SELECT COUNT(*) FROM session_metadata WHERE project_id NOT IN (SELECT id FROM projects)")?;
+        let orphaned_command_history = count(
+            "SELECT COUNT(*) FROM command_history WHERE session_id NOT IN (SELECT session_id FROM session_metadata)",
+        )?;
+
+        Ok(DatabaseHealth {
+            integrity_errors,
+            foreign_key_violations,
+            orphaned_snapshots,
+            orphaned_sessions,
+            orphaned_command_history,
+            checked_at: chrono::Utc::now().timestamp(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::{Project, SessionMetadata, Snapshot};
+
+    fn test_db() -> (tempfile::TempDir, Database) {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let db = Database::new(&temp_dir.path().join("test.db")).unwrap();
+        (temp_dir, db)
+    }
+
+    #[test]
+    fn test_healthy_database() {
+        let (_temp_dir, db) = test_db();
+        let project = Project::new("/tmp/project");
+        db.insert_project(&project).unwrap();
+        db.upsert_session_metadata(&SessionMetadata::new("s1", &project.id))
+            .unwrap();
+        db.insert_snapshot(&Snapshot::new_git_ghost("s1", "abc1234"))
+            .unwrap();
+
+        let health = db.check_health().unwrap();
+        assert!(health.is_healthy(), "{health:?}");
+    }
+
+    #[test]
+    fn test_orphans_are_reported() {
+        let (_temp_dir, db) = test_db();
+        let project = Project::new("/tmp/project");
+        db.insert_project(&project).unwrap();
+        db.upsert_session_metadata(&SessionMetadata::new("deleted", &project.id))
+            .unwrap();
+        db.delete_session_metadata("deleted").unwrap();
+        // A snapshot that raced the delete
+        db.insert_snapshot(&Snapshot::new_git_ghost("deleted", "abc1234"))
+            .unwrap();
+        // Threads resumed without metadata keep their snapshots
+        db.insert_snapshot(&Snapshot::new_git_ghost("no-metadata", "def5678"))
+            .unwrap();
+
+        // A session row left behind with foreign keys off, e.g. by an older version
+        {
+            let conn = db.conn.lock();
+            conn.execute_batch(
+                r#"PRAGMA foreign_keys = OFF;
+                   INSERT INTO session_metadata (session_id, project_id) VALUES ('stray', 'gone');
+                   PRAGMA foreign_keys = ON;"#,
+            )
+            .unwrap();
+        }
+
+        let health = db.check_health().unwrap();
+        assert!(!health.is_healthy());
+        assert!(health.integrity_errors.is_empty());
+        assert_eq!(health.orphaned_snapshots, 1);
+        assert_eq!(health.orphaned_sessions, 1);
+        assert_eq!(health.foreign_key_violations.len(), 1);
+        assert_eq!(health.foreign_key_violations[0].table, "session_metadata");
+        assert_eq!(health.foreign_key_violations[0].parent, "projects");
+    }
+}
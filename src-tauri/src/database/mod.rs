@@ -3,16 +3,204 @@
 //! Uses SQLite to store project metadata, session extensions (tags, favorites),
 //! snapshots, and command allowlists.
 
+mod bundle;
+mod health;
 mod models;
 
+pub use bundle::{ProjectBundle, ProjectBundleImport};
+pub use health::{DatabaseHealth, ForeignKeyViolation};
 pub use models::*;
 
-use rusqlite::{params, Connection};
-use std::path::Path;
 use parking_lot::Mutex;
+use rusqlite::{params, Connection, OptionalExtension};
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
 
 use crate::Result;
 
+/// Column list matching `session_from_row`
+const SESSION_COLUMNS: &str = "session_id, project_id, title, tags, is_favorite, is_archived, \
+     last_accessed_at, created_at, status, first_message, tasks_json, approval_policy, source";
+
+/// Map a row selected with `SESSION_COLUMNS` to `SessionMetadata`
+fn session_from_row(row: &rusqlite::Row<'_>) -> rusqlite::Result<SessionMetadata> {
+    let status_str: String = row
+        .get::<_, Option<String>>(8)?
+        .unwrap_or_else(|| "idle".to_string());
+    Ok(SessionMetadata {
+        session_id: row.get(0)?,
+        project_id: row.get(1)?,
+        title: row.get(2)?,
+        tags: row.get(3)?,
+        is_favorite: row.get(4)?,
+        is_archived: row.get(5)?,
+        last_accessed_at: row.get(6)?,
+        created_at: row.get(7)?,
+        status: SessionStatus::from_str(&status_str),
+        first_message: row.get(9)?,
+        tasks_json: row.get(10)?,
+        approval_policy: row.get(11)?,
+        source: SessionSource::from_str(&row.get::<_, String>(12)?),
+    })
+}
+
+/// Column list matching `snapshot_from_row`
+const SNAPSHOT_COLUMNS: &str = "id, session_id, created_at, snapshot_type, metadata_json, label";
+
+/// Map a row selected with `SNAPSHOT_COLUMNS` to `Snapshot`
+fn snapshot_from_row(row: &rusqlite::Row<'_>) -> rusqlite::Result<Snapshot> {
+    Ok(Snapshot {
+        id: row.get(0)?,
+        session_id: row.get(1)?,
+        created_at: row.get(2)?,
+        snapshot_type: row.get(3)?,
+        metadata_json: row.get(4)?,
+        label: row.get(5)?,
+    })
+}
+
+/// Map a `command_history` row to `CommandHistoryEntry`
+fn command_history_from_row(row: &rusqlite::Row<'_>) -> rusqlite::Result<CommandHistoryEntry> {
+    Ok(CommandHistoryEntry {
+        id: row.get(0)?,
+        session_id: row.get(1)?,
+        command: row.get(2)?,
+        decision: row.get(3)?,
+        timestamp: row.get(4)?,
+    })
+}
+
+/// A schema change, applied once and recorded in `schema_version`
+type Migration = fn(&Connection) -> Result<()>;
+
+/// Schema migrations; the one at index `i` is version `i + 1`
+///
+/// Append new migrations, never reorder or edit applied ones. Databases from
+/// before `schema_version` existed start at version 0 and may already have
+/// some of these changes, so each migration checks before altering.
+const MIGRATIONS: &[Migration] = &[
+    // 1: session status tracking
+    |conn| {
+        if !has_column(conn, "session_metadata", "status")? {
+            conn.execute_batch(
+                r#"
+                ALTER TABLE session_metadata ADD COLUMN status TEXT NOT NULL DEFAULT 'idle';
+                ALTER TABLE session_metadata ADD COLUMN first_message TEXT;
+                ALTER TABLE session_metadata ADD COLUMN tasks_json TEXT;
+                CREATE INDEX IF NOT EXISTS idx_session_metadata_status ON session_metadata(status);
+                "#,
+            )?;
+        }
+        Ok(())
+    },
+    // 2: soft-deleted sessions keep their row (and snapshots) until purged
+    |conn| add_column_if_missing(conn, "session_metadata", "deleted_at", "INTEGER"),
+    // 3: per-session default approval policy
+    |conn| add_column_if_missing(conn, "session_metadata", "approval_policy", "TEXT"),
+    // 4: blobs written before compression keep their bytes as-is
+    |conn| {
+        add_column_if_missing(
+            conn,
+            "snapshot_blobs",
+            "encoding",
+            "TEXT NOT NULL DEFAULT 'raw'",
+        )
+    },
+    // 5: existing rows predate source tracking and stay 'unknown'
+    |conn| {
+        add_column_if_missing(
+            conn,
+            "session_metadata",
+            "source",
+            "TEXT NOT NULL DEFAULT 'unknown'",
+        )
+    },
+    // 6: snapshot labels
+    |conn| add_column_if_missing(conn, "snapshots", "label", "TEXT"),
+    // 7: link snapshots to sessions. This is a trigger rather than a foreign key:
+    // SQLite can't add an FK without rebuilding the table, and snapshots may be
+    // taken for resumed threads that have no metadata row yet, which an FK
+    // would reject. For the same reason existing snapshots without a metadata
+    // row are kept.
+    |conn| {
+        let has_snapshot_trigger: bool = conn
+            .prepare(
+                "SELECT 1 FROM sqlite_master WHERE type = 'trigger' AND name = 'session_metadata_delete_snapshots'",
+            )?
+            .exists([])?;
+        if has_snapshot_trigger {
+            return Ok(());
+        }
+
+        conn.execute_batch(
+            r#"
+            CREATE TRIGGER session_metadata_delete_snapshots
+            AFTER DELETE ON session_metadata
+            BEGIN
+                DELETE FROM snapshots WHERE session_id = OLD.session_id;
+            END;
+            "#,
+        )?;
+        Ok(())
+    },
+    // 8: remember deleted sessions, so their snapshots can be told apart from
+    // those of threads that never had metadata
+    |conn| {
+        conn.execute_batch(
+            r#"
+            CREATE TABLE IF NOT EXISTS session_tombstones (
+                session_id TEXT PRIMARY KEY,
+                deleted_at INTEGER NOT NULL DEFAULT (strftime('%s', 'now'))
+            );
+            CREATE TRIGGER IF NOT EXISTS session_metadata_tombstone
+            AFTER DELETE ON session_metadata
+            BEGIN
+                INSERT OR REPLACE INTO session_tombstones (session_id) VALUES (OLD.session_id);
+            END;
+            CREATE TRIGGER IF NOT EXISTS session_metadata_clear_tombstone
+            AFTER INSERT ON session_metadata
+            BEGIN
+                DELETE FROM session_tombstones WHERE session_id = NEW.session_id;
+            END;
+            "#,
+        )?;
+        Ok(())
+    },
+];
+
+/// Matches snapshots left behind by a deleted session
+///
+/// Snapshots with no metadata row aren't enough: resumed and CLI threads
+/// have none until they are linked, and their snapshots are still wanted.
+pub(super) const ORPHANED_SNAPSHOT_FILTER: &str =
+    "session_id IN (SELECT session_id FROM session_tombstones) \
+     AND session_id NOT IN (SELECT session_id FROM session_metadata)";
+
+/// Whether `table` has a column named `column`
+fn has_column(conn: &Connection, table: &str, column: &str) -> Result<bool> {
+    Ok(conn
+        .prepare("SELECT 1 FROM pragma_table_info(?1) WHERE name = ?2")?
+        .exists(params![table, column])?)
+}
+
+/// Add a column unless an older, unversioned database already has it
+fn add_column_if_missing(
+    conn: &Connection,
+    table: &str,
+    column: &str,
+    definition: &str,
+) -> Result<()> {
+    if !has_column(conn, table, column)? {
+        conn.execute_batch(&format!(
+            "ALTER TABLE {table} ADD COLUMN {column} {definition};"
+        ))?;
+    }
+    Ok(())
+}
+
+/// How long a statement waits for another connection's lock before failing
+const BUSY_TIMEOUT_MS: u32 = 5000;
+
 /// Database wrapper with thread-safe connection
 pub struct Database {
     conn: Mutex<Connection>,
@@ -34,8 +222,27 @@ impl Database {
         })
     }
 
+    /// Directory containing the database file, or None for an in-memory database
+    pub fn data_dir(&self) -> Option<PathBuf> {
+        let conn = self.conn.lock();
+        let path = conn.path().filter(|p| !p.is_empty())?;
+        Path::new(path).parent().map(Path::to_path_buf)
+    }
+
     /// Initialize the database schema
     fn init_schema(conn: &Connection) -> Result<()> {
+        // WAL lets readers proceed during a write; writers wait on a lock instead of failing with
+        // SQLITE_BUSY
+        conn.execute_batch(&format!("PRAGMA busy_timeout = {BUSY_TIMEOUT_MS};"))?;
+        let journal_mode: String =
+            conn.query_row("PRAGMA journal_mode = WAL", [], |row| row.get(0))?;
+        if !journal_mode.eq_ignore_ascii_case("wal") && conn.path().is_some_and(|p| !p.is_empty()) {
+            tracing::warn!(
+                "SQLite WAL mode unavailable, using journal mode {}",
+                journal_mode
+            );
+        }
+
         conn.execute_batch(
             r#"
             -- Projects table
@@ -60,7 +267,10 @@ impl Database {
                 created_at INTEGER NOT NULL DEFAULT (strftime('%s', 'now')),
                 status TEXT NOT NULL DEFAULT 'idle',
                 first_message TEXT,
-                tasks_json TEXT
+                tasks_json TEXT,
+                deleted_at INTEGER,
+                approval_policy TEXT,
+                source TEXT NOT NULL DEFAULT 'unknown'
             );
 
             -- Snapshots for revert functionality
@@ -69,7 +279,32 @@ impl Database {
                 session_id TEXT NOT NULL,
                 created_at INTEGER NOT NULL DEFAULT (strftime('%s', 'now')),
                 snapshot_type TEXT NOT NULL,
-                metadata_json TEXT
+                metadata_json TEXT,
+                label TEXT
+            );
+
+            -- Content-addressed file contents shared by file backup snapshots
+            CREATE TABLE IF NOT EXISTS snapshot_blobs (
+                hash TEXT PRIMARY KEY,
+                content BLOB NOT NULL,
+                size INTEGER NOT NULL,
+                refcount INTEGER NOT NULL DEFAULT 0,
+                encoding TEXT NOT NULL DEFAULT 'raw'
+            );
+
+            -- App-wide settings (key/value)
+            CREATE TABLE IF NOT EXISTS app_settings (
+                key TEXT PRIMARY KEY,
+                value TEXT NOT NULL
+            );
+
+            -- Commands the agent ran or asked to run, with the user's decision
+            CREATE TABLE IF NOT EXISTS command_history (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                session_id TEXT NOT NULL,
+                command TEXT NOT NULL,
+                decision TEXT NOT NULL,
+                timestamp INTEGER NOT NULL DEFAULT (strftime('%s', 'now'))
             );
 
             -- Command allowlist per project
@@ -87,6 +322,46 @@ impl Database {
                 ON session_metadata(last_accessed_at DESC);
             CREATE INDEX IF NOT EXISTS idx_snapshots_session
                 ON snapshots(session_id);
+            CREATE INDEX IF NOT EXISTS idx_command_history_session
+                ON command_history(session_id, timestamp);
+
+            -- A session's command history goes with it, like its snapshots
+            CREATE TRIGGER IF NOT EXISTS session_metadata_delete_command_history
+            AFTER DELETE ON session_metadata
+            BEGIN
+                DELETE FROM command_history WHERE session_id = OLD.session_id;
+            END;
+
+            -- Blob refcounts follow the file backups that reference them, so
+            -- every way of deleting snapshots also releases their blobs
+            CREATE TRIGGER IF NOT EXISTS snapshots_insert_blob_refs
+            AFTER INSERT ON snapshots
+            WHEN NEW.snapshot_type = 'file_backup' AND json_valid(NEW.metadata_json)
+            BEGIN
+                UPDATE snapshot_blobs SET refcount = refcount + 1
+                WHERE hash IN (SELECT value FROM json_each(NEW.metadata_json, '$.blobs'));
+            END;
+
+            CREATE TRIGGER IF NOT EXISTS snapshots_delete_blob_refs
+            AFTER DELETE ON snapshots
+            WHEN OLD.snapshot_type = 'file_backup' AND json_valid(OLD.metadata_json)
+            BEGIN
+                UPDATE snapshot_blobs SET refcount = refcount - 1
+                WHERE hash IN (SELECT value FROM json_each(OLD.metadata_json, '$.blobs'));
+                DELETE FROM snapshot_blobs WHERE refcount <= 0;
+            END;
+
+            CREATE TRIGGER IF NOT EXISTS snapshots_update_blob_refs
+            AFTER UPDATE OF metadata_json ON snapshots
+            WHEN NEW.snapshot_type = 'file_backup'
+                AND json_valid(NEW.metadata_json) AND json_valid(OLD.metadata_json)
+            BEGIN
+                UPDATE snapshot_blobs SET refcount = refcount + 1
+                WHERE hash IN (SELECT value FROM json_each(NEW.metadata_json, '$.blobs'));
+                UPDATE snapshot_blobs SET refcount = refcount - 1
+                WHERE hash IN (SELECT value FROM json_each(OLD.metadata_json, '$.blobs'));
+                DELETE FROM snapshot_blobs WHERE refcount <= 0;
+            END;
             "#,
         )?;
 
@@ -106,23 +381,35 @@ impl Database {
         Ok(())
     }
 
-    /// Run database migrations to add new columns to existing tables
+    /// Apply the migrations in `MIGRATIONS` newer than the recorded schema version
+    ///
+    /// Each migration runs in its own transaction together with recording its
+    /// version, so a failure leaves the database at the last complete version.
     fn run_migrations(conn: &Connection) -> Result<()> {
-        // Check if status column exists in session_metadata
-        let has_status: bool = conn
-            .prepare("SELECT 1 FROM pragma_table_info('session_metadata') WHERE name = 'status'")?
-            .exists([])?;
-
-        if !has_status {
-            // Add new columns for session status tracking
-            conn.execute_batch(
-                r#"
-                ALTER TABLE session_metadata ADD COLUMN status TEXT NOT NULL DEFAULT 'idle';
-                ALTER TABLE session_metadata ADD COLUMN first_message TEXT;
-                ALTER TABLE session_metadata ADD COLUMN tasks_json TEXT;
-                CREATE INDEX IF NOT EXISTS idx_session_metadata_status ON session_metadata(status);
-                "#,
+        conn.execute_batch(
+            r#"
+            CREATE TABLE IF NOT EXISTS schema_version (
+                version INTEGER PRIMARY KEY,
+                applied_at INTEGER NOT NULL DEFAULT (strftime('%s', 'now'))
+            );
+            "#,
+        )?;
+        let current: usize = conn.query_row(
+            "SELECT COALESCE(MAX(version), 0) FROM schema_version",
+            [],
+            |row| row.get::<_, i64>(0),
+        )? as usize;
+
+        for (index, migration) in MIGRATIONS.iter().enumerate().skip(current) {
+            let version = index + 1;
+            let tx = conn.unchecked_transaction()?;
+            migration(&tx)?;
+            tx.execute(
+                "INSERT INTO schema_version (version) VALUES (?1)",
+                params![version as i64],
             )?;
+            tx.commit()?;
+            tracing::info!("Applied database migration {}", version);
         }
 
         Ok(())
@@ -229,6 +516,81 @@ impl Database {
         Ok(())
     }
 
+    /// Repoint a project at a new directory, keeping its sessions and allowlist
+    pub fn update_project_path(&self, id: &str, path: &str) -> Result<()> {
+        let conn = self.conn.lock();
+        let updated = conn.execute(
+            "UPDATE projects SET path = ?1 WHERE id = ?2",
+            params![path, id],
+        )?;
+        if updated == 0 {
+            return Err(crate::Error::ProjectNotFound(id.to_string()));
+        }
+        Ok(())
+    }
+
+    /// Update a project's row in place, keeping its sessions, allowlist and history
+    pub fn update_project(&self, project: &Project) -> Result<()> {
+        let conn = self.conn.lock();
+        let updated = conn.execute(
+            r#"UPDATE projects
+               SET path = ?1, display_name = ?2, created_at = ?3, last_opened_at = ?4, settings_json = ?5
+               WHERE id = ?6"#,
+            params![
+                project.path,
+                project.display_name,
+                project.created_at,
+                project.last_opened_at,
+                project.settings_json,
+                project.id,
+            ],
+        )?;
+        if updated == 0 {
+            return Err(crate::Error::ProjectNotFound(project.id.clone()));
+        }
+        Ok(())
+    }
+
+    /// Replace a project's settings JSON
+    pub fn update_project_settings(&self, id: &str, settings_json: &str) -> Result<()> {
+        let conn = self.conn.lock();
+        let updated = conn.execute(
+            "UPDATE projects SET settings_json = ?1 WHERE id = ?2",
+            params![settings_json, id],
+        )?;
+        if updated == 0 {
+            return Err(crate::Error::ProjectNotFound(id.to_string()));
+        }
+        Ok(())
+    }
+
+    /// Get an app-wide setting
+    pub fn get_setting(&self, key: &str) -> Result<Option<String>> {
+        let conn = self.conn.lock();
+        let value = conn
+            .query_row(
+                "SELECT value FROM app_settings WHERE key = ?1",
+                params![key],
+                |row| row.get(0),
+            )
+            .optional()?;
+        Ok(value)
+    }
+
+    /// Set an app-wide setting, or remove it with `None`
+    pub fn set_setting(&self, key: &str, value: Option<&str>) -> Result<()> {
+        let conn = self.conn.lock();
+        match value {
+            Some(value) => conn.execute(
+                r#"INSERT INTO app_settings (key, value) VALUES (?1, ?2)
+                   ON CONFLICT(key) DO UPDATE SET value = excluded.value"#,
+                params![key, value],
+            )?,
+            None => conn.execute("DELETE FROM app_settings WHERE key = ?1", params![key])?,
+        };
+        Ok(())
+    }
+
     /// Delete a project
     pub fn delete_project(&self, id: &str) -> Result<()> {
         let conn = self.conn.lock();
@@ -243,8 +605,8 @@ impl Database {
         let conn = self.conn.lock();
         conn.execute(
             r#"INSERT INTO session_metadata
-               (session_id, project_id, title, tags, is_favorite, is_archived, last_accessed_at, created_at, status, first_message, tasks_json)
-               VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11)
+               (session_id, project_id, title, tags, is_favorite, is_archived, last_accessed_at, created_at, status, first_message, tasks_json, source)
+               VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12)
                ON CONFLICT(session_id) DO UPDATE SET
                    title = excluded.title,
                    tags = excluded.tags,
@@ -266,6 +628,7 @@ impl Database {
                 metadata.status.as_str(),
                 metadata.first_message,
                 metadata.tasks_json,
+                metadata.source.as_str(),
             ],
         )?;
         Ok(())
@@ -274,77 +637,210 @@ impl Database {
     /// Get sessions for a project
     pub fn get_sessions_for_project(&self, project_id: &str) -> Result<Vec<SessionMetadata>> {
         let conn = self.conn.lock();
-        let mut stmt = conn.prepare_cached(
-            r#"SELECT session_id, project_id, title, tags, is_favorite, is_archived,
-                      last_accessed_at, created_at, status, first_message, tasks_json
+        let mut stmt = conn.prepare_cached(&format!(
+            r#"SELECT {SESSION_COLUMNS}
                FROM session_metadata
-               WHERE project_id = ?1 AND is_archived = 0
-               ORDER BY last_accessed_at DESC NULLS LAST"#,
+               WHERE project_id = ?1 AND is_archived = 0 AND deleted_at IS NULL
+               ORDER BY last_accessed_at DESC NULLS LAST"#
+        ))?;
+
+        let sessions = stmt
+            .query_map(params![project_id], session_from_row)?
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+
+        Ok(sessions)
+    }
+
+    /// Get a project's archived sessions, most recently accessed first
+    pub fn get_archived_sessions_for_project(
+        &self,
+        project_id: &str,
+    ) -> Result<Vec<SessionMetadata>> {
+        let conn = self.conn.lock();
+        let mut stmt = conn.prepare_cached(&format!(
+            r#"SELECT {SESSION_COLUMNS}
+               FROM session_metadata
+               WHERE project_id = ?1 AND is_archived = 1 AND deleted_at IS NULL
+               ORDER BY last_accessed_at DESC NULLS LAST"#
+        ))?;
+
+        let sessions = stmt
+            .query_map(params![project_id], session_from_row)?
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+
+        Ok(sessions)
+    }
+
+    /// Get one page of a project's sessions, in `get_sessions_for_project` order, with the total
+    /// count
+    pub fn get_sessions_for_project_paged(
+        &self,
+        project_id: &str,
+        limit: usize,
+        offset: usize,
+    ) -> Result<SessionPage> {
+        let conn = self.conn.lock();
+        let total: i64 = conn.query_row(
+            r#"SELECT COUNT(*)
+               FROM session_metadata
+               WHERE project_id = ?1 AND is_archived = 0 AND deleted_at IS NULL"#,
+            params![project_id],
+            |row| row.get(0),
         )?;
 
+        let mut stmt = conn.prepare_cached(&format!(
+            r#"SELECT {SESSION_COLUMNS}
+               FROM session_metadata
+               WHERE project_id = ?1 AND is_archived = 0 AND deleted_at IS NULL
+               ORDER BY last_accessed_at DESC NULLS LAST, session_id
+               LIMIT ?2 OFFSET ?3"#
+        ))?;
+        let limit = i64::try_from(limit).unwrap_or(i64::MAX);
+        let offset = i64::try_from(offset).unwrap_or(i64::MAX);
         let sessions = stmt
-            .query_map(params![project_id], |row| {
-                let status_str: String = row.get::<_, Option<String>>(8)?.unwrap_or_else(|| "idle".to_string());
-                Ok(SessionMetadata {
-                    session_id: row.get(0)?,
-                    project_id: row.get(1)?,
-                    title: row.get(2)?,
-                    tags: row.get(3)?,
-                    is_favorite: row.get(4)?,
-                    is_archived: row.get(5)?,
-                    last_accessed_at: row.get(6)?,
-                    created_at: row.get(7)?,
-                    status: SessionStatus::from_str(&status_str),
-                    first_message: row.get(9)?,
-                    tasks_json: row.get(10)?,
-                })
-            })?
+            .query_map(params![project_id, limit, offset], session_from_row)?
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+
+        Ok(SessionPage {
+            sessions,
+            total: total as usize,
+        })
+    }
+
+    /// Get sessions with the given status, most recently accessed first
+    pub fn get_sessions_with_status(&self, status: &SessionStatus) -> Result<Vec<SessionMetadata>> {
+        let conn = self.conn.lock();
+        let mut stmt = conn.prepare(&format!(
+            r#"SELECT {SESSION_COLUMNS}
+               FROM session_metadata
+               WHERE status = ?1 AND deleted_at IS NULL
+               ORDER BY last_accessed_at DESC"#
+        ))?;
+
+        let sessions = stmt
+            .query_map(params![status.as_str()], session_from_row)?
             .collect::<std::result::Result<Vec<_>, _>>()?;
 
         Ok(sessions)
     }
 
+    /// Count non-archived sessions per project in a single query
+    pub fn get_session_counts_by_project(&self) -> Result<HashMap<String, usize>> {
+        let conn = self.conn.lock();
+        let mut stmt = conn.prepare_cached(
+            r#"SELECT project_id, COUNT(*)
+               FROM session_metadata
+               WHERE is_archived = 0 AND deleted_at IS NULL
+               GROUP BY project_id"#,
+        )?;
+
+        let counts = stmt
+            .query_map([], |row| {
+                Ok((row.get::<_, String>(0)?, row.get::<_, i64>(1)? as usize))
+            })?
+            .collect::<std::result::Result<HashMap<_, _>, _>>()?;
+
+        Ok(counts)
+    }
+
     /// Update session status
     pub fn update_session_status(&self, session_id: &str, status: &SessionStatus) -> Result<()> {
         let conn = self.conn.lock();
         conn.execute(
-            r#"UPDATE session_metadata SET status = ?1, last_accessed_at = strftime('%s', 'now') WHERE session_id = ?2"#,
+            r#"UPDATE session_metadata SET status = ?1, last_accessed_at = strftime('%s', 'now') WHERE session_id = ?2 AND deleted_at IS NULL"#,
             params![status.as_str(), session_id],
         )?;
         Ok(())
     }
 
+    /// Whether metadata exists for a session, including a soft-deleted one
+    ///
+    /// `get_session_by_id` hides soft-deleted sessions, so check this before
+    /// creating metadata for a session that looks missing.
+    pub fn session_exists_any(&self, session_id: &str) -> Result<bool> {
+        let conn = self.conn.lock();
+        let exists = conn
+            .query_row(
+                "SELECT 1 FROM session_metadata WHERE session_id = ?1",
+                params![session_id],
+                |_| Ok(()),
+            )
+            .optional()?
+            .is_some();
+        Ok(exists)
+    }
+
     /// Get a session by ID (optimized direct lookup)
+    ///
+    /// Soft-deleted sessions are not returned.
     pub fn get_session_by_id(&self, session_id: &str) -> Result<Option<SessionMetadata>> {
         let conn = self.conn.lock();
-        let mut stmt = conn.prepare_cached(
-            r#"SELECT session_id, project_id, title, tags, is_favorite, is_archived,
-                      last_accessed_at, created_at, status, first_message, tasks_json
+        let mut stmt = conn.prepare_cached(&format!(
+            r#"SELECT {SESSION_COLUMNS}
                FROM session_metadata
-               WHERE session_id = ?1"#,
-        )?;
+               WHERE session_id = ?1 AND deleted_at IS NULL"#
+        ))?;
 
         let mut rows = stmt.query(params![session_id])?;
 
         if let Some(row) = rows.next()? {
-            Ok(Some(SessionMetadata {
-                session_id: row.get(0)?,
-                project_id: row.get(1)?,
-                title: row.get(2)?,
-                tags: row.get(3)?,
-                is_favorite: row.get(4)?,
-                is_archived: row.get(5)?,
-                last_accessed_at: row.get(6)?,
-                created_at: row.get(7)?,
-                status: SessionStatus::from_str(row.get::<_, String>(8)?.as_str()),
-                first_message: row.get(9)?,
-                tasks_json: row.get(10)?,
-            }))
+            Ok(Some(session_from_row(row)?))
         } else {
             Ok(None)
         }
     }
 
+    /// Get the metadata of each of the given sessions that has any, keyed by session ID
+    pub fn get_sessions_by_ids(
+        &self,
+        session_ids: &[String],
+    ) -> Result<HashMap<String, SessionMetadata>> {
+        let conn = self.conn.lock();
+        let mut stmt = conn.prepare_cached(&format!(
+            r#"SELECT {SESSION_COLUMNS}
+               FROM session_metadata
+               WHERE session_id = ?1 AND deleted_at IS NULL"#
+        ))?;
+
+        let mut sessions = HashMap::new();
+        for session_id in session_ids {
+            if let Some(session) = stmt
+                .query_row(params![session_id], session_from_row)
+                .optional()?
+            {
+                sessions.insert(session.session_id.clone(), session);
+            }
+        }
+        Ok(sessions)
+    }
+
+    /// Get session metadata rows whose project_id is empty or doesn't match any project
+    pub fn find_orphaned_session_metadata(&self) -> Result<Vec<SessionMetadata>> {
+        let conn = self.conn.lock();
+        let mut stmt = conn.prepare(&format!(
+            r#"SELECT {SESSION_COLUMNS}
+               FROM session_metadata
+               WHERE deleted_at IS NULL
+                 AND (project_id = '' OR project_id NOT IN (SELECT id FROM projects))"#
+        ))?;
+
+        let sessions = stmt
+            .query_map([], session_from_row)?
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+
+        Ok(sessions)
+    }
+
+    /// Reassign a session to a different project
+    pub fn update_session_project(&self, session_id: &str, project_id: &str) -> Result<()> {
+        let conn = self.conn.lock();
+        conn.execute(
+            "UPDATE session_metadata SET project_id = ?1 WHERE session_id = ?2",
+            params![project_id, session_id],
+        )?;
+        Ok(())
+    }
+
     /// Update session first message (only if not already set)
     pub fn update_session_first_message(&self, session_id: &str, first_message: &str) -> Result<()> {
         let conn = self.conn.lock();
@@ -355,17 +851,58 @@ impl Database {
         Ok(())
     }
 
+    /// Set the session title unless one is already set
+    ///
+    /// Returns true if the title was written.
+    pub fn set_session_title_if_empty(&self, session_id: &str, title: &str) -> Result<bool> {
+        let conn = self.conn.lock();
+        let updated = conn.execute(
+            r#"UPDATE session_metadata SET title = ?1
+               WHERE session_id = ?2 AND (title IS NULL OR title = '')"#,
+            params![title, session_id],
+        )?;
+        Ok(updated > 0)
+    }
+
+    /// Mark every session of a project as accessed at `timestamp` in a single UPDATE
+    pub fn touch_project_sessions(&self, project_id: &str, timestamp: i64) -> Result<usize> {
+        let conn = self.conn.lock();
+        let count = conn.execute(
+            "UPDATE session_metadata SET last_accessed_at = ?1 WHERE project_id = ?2 AND deleted_at IS NULL",
+            params![timestamp, project_id],
+        )?;
+        Ok(count)
+    }
+
     /// Update session tasks
     pub fn update_session_tasks(&self, session_id: &str, tasks_json: &str) -> Result<()> {
         let conn = self.conn.lock();
         conn.execute(
-            r#"UPDATE session_metadata SET tasks_json = ?1, last_accessed_at = strftime('%s', 'now') WHERE session_id = ?2"#,
+            r#"UPDATE session_metadata SET tasks_json = ?1, last_accessed_at = strftime('%s', 'now') WHERE session_id = ?2 AND deleted_at IS NULL"#,
             params![tasks_json, session_id],
         )?;
         Ok(())
     }
 
+    /// Set or clear a session's default approval policy
+    ///
+    /// Returns false if the session doesn't exist.
+    pub fn set_session_approval_policy(
+        &self,
+        session_id: &str,
+        approval_policy: Option<&str>,
+    ) -> Result<bool> {
+        let conn = self.conn.lock();
+        let updated = conn.execute(
+            "UPDATE session_metadata SET approval_policy = ?1 WHERE session_id = ?2 AND deleted_at IS NULL",
+            params![approval_policy, session_id],
+        )?;
+        Ok(updated > 0)
+    }
+
     /// Delete session metadata
+    ///
+    /// The session's snapshots are removed by the `session_metadata_delete_snapshots` trigger.
     pub fn delete_session_metadata(&self, session_id: &str) -> Result<()> {
         let conn = self.conn.lock();
         conn.execute(
@@ -375,26 +912,343 @@ impl Database {
         Ok(())
     }
 
+    /// Delete several sessions' metadata in a single transaction, returning how many were removed
+    ///
+    /// Their snapshots go with them via the `session_metadata_delete_snapshots` trigger.
+    pub fn delete_sessions_metadata(&self, session_ids: &[String]) -> Result<usize> {
+        self.with_transaction(|conn| {
+            let mut delete = conn.prepare("DELETE FROM session_metadata WHERE session_id = ?1")?;
+            let mut deleted = 0;
+            for session_id in session_ids {
+                deleted += delete.execute(params![session_id])?;
+            }
+            Ok(deleted)
+        })
+    }
+
+    /// Archive or unarchive several sessions in a single transaction, returning how many were
+    /// updated
+    pub fn set_sessions_archived(&self, session_ids: &[String], archived: bool) -> Result<usize> {
+        self.with_transaction(|conn| {
+            let mut update = conn.prepare(
+                "UPDATE session_metadata SET is_archived = ?1 WHERE session_id = ?2 AND deleted_at IS NULL",
+            )?;
+            let mut updated = 0;
+            for session_id in session_ids {
+                updated += update.execute(params![archived, session_id])?;
+            }
+            Ok(updated)
+        })
+    }
+
+    /// Soft-delete several sessions in a single transaction, returning how many were deleted
+    pub fn soft_delete_sessions(&self, session_ids: &[String], deleted_at: i64) -> Result<usize> {
+        self.with_transaction(|conn| {
+            let mut update = conn.prepare(
+                "UPDATE session_metadata SET deleted_at = ?1 WHERE session_id = ?2 AND deleted_at IS NULL",
+            )?;
+            let mut deleted = 0;
+            for session_id in session_ids {
+                deleted += update.execute(params![deleted_at, session_id])?;
+            }
+            Ok(deleted)
+        })
+    }
+
+    /// Soft-delete a session, hiding it from normal queries until restored or purged
+    ///
+    /// Returns false if the session doesn't exist or is already deleted.
+    pub fn soft_delete_session(&self, session_id: &str, deleted_at: i64) -> Result<bool> {
+        let conn = self.conn.lock();
+        let updated = conn.execute(
+            "UPDATE session_metadata SET deleted_at = ?1 WHERE session_id = ?2 AND deleted_at IS NULL",
+            params![deleted_at, session_id],
+        )?;
+        Ok(updated > 0)
+    }
+
+    /// Get soft-deleted sessions, most recently deleted first
+    pub fn get_deleted_sessions(&self) -> Result<Vec<DeletedSession>> {
+        let conn = self.conn.lock();
+        let mut stmt = conn.prepare(&format!(
+            r#"SELECT {SESSION_COLUMNS}, deleted_at
+               FROM session_metadata
+               WHERE deleted_at IS NOT NULL
+               ORDER BY deleted_at DESC"#
+        ))?;
+
+        let sessions = stmt
+            .query_map([], |row| {
+                Ok(DeletedSession {
+                    session: session_from_row(row)?,
+                    deleted_at: row.get("deleted_at")?,
+                })
+            })?
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+
+        Ok(sessions)
+    }
+
+    /// Undo a soft delete
+    ///
+    /// Returns false if the session isn't soft-deleted.
+    pub fn restore_deleted_session(&self, session_id: &str) -> Result<bool> {
+        let conn = self.conn.lock();
+        let updated = conn.execute(
+            "UPDATE session_metadata SET deleted_at = NULL WHERE session_id = ?1 AND deleted_at IS NOT NULL",
+            params![session_id],
+        )?;
+        Ok(updated > 0)
+    }
+
+    /// Permanently delete sessions soft-deleted at or before `cutoff`
+    ///
+    /// Their snapshots go with them via the `session_metadata_delete_snapshots` trigger.
+    pub fn purge_deleted_sessions(&self, cutoff: i64) -> Result<usize> {
+        let conn = self.conn.lock();
+        let purged = conn.execute(
+            "DELETE FROM session_metadata WHERE deleted_at IS NOT NULL AND deleted_at <= ?1",
+            params![cutoff],
+        )?;
+        Ok(purged)
+    }
+
     // ==================== Snapshot Operations ====================
 
     /// Insert a snapshot
     pub fn insert_snapshot(&self, snapshot: &Snapshot) -> Result<()> {
         let conn = self.conn.lock();
         conn.execute(
-            r#"INSERT INTO snapshots (id, session_id, created_at, snapshot_type, metadata_json)
-               VALUES (?1, ?2, ?3, ?4, ?5)"#,
+            r#"INSERT INTO snapshots (id, session_id, created_at, snapshot_type, metadata_json, label)
+               VALUES (?1, ?2, ?3, ?4, ?5, ?6)"#,
             params![
                 snapshot.id,
                 snapshot.session_id,
                 snapshot.created_at,
                 snapshot.snapshot_type,
                 snapshot.metadata_json,
+                snapshot.label,
             ],
         )?;
         Ok(())
     }
 
+    /// Insert a file backup snapshot together with the blobs it references
+    ///
+    /// Blobs already stored are left alone; the insert trigger counts the new
+    /// reference either way.
+    pub fn insert_snapshot_with_blobs(
+        &self,
+        snapshot: &Snapshot,
+        blobs: &[(String, Vec<u8>)],
+    ) -> Result<()> {
+        self.with_transaction(|conn| {
+            Self::insert_blobs(conn, blobs)?;
+            conn.execute(
+                r#"INSERT INTO snapshots (id, session_id, created_at, snapshot_type, metadata_json, label)
+                   VALUES (?1, ?2, ?3, ?4, ?5, ?6)"#,
+                params![
+                    snapshot.id,
+                    snapshot.session_id,
+                    snapshot.created_at,
+                    snapshot.snapshot_type,
+                    snapshot.metadata_json,
+                    snapshot.label,
+                ],
+            )?;
+            Ok(())
+        })
+    }
+
+    /// Replace a snapshot's metadata, storing any blobs it newly references
+    pub fn update_snapshot_metadata(
+        &self,
+        id: &str,
+        metadata_json: &str,
+        blobs: &[(String, Vec<u8>)],
+    ) -> Result<()> {
+        self.with_transaction(|conn| {
+            Self::insert_blobs(conn, blobs)?;
+            let updated = conn.execute(
+                "UPDATE snapshots SET metadata_json = ?1 WHERE id = ?2",
+                params![metadata_json, id],
+            )?;
+            if updated == 0 {
+                return Err(crate::Error::SnapshotNotFound(id.to_string()));
+            }
+            Ok(())
+        })
+    }
+
+    /// Get file backups whose metadata predates `version`, oldest first
+    pub fn get_file_backups_before_version(&self, version: u32) -> Result<Vec<Snapshot>> {
+        let conn = self.conn.lock();
+        let mut stmt = conn.prepare(&format!(
+            r#"SELECT {SNAPSHOT_COLUMNS}
+               FROM snapshots
+               WHERE snapshot_type = 'file_backup' AND json_valid(metadata_json)
+                 AND COALESCE(json_extract(metadata_json, '$.version'), 0) < ?1
+               ORDER BY created_at ASC"#
+        ))?;
+
+        let snapshots = stmt
+            .query_map(params![version], snapshot_from_row)?
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+
+        Ok(snapshots)
+    }
+
+    /// Store blobs that aren't stored yet, with no references
+    ///
+    /// Contents are zstd-compressed; `size` records the uncompressed length.
+    fn insert_blobs(conn: &Connection, blobs: &[(String, Vec<u8>)]) -> Result<()> {
+        let mut exists = conn.prepare_cached("SELECT 1 FROM snapshot_blobs WHERE hash = ?1")?;
+        let mut insert = conn.prepare_cached(
+            r#"INSERT INTO snapshot_blobs (hash, content, size, refcount, encoding)
+               VALUES (?1, ?2, ?3, 0, 'zstd')"#,
+        )?;
+        for (hash, content) in blobs {
+            if exists.exists(params![hash])? {
+                continue;
+            }
+            let compressed = zstd::encode_all(content.as_slice(), zstd::DEFAULT_COMPRESSION_LEVEL)?;
+            insert.execute(params![hash, compressed, content.len() as i64])?;
+        }
+        Ok(())
+    }
+
+    /// Drop any of `blobs` that no snapshot ended up referencing
+    ///
+    /// Pairs with `insert_blobs` when some of the snapshots that would have
+    /// referenced them were skipped.
+    fn delete_unreferenced_blobs(conn: &Connection, blobs: &[(String, Vec<u8>)]) -> Result<()> {
+        let mut unreferenced =
+            conn.prepare_cached("DELETE FROM snapshot_blobs WHERE hash = ?1 AND refcount = 0")?;
+        for (hash, _) in blobs {
+            unreferenced.execute(params![hash])?;
+        }
+        Ok(())
+    }
+
+    /// Undo the storage encoding of a blob row
+    fn decode_blob(hash: &str, encoding: &str, content: Vec<u8>) -> Result<Vec<u8>> {
+        match encoding {
+            "raw" => Ok(content),
+            "zstd" => Ok(zstd::decode_all(content.as_slice())?),
+            other => Err(crate::Error::Other(format!(
+                "Unknown encoding '{other}' for snapshot blob {hash}"
+            ))),
+        }
+    }
+
+    /// Get the contents of a snapshot blob by hash
+    pub fn get_snapshot_blob(&self, hash: &str) -> Result<Option<Vec<u8>>> {
+        let conn = self.conn.lock();
+        let row: Option<(String, Vec<u8>)> = conn
+            .query_row(
+                "SELECT encoding, content FROM snapshot_blobs WHERE hash = ?1",
+                params![hash],
+                |row| Ok((row.get(0)?, row.get(1)?)),
+            )
+            .optional()?;
+        row.map(|(encoding, content)| Self::decode_blob(hash, &encoding, content))
+            .transpose()
+    }
+
+    /// Get the uncompressed size of a snapshot blob, or None if it isn't stored
+    pub fn get_snapshot_blob_size(&self, hash: &str) -> Result<Option<u64>> {
+        let conn = self.conn.lock();
+        let size: Option<i64> = conn
+            .query_row(
+                "SELECT size FROM snapshot_blobs WHERE hash = ?1",
+                params![hash],
+                |row| row.get(0),
+            )
+            .optional()?;
+        Ok(size.map(|s| s as u64))
+    }
+
+    /// Get how much space file backup contents take up
+    pub fn get_snapshot_blob_usage(&self) -> Result<SnapshotBlobUsage> {
+        let conn = self.conn.lock();
+        let usage = conn.query_row(
+            "SELECT COUNT(*), COALESCE(SUM(size), 0), COALESCE(SUM(length(content)), 0) FROM snapshot_blobs",
+            [],
+            |row| {
+                Ok(SnapshotBlobUsage {
+                    blobs: row.get::<_, i64>(0)? as usize,
+                    raw_bytes: row.get::<_, i64>(1)? as u64,
+                    stored_bytes: row.get::<_, i64>(2)? as u64,
+                })
+            },
+        )?;
+        Ok(usage)
+    }
+
+    /// Get every blob referenced by the given snapshots, each once, uncompressed
+    pub fn get_blobs_for_snapshots(
+        &self,
+        snapshot_ids: &[String],
+    ) -> Result<Vec<(String, Vec<u8>)>> {
+        let conn = self.conn.lock();
+        let mut stmt = conn.prepare_cached(
+            r#"SELECT b.hash, b.encoding, b.content
+               FROM snapshots s, json_each(s.metadata_json, '$.blobs') j
+               JOIN snapshot_blobs b ON b.hash = j.value
+               WHERE s.id = ?1 AND s.snapshot_type = 'file_backup' AND json_valid(s.metadata_json)"#,
+        )?;
+
+        let mut blobs: HashMap<String, Vec<u8>> = HashMap::new();
+        for id in snapshot_ids {
+            let rows = stmt.query_map(params![id], |row| {
+                Ok((
+                    row.get::<_, String>(0)?,
+                    row.get::<_, String>(1)?,
+                    row.get::<_, Vec<u8>>(2)?,
+                ))
+            })?;
+            for row in rows {
+                let (hash, encoding, content) = row?;
+                if let std::collections::hash_map::Entry::Vacant(entry) = blobs.entry(hash) {
+                    let content = Self::decode_blob(entry.key(), &encoding, content)?;
+                    entry.insert(content);
+                }
+            }
+        }
+        Ok(blobs.into_iter().collect())
+    }
+
+    /// Get the hashes of every sidecar file referenced by a file backup snapshot
+    pub fn get_referenced_sidecar_hashes(&self) -> Result<HashSet<String>> {
+        let conn = self.conn.lock();
+        let mut stmt = conn.prepare(
+            r#"SELECT DISTINCT j.value
+               FROM snapshots s, json_each(s.metadata_json, '$.sidecars') j
+               WHERE s.snapshot_type = 'file_backup' AND json_valid(s.metadata_json)"#,
+        )?;
+        let hashes = stmt
+            .query_map([], |row| row.get(0))?
+            .collect::<std::result::Result<HashSet<String>, _>>()?;
+        Ok(hashes)
+    }
+
+    /// Get how many snapshots reference a blob, or None if it isn't stored
+    pub fn get_snapshot_blob_refcount(&self, hash: &str) -> Result<Option<i64>> {
+        let conn = self.conn.lock();
+        let refcount = conn
+            .query_row(
+                "SELECT refcount FROM snapshot_blobs WHERE hash = ?1",
+                params![hash],
+                |row| row.get(0),
+            )
+            .optional()?;
+        Ok(refcount)
+    }
+
     /// Delete old snapshots, keeping only N most recent per session
+    ///
+    /// Blobs referenced only by the deleted file backups are garbage-collected
+    /// by the `snapshots_delete_blob_refs` trigger.
     pub fn cleanup_old_snapshots(&self, session_id: &str, keep_count: usize) -> Result<usize> {
         let conn = self.conn.lock();
 
@@ -430,9 +1284,12 @@ impl Database {
 
     /// Delete all snapshots older than a specific date
     pub fn cleanup_snapshots_older_than(&self, days: i64) -> Result<usize> {
-        let conn = self.conn.lock();
-        let cutoff = chrono::Utc::now().timestamp() - (days * 86400);
+        self.cleanup_snapshots_created_before(chrono::Utc::now().timestamp() - (days * 86400))
+    }
 
+    /// Delete snapshots created before `cutoff` (unix seconds)
+    pub fn cleanup_snapshots_created_before(&self, cutoff: i64) -> Result<usize> {
+        let conn = self.conn.lock();
         let count = conn.execute(
             "DELETE FROM snapshots WHERE created_at < ?1",
             params![cutoff],
@@ -441,49 +1298,146 @@ impl Database {
         Ok(count)
     }
 
+    /// Delete snapshots of sessions that have been permanently deleted
+    ///
+    /// Snapshots of threads that never had metadata are kept.
+    pub fn prune_orphaned_snapshots(&self) -> Result<usize> {
+        let conn = self.conn.lock();
+        let count = conn.execute(
+            &format!("DELETE FROM snapshots WHERE {ORPHANED_SNAPSHOT_FILTER}"),
+            [],
+        )?;
+        Ok(count)
+    }
+
     /// Get snapshots for a session
     pub fn get_snapshots_for_session(&self, session_id: &str) -> Result<Vec<Snapshot>> {
         let conn = self.conn.lock();
-        let mut stmt = conn.prepare_cached(
-            r#"SELECT id, session_id, created_at, snapshot_type, metadata_json
-               FROM snapshots WHERE session_id = ?1 ORDER BY created_at DESC"#,
-        )?;
+        let mut stmt = conn.prepare_cached(&format!(
+            r#"SELECT {SNAPSHOT_COLUMNS}
+               FROM snapshots WHERE session_id = ?1 ORDER BY created_at DESC"#
+        ))?;
 
         let snapshots = stmt
-            .query_map(params![session_id], |row| {
-                Ok(Snapshot {
-                    id: row.get(0)?,
-                    session_id: row.get(1)?,
-                    created_at: row.get(2)?,
-                    snapshot_type: row.get(3)?,
-                    metadata_json: row.get(4)?,
-                })
-            })?
+            .query_map(params![session_id], snapshot_from_row)?
             .collect::<std::result::Result<Vec<_>, _>>()?;
 
         Ok(snapshots)
     }
 
+    /// Find snapshots by label and creation time, newest first
+    ///
+    /// Every filter is optional: `session_id` limits to one session,
+    /// `label_query` matches labels containing it (case-insensitive for ASCII),
+    /// and `since`/`until` bound `created_at` inclusively.
+    pub fn search_snapshots(
+        &self,
+        session_id: Option<&str>,
+        label_query: Option<&str>,
+        since: Option<i64>,
+        until: Option<i64>,
+    ) -> Result<Vec<Snapshot>> {
+        let label_pattern = label_query.filter(|q| !q.is_empty()).map(|q| {
+            let escaped = q
+                .replace('\\', "\\\\")
+                .replace('%', "\\%")
+                .replace('_', "\\_");
+            format!("%{escaped}%")
+        });
+
+        let conn = self.conn.lock();
+        let mut stmt = conn.prepare(&format!(
+            r#"SELECT {SNAPSHOT_COLUMNS}
+               FROM snapshots
+               WHERE (?1 IS NULL OR session_id = ?1)
+                 AND (?2 IS NULL OR label LIKE ?2 ESCAPE '\')
+                 AND (?3 IS NULL OR created_at >= ?3)
+                 AND (?4 IS NULL OR created_at <= ?4)
+               ORDER BY created_at DESC"#
+        ))?;
+
+        let snapshots = stmt
+            .query_map(
+                params![session_id, label_pattern, since, until],
+                snapshot_from_row,
+            )?
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+
+        Ok(snapshots)
+    }
+
+    /// Get a session's snapshots created before `cutoff`, oldest first
+    pub fn get_snapshots_created_before(
+        &self,
+        session_id: &str,
+        cutoff: i64,
+    ) -> Result<Vec<Snapshot>> {
+        let conn = self.conn.lock();
+        let mut stmt = conn.prepare(&format!(
+            r#"SELECT {SNAPSHOT_COLUMNS}
+               FROM snapshots WHERE session_id = ?1 AND created_at < ?2 ORDER BY created_at ASC"#
+        ))?;
+
+        let snapshots = stmt
+            .query_map(params![session_id, cutoff], snapshot_from_row)?
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+
+        Ok(snapshots)
+    }
+
+    /// Delete snapshots by ID in a single transaction, returning how many were removed
+    pub fn delete_snapshots(&self, ids: &[String]) -> Result<usize> {
+        self.with_transaction(|conn| {
+            let mut delete = conn.prepare("DELETE FROM snapshots WHERE id = ?1")?;
+            let mut deleted = 0;
+            for id in ids {
+                deleted += delete.execute(params![id])?;
+            }
+            Ok(deleted)
+        })
+    }
+
+    /// Insert snapshots that don't already exist, returning how many were added
+    ///
+    /// `blobs` are the file contents the snapshots reference; they are stored
+    /// first so the insert trigger can count the references. Blobs no inserted
+    /// snapshot references (e.g. because it already existed) are removed again.
+    pub fn insert_snapshots_if_missing(
+        &self,
+        snapshots: &[Snapshot],
+        blobs: &[(String, Vec<u8>)],
+    ) -> Result<usize> {
+        self.with_transaction(|conn| {
+            Self::insert_blobs(conn, blobs)?;
+            let mut insert = conn.prepare(
+                r#"INSERT OR IGNORE INTO snapshots (id, session_id, created_at, snapshot_type, metadata_json, label)
+                   VALUES (?1, ?2, ?3, ?4, ?5, ?6)"#,
+            )?;
+            let mut inserted = 0;
+            for snapshot in snapshots {
+                inserted += insert.execute(params![
+                    snapshot.id,
+                    snapshot.session_id,
+                    snapshot.created_at,
+                    snapshot.snapshot_type,
+                    snapshot.metadata_json,
+                    snapshot.label,
+                ])?;
+            }
+            Self::delete_unreferenced_blobs(conn, blobs)?;
+            Ok(inserted)
+        })
+    }
+
     /// Get a snapshot by ID
     pub fn get_snapshot(&self, id: &str) -> Result<Option<Snapshot>> {
         let conn = self.conn.lock();
-        let mut stmt = conn.prepare(
-            r#"SELECT id, session_id, created_at, snapshot_type, metadata_json
-               FROM snapshots WHERE id = ?1"#,
-        )?;
+        let mut stmt = conn.prepare(&format!(
+            r#"SELECT {SNAPSHOT_COLUMNS}
+               FROM snapshots WHERE id = ?1"#
+        ))?;
 
-        let mut rows = stmt.query(params![id])?;
-        if let Some(row) = rows.next()? {
-            Ok(Some(Snapshot {
-                id: row.get(0)?,
-                session_id: row.get(1)?,
-                created_at: row.get(2)?,
-                snapshot_type: row.get(3)?,
-                metadata_json: row.get(4)?,
-            }))
-        } else {
-            Ok(None)
-        }
+        Ok(stmt.query_row(params![id], snapshot_from_row).optional()?)
     }
 
     // ==================== Command Allowlist Operations ====================
@@ -513,6 +1467,181 @@ impl Database {
         Ok(patterns)
     }
 
+    /// Get the allowlists of all projects, keyed by project path
+    ///
+    /// Paths rather than IDs, since a project gets a new ID when it is added
+    /// again on another machine or after a reinstall.
+    pub fn get_all_allowlists(&self) -> Result<HashMap<String, Vec<String>>> {
+        let conn = self.conn.lock();
+        let mut stmt = conn.prepare(
+            r#"SELECT p.path, a.command_pattern
+               FROM command_allowlist a JOIN projects p ON p.id = a.project_id
+               ORDER BY p.path, a.created_at, a.rowid"#,
+        )?;
+
+        let mut allowlists: HashMap<String, Vec<String>> = HashMap::new();
+        let rows = stmt.query_map([], |row| {
+            Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?))
+        })?;
+        for row in rows {
+            let (path, pattern) = row?;
+            allowlists.entry(path).or_default().push(pattern);
+        }
+
+        Ok(allowlists)
+    }
+
+    /// Restore allowlists keyed by project path in one transaction
+    ///
+    /// Paths without a project are skipped and reported. With `replace`,
+    /// each imported project's existing patterns are cleared first; otherwise
+    /// patterns are merged.
+    pub fn import_allowlists(
+        &self,
+        allowlists: &HashMap<String, Vec<String>>,
+        replace: bool,
+    ) -> Result<AllowlistImportReport> {
+        self.with_transaction(|conn| {
+            let mut report = AllowlistImportReport::default();
+            let mut project_by_path = conn.prepare("SELECT id FROM projects WHERE path = ?1")?;
+            let mut insert = conn.prepare(
+                r#"INSERT OR IGNORE INTO command_allowlist (project_id, command_pattern)
+                   VALUES (?1, ?2)"#,
+            )?;
+
+            for (path, patterns) in allowlists {
+                let mut project_id: Option<String> = project_by_path
+                    .query_row(params![path], |row| row.get(0))
+                    .optional()?;
+                if project_id.is_none() {
+                    // Project paths are stored canonicalized; the backup's may not be
+                    let canonical = std::fs::canonicalize(path)
+                        .ok()
+                        .and_then(|p| p.to_str().map(str::to_string));
+                    if let Some(canonical) = canonical.filter(|c| c != path) {
+                        project_id = project_by_path
+                            .query_row(params![canonical], |row| row.get(0))
+                            .optional()?;
+                    }
+                }
+                let Some(project_id) = project_id else {
+                    report.skipped_projects.push(path.clone());
+                    continue;
+                };
+                if replace {
+                    conn.execute(
+                        "DELETE FROM command_allowlist WHERE project_id = ?1",
+                        params![project_id],
+                    )?;
+                }
+                for pattern in patterns {
+                    report.imported += insert.execute(params![project_id, pattern])?;
+                }
+            }
+
+            report.skipped_projects.sort();
+            Ok(report)
+        })
+    }
+
+    // ==================== Command History Operations ====================
+
+    /// Record a command and the decision made about it
+    pub fn record_command(
+        &self,
+        session_id: &str,
+        command: &str,
+        decision: &str,
+        timestamp: i64,
+    ) -> Result<()> {
+        let conn = self.conn.lock();
+        conn.execute(
+            "INSERT INTO command_history (session_id, command, decision, timestamp) VALUES (?1, ?2, ?3, ?4)",
+            params![session_id, command, decision, timestamp],
+        )?;
+        Ok(())
+    }
+
+    /// Get a session's command history, newest first
+    pub fn get_command_history(&self, session_id: &str) -> Result<Vec<CommandHistoryEntry>> {
+        let conn = self.conn.lock();
+        let mut stmt = conn.prepare(
+            r#"SELECT id, session_id, command, decision, timestamp
+               FROM command_history
+               WHERE session_id = ?1
+               ORDER BY timestamp DESC, id DESC"#,
+        )?;
+
+        let entries = stmt
+            .query_map(params![session_id], command_history_from_row)?
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+
+        Ok(entries)
+    }
+
+    /// Get the command history of all of a project's sessions, newest first
+    pub fn get_project_command_history(
+        &self,
+        project_id: &str,
+    ) -> Result<Vec<CommandHistoryEntry>> {
+        let conn = self.conn.lock();
+        let mut stmt = conn.prepare(
+            r#"SELECT h.id, h.session_id, h.command, h.decision, h.timestamp
+               FROM command_history h
+               JOIN session_metadata m ON m.session_id = h.session_id
+               WHERE m.project_id = ?1
+               ORDER BY h.timestamp DESC, h.id DESC"#,
+        )?;
+
+        let entries = stmt
+            .query_map(params![project_id], command_history_from_row)?
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+
+        Ok(entries)
+    }
+
+    /// Delete command history, optionally limited to one session and/or to entries before `before`
+    ///
+    /// With neither filter, every entry is deleted. Returns the number removed.
+    pub fn clear_command_history(
+        &self,
+        session_id: Option<&str>,
+        before: Option<i64>,
+    ) -> Result<usize> {
+        let conn = self.conn.lock();
+        let count = conn.execute(
+            r#"DELETE FROM command_history
+               WHERE (?1 IS NULL OR session_id = ?1)
+                 AND (?2 IS NULL OR timestamp < ?2)"#,
+            params![session_id, before],
+        )?;
+        Ok(count)
+    }
+
+    /// Commands that ran in a project's sessions at or after `since`
+    ///
+    /// Declined and cancelled commands are left out.
+    pub fn get_project_commands_run_since(
+        &self,
+        project_id: &str,
+        since: i64,
+    ) -> Result<Vec<String>> {
+        let conn = self.conn.lock();
+        let mut stmt = conn.prepare(
+            r#"SELECT h.command
+               FROM command_history h
+               JOIN session_metadata m ON m.session_id = h.session_id
+               WHERE m.project_id = ?1 AND h.timestamp >= ?2
+                 AND h.decision NOT IN ('decline', 'cancel')"#,
+        )?;
+
+        let commands = stmt
+            .query_map(params![project_id, since], |row| row.get(0))?
+            .collect::<std::result::Result<Vec<String>, _>>()?;
+
+        Ok(commands)
+    }
+
     /// Remove command from allowlist
     pub fn remove_from_allowlist(&self, project_id: &str, command_pattern: &str) -> Result<()> {
         let conn = self.conn.lock();
@@ -523,3 +1652,753 @@ impl Database {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn test_db() -> (tempfile::TempDir, Database) {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let db = Database::new(&temp_dir.path().join("test.db")).unwrap();
+        (temp_dir, db)
+    }
+
+    #[test]
+    fn test_connection_uses_wal_and_busy_timeout() {
+        let (_temp_dir, db) = test_db();
+        let conn = db.conn.lock();
+        let journal_mode: String = conn
+            .query_row("PRAGMA journal_mode", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(journal_mode, "wal");
+        let busy_timeout: u32 = conn
+            .query_row("PRAGMA busy_timeout", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(busy_timeout, BUSY_TIMEOUT_MS);
+    }
+
+    #[test]
+    fn test_sessions_for_project_paged() {
+        let (_temp_dir, db) = test_db();
+        let project = Project::new("/tmp/project");
+        db.insert_project(&project).unwrap();
+        for i in 0..5 {
+            let mut session = SessionMetadata::new(&format!("s{i}"), &project.id);
+            session.last_accessed_at = Some(100 + i);
+            db.upsert_session_metadata(&session).unwrap();
+        }
+        let mut archived = SessionMetadata::new("archived", &project.id);
+        archived.is_archived = true;
+        db.upsert_session_metadata(&archived).unwrap();
+
+        let first = db
+            .get_sessions_for_project_paged(&project.id, 2, 0)
+            .unwrap();
+        assert_eq!(first.total, 5);
+        let ids: Vec<&str> = first
+            .sessions
+            .iter()
+            .map(|s| s.session_id.as_str())
+            .collect();
+        assert_eq!(ids, vec!["s4", "s3"]);
+
+        let last = db
+            .get_sessions_for_project_paged(&project.id, 2, 4)
+            .unwrap();
+        assert_eq!(last.total, 5);
+        assert_eq!(last.sessions.len(), 1);
+        assert_eq!(last.sessions[0].session_id, "s0");
+        assert!(db
+            .get_sessions_for_project_paged(&project.id, 2, 10)
+            .unwrap()
+            .sessions
+            .is_empty());
+
+        // Pages line up with the unpaged listing
+        let all = db
+            .get_sessions_for_project_paged(&project.id, usize::MAX, 0)
+            .unwrap();
+        let unpaged: Vec<String> = db
+            .get_sessions_for_project(&project.id)
+            .unwrap()
+            .into_iter()
+            .map(|s| s.session_id)
+            .collect();
+        let paged: Vec<String> = all.sessions.into_iter().map(|s| s.session_id).collect();
+        assert_eq!(paged, unpaged);
+    }
+
+    #[test]
+    fn test_get_session_by_id_finds_archived_sessions() {
+        let (_temp_dir, db) = test_db();
+        let project = Project::new("/tmp/project");
+        db.insert_project(&project).unwrap();
+        let mut archived = SessionMetadata::new("archived", &project.id);
+        archived.is_archived = true;
+        db.upsert_session_metadata(&archived).unwrap();
+
+        // Hidden from the project listing, but still found by id
+        assert!(db.get_sessions_for_project(&project.id).unwrap().is_empty());
+        let found = db.get_session_by_id("archived").unwrap().unwrap();
+        assert!(found.is_archived);
+        assert!(db.get_session_by_id("missing").unwrap().is_none());
+    }
+
+    #[test]
+    fn test_archived_sessions_are_listed_separately() {
+        let (_temp_dir, db) = test_db();
+        let project = Project::new("/tmp/project");
+        let other = Project::new("/tmp/other");
+        db.insert_project(&project).unwrap();
+        db.insert_project(&other).unwrap();
+        for (id, project_id, archived, last_accessed_at) in [
+            ("active", &project.id, false, Some(300)),
+            ("old", &project.id, true, Some(100)),
+            ("recent", &project.id, true, Some(200)),
+            ("never-opened", &project.id, true, None),
+            ("elsewhere", &other.id, true, Some(400)),
+        ] {
+            let mut session = SessionMetadata::new(id, project_id);
+            session.is_archived = archived;
+            session.last_accessed_at = last_accessed_at;
+            db.upsert_session_metadata(&session).unwrap();
+        }
+        db.upsert_session_metadata(&SessionMetadata {
+            is_archived: true,
+            ..SessionMetadata::new("trashed", &project.id)
+        })
+        .unwrap();
+        assert!(db.soft_delete_session("trashed", 100).unwrap());
+
+        let ids = |sessions: Vec<SessionMetadata>| {
+            sessions
+                .into_iter()
+                .map(|s| s.session_id)
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(
+            ids(db.get_archived_sessions_for_project(&project.id).unwrap()),
+            vec!["recent", "old", "never-opened"]
+        );
+        assert_eq!(
+            ids(db.get_sessions_for_project(&project.id).unwrap()),
+            vec!["active"]
+        );
+
+        // Unarchiving moves a session back to the normal listing
+        db.set_sessions_archived(&["old".to_string()], false)
+            .unwrap();
+        assert_eq!(
+            ids(db.get_archived_sessions_for_project(&project.id).unwrap()),
+            vec!["recent", "never-opened"]
+        );
+        assert_eq!(
+            ids(db.get_sessions_for_project(&project.id).unwrap()),
+            vec!["active", "old"]
+        );
+    }
+
+    #[test]
+    fn test_session_counts_by_project() {
+        let (_temp_dir, db) = test_db();
+        let alpha = Project::new("/tmp/alpha");
+        let beta = Project::new("/tmp/beta");
+        let empty = Project::new("/tmp/empty");
+        for project in [&alpha, &beta, &empty] {
+            db.insert_project(project).unwrap();
+        }
+
+        for i in 0..3 {
+            db.upsert_session_metadata(&SessionMetadata::new(&format!("a{i}"), &alpha.id))
+                .unwrap();
+        }
+        db.upsert_session_metadata(&SessionMetadata::new("b0", &beta.id))
+            .unwrap();
+        let mut archived = SessionMetadata::new("b1", &beta.id);
+        archived.is_archived = true;
+        db.upsert_session_metadata(&archived).unwrap();
+
+        let counts = db.get_session_counts_by_project().unwrap();
+        assert_eq!(counts.get(&alpha.id), Some(&3));
+        assert_eq!(counts.get(&beta.id), Some(&1));
+        assert_eq!(counts.get(&empty.id), None);
+    }
+
+    #[test]
+    fn test_touch_project_sessions_only_touches_project() {
+        let (_temp_dir, db) = test_db();
+        let read = Project::new("/tmp/read");
+        let other = Project::new("/tmp/other");
+        db.insert_project(&read).unwrap();
+        db.insert_project(&other).unwrap();
+
+        for (id, project) in [("r1", &read), ("r2", &read), ("o1", &other)] {
+            let mut session = SessionMetadata::new(id, &project.id);
+            session.last_accessed_at = Some(100);
+            db.upsert_session_metadata(&session).unwrap();
+        }
+
+        assert_eq!(db.touch_project_sessions(&read.id, 5_000).unwrap(), 2);
+
+        for id in ["r1", "r2"] {
+            let session = db.get_session_by_id(id).unwrap().unwrap();
+            assert_eq!(session.last_accessed_at, Some(5_000));
+            assert!(!session.is_unread(4_000));
+            assert!(session.is_unread(6_000));
+        }
+        let untouched = db.get_session_by_id("o1").unwrap().unwrap();
+        assert_eq!(untouched.last_accessed_at, Some(100));
+        assert!(untouched.is_unread(4_000));
+    }
+
+    #[test]
+    fn test_allowlists_round_trip() {
+        let (_temp_dir, source) = test_db();
+        let web = Project::new("/tmp/web");
+        let api = Project::new("/tmp/api");
+        source.insert_project(&web).unwrap();
+        source.insert_project(&api).unwrap();
+        source.add_to_allowlist(&web.id, "npm test").unwrap();
+        source.add_to_allowlist(&web.id, "npm run lint").unwrap();
+        source.add_to_allowlist(&api.id, "cargo test").unwrap();
+
+        let backup = source.get_all_allowlists().unwrap();
+        assert_eq!(backup.len(), 2);
+        assert_eq!(backup["/tmp/web"], vec!["npm test", "npm run lint"]);
+
+        // Restore into a fresh install where only some projects were added again,
+        // with new IDs
+        let (_temp_dir, target) = test_db();
+        let web_again = Project::new("/tmp/web");
+        assert_ne!(web_again.id, web.id);
+        target.insert_project(&web_again).unwrap();
+        target
+            .add_to_allowlist(&web_again.id, "rm -rf build")
+            .unwrap();
+
+        let report = target.import_allowlists(&backup, false).unwrap();
+        assert_eq!(report.imported, 2);
+        assert_eq!(report.skipped_projects, vec!["/tmp/api".to_string()]);
+        let mut merged = target.get_allowlist(&web_again.id).unwrap();
+        merged.sort();
+        assert_eq!(merged, vec!["npm run lint", "npm test", "rm -rf build"]);
+
+        let report = target.import_allowlists(&backup, true).unwrap();
+        assert_eq!(report.imported, 2);
+        let mut replaced = target.get_allowlist(&web_again.id).unwrap();
+        replaced.sort();
+        assert_eq!(replaced, vec!["npm run lint", "npm test"]);
+    }
+
+    #[test]
+    fn test_delete_session_removes_its_snapshots() {
+        let (_temp_dir, db) = test_db();
+        let project = Project::new("/tmp/snapshots");
+        db.insert_project(&project).unwrap();
+        db.upsert_session_metadata(&SessionMetadata::new("doomed", &project.id))
+            .unwrap();
+        db.upsert_session_metadata(&SessionMetadata::new("kept", &project.id))
+            .unwrap();
+        db.insert_snapshot(&Snapshot::new_git_ghost("doomed", "abc123"))
+            .unwrap();
+        db.insert_snapshot(&Snapshot::new_git_ghost("kept", "def456"))
+            .unwrap();
+
+        db.delete_session_metadata("doomed").unwrap();
+
+        assert!(db.get_snapshots_for_session("doomed").unwrap().is_empty());
+        assert_eq!(db.get_snapshots_for_session("kept").unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_prune_orphaned_snapshots() {
+        let (_temp_dir, db) = test_db();
+        let project = Project::new("/tmp/prune");
+        db.insert_project(&project).unwrap();
+        db.upsert_session_metadata(&SessionMetadata::new("live", &project.id))
+            .unwrap();
+        db.insert_snapshot(&Snapshot::new_git_ghost("live", "abc123"))
+            .unwrap();
+        db.upsert_session_metadata(&SessionMetadata::new("gone", &project.id))
+            .unwrap();
+        db.delete_session_metadata("gone").unwrap();
+        // Snapshots that raced the delete, e.g. an auto snapshot finishing late
+        db.insert_snapshot(&Snapshot::new_git_ghost("gone", "def456"))
+            .unwrap();
+        db.insert_snapshot(&Snapshot::new_file_backup("gone", "{}"))
+            .unwrap();
+        // A resumed thread that hasn't been linked to a project yet
+        db.insert_snapshot(&Snapshot::new_git_ghost("thr_resumed", "fed789"))
+            .unwrap();
+
+        assert_eq!(db.prune_orphaned_snapshots().unwrap(), 2);
+        assert!(db.get_snapshots_for_session("gone").unwrap().is_empty());
+        assert_eq!(db.get_snapshots_for_session("live").unwrap().len(), 1);
+        assert_eq!(
+            db.get_snapshots_for_session("thr_resumed").unwrap().len(),
+            1
+        );
+        assert_eq!(db.prune_orphaned_snapshots().unwrap(), 0);
+
+        // A deleted session that comes back is no longer treated as deleted
+        db.upsert_session_metadata(&SessionMetadata::new("gone", &project.id))
+            .unwrap();
+        db.insert_snapshot(&Snapshot::new_git_ghost("gone", "abc999"))
+            .unwrap();
+        assert_eq!(db.prune_orphaned_snapshots().unwrap(), 0);
+    }
+
+    fn schema_version(db: &Database) -> (i64, i64) {
+        db.conn
+            .lock()
+            .query_row(
+                "SELECT MAX(version), COUNT(*) FROM schema_version",
+                [],
+                |row| Ok((row.get(0)?, row.get(1)?)),
+            )
+            .unwrap()
+    }
+
+    #[test]
+    fn test_migrations_are_recorded_and_applied_once() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let db_path = temp_dir.path().join("test.db");
+        let latest = MIGRATIONS.len() as i64;
+
+        let db = Database::new(&db_path).unwrap();
+        assert_eq!(schema_version(&db), (latest, latest));
+        drop(db);
+
+        let reopened = Database::new(&db_path).unwrap();
+        assert_eq!(schema_version(&reopened), (latest, latest));
+    }
+
+    #[test]
+    fn test_migrations_upgrade_unversioned_database() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let db_path = temp_dir.path().join("oldest.db");
+
+        // Original shape, before status tracking and schema_version
+        {
+            let conn = Connection::open(&db_path).unwrap();
+            conn.execute_batch(
+                r#"
+                CREATE TABLE projects (
+                    id TEXT PRIMARY KEY,
+                    path TEXT NOT NULL UNIQUE,
+                    display_name TEXT,
+                    created_at INTEGER NOT NULL DEFAULT (strftime('%s', 'now')),
+                    last_opened_at INTEGER,
+                    settings_json TEXT
+                );
+                CREATE TABLE session_metadata (
+                    session_id TEXT PRIMARY KEY,
+                    project_id TEXT NOT NULL REFERENCES projects(id) ON DELETE CASCADE,
+                    title TEXT,
+                    tags TEXT,
+                    is_favorite INTEGER NOT NULL DEFAULT 0,
+                    is_archived INTEGER NOT NULL DEFAULT 0,
+                    last_accessed_at INTEGER,
+                    created_at INTEGER NOT NULL DEFAULT (strftime('%s', 'now'))
+                );
+                INSERT INTO projects (id, path) VALUES ('p1', '/tmp/oldest');
+                INSERT INTO session_metadata (session_id, project_id, title) VALUES ('s1', 'p1', 'Old');
+                "#,
+            )
+            .unwrap();
+        }
+
+        let db = Database::new(&db_path).unwrap();
+        let latest = MIGRATIONS.len() as i64;
+        assert_eq!(schema_version(&db), (latest, latest));
+        {
+            let conn = db.conn.lock();
+            for column in [
+                "status",
+                "first_message",
+                "tasks_json",
+                "deleted_at",
+                "approval_policy",
+                "source",
+            ] {
+                assert!(
+                    has_column(&conn, "session_metadata", column).unwrap(),
+                    "{column}"
+                );
+            }
+        }
+        let session = db.get_session_by_id("s1").unwrap().unwrap();
+        assert_eq!(session.title.as_deref(), Some("Old"));
+        assert_eq!(session.status, SessionStatus::Idle);
+    }
+
+    #[test]
+    fn test_migration_cascades_snapshot_deletes() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let db_path = temp_dir.path().join("legacy.db");
+
+        // Database created before snapshots were linked to sessions
+        {
+            let conn = Connection::open(&db_path).unwrap();
+            conn.execute_batch(
+                r#"
+                CREATE TABLE projects (
+                    id TEXT PRIMARY KEY,
+                    path TEXT NOT NULL UNIQUE,
+                    display_name TEXT,
+                    created_at INTEGER NOT NULL DEFAULT (strftime('%s', 'now')),
+                    last_opened_at INTEGER,
+                    settings_json TEXT
+                );
+                CREATE TABLE session_metadata (
+                    session_id TEXT PRIMARY KEY,
+                    project_id TEXT NOT NULL REFERENCES projects(id) ON DELETE CASCADE,
+                    title TEXT,
+                    tags TEXT,
+                    is_favorite INTEGER NOT NULL DEFAULT 0,
+                    is_archived INTEGER NOT NULL DEFAULT 0,
+                    last_accessed_at INTEGER,
+                    created_at INTEGER NOT NULL DEFAULT (strftime('%s', 'now')),
+                    status TEXT NOT NULL DEFAULT 'idle',
+                    first_message TEXT,
+                    tasks_json TEXT
+                );
+                CREATE TABLE snapshots (
+                    id TEXT PRIMARY KEY,
+                    session_id TEXT NOT NULL,
+                    created_at INTEGER NOT NULL DEFAULT (strftime('%s', 'now')),
+                    snapshot_type TEXT NOT NULL,
+                    metadata_json TEXT
+                );
+                INSERT INTO projects (id, path) VALUES ('p1', '/tmp/legacy');
+                INSERT INTO session_metadata (session_id, project_id) VALUES ('s1', 'p1');
+                INSERT INTO snapshots (id, session_id, snapshot_type) VALUES ('snap-live', 's1', 'git_ghost');
+                INSERT INTO snapshots (id, session_id, snapshot_type) VALUES ('snap-orphan', 'gone', 'git_ghost');
+                "#,
+            )
+            .unwrap();
+        }
+
+        let db = Database::new(&db_path).unwrap();
+
+        // Snapshots without a metadata row (e.g. of resumed threads) survive the upgrade
+        assert!(db.get_snapshot("snap-orphan").unwrap().is_some());
+        assert!(db.get_snapshot("snap-live").unwrap().is_some());
+
+        // Rows from before source tracking read back as unknown
+        assert_eq!(
+            db.get_session_by_id("s1").unwrap().unwrap().source,
+            SessionSource::Unknown
+        );
+
+        // Deleting the session (here via project cascade) removes its snapshots
+        db.delete_project("p1").unwrap();
+        assert!(db.get_snapshot("snap-live").unwrap().is_none());
+
+        // Reopening doesn't re-run the migration
+        drop(db);
+        assert!(Database::new(&db_path).is_ok());
+    }
+
+    #[test]
+    fn test_update_project_path_keeps_related_rows() {
+        let (_temp_dir, db) = test_db();
+        let project = Project::new("/tmp/old-location");
+        db.insert_project(&project).unwrap();
+        db.upsert_session_metadata(&SessionMetadata::new("s1", &project.id))
+            .unwrap();
+        db.add_to_allowlist(&project.id, "cargo test").unwrap();
+
+        db.update_project_path(&project.id, "/tmp/new-location")
+            .unwrap();
+
+        let moved = db.get_project(&project.id).unwrap().unwrap();
+        assert_eq!(moved.path, "/tmp/new-location");
+        assert_eq!(db.get_sessions_for_project(&project.id).unwrap().len(), 1);
+        assert_eq!(
+            db.get_allowlist(&project.id).unwrap(),
+            vec!["cargo test".to_string()]
+        );
+
+        assert!(matches!(
+            db.update_project_path("missing", "/tmp/x"),
+            Err(crate::Error::ProjectNotFound(_))
+        ));
+    }
+
+    #[test]
+    fn test_update_project_keeps_session_metadata() {
+        let (_temp_dir, db) = test_db();
+        let mut project = Project::new("/tmp/project");
+        db.insert_project(&project).unwrap();
+        db.upsert_session_metadata(&SessionMetadata::new("s1", &project.id))
+            .unwrap();
+
+        project.display_name = Some("Renamed".to_string());
+        project.settings_json = Some(r#"{"model":"o3"}"#.to_string());
+        db.update_project(&project).unwrap();
+
+        let updated = db.get_project(&project.id).unwrap().unwrap();
+        assert_eq!(updated.display_name.as_deref(), Some("Renamed"));
+        assert_eq!(updated.settings_json.as_deref(), Some(r#"{"model":"o3"}"#));
+        assert!(db.get_session_by_id("s1").unwrap().is_some());
+
+        assert!(matches!(
+            db.update_project(&Project::new("/tmp/missing")),
+            Err(crate::Error::ProjectNotFound(_))
+        ));
+    }
+
+    #[test]
+    fn test_bulk_archive_and_delete_sessions() {
+        let (_temp_dir, db) = test_db();
+        let project = Project::new("/tmp/project");
+        db.insert_project(&project).unwrap();
+        for id in ["a", "b", "c", "d"] {
+            db.upsert_session_metadata(&SessionMetadata::new(id, &project.id))
+                .unwrap();
+        }
+        db.insert_snapshot(&Snapshot::new_git_ghost("c", "abc123"))
+            .unwrap();
+        let ids = |ids: &[&str]| ids.iter().map(|id| id.to_string()).collect::<Vec<_>>();
+
+        // Unknown ids are skipped rather than failing the batch
+        assert_eq!(
+            db.set_sessions_archived(&ids(&["a", "b", "missing"]), true)
+                .unwrap(),
+            2
+        );
+        let listed: Vec<_> = db
+            .get_sessions_for_project(&project.id)
+            .unwrap()
+            .into_iter()
+            .map(|s| s.session_id)
+            .collect();
+        assert_eq!(listed.len(), 2);
+        assert!(listed.contains(&"c".to_string()) && listed.contains(&"d".to_string()));
+        assert_eq!(db.set_sessions_archived(&ids(&["b"]), false).unwrap(), 1);
+        assert!(!db.get_session_by_id("b").unwrap().unwrap().is_archived);
+
+        assert_eq!(db.soft_delete_sessions(&ids(&["a", "b"]), 100).unwrap(), 2);
+        assert_eq!(db.soft_delete_sessions(&ids(&["a"]), 200).unwrap(), 0);
+        assert_eq!(db.get_deleted_sessions().unwrap().len(), 2);
+        // Deleted sessions can't be archived or unarchived
+        assert_eq!(db.set_sessions_archived(&ids(&["a"]), false).unwrap(), 0);
+
+        assert_eq!(
+            db.delete_sessions_metadata(&ids(&["a", "c", "missing"]))
+                .unwrap(),
+            2
+        );
+        assert!(db.get_snapshots_for_session("c").unwrap().is_empty());
+        assert_eq!(db.get_deleted_sessions().unwrap().len(), 1);
+        assert!(db.get_session_by_id("d").unwrap().is_some());
+    }
+
+    #[test]
+    fn test_soft_delete_restore_and_purge() {
+        let (_temp_dir, db) = test_db();
+        let project = Project::new("/tmp/project");
+        db.insert_project(&project).unwrap();
+        for id in ["keep", "old", "recent"] {
+            db.upsert_session_metadata(&SessionMetadata::new(id, &project.id))
+                .unwrap();
+        }
+        db.insert_snapshot(&Snapshot::new_git_ghost("old", "abc123"))
+            .unwrap();
+
+        assert!(db.soft_delete_session("old", 100).unwrap());
+        assert!(db.soft_delete_session("recent", 200).unwrap());
+        assert!(!db.soft_delete_session("old", 300).unwrap());
+        assert!(db.session_exists_any("old").unwrap());
+        assert!(!db.session_exists_any("missing").unwrap());
+
+        // Hidden from normal queries
+        assert_eq!(db.get_sessions_for_project(&project.id).unwrap().len(), 1);
+        assert!(db.get_session_by_id("old").unwrap().is_none());
+        assert_eq!(db.get_session_counts_by_project().unwrap()[&project.id], 1);
+
+        let deleted = db.get_deleted_sessions().unwrap();
+        let ids: Vec<_> = deleted
+            .iter()
+            .map(|d| d.session.session_id.as_str())
+            .collect();
+        assert_eq!(ids, vec!["recent", "old"]);
+        assert_eq!(deleted[0].deleted_at, 200);
+
+        assert!(db.restore_deleted_session("recent").unwrap());
+        assert!(!db.restore_deleted_session("keep").unwrap());
+        assert!(db.get_session_by_id("recent").unwrap().is_some());
+
+        // Snapshots survive a soft delete but not a purge
+        assert_eq!(db.get_snapshots_for_session("old").unwrap().len(), 1);
+        assert_eq!(db.purge_deleted_sessions(150).unwrap(), 1);
+        assert!(db.get_deleted_sessions().unwrap().is_empty());
+        assert!(db.get_snapshots_for_session("old").unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_search_snapshots_by_label_and_date() {
+        let (_temp_dir, db) = test_db();
+        let snapshot = |session: &str, label: Option<&str>, created_at: i64| {
+            let snapshot = Snapshot {
+                created_at,
+                ..Snapshot::new_git_ghost(session, "abc1234").with_label(label)
+            };
+            db.insert_snapshot(&snapshot).unwrap();
+            snapshot.id
+        };
+        let refactor = snapshot("s1", Some("Before refactor"), 1_000);
+        let release = snapshot("s1", Some("before release"), 2_000);
+        let other = snapshot("s2", Some("refactor part 2"), 3_000);
+        let unlabeled = snapshot("s2", None, 4_000);
+        let percent = snapshot("s2", Some("100% done"), 5_000);
+
+        let ids = |found: Vec<Snapshot>| found.into_iter().map(|s| s.id).collect::<Vec<_>>();
+
+        assert_eq!(
+            ids(db.search_snapshots(None, None, None, None).unwrap()),
+            vec![
+                percent.clone(),
+                unlabeled,
+                other.clone(),
+                release.clone(),
+                refactor.clone()
+            ]
+        );
+        assert_eq!(
+            ids(db
+                .search_snapshots(None, Some("REFACTOR"), None, None)
+                .unwrap()),
+            vec![other.clone(), refactor.clone()]
+        );
+        assert_eq!(
+            ids(db
+                .search_snapshots(Some("s1"), Some("before"), None, None)
+                .unwrap()),
+            vec![release.clone(), refactor]
+        );
+        assert_eq!(
+            ids(db
+                .search_snapshots(None, None, Some(2_000), Some(3_000))
+                .unwrap()),
+            vec![other, release]
+        );
+        // LIKE wildcards in the query match literally
+        assert_eq!(
+            ids(db.search_snapshots(None, Some("%"), None, None).unwrap()),
+            vec![percent]
+        );
+        assert!(db
+            .search_snapshots(None, Some("_"), None, None)
+            .unwrap()
+            .is_empty());
+    }
+
+    #[test]
+    fn test_app_settings_roundtrip() {
+        let (_dir, db) = test_db();
+        assert_eq!(db.get_setting("default_model").unwrap(), None);
+
+        db.set_setting("default_model", Some("gpt-5")).unwrap();
+        db.set_setting("default_model", Some("gpt-5-codex"))
+            .unwrap();
+        assert_eq!(
+            db.get_setting("default_model").unwrap().as_deref(),
+            Some("gpt-5-codex")
+        );
+
+        db.set_setting("default_model", None).unwrap();
+        assert_eq!(db.get_setting("default_model").unwrap(), None);
+    }
+
+    #[test]
+    fn test_project_commands_run_since() {
+        let (_dir, db) = test_db();
+        let project = Project::new("/tmp/project");
+        db.insert_project(&project).unwrap();
+        db.upsert_session_metadata(&SessionMetadata::new("s1", &project.id))
+            .unwrap();
+
+        db.record_command("s1", "cargo test", "accept", 100)
+            .unwrap();
+        db.record_command("s1", "rm -rf target", "decline", 200)
+            .unwrap();
+        db.record_command("s1", "cargo build", "acceptForSession", 50)
+            .unwrap();
+        db.record_command("other", "ls", "accept", 200).unwrap();
+
+        assert_eq!(
+            db.get_project_commands_run_since(&project.id, 60).unwrap(),
+            vec!["cargo test".to_string()]
+        );
+    }
+
+    #[test]
+    fn test_command_history_by_session_and_project() {
+        let (_dir, db) = test_db();
+        let project = Project::new("/tmp/project");
+        db.insert_project(&project).unwrap();
+        db.upsert_session_metadata(&SessionMetadata::new("s1", &project.id))
+            .unwrap();
+        db.upsert_session_metadata(&SessionMetadata::new("s2", &project.id))
+            .unwrap();
+
+        db.record_command("s1", "cargo test", "accept", 100)
+            .unwrap();
+        db.record_command("s1", "rm -rf /", "decline", 200).unwrap();
+        db.record_command("s2", "ls", "userShell", 150).unwrap();
+
+        let history = db.get_command_history("s1").unwrap();
+        let recorded: Vec<(&str, &str)> = history
+            .iter()
+            .map(|e| (e.command.as_str(), e.decision.as_str()))
+            .collect();
+        assert_eq!(
+            recorded,
+            vec![("rm -rf /", "decline"), ("cargo test", "accept")]
+        );
+
+        let project_history = db.get_project_command_history(&project.id).unwrap();
+        let sessions: Vec<&str> = project_history
+            .iter()
+            .map(|e| e.session_id.as_str())
+            .collect();
+        assert_eq!(sessions, vec!["s1", "s2", "s1"]);
+    }
+
+    #[test]
+    fn test_clear_command_history_filters() {
+        let (_dir, db) = test_db();
+        for (session, timestamp) in [("s1", 100), ("s1", 300), ("s2", 100), ("s2", 300)] {
+            db.record_command(session, "ls", "accept", timestamp)
+                .unwrap();
+        }
+
+        // Old entries of one session
+        assert_eq!(db.clear_command_history(Some("s1"), Some(200)).unwrap(), 1);
+        assert_eq!(db.get_command_history("s1").unwrap().len(), 1);
+        assert_eq!(db.get_command_history("s2").unwrap().len(), 2);
+
+        // Old entries of every session
+        assert_eq!(db.clear_command_history(None, Some(200)).unwrap(), 1);
+        assert_eq!(db.get_command_history("s2").unwrap()[0].timestamp, 300);
+
+        // Everything
+        assert_eq!(db.clear_command_history(None, None).unwrap(), 2);
+        assert!(db.get_command_history("s1").unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_purging_session_clears_command_history() {
+        let (_dir, db) = test_db();
+        let project = Project::new("/tmp/project");
+        db.insert_project(&project).unwrap();
+        db.upsert_session_metadata(&SessionMetadata::new("s1", &project.id))
+            .unwrap();
+        db.record_command("s1", "ls", "accept", 100).unwrap();
+
+        db.delete_session_metadata("s1").unwrap();
+        assert!(db.get_command_history("s1").unwrap().is_empty());
+    }
+}
@@ -42,6 +42,14 @@ impl Project {
             settings_json: None,
         }
     }
+
+    /// Parse the project's settings, falling back to defaults if unset or invalid
+    pub fn settings(&self) -> ProjectSettings {
+        self.settings_json
+            .as_deref()
+            .and_then(|json| serde_json::from_str(json).ok())
+            .unwrap_or_default()
+    }
 }
 
 /// Session status enum for agent state tracking
@@ -79,6 +87,38 @@ impl SessionStatus {
     }
 }
 
+/// Where a session was created
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum SessionSource {
+    /// Started from the desktop app
+    Desktop,
+    /// Started elsewhere (e.g. the Codex CLI) and linked when resumed
+    CliImport,
+    /// Recorded before sources were tracked
+    #[default]
+    Unknown,
+}
+
+impl SessionSource {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            SessionSource::Desktop => "desktop",
+            SessionSource::CliImport => "cli_import",
+            SessionSource::Unknown => "unknown",
+        }
+    }
+
+    #[allow(clippy::should_implement_trait)]
+    pub fn from_str(s: &str) -> Self {
+        match s {
+            "desktop" => SessionSource::Desktop,
+            "cli_import" => SessionSource::CliImport,
+            _ => SessionSource::Unknown,
+        }
+    }
+}
+
 /// Session metadata extensions (tags, favorites, etc.)
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
@@ -116,6 +156,14 @@ pub struct SessionMetadata {
 
     /// JSON array of tasks for progress tracking
     pub tasks_json: Option<String>,
+
+    /// Default approval policy for turns that don't set one
+    #[serde(default)]
+    pub approval_policy: Option<String>,
+
+    /// Where the session was created
+    #[serde(default)]
+    pub source: SessionSource,
 }
 
 impl SessionMetadata {
@@ -133,9 +181,17 @@ impl SessionMetadata {
             status: SessionStatus::Idle,
             first_message: None,
             tasks_json: None,
+            approval_policy: None,
+            source: SessionSource::Unknown,
         }
     }
 
+    /// Set where the session was created
+    pub fn with_source(mut self, source: SessionSource) -> Self {
+        self.source = source;
+        self
+    }
+
     /// Parse tags from JSON
     pub fn get_tags(&self) -> Vec<String> {
         self.tags
@@ -163,7 +219,19 @@ impl SessionMetadata {
             }
             return first_msg.clone();
         }
-        format!("Session {}", &self.session_id[..8.min(self.session_id.len())])
+        format!(
+            "Session {}",
+            &self.session_id[..8.min(self.session_id.len())]
+        )
+    }
+
+    /// Whether the session has activity the user hasn't seen yet
+    ///
+    /// `last_activity_at` is when the thread last changed (e.g. its rollout
+    /// file's mtime). A session that was never accessed counts as unread.
+    pub fn is_unread(&self, last_activity_at: i64) -> bool {
+        self.last_accessed_at
+            .is_none_or(|accessed| last_activity_at > accessed)
     }
 
     /// Parse tasks from JSON
@@ -190,6 +258,31 @@ pub struct TaskItem {
     pub status: String,
 }
 
+/// A command the agent ran or asked to run
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CommandHistoryEntry {
+    pub id: i64,
+    pub session_id: String,
+    pub command: String,
+    /// Approval decision (e.g. "accept", "decline"), or "userShell" for commands the user ran
+    pub decision: String,
+    /// Unix timestamp when the command was recorded
+    pub timestamp: i64,
+}
+
+/// Storage used by file backup contents in `snapshot_blobs`
+#[derive(Debug, Clone, Copy, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SnapshotBlobUsage {
+    /// Number of distinct blobs stored
+    pub blobs: usize,
+    /// Total size of the file contents before compression
+    pub raw_bytes: u64,
+    /// Total size actually stored
+    pub stored_bytes: u64,
+}
+
 /// Snapshot for revert functionality
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
@@ -208,6 +301,10 @@ pub struct Snapshot {
 
     /// JSON-encoded snapshot metadata
     pub metadata_json: Option<String>,
+
+    /// User-facing name, e.g. "before refactor"
+    #[serde(default)]
+    pub label: Option<String>,
 }
 
 impl Snapshot {
@@ -223,6 +320,23 @@ impl Snapshot {
             created_at: chrono::Utc::now().timestamp(),
             snapshot_type: "git_ghost".to_string(),
             metadata_json: Some(metadata.to_string()),
+            label: None,
+        }
+    }
+
+    /// Create a git snapshot of uncommitted changes from a `git stash create` commit
+    ///
+    /// The stash commit's first parent is the HEAD it was made on, so reverting
+    /// resets to that parent and applies the commit on top.
+    pub fn new_git_stash(session_id: &str, stash_sha: &str) -> Self {
+        let metadata = serde_json::json!({
+            "commit_sha": stash_sha,
+            "stash": true,
+        });
+
+        Self {
+            metadata_json: Some(metadata.to_string()),
+            ..Self::new_git_ghost(session_id, stash_sha)
         }
     }
 
@@ -234,8 +348,39 @@ impl Snapshot {
             created_at: chrono::Utc::now().timestamp(),
             snapshot_type: "file_backup".to_string(),
             metadata_json: Some(metadata_json.to_string()),
+            label: None,
         }
     }
+
+    /// Set the label, treating a blank one as no label
+    pub fn with_label(mut self, label: Option<&str>) -> Self {
+        self.label = label
+            .map(str::trim)
+            .filter(|l| !l.is_empty())
+            .map(str::to_string);
+        self
+    }
+}
+
+/// One page of a project's sessions
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SessionPage {
+    pub sessions: Vec<SessionMetadata>,
+
+    /// Number of sessions across all pages
+    pub total: usize,
+}
+
+/// A soft-deleted session awaiting restore or purge
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DeletedSession {
+    #[serde(flatten)]
+    pub session: SessionMetadata,
+
+    /// Unix timestamp when the session was deleted
+    pub deleted_at: i64,
 }
 
 /// Project settings stored as JSON
@@ -256,4 +401,45 @@ pub struct ProjectSettings {
 
     /// Default approval policy
     pub ask_for_approval: Option<String>,
+
+    /// Maximum size of a message's text input, in bytes (defaults to `DEFAULT_MAX_INPUT_BYTES`)
+    pub max_input_bytes: Option<u64>,
+
+    /// Snapshots kept per session (defaults to `DEFAULT_SNAPSHOT_RETENTION`; 0 keeps all)
+    pub snapshot_retention: Option<usize>,
+
+    /// Take a snapshot of the project before every turn
+    #[serde(default)]
+    pub auto_snapshot: bool,
+}
+
+impl ProjectSettings {
+    /// Default cap on a message's text input when a project doesn't set one
+    pub const DEFAULT_MAX_INPUT_BYTES: u64 = 1024 * 1024;
+
+    /// The input cap in effect for this project
+    pub fn effective_max_input_bytes(&self) -> u64 {
+        self.max_input_bytes
+            .unwrap_or(Self::DEFAULT_MAX_INPUT_BYTES)
+    }
+
+    /// Default number of snapshots kept per session when a project doesn't set one
+    pub const DEFAULT_SNAPSHOT_RETENTION: usize = 10;
+
+    /// Snapshots kept per session in this project; 0 means keep all
+    pub fn effective_snapshot_retention(&self) -> usize {
+        self.snapshot_retention
+            .unwrap_or(Self::DEFAULT_SNAPSHOT_RETENTION)
+    }
+}
+
+/// Result of restoring allowlists from a backup
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct AllowlistImportReport {
+    /// Number of patterns written
+    pub imported: usize,
+
+    /// Project paths in the backup with no matching project
+    pub skipped_projects: Vec<String>,
 }
@@ -76,6 +76,80 @@ impl CodexErrorInfo {
     }
 }
 
+/// Reason a path was rejected by validation
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum PathValidationError {
+    /// Path contains null bytes
+    NullByte,
+    /// Path contains parent directory traversal (..)
+    ParentTraversal,
+    /// Path is absolute (starts with / or \)
+    AbsolutePath,
+    /// Path escapes the project directory
+    DirectoryEscape,
+    /// Path is a symbolic link
+    SymbolicLink,
+    /// Path contains invalid characters
+    InvalidCharacters,
+    /// Path does not exist or cannot be resolved
+    NotFound,
+}
+
+impl std::fmt::Display for PathValidationError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PathValidationError::NullByte => {
+                write!(f, "Path contains null byte character")
+            }
+            PathValidationError::ParentTraversal => {
+                write!(f, "Path contains parent directory traversal (..)")
+            }
+            PathValidationError::AbsolutePath => {
+                write!(f, "Path must be relative, not absolute")
+            }
+            PathValidationError::DirectoryEscape => {
+                write!(f, "Path would escape the project directory")
+            }
+            PathValidationError::SymbolicLink => {
+                write!(f, "Symbolic links are not allowed in this path")
+            }
+            PathValidationError::InvalidCharacters => {
+                write!(f, "Path contains invalid characters")
+            }
+            PathValidationError::NotFound => {
+                write!(f, "Path does not exist or cannot be resolved")
+            }
+        }
+    }
+}
+
+/// Why the app server could not be started
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum AppServerStartupErrorKind {
+    /// The codex binary could not be found
+    BinaryNotFound,
+    /// The binary was found but the process could not be spawned
+    SpawnFailed,
+    /// The installed codex doesn't speak the protocol this app expects
+    VersionMismatch,
+    /// The app server requires the user to log in first
+    AuthRequired,
+    /// The initialize handshake failed for another reason
+    InitializeFailed,
+}
+
+/// Details of an app server startup failure
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct AppServerStartupInfo {
+    pub kind: AppServerStartupErrorKind,
+    pub message: String,
+    /// Last lines the process wrote to stderr before failing
+    pub stderr_tail: Vec<String>,
+}
+
 /// Application-wide error type
 #[derive(Error, Debug)]
 pub enum Error {
@@ -97,6 +171,9 @@ pub enum Error {
         info: Option<CodexErrorInfo>,
     },
 
+    #[error("App server failed to start: {}", .0.message)]
+    AppServerStartup(AppServerStartupInfo),
+
     #[error("Project not found: {0}")]
     ProjectNotFound(String),
 
@@ -109,6 +186,12 @@ pub enum Error {
     #[error("Invalid path: {0}")]
     InvalidPath(String),
 
+    #[error("Invalid path: {kind}: {path}")]
+    PathValidation {
+        path: String,
+        kind: PathValidationError,
+    },
+
     #[error("Git error: {0}")]
     Git(String),
 
@@ -135,6 +218,22 @@ impl Error {
             info: Some(CodexErrorInfo::from_type_string(type_str, http_status)),
         }
     }
+
+    /// Create a path validation error for the given path
+    pub fn path_validation(path: impl Into<String>, kind: PathValidationError) -> Self {
+        Error::PathValidation {
+            path: path.into(),
+            kind,
+        }
+    }
+
+    /// The validation failure behind this error, if it is a path validation error
+    pub fn path_validation_kind(&self) -> Option<PathValidationError> {
+        match self {
+            Error::PathValidation { kind, .. } => Some(*kind),
+            _ => None,
+        }
+    }
 }
 
 /// Result type alias for this crate
@@ -147,6 +246,10 @@ struct ErrorResponse {
     message: String,
     #[serde(skip_serializing_if = "Option::is_none")]
     error_info: Option<CodexErrorInfo>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    path_error: Option<PathValidationError>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    startup_error: Option<AppServerStartupInfo>,
 }
 
 // Implement conversion to Tauri's invoke error with structured info
@@ -160,6 +263,8 @@ impl Serialize for Error {
                 let response = ErrorResponse {
                     message: message.clone(),
                     error_info: info.clone(),
+                    path_error: None,
+                    startup_error: None,
                 };
                 response.serialize(serializer)
             }
@@ -167,6 +272,11 @@ impl Serialize for Error {
                 let response = ErrorResponse {
                     message: self.to_string(),
                     error_info: None,
+                    path_error: self.path_validation_kind(),
+                    startup_error: match self {
+                        Error::AppServerStartup(info) => Some(info.clone()),
+                        _ => None,
+                    },
                 };
                 response.serialize(serializer)
             }
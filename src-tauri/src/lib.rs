@@ -12,12 +12,16 @@ pub mod snapshots;
 
 mod error;
 mod state;
+mod tasks;
 mod utils;
 
-pub use error::{CodexErrorInfo, CodexErrorType, Error, Result};
+pub use error::{
+    AppServerStartupErrorKind, AppServerStartupInfo, CodexErrorInfo, CodexErrorType, Error,
+    PathValidationError, Result,
+};
 pub use state::AppState;
 
-use tauri::Manager;
+use tauri::{Emitter, Manager};
 
 /// Clean up old temp image files from previous sessions
 fn cleanup_temp_images() {
@@ -63,6 +67,38 @@ fn cleanup_temp_images() {
     }
 }
 
+/// Check the database in the background and keep the report in `last_health`
+///
+/// The check usually finishes before the webview has loaded, so the UI reads
+/// the report with `get_last_database_health`; the `database-health` event
+/// only reaches windows already listening.
+fn check_database_health(
+    database: std::sync::Arc<database::Database>,
+    last_health: std::sync::Arc<parking_lot::Mutex<Option<database::DatabaseHealth>>>,
+    app_handle: tauri::AppHandle,
+) {
+    std::thread::spawn(move || match database.check_health() {
+        Ok(health) => {
+            if !health.is_healthy() {
+                tracing::warn!(
+                    "Database health check found problems: {} integrity errors, {} foreign key violations, \
+                     {} orphaned snapshots, {} orphaned sessions, {} orphaned command history entries",
+                    health.integrity_errors.len(),
+                    health.foreign_key_violations.len(),
+                    health.orphaned_snapshots,
+                    health.orphaned_sessions,
+                    health.orphaned_command_history
+                );
+            }
+            if let Err(e) = app_handle.emit("database-health", &health) {
+                tracing::warn!("Failed to emit database-health event: {}", e);
+            }
+            *last_health.lock() = Some(health);
+        }
+        Err(e) => tracing::error!("Database health check failed: {}", e),
+    });
+}
+
 /// Initialize and run the Tauri application
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
 pub fn run() {
@@ -86,6 +122,11 @@ pub fn run() {
 
             // Initialize application state
             let state = AppState::new(&app_handle)?;
+            check_database_health(
+                state.database.clone(),
+                state.last_database_health.clone(),
+                app_handle.clone(),
+            );
             app.manage(state);
 
             tracing::info!("Application state initialized");
@@ -97,60 +138,157 @@ pub fn run() {
             commands::projects::add_project,
             commands::projects::remove_project,
             commands::projects::update_project,
+            commands::projects::set_project_auto_snapshot,
+            commands::projects::update_project_path,
+            commands::projects::export_project_bundle,
+            commands::projects::import_project_bundle,
+            commands::projects::set_project_max_input_bytes,
+            commands::projects::find_project_for_cwd,
+            commands::projects::find_projects_with_missing_paths,
+            commands::projects::find_duplicate_projects,
             commands::projects::get_project_git_info,
             commands::projects::get_project_git_diff,
+            commands::projects::get_diff_context_lines,
+            commands::projects::set_diff_context_lines,
+            commands::projects::get_git_diff_stat,
+            commands::projects::get_diff_file_summaries,
+            commands::projects::get_branch_diff,
+            commands::projects::get_imported_session_drift,
+            commands::projects::export_git_patch,
+            commands::projects::git_restore_file,
+            commands::projects::git_restore_file_to_commit,
             commands::projects::list_project_files,
+            commands::projects::is_file_text,
+            commands::projects::detect_package_manager,
+            commands::projects::check_project_path,
             commands::projects::get_git_branches,
             commands::projects::get_git_commits,
+            commands::projects::get_hotspot_files,
             // Session commands
             commands::sessions::list_sessions,
+            commands::sessions::list_archived_sessions,
             commands::sessions::get_session,
             commands::sessions::update_session_metadata,
             commands::sessions::delete_session,
+            commands::sessions::bulk_archive_sessions,
+            commands::sessions::bulk_delete_sessions,
+            commands::sessions::list_recently_deleted_sessions,
+            commands::sessions::restore_session,
+            commands::sessions::purge_deleted_sessions,
             commands::sessions::search_sessions,
+            commands::sessions::search_sessions_stream,
+            commands::sessions::cancel_search,
             commands::sessions::update_session_status,
             commands::sessions::set_session_first_message,
             commands::sessions::update_session_tasks,
+            commands::sessions::get_session_approval_policy,
+            commands::sessions::set_session_approval_policy,
+            commands::sessions::get_command_history,
+            commands::sessions::get_project_command_history,
+            commands::sessions::clear_command_history,
+            commands::sessions::touch_all_sessions,
+            commands::sessions::generate_session_title,
+            commands::sessions::summarize_session_for_sharing,
+            commands::sessions::get_session_counts,
+            commands::sessions::list_attention_sessions,
+            commands::sessions::find_orphaned_metadata,
+            commands::sessions::repair_orphaned_metadata,
             // Thread commands (proxy to app-server)
             commands::thread::start_thread,
             commands::thread::resume_thread,
             commands::thread::send_message,
+            commands::thread::estimate_tokens,
             commands::thread::interrupt_turn,
+            commands::thread::cancel_thread_requests,
+            commands::thread::preview_approval,
             commands::thread::respond_to_approval,
             commands::thread::list_threads,
+            commands::thread::list_threads_enriched,
+            commands::thread::list_sandbox_presets,
+            commands::thread::validate_writable_roots,
+            commands::thread::get_thread_items,
+            commands::thread::get_thread_model,
             // Snapshot commands
             commands::snapshots::create_snapshot,
+            commands::snapshots::create_snapshots_batch,
+            commands::snapshots::preview_snapshot,
+            commands::snapshots::estimate_snapshot_cost,
+            commands::snapshots::find_non_utf8_paths,
+            commands::snapshots::get_conflicted_files,
+            commands::snapshots::get_snapshot_diff,
             commands::snapshots::revert_to_snapshot,
             commands::snapshots::list_snapshots,
+            commands::snapshots::search_snapshots,
+            commands::snapshots::get_backup_large_files,
+            commands::snapshots::set_backup_large_files,
+            commands::snapshots::get_git_snapshot_refs,
+            commands::snapshots::set_git_snapshot_refs,
             commands::snapshots::cleanup_old_snapshots_by_age,
             commands::snapshots::cleanup_session_snapshots,
+            commands::snapshots::prune_orphaned_snapshots,
+            commands::snapshots::archive_snapshots_to_file,
+            commands::snapshots::restore_snapshots_from_file,
+            commands::snapshots::export_snapshot,
+            commands::snapshots::import_snapshot,
+            commands::snapshots::migrate_legacy_snapshots,
             // App server commands
             commands::app_server::get_server_status,
+            commands::app_server::get_app_version,
             commands::app_server::restart_server,
+            commands::thread::restart_server_preserving,
+            commands::app_server::detect_conflicting_instances,
+            commands::app_server::set_ipc_debug,
+            commands::app_server::replay_recent_events,
+            commands::app_server::get_stderr_issues,
             commands::app_server::get_account_info,
+            commands::app_server::get_account_info_cached,
+            commands::app_server::list_accounts,
+            commands::app_server::switch_account,
             commands::app_server::start_login,
+            commands::app_server::cancel_login,
             commands::app_server::logout,
             commands::app_server::get_models,
+            commands::app_server::list_models_filtered,
+            commands::app_server::get_model_efforts,
+            commands::app_server::get_default_model,
+            commands::app_server::set_default_model,
             commands::app_server::list_skills,
+            commands::app_server::list_skills_merged,
+            commands::app_server::validate_skill,
             commands::app_server::list_mcp_servers,
             commands::app_server::start_review,
             commands::app_server::run_user_shell_command,
             // Config commands
             commands::app_server::read_config,
+            commands::app_server::get_config_key_layers,
+            commands::app_server::get_effective_config_value,
             commands::app_server::write_config,
+            commands::app_server::write_config_batch,
             // Account rate limits
             commands::app_server::get_account_rate_limits,
             // Allowlist commands
             commands::allowlist::get_allowlist,
             commands::allowlist::add_to_allowlist,
             commands::allowlist::remove_from_allowlist,
+            commands::allowlist::find_unused_allowlist_patterns,
+            commands::allowlist::export_all_allowlists,
+            commands::allowlist::import_all_allowlists,
             // Codex CLI import commands
             commands::codex_import::get_codex_config,
+            commands::codex_import::get_project_codex_config,
             commands::codex_import::list_codex_sessions,
             commands::codex_import::get_codex_session,
+            commands::codex_import::validate_codex_session,
+            commands::codex_import::get_codex_session_message_raw,
             commands::codex_import::search_codex_sessions,
             commands::codex_import::delete_codex_session,
             commands::codex_import::get_codex_dir,
+            // Background task commands
+            commands::tasks::list_background_tasks,
+            commands::tasks::cancel_background_task,
+            // Database commands
+            commands::database::validate_database_health,
+            commands::database::get_last_database_health,
         ])
         .run(tauri::generate_context!())
         .expect("error while running tauri application");
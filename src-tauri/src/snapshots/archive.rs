@@ -0,0 +1,242 @@
+//! Archiving snapshots out of the database into compressed files
+//!
+//! An archive is a gzip-compressed JSON document holding the snapshot rows
+//! as stored, plus the file contents their file backups reference, so
+//! restoring one puts back exactly what was removed.
+
+use std::collections::HashMap;
+use std::fs;
+use std::io::{Read, Write};
+use std::path::Path;
+
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use serde::{Deserialize, Serialize};
+
+use crate::database::{Database, Snapshot};
+use crate::{Error, Result};
+
+/// Current archive format version
+///
+/// Version 2 added `blobs`; version 1 archives hold file contents inline.
+const ARCHIVE_VERSION: u32 = 2;
+
+/// Upper bound on the decompressed size of an archive being restored
+const MAX_ARCHIVE_BYTES: u64 = 1024 * 1024 * 1024;
+
+/// On-disk archive contents
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct SnapshotArchive {
+    version: u32,
+    session_id: String,
+    archived_at: i64,
+    snapshots: Vec<Snapshot>,
+    /// Base64 contents of the blobs the file backups reference, by hash
+    #[serde(default)]
+    blobs: HashMap<String, String>,
+}
+
+/// Write a session's snapshots created before `cutoff` to `dest`, then remove them from the
+/// database
+///
+/// The file is fully written and synced before anything is deleted. Returns
+/// the number of snapshots archived; no file is written if there are none.
+pub fn archive_snapshots(
+    db: &Database,
+    session_id: &str,
+    cutoff: i64,
+    dest: &Path,
+) -> Result<usize> {
+    let snapshots = db.get_snapshots_created_before(session_id, cutoff)?;
+    if snapshots.is_empty() {
+        return Ok(0);
+    }
+
+    let ids: Vec<String> = snapshots.iter().map(|s| s.id.clone()).collect();
+    let blobs = db
+        .get_blobs_for_snapshots(&ids)?
+        .into_iter()
+        .map(|(hash, content)| (hash, BASE64.encode(content)))
+        .collect();
+
+    let archive = SnapshotArchive {
+        version: ARCHIVE_VERSION,
+        session_id: session_id.to_string(),
+        archived_at: chrono::Utc::now().timestamp(),
+        snapshots,
+        blobs,
+    };
+
+    let file = fs::File::create(dest)?;
+    let mut encoder = GzEncoder::new(file, Compression::default());
+    serde_json::to_writer(&mut encoder, &archive)?;
+    encoder.flush()?;
+    encoder.finish()?.sync_all()?;
+
+    let removed = db.delete_snapshots(&ids)?;
+
+    tracing::info!(
+        "Archived {} snapshots for session {} to {:?}",
+        removed,
+        session_id,
+        dest
+    );
+    Ok(removed)
+}
+
+/// Re-import snapshots from an archive written by `archive_snapshots`
+///
+/// Snapshots already in the database are left alone. Returns the number added.
+pub fn restore_snapshots(db: &Database, src: &Path) -> Result<usize> {
+    let file = fs::File::open(src)?;
+    let mut json = Vec::new();
+    GzDecoder::new(file)
+        .take(MAX_ARCHIVE_BYTES + 1)
+        .read_to_end(&mut json)
+        .map_err(|e| Error::Other(format!("Not a snapshot archive: {e}")))?;
+    if json.len() as u64 > MAX_ARCHIVE_BYTES {
+        return Err(Error::Other("Snapshot archive is too large".to_string()));
+    }
+
+    let archive: SnapshotArchive = serde_json::from_slice(&json)?;
+    if archive.version > ARCHIVE_VERSION {
+        return Err(Error::Other(format!(
+            "Unsupported snapshot archive version {}",
+            archive.version
+        )));
+    }
+    if let Some(stray) = archive
+        .snapshots
+        .iter()
+        .find(|s| s.session_id != archive.session_id)
+    {
+        return Err(Error::Other(format!(
+            "Snapshot {} in archive belongs to a different session",
+            stray.id
+        )));
+    }
+
+    let blobs = archive
+        .blobs
+        .iter()
+        .map(|(hash, content)| {
+            let content = BASE64.decode(content).map_err(|e| {
+                Error::Other(format!("Invalid blob {hash} in snapshot archive: {e}"))
+            })?;
+            // Blobs are shared by hash, so a mislabeled one would be restored
+            // in place of some other file's contents
+            if super::blob_hash(&content) != *hash {
+                return Err(Error::Other(format!(
+                    "Blob {hash} in snapshot archive doesn't match its contents"
+                )));
+            }
+            Ok((hash.clone(), content))
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    db.insert_snapshots_if_missing(&archive.snapshots, &blobs)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_archive_round_trip() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let db = Database::new(&temp_dir.path().join("test.db")).unwrap();
+        let dest = temp_dir.path().join("snapshots.json.gz");
+
+        let mut old = Snapshot::new_file_backup("s1", r#"{"files":{}}"#);
+        old.created_at = 1_000;
+        let mut recent = Snapshot::new_git_ghost("s1", "abc123");
+        recent.created_at = 5_000;
+        let mut other_session = Snapshot::new_git_ghost("s2", "def456");
+        other_session.created_at = 1_000;
+        for snapshot in [&old, &recent, &other_session] {
+            db.insert_snapshot(snapshot).unwrap();
+        }
+
+        assert_eq!(archive_snapshots(&db, "s1", 2_000, &dest).unwrap(), 1);
+        assert!(db.get_snapshot(&old.id).unwrap().is_none());
+        assert!(db.get_snapshot(&recent.id).unwrap().is_some());
+        assert!(db.get_snapshot(&other_session.id).unwrap().is_some());
+
+        assert_eq!(restore_snapshots(&db, &dest).unwrap(), 1);
+        let restored = db.get_snapshot(&old.id).unwrap().unwrap();
+        assert_eq!(restored.created_at, 1_000);
+        assert_eq!(restored.metadata_json, old.metadata_json);
+
+        // Restoring again is a no-op
+        assert_eq!(restore_snapshots(&db, &dest).unwrap(), 0);
+    }
+
+    fn write_archive(dest: &Path, snapshots: Vec<Snapshot>, blobs: HashMap<String, String>) {
+        let archive = SnapshotArchive {
+            version: ARCHIVE_VERSION,
+            session_id: "s1".to_string(),
+            archived_at: 0,
+            snapshots,
+            blobs,
+        };
+        let mut encoder = GzEncoder::new(fs::File::create(dest).unwrap(), Compression::default());
+        serde_json::to_writer(&mut encoder, &archive).unwrap();
+        encoder.finish().unwrap();
+    }
+
+    #[test]
+    fn test_restore_rejects_mislabeled_blobs() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let db = Database::new(&temp_dir.path().join("test.db")).unwrap();
+        let src = temp_dir.path().join("crafted.json.gz");
+
+        // Planted contents under the hash of some other file
+        let victim = crate::snapshots::blob_hash(b"fn main() {}\n");
+        let blobs = HashMap::from([(victim.clone(), BASE64.encode(b"malicious contents"))]);
+        write_archive(&src, Vec::new(), blobs);
+
+        let err = restore_snapshots(&db, &src).unwrap_err();
+        assert!(err.to_string().contains("doesn't match"), "{err}");
+        assert_eq!(db.get_snapshot_blob_refcount(&victim).unwrap(), None);
+    }
+
+    #[test]
+    fn test_restore_keeps_no_unreferenced_blobs() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let db = Database::new(&temp_dir.path().join("test.db")).unwrap();
+        let src = temp_dir.path().join("dup.json.gz");
+
+        // The archived snapshot is already in the database, so its insert is skipped
+        let existing = Snapshot::new_git_ghost("s1", "abc123");
+        db.insert_snapshot(&existing).unwrap();
+        let hash = crate::snapshots::blob_hash(b"contents");
+        let blobs = HashMap::from([(hash.clone(), BASE64.encode(b"contents"))]);
+        write_archive(&src, vec![existing], blobs);
+
+        assert_eq!(restore_snapshots(&db, &src).unwrap(), 0);
+        assert_eq!(db.get_snapshot_blob_refcount(&hash).unwrap(), None);
+    }
+
+    #[test]
+    fn test_archive_nothing_to_archive_writes_no_file() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let db = Database::new(&temp_dir.path().join("test.db")).unwrap();
+        let dest = temp_dir.path().join("empty.json.gz");
+
+        assert_eq!(archive_snapshots(&db, "s1", 2_000, &dest).unwrap(), 0);
+        assert!(!dest.exists());
+    }
+
+    #[test]
+    fn test_restore_rejects_non_archive() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let db = Database::new(&temp_dir.path().join("test.db")).unwrap();
+        let src = temp_dir.path().join("plain.txt");
+        fs::write(&src, "not gzip").unwrap();
+
+        assert!(restore_snapshots(&db, &src).is_err());
+    }
+}
@@ -0,0 +1,485 @@
+//! Exporting a single snapshot as a portable tar file
+//!
+//! Unlike archives, an export is meant to leave the machine: it holds a
+//! `snapshot.json` manifest plus, for file backups, every backed-up file under
+//! `files/`, so nothing depends on this database's blobs or sidecar files.
+//! Git snapshots export only their metadata; the commit they name must exist
+//! in the repository they are imported into.
+
+use std::collections::HashMap;
+use std::fs;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+use super::{
+    blob_hash, file_backup_metadata, git_snapshot_ref, sidecar, symlink_within_project,
+    validate_restore_path, FileBackupMetadata, StoredContent, FILE_BACKUP_METADATA_VERSION,
+    MAX_BACKUP_FILE_SIZE,
+};
+use crate::database::{Database, Snapshot};
+use crate::{Error, Result};
+
+/// Current export format version
+const EXPORT_VERSION: u32 = 1;
+
+/// Name of the manifest entry
+const MANIFEST_NAME: &str = "snapshot.json";
+
+/// Prefix of the entries holding backed-up files
+const FILES_PREFIX: &str = "files/";
+
+/// Upper bound on the manifest entry's size
+const MAX_MANIFEST_BYTES: u64 = 16 * 1024 * 1024;
+
+/// Upper bound on the total size of the files imported into the database
+const MAX_IMPORT_BYTES: u64 = 1024 * 1024 * 1024;
+
+/// The `snapshot.json` entry
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ExportManifest {
+    version: u32,
+    exported_at: i64,
+    /// The snapshot row; a file backup's metadata lists no stored contents,
+    /// since those are the `files/` entries
+    snapshot: Snapshot,
+}
+
+/// Write `snapshot_id` and the files it backed up to a tar file at `dest`
+///
+/// Returns the number of files written. A partially written file is removed
+/// if the export fails.
+pub fn export_snapshot(db: &Database, snapshot_id: &str, dest: &Path) -> Result<usize> {
+    let snapshot = db
+        .get_snapshot(snapshot_id)?
+        .ok_or_else(|| Error::SnapshotNotFound(snapshot_id.to_string()))?;
+
+    let write = || -> Result<usize> {
+        let mut builder = tar::Builder::new(fs::File::create(dest)?);
+        let file_count = match snapshot.snapshot_type.as_str() {
+            "file_backup" => export_file_backup(db, &snapshot, &mut builder)?,
+            "git_ghost" => {
+                git_snapshot_ref(&snapshot)?;
+                append_manifest(&mut builder, snapshot.clone())?;
+                0
+            }
+            other => return Err(Error::Other(format!("Unknown snapshot type: {other}"))),
+        };
+        builder.into_inner()?.sync_all()?;
+        Ok(file_count)
+    };
+
+    match write() {
+        Ok(file_count) => {
+            tracing::info!(
+                "Exported snapshot {} ({} files) to {:?}",
+                snapshot_id,
+                file_count,
+                dest
+            );
+            Ok(file_count)
+        }
+        Err(e) => {
+            let _ = fs::remove_file(dest);
+            Err(e)
+        }
+    }
+}
+
+/// Write a file backup's manifest followed by its files
+fn export_file_backup(
+    db: &Database,
+    snapshot: &Snapshot,
+    builder: &mut tar::Builder<fs::File>,
+) -> Result<usize> {
+    let metadata = file_backup_metadata(snapshot)?;
+
+    let manifest_metadata = FileBackupMetadata {
+        version: FILE_BACKUP_METADATA_VERSION,
+        files: HashMap::new(),
+        blobs: HashMap::new(),
+        sidecars: HashMap::new(),
+        skipped_large: metadata.skipped_large.clone(),
+        skipped_non_utf8: metadata.skipped_non_utf8.clone(),
+        modes: metadata.modes.clone(),
+        symlinks: metadata.symlinks.clone(),
+        description: metadata.description.clone(),
+    };
+    append_manifest(
+        builder,
+        Snapshot {
+            metadata_json: Some(serde_json::to_string(&manifest_metadata)?),
+            ..snapshot.clone()
+        },
+    )?;
+
+    let mut file_count = 0;
+    for (path, content) in metadata.stored_files() {
+        let bytes = content.load(db, &snapshot.id, path)?;
+        append_file(builder, path, bytes.len() as u64, bytes.as_slice())?;
+        file_count += 1;
+    }
+    for (path, hash) in &metadata.sidecars {
+        let sidecar_path = StoredContent::existing_sidecar(db, hash, &snapshot.id, path)?;
+        let file = fs::File::open(sidecar_path)?;
+        let size = file.metadata()?.len();
+        append_file(builder, path, size, file)?;
+        file_count += 1;
+    }
+    Ok(file_count)
+}
+
+fn append_manifest(builder: &mut tar::Builder<fs::File>, snapshot: Snapshot) -> Result<()> {
+    let manifest = serde_json::to_vec(&ExportManifest {
+        version: EXPORT_VERSION,
+        exported_at: chrono::Utc::now().timestamp(),
+        snapshot,
+    })?;
+    let mut header = entry_header(manifest.len() as u64);
+    builder.append_data(&mut header, MANIFEST_NAME, manifest.as_slice())?;
+    Ok(())
+}
+
+fn append_file(
+    builder: &mut tar::Builder<fs::File>,
+    relative_path: &str,
+    size: u64,
+    contents: impl Read,
+) -> Result<()> {
+    let mut header = entry_header(size);
+    builder.append_data(
+        &mut header,
+        format!("{FILES_PREFIX}{relative_path}"),
+        contents,
+    )?;
+    Ok(())
+}
+
+fn entry_header(size: u64) -> tar::Header {
+    let mut header = tar::Header::new_gnu();
+    header.set_entry_type(tar::EntryType::Regular);
+    header.set_size(size);
+    header.set_mode(0o644);
+    header.set_mtime(chrono::Utc::now().timestamp().max(0) as u64);
+    header
+}
+
+/// Canonical path of the project `session_id` belongs to
+fn session_project_path(db: &Database, session_id: &str) -> Result<PathBuf> {
+    let session = db
+        .get_session_by_id(session_id)?
+        .ok_or_else(|| Error::SessionNotFound(session_id.to_string()))?;
+    let project = db
+        .get_project(&session.project_id)?
+        .ok_or_else(|| Error::ProjectNotFound(session.project_id.clone()))?;
+    Path::new(&project.path)
+        .canonicalize()
+        .map_err(|_| Error::Other("Invalid or non-existent path".to_string()))
+}
+
+/// Register a snapshot exported by `export_snapshot` under `session_id`
+///
+/// The snapshot gets a new id but keeps its creation time and label. Every
+/// file path in the archive must pass `validate_restore_path` against the
+/// session's project, so a crafted archive can't later restore outside it.
+/// Large files are streamed to sidecar files as they are read; those are
+/// removed again if the import fails.
+pub fn import_snapshot(db: &Database, session_id: &str, archive_path: &Path) -> Result<Snapshot> {
+    let project_path = session_project_path(db, session_id)?;
+
+    let mut new_sidecars = Vec::new();
+    let result = read_and_insert_export(
+        db,
+        session_id,
+        archive_path,
+        &project_path,
+        &mut new_sidecars,
+    );
+    if result.is_err() && !new_sidecars.is_empty() {
+        if let Err(e) = sidecar::discard_sidecar_files(db, &new_sidecars) {
+            tracing::warn!("Failed to clean up sidecar files of a failed import: {}", e);
+        }
+    }
+    result
+}
+
+/// The body of `import_snapshot`; sidecar files it creates are pushed to `new_sidecars`
+fn read_and_insert_export(
+    db: &Database,
+    session_id: &str,
+    archive_path: &Path,
+    project_path: &Path,
+    new_sidecars: &mut Vec<String>,
+) -> Result<Snapshot> {
+    let mut manifest: Option<ExportManifest> = None;
+    let mut file_hashes: HashMap<String, String> = HashMap::new();
+    let mut sidecars: HashMap<String, String> = HashMap::new();
+    let mut blobs: HashMap<String, Vec<u8>> = HashMap::new();
+    let mut imported_bytes: u64 = 0;
+
+    let mut archive = tar::Archive::new(fs::File::open(archive_path)?);
+    for entry in archive.entries()? {
+        let mut entry = entry?;
+        let name = String::from_utf8(entry.path_bytes().into_owned())
+            .map_err(|_| Error::Other("Snapshot export has a non-UTF-8 entry name".to_string()))?;
+        match entry.header().entry_type() {
+            tar::EntryType::Directory => continue,
+            tar::EntryType::Regular => {}
+            other => {
+                return Err(Error::Other(format!(
+                    "Unsupported entry {name:?} ({other:?}) in snapshot export"
+                )))
+            }
+        }
+        let size = entry.size();
+
+        if name == MANIFEST_NAME {
+            if size > MAX_MANIFEST_BYTES {
+                return Err(Error::Other(
+                    "Snapshot export manifest is too large".to_string(),
+                ));
+            }
+            manifest = Some(serde_json::from_reader(&mut entry)?);
+            continue;
+        }
+
+        let Some(relative_path) = name.strip_prefix(FILES_PREFIX) else {
+            return Err(Error::Other(format!(
+                "Unexpected entry {name:?} in snapshot export"
+            )));
+        };
+        validate_restore_path(relative_path, project_path).map_err(|e| {
+            Error::Other(format!(
+                "Unsafe path '{relative_path}' in snapshot export: {e}"
+            ))
+        })?;
+        if file_hashes.contains_key(relative_path) || sidecars.contains_key(relative_path) {
+            return Err(Error::Other(format!(
+                "Duplicate entry {name:?} in snapshot export"
+            )));
+        }
+
+        if size > MAX_BACKUP_FILE_SIZE {
+            if size > sidecar::MAX_SIDECAR_FILE_SIZE {
+                return Err(Error::Other(format!(
+                    "'{relative_path}' in snapshot export is too large"
+                )));
+            }
+            let (hash, created) =
+                sidecar::store_sidecar_contents_tracked(&sidecar::sidecar_dir(db)?, &mut entry)?;
+            if created {
+                new_sidecars.push(hash.clone());
+            }
+            sidecars.insert(relative_path.to_string(), hash);
+        } else {
+            imported_bytes += size;
+            if imported_bytes > MAX_IMPORT_BYTES {
+                return Err(Error::Other("Snapshot export is too large".to_string()));
+            }
+            let mut contents = Vec::with_capacity(size as usize);
+            entry.read_to_end(&mut contents)?;
+            let hash = blob_hash(&contents);
+            file_hashes.insert(relative_path.to_string(), hash.clone());
+            blobs.entry(hash).or_insert(contents);
+        }
+    }
+
+    let manifest = manifest
+        .ok_or_else(|| Error::Other("Not a snapshot export: missing snapshot.json".to_string()))?;
+    if manifest.version > EXPORT_VERSION {
+        return Err(Error::Other(format!(
+            "Unsupported snapshot export version {}",
+            manifest.version
+        )));
+    }
+    let exported = manifest.snapshot;
+
+    let metadata_json = match exported.snapshot_type.as_str() {
+        "file_backup" => {
+            let mut metadata = file_backup_metadata(&exported)?;
+            metadata.version = FILE_BACKUP_METADATA_VERSION;
+            metadata.files = HashMap::new();
+            metadata.blobs = file_hashes;
+            metadata.sidecars = sidecars;
+            metadata.modes.retain(|path, _| {
+                metadata.blobs.contains_key(path) || metadata.sidecars.contains_key(path)
+            });
+            if let Some(path) = metadata
+                .symlinks
+                .iter()
+                .find(|(path, target)| !symlink_within_project(path, target, project_path))
+                .map(|(path, _)| path)
+            {
+                return Err(Error::Other(format!(
+                    "Unsafe symlink '{path}' in snapshot export"
+                )));
+            }
+            serde_json::to_string(&metadata)?
+        }
+        "git_ghost" => {
+            if !file_hashes.is_empty() || !sidecars.is_empty() {
+                return Err(Error::Other(
+                    "Git snapshot export must not contain files".to_string(),
+                ));
+            }
+            git_snapshot_ref(&exported)?;
+            exported.metadata_json.clone().unwrap_or_default()
+        }
+        other => return Err(Error::Other(format!("Unknown snapshot type: {other}"))),
+    };
+
+    let label = exported.label.clone();
+    let snapshot = Snapshot {
+        id: uuid::Uuid::new_v4().to_string(),
+        session_id: session_id.to_string(),
+        metadata_json: Some(metadata_json),
+        ..exported
+    }
+    .with_label(label.as_deref());
+    let blobs: Vec<(String, Vec<u8>)> = blobs.into_iter().collect();
+    db.insert_snapshot_with_blobs(&snapshot, &blobs)?;
+
+    tracing::info!(
+        "Imported snapshot {} for session {} from {:?}",
+        snapshot.id,
+        session_id,
+        archive_path
+    );
+    Ok(snapshot)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::{Project, SessionMetadata};
+    use crate::snapshots::create_file_backup_snapshot;
+
+    /// A database with session `s1` belonging to a project at `project`
+    fn test_db(project: &Path) -> (tempfile::TempDir, Database) {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let db = Database::new(&temp_dir.path().join("test.db")).unwrap();
+        let project = Project::new(&project.to_string_lossy());
+        db.insert_project(&project).unwrap();
+        db.upsert_session_metadata(&SessionMetadata::new("s1", &project.id))
+            .unwrap();
+        (temp_dir, db)
+    }
+
+    #[test]
+    fn test_export_import_file_backup() {
+        let project = tempfile::tempdir().unwrap();
+        fs::create_dir(project.path().join("src")).unwrap();
+        fs::write(project.path().join("src/main.rs"), "fn main() {}").unwrap();
+        fs::write(project.path().join("notes.txt"), "hello").unwrap();
+
+        let (source_dir, source_db) = test_db(project.path());
+        let snapshot = create_file_backup_snapshot(
+            &source_db,
+            "s1",
+            project.path(),
+            Some("before"),
+            &mut |_| {},
+        )
+        .unwrap();
+        let dest = source_dir.path().join("snapshot.tar");
+        assert_eq!(export_snapshot(&source_db, &snapshot.id, &dest).unwrap(), 2);
+
+        // Import into a separate database, as on another machine
+        let (_target_dir, target_db) = test_db(project.path());
+        let imported = import_snapshot(&target_db, "s1", &dest).unwrap();
+        assert_ne!(imported.id, snapshot.id);
+        assert_eq!(imported.created_at, snapshot.created_at);
+        assert_eq!(imported.label.as_deref(), Some("before"));
+
+        let original = file_backup_metadata(&snapshot).unwrap();
+        let restored =
+            file_backup_metadata(&target_db.get_snapshot(&imported.id).unwrap().unwrap()).unwrap();
+        assert_eq!(restored.blobs, original.blobs);
+        for (path, content) in restored.stored_files() {
+            let bytes = content.load(&target_db, &imported.id, path).unwrap();
+            assert_eq!(bytes, fs::read(project.path().join(path)).unwrap());
+        }
+    }
+
+    #[test]
+    fn test_import_rejects_paths_outside_project() {
+        let project = tempfile::tempdir().unwrap();
+        let (temp_dir, db) = test_db(project.path());
+        let archive_path = temp_dir.path().join("evil.tar");
+
+        let manifest = serde_json::to_vec(&ExportManifest {
+            version: EXPORT_VERSION,
+            exported_at: 0,
+            snapshot: Snapshot::new_file_backup("s1", r#"{"blobs":{},"description":""}"#),
+        })
+        .unwrap();
+        let mut builder = tar::Builder::new(fs::File::create(&archive_path).unwrap());
+        let mut header = entry_header(manifest.len() as u64);
+        builder
+            .append_data(&mut header, MANIFEST_NAME, manifest.as_slice())
+            .unwrap();
+        // `append_data` refuses `..`, so write the name into the header directly
+        let mut header = entry_header(4);
+        let name = b"files/../escaped.txt";
+        header.as_old_mut().name[..name.len()].copy_from_slice(name);
+        header.set_cksum();
+        builder.append(&header, &b"evil"[..]).unwrap();
+        builder.finish().unwrap();
+
+        assert!(import_snapshot(&db, "s1", &archive_path).is_err());
+        assert!(db.get_snapshots_for_session("s1").unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_failed_import_removes_its_sidecar_files() {
+        let project = tempfile::tempdir().unwrap();
+        let (temp_dir, db) = test_db(project.path());
+        let archive_path = temp_dir.path().join("future.tar");
+
+        // The large file is stored before the manifest turns out to be unsupported
+        let mut builder = tar::Builder::new(fs::File::create(&archive_path).unwrap());
+        let large = vec![b'x'; MAX_BACKUP_FILE_SIZE as usize + 1];
+        let mut header = entry_header(large.len() as u64);
+        builder
+            .append_data(&mut header, "files/large.bin", large.as_slice())
+            .unwrap();
+        let manifest = serde_json::to_vec(&ExportManifest {
+            version: EXPORT_VERSION + 1,
+            exported_at: 0,
+            snapshot: Snapshot::new_file_backup("s1", r#"{"blobs":{},"description":""}"#),
+        })
+        .unwrap();
+        let mut header = entry_header(manifest.len() as u64);
+        builder
+            .append_data(&mut header, MANIFEST_NAME, manifest.as_slice())
+            .unwrap();
+        builder.finish().unwrap();
+
+        let err = import_snapshot(&db, "s1", &archive_path).unwrap_err();
+        assert!(err.to_string().contains("version"), "{err}");
+        let sidecar_dir = sidecar::sidecar_dir(&db).unwrap();
+        assert_eq!(fs::read_dir(&sidecar_dir).unwrap().count(), 0);
+    }
+
+    #[test]
+    fn test_export_import_git_snapshot() {
+        let project = tempfile::tempdir().unwrap();
+        let (temp_dir, db) = test_db(project.path());
+        let snapshot = Snapshot::new_git_ghost("s1", "abc1234");
+        db.insert_snapshot(&snapshot).unwrap();
+        let dest = temp_dir.path().join("git.tar");
+
+        assert_eq!(export_snapshot(&db, &snapshot.id, &dest).unwrap(), 0);
+        let imported = import_snapshot(&db, "s1", &dest).unwrap();
+        assert_eq!(imported.snapshot_type, "git_ghost");
+        assert_eq!(imported.metadata_json, snapshot.metadata_json);
+
+        // Sessions without a known project have nowhere to validate paths against
+        assert!(matches!(
+            import_snapshot(&db, "unknown", &dest),
+            Err(Error::SessionNotFound(_))
+        ));
+    }
+}
@@ -0,0 +1,126 @@
+//! Guard against overlapping snapshot operations on one project
+//!
+//! Creating and reverting a snapshot both touch the project's files (and for
+//! git, its HEAD and stash), so two at once can leave the working tree in a
+//! mix of states. Each operation takes the project's lock first and fails
+//! fast if another already holds it, rather than queueing behind it.
+
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use crate::{Error, Result};
+
+/// Projects with a snapshot operation in progress, keyed by canonical path
+#[derive(Clone, Default)]
+pub struct SnapshotLocks {
+    held: Arc<parking_lot::Mutex<HashSet<PathBuf>>>,
+}
+
+impl SnapshotLocks {
+    /// Lock `project_path` for a snapshot operation until the guard is dropped
+    ///
+    /// Fails if another operation on the same project is still running.
+    pub fn try_lock(&self, project_path: &Path) -> Result<SnapshotLockGuard> {
+        let canonical_path = project_path
+            .canonicalize()
+            .map_err(|_| Error::Other("Invalid or non-existent path".to_string()))?;
+        if !self.held.lock().insert(canonical_path.clone()) {
+            return Err(Error::Other(
+                "Snapshot operation already in progress".to_string(),
+            ));
+        }
+        Ok(SnapshotLockGuard {
+            path: canonical_path,
+            locks: self.clone(),
+        })
+    }
+}
+
+/// A held project lock; released when dropped
+pub struct SnapshotLockGuard {
+    path: PathBuf,
+    locks: SnapshotLocks,
+}
+
+impl Drop for SnapshotLockGuard {
+    fn drop(&mut self) {
+        self.locks.held.lock().remove(&self.path);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::Database;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Barrier;
+
+    #[test]
+    fn test_lock_is_per_project_and_released_on_drop() {
+        let project = tempfile::tempdir().unwrap();
+        let other = tempfile::tempdir().unwrap();
+        let locks = SnapshotLocks::default();
+
+        let guard = locks.try_lock(project.path()).unwrap();
+        // The same directory through a different spelling is the same project
+        assert!(matches!(
+            locks.try_lock(&project.path().join(".")),
+            Err(Error::Other(msg)) if msg == "Snapshot operation already in progress"
+        ));
+        assert!(locks.try_lock(other.path()).is_ok());
+
+        drop(guard);
+        assert!(locks.try_lock(project.path()).is_ok());
+    }
+
+    #[test]
+    fn test_concurrent_creates_do_not_overlap() {
+        let project = tempfile::tempdir().unwrap();
+        std::fs::write(project.path().join("main.rs"), "fn main() {}").unwrap();
+        let db_dir = tempfile::tempdir().unwrap();
+        let db = Database::new(&db_dir.path().join("test.db")).unwrap();
+        let locks = SnapshotLocks::default();
+
+        let start = Barrier::new(2);
+        let active = AtomicUsize::new(0);
+        let max_active = AtomicUsize::new(0);
+        let outcomes: Vec<Result<()>> = std::thread::scope(|scope| {
+            let handles: Vec<_> = ["s1", "s2"]
+                .into_iter()
+                .map(|session_id| {
+                    let (db, locks, start, active, max_active) =
+                        (&db, &locks, &start, &active, &max_active);
+                    let project = project.path();
+                    scope.spawn(move || {
+                        start.wait();
+                        let _guard = locks.try_lock(project)?;
+                        let now = active.fetch_add(1, Ordering::SeqCst) + 1;
+                        max_active.fetch_max(now, Ordering::SeqCst);
+                        std::thread::sleep(std::time::Duration::from_millis(50));
+                        let created =
+                            crate::snapshots::create_snapshot(db, session_id, project, None);
+                        active.fetch_sub(1, Ordering::SeqCst);
+                        created.map(|_| ())
+                    })
+                })
+                .collect();
+            handles.into_iter().map(|h| h.join().unwrap()).collect()
+        });
+
+        assert_eq!(max_active.load(Ordering::SeqCst), 1);
+        let succeeded = outcomes.iter().filter(|o| o.is_ok()).count();
+        assert!(succeeded >= 1);
+        for outcome in outcomes.iter().filter_map(|o| o.as_ref().err()) {
+            assert!(
+                outcome.to_string().contains("already in progress"),
+                "{outcome}"
+            );
+        }
+        let created: usize = ["s1", "s2"]
+            .iter()
+            .map(|session_id| db.get_snapshots_for_session(session_id).unwrap().len())
+            .sum();
+        assert_eq!(created, succeeded);
+    }
+}
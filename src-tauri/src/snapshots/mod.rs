@@ -4,6 +4,23 @@
 //! - Git ghost commits for git repositories
 //! - File backups for non-git directories
 
+mod archive;
+mod export;
+mod lock;
+mod preview;
+mod refs;
+mod sidecar;
+
+pub use archive::{archive_snapshots, restore_snapshots};
+pub use export::{export_snapshot, import_snapshot};
+pub use lock::{SnapshotLockGuard, SnapshotLocks};
+pub use preview::{list_snapshot_files, SnapshotFileEntry};
+pub use refs::{
+    git_snapshot_refs_enabled, prune_all_snapshot_refs, prune_snapshot_refs,
+    GIT_SNAPSHOT_REFS_SETTING,
+};
+pub use sidecar::{backup_large_files_enabled, BACKUP_LARGE_FILES_SETTING};
+
 use std::collections::HashMap;
 use std::fs;
 use std::path::{Path, PathBuf};
@@ -11,51 +28,10 @@ use std::process::Command;
 
 use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
 use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
 
-use crate::database::{Database, Snapshot};
-use crate::{Error, Result};
-
-/// Path validation error types for detailed error reporting
-#[derive(Debug)]
-enum PathValidationError {
-    /// Path contains null bytes
-    NullByte,
-    /// Path contains parent directory traversal (..)
-    ParentTraversal,
-    /// Path is absolute (starts with / or \)
-    AbsolutePath,
-    /// Path escapes the project directory
-    DirectoryEscape,
-    /// Path is a symbolic link
-    SymbolicLink,
-    /// Path contains invalid characters
-    InvalidCharacters,
-}
-
-impl std::fmt::Display for PathValidationError {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        match self {
-            PathValidationError::NullByte => {
-                write!(f, "Path contains null byte character")
-            }
-            PathValidationError::ParentTraversal => {
-                write!(f, "Path contains parent directory traversal (..)")
-            }
-            PathValidationError::AbsolutePath => {
-                write!(f, "Path must be relative, not absolute")
-            }
-            PathValidationError::DirectoryEscape => {
-                write!(f, "Path would escape the project directory")
-            }
-            PathValidationError::SymbolicLink => {
-                write!(f, "Symbolic links are not allowed in restore paths")
-            }
-            PathValidationError::InvalidCharacters => {
-                write!(f, "Path contains invalid characters")
-            }
-        }
-    }
-}
+use crate::database::{Database, ProjectSettings, Snapshot};
+use crate::{Error, PathValidationError, Result};
 
 /// Validated restore path that is guaranteed to be safe
 /// This struct can only be created through validate_restore_path()
@@ -274,178 +250,845 @@ fn validate_commit_sha(sha: &str) -> Result<()> {
     Ok(())
 }
 
+/// Current `FileBackupMetadata::version`; older snapshots are upgraded by
+/// `migrate_snapshot_metadata`
+const FILE_BACKUP_METADATA_VERSION: u32 = 1;
+
 /// Metadata for file backup snapshots
+///
+/// Contents live zstd-compressed in the `snapshot_blobs` table and are
+/// referenced by hash, so unchanged files are stored once across snapshots. Older snapshots embed
+/// base64 contents in `files` instead; both are restored. Large files are
+/// either in `sidecars` (see the `sidecar` module) or listed in `skipped_large`.
 #[derive(Debug, Serialize, Deserialize)]
 struct FileBackupMetadata {
-    /// Map of relative path -> base64-encoded file contents
+    /// Shape of this metadata; 0 (absent) for snapshots written before it was versioned
+    #[serde(default)]
+    version: u32,
+    /// Map of relative path -> base64-encoded file contents (legacy snapshots)
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
     files: HashMap<String, String>,
+    /// Map of relative path -> SHA-256 hash of the contents in `snapshot_blobs`
+    #[serde(default)]
+    blobs: HashMap<String, String>,
+    /// Map of relative path -> SHA-256 hash of a sidecar file, for files too large for the database
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    sidecars: HashMap<String, String>,
+    /// Files too large to back up, which a revert leaves as they are
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    skipped_large: Vec<String>,
+    /// Files whose paths aren't valid UTF-8 (decoded lossily), which a revert leaves as they are
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    skipped_non_utf8: Vec<String>,
+    /// Map of relative path -> Unix permission bits; empty on Windows and in older snapshots
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    modes: HashMap<String, u32>,
+    /// Map of relative path -> target of a symlink inside the project, recreated as a link on
+    /// revert
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    symlinks: HashMap<String, String>,
     /// Description of what was backed up
     description: String,
 }
 
+/// Where a file backup keeps one file's contents
+enum StoredContent<'a> {
+    /// Embedded base64, as written by older versions
+    Base64(&'a str),
+    /// SHA-256 hash of a row in `snapshot_blobs`
+    Blob(&'a str),
+    /// SHA-256 hash naming a file in the sidecar directory
+    Sidecar(&'a str),
+}
+
+impl StoredContent<'_> {
+    /// Read the stored bytes of `relative_path`
+    fn load(&self, db: &Database, snapshot_id: &str, relative_path: &str) -> Result<Vec<u8>> {
+        match self {
+            StoredContent::Base64(content) => BASE64.decode(content).map_err(|e| {
+                Error::Other(format!(
+                    "Failed to decode file content for '{relative_path}': {e}"
+                ))
+            }),
+            StoredContent::Blob(hash) => db.get_snapshot_blob(hash)?.ok_or_else(|| {
+                Error::Other(format!(
+                    "Missing blob {hash} for '{relative_path}' in snapshot {snapshot_id}"
+                ))
+            }),
+            StoredContent::Sidecar(hash) => {
+                let path = Self::existing_sidecar(db, hash, snapshot_id, relative_path)?;
+                Ok(fs::read(path)?)
+            }
+        }
+    }
+
+    /// Path of a sidecar file that must still exist for the snapshot to be restored
+    fn existing_sidecar(
+        db: &Database,
+        hash: &str,
+        snapshot_id: &str,
+        relative_path: &str,
+    ) -> Result<PathBuf> {
+        let path = sidecar::sidecar_path(&sidecar::sidecar_dir(db)?, hash)?;
+        if !path.is_file() {
+            return Err(Error::Other(format!(
+                "Missing sidecar file {hash} for '{relative_path}' in snapshot {snapshot_id}"
+            )));
+        }
+        Ok(path)
+    }
+}
+
+impl FileBackupMetadata {
+    /// Every stored file, legacy inline contents first
+    fn stored_files(&self) -> impl Iterator<Item = (&String, StoredContent<'_>)> {
+        let legacy = self
+            .files
+            .iter()
+            .map(|(path, content)| (path, StoredContent::Base64(content)));
+        let blobs = self
+            .blobs
+            .iter()
+            .map(|(path, hash)| (path, StoredContent::Blob(hash)));
+        legacy.chain(blobs)
+    }
+
+    /// Large files kept in sidecar files
+    fn sidecar_files(&self) -> impl Iterator<Item = (&String, StoredContent<'_>)> {
+        self.sidecars
+            .iter()
+            .map(|(path, hash)| (path, StoredContent::Sidecar(hash)))
+    }
+}
+
+/// Upgrade a file backup's metadata to `FILE_BACKUP_METADATA_VERSION` and save it
+///
+/// Version 0 backups may embed file contents as base64; those move into
+/// `snapshot_blobs` like every newer backup's. Returns the upgraded snapshot,
+/// or `None` if it was already current (or isn't a file backup).
+pub fn migrate_snapshot_metadata(db: &Database, snapshot: &Snapshot) -> Result<Option<Snapshot>> {
+    if snapshot.snapshot_type != "file_backup" {
+        return Ok(None);
+    }
+    let mut metadata = file_backup_metadata(snapshot)?;
+    if metadata.version >= FILE_BACKUP_METADATA_VERSION {
+        return Ok(None);
+    }
+
+    let mut blobs: HashMap<String, Vec<u8>> = HashMap::new();
+    for (path, content) in std::mem::take(&mut metadata.files) {
+        // Legacy contents are restored before blobs, so a blob for the same path wins
+        if metadata.blobs.contains_key(&path) {
+            continue;
+        }
+        let contents = StoredContent::Base64(&content).load(db, &snapshot.id, &path)?;
+        let hash = blob_hash(&contents);
+        metadata.blobs.insert(path, hash.clone());
+        blobs.entry(hash).or_insert(contents);
+    }
+    metadata.version = FILE_BACKUP_METADATA_VERSION;
+
+    let metadata_json = serde_json::to_string(&metadata)?;
+    let blobs: Vec<(String, Vec<u8>)> = blobs.into_iter().collect();
+    db.update_snapshot_metadata(&snapshot.id, &metadata_json, &blobs)?;
+    tracing::info!(
+        "Migrated snapshot {} metadata to version {}",
+        snapshot.id,
+        FILE_BACKUP_METADATA_VERSION
+    );
+
+    Ok(Some(Snapshot {
+        metadata_json: Some(metadata_json),
+        ..snapshot.clone()
+    }))
+}
+
+/// Migrate every file backup whose metadata predates the current version
+///
+/// Snapshots that fail to migrate are logged and left as they are, since
+/// reverting still understands every older version.
+pub fn migrate_legacy_snapshots(db: &Database) -> Result<usize> {
+    let mut migrated = 0;
+    for snapshot in db.get_file_backups_before_version(FILE_BACKUP_METADATA_VERSION)? {
+        match migrate_snapshot_metadata(db, &snapshot) {
+            Ok(Some(_)) => migrated += 1,
+            Ok(None) => {}
+            Err(e) => tracing::warn!("Failed to migrate snapshot {} metadata: {}", snapshot.id, e),
+        }
+    }
+    Ok(migrated)
+}
+
+/// Parse the metadata of a file backup snapshot
+fn file_backup_metadata(snapshot: &Snapshot) -> Result<FileBackupMetadata> {
+    let metadata_str = snapshot
+        .metadata_json
+        .as_ref()
+        .ok_or_else(|| Error::Other("Missing metadata in file backup snapshot".to_string()))?;
+    serde_json::from_str(metadata_str)
+        .map_err(|e| Error::Other(format!("Failed to parse file backup metadata: {e}")))
+}
+
+/// The commit a git snapshot points at: a stash ref or a validated sha
+fn git_snapshot_ref(snapshot: &Snapshot) -> Result<String> {
+    let metadata: serde_json::Value = snapshot
+        .metadata_json
+        .as_ref()
+        .and_then(|m| serde_json::from_str(m).ok())
+        .ok_or_else(|| Error::Other("Invalid snapshot metadata".to_string()))?;
+    let commit_ref = metadata["commit_sha"]
+        .as_str()
+        .ok_or_else(|| Error::Other("Missing commit_sha in snapshot".to_string()))?;
+
+    // Security: Only a stash ref or a plain sha may reach git
+    if let Some(index) = commit_ref
+        .strip_prefix("stash@{")
+        .and_then(|r| r.strip_suffix('}'))
+    {
+        if index.is_empty() || !index.bytes().all(|b| b.is_ascii_digit()) {
+            return Err(Error::Other("Invalid stash reference format".to_string()));
+        }
+    } else {
+        validate_commit_sha(commit_ref)?;
+    }
+    Ok(commit_ref.to_string())
+}
+
+/// Permission bits of a file, where the platform has them
+#[cfg(unix)]
+fn file_mode(metadata: &fs::Metadata) -> Option<u32> {
+    use std::os::unix::fs::PermissionsExt;
+    Some(metadata.permissions().mode() & 0o7777)
+}
+
+#[cfg(not(unix))]
+fn file_mode(_metadata: &fs::Metadata) -> Option<u32> {
+    None
+}
+
+/// Apply permission bits captured by `file_mode`
+#[cfg(unix)]
+fn set_file_mode(path: &Path, mode: u32) -> std::io::Result<()> {
+    use std::os::unix::fs::PermissionsExt;
+    fs::set_permissions(path, fs::Permissions::from_mode(mode))
+}
+
+#[cfg(not(unix))]
+fn set_file_mode(_path: &Path, _mode: u32) -> std::io::Result<()> {
+    Ok(())
+}
+
+/// Content address of a file backup blob: hex SHA-256 of the contents
+pub(crate) fn blob_hash(contents: &[u8]) -> String {
+    format!("{:x}", Sha256::digest(contents))
+}
+
+/// Emitted as `snapshot-progress` while a file backup is created or reverted
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SnapshotProgress {
+    pub snapshot_id: String,
+    /// Files handled so far
+    pub processed: usize,
+    pub total: usize,
+}
+
+/// Files handled between progress reports
+const SNAPSHOT_PROGRESS_INTERVAL: usize = 100;
+
+/// Throttles progress callbacks to every `SNAPSHOT_PROGRESS_INTERVAL` files, plus the start and end
+struct ProgressReporter<'a> {
+    snapshot_id: &'a str,
+    total: usize,
+    last_reported: Option<usize>,
+    on_progress: &'a mut dyn FnMut(SnapshotProgress),
+}
+
+impl<'a> ProgressReporter<'a> {
+    fn new(
+        snapshot_id: &'a str,
+        total: usize,
+        on_progress: &'a mut dyn FnMut(SnapshotProgress),
+    ) -> Self {
+        Self {
+            snapshot_id,
+            total,
+            last_reported: None,
+            on_progress,
+        }
+    }
+
+    fn report(&mut self, processed: usize) {
+        let due = processed.is_multiple_of(SNAPSHOT_PROGRESS_INTERVAL) || processed == self.total;
+        if due && self.last_reported != Some(processed) {
+            self.last_reported = Some(processed);
+            (self.on_progress)(SnapshotProgress {
+                snapshot_id: self.snapshot_id.to_string(),
+                processed,
+                total: self.total,
+            });
+        }
+    }
+}
+
+/// How line endings are handled when restoring file backups
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum LineEndingMode {
+    /// Write the stored bytes exactly as captured
+    #[default]
+    Preserve,
+    /// Convert text files to the platform's line endings (CRLF on Windows, LF elsewhere)
+    Native,
+}
+
+/// How a git snapshot is restored
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum GitRevertMode {
+    /// Hard-reset the current branch to the snapshot, discarding later work
+    #[default]
+    Reset,
+    /// Check out a new branch at the snapshot, leaving the current branch as it was
+    Branch,
+}
+
+/// Outcome of reverting to a snapshot
+#[derive(Debug, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RevertReport {
+    /// Number of files written (file backups only)
+    pub restored: usize,
+    /// Paths skipped because they failed security validation
+    pub skipped: Vec<String>,
+    /// Paths whose stored content mixes CRLF and LF line endings
+    pub mixed_line_endings: Vec<String>,
+    /// Files the snapshot didn't back up (too large, or a non-UTF-8 path), left in their current
+    /// state
+    pub not_backed_up: Vec<String>,
+    /// Branch created for the snapshot, when a git snapshot was reverted in `GitRevertMode::Branch`
+    pub branch: Option<String>,
+    /// Files left with merge conflicts when re-applying a git snapshot's stash
+    ///
+    /// The working tree is left conflicted for the user to resolve.
+    pub conflicts: Vec<String>,
+}
+
+/// Whether content looks like text (no NUL bytes and valid UTF-8)
+fn is_text_content(bytes: &[u8]) -> bool {
+    !bytes.contains(&0) && std::str::from_utf8(bytes).is_ok()
+}
+
+/// Whether text content contains both CRLF and bare LF line endings
+fn has_mixed_line_endings(bytes: &[u8]) -> bool {
+    let mut crlf = false;
+    let mut lf = false;
+    for (i, &b) in bytes.iter().enumerate() {
+        if b == b'\n' {
+            if i > 0 && bytes[i - 1] == b'\r' {
+                crlf = true;
+            } else {
+                lf = true;
+            }
+            if crlf && lf {
+                return true;
+            }
+        }
+    }
+    false
+}
+
+/// Rewrite every line ending as CRLF (`crlf`) or LF
+fn convert_line_endings(bytes: &[u8], crlf: bool) -> Vec<u8> {
+    let mut out = Vec::with_capacity(bytes.len());
+    for (i, &b) in bytes.iter().enumerate() {
+        match b {
+            // Drop the CR of a CRLF pair; it is re-added below if wanted
+            b'\r' if bytes.get(i + 1) == Some(&b'\n') => {}
+            b'\n' => {
+                if crlf {
+                    out.push(b'\r');
+                }
+                out.push(b'\n');
+            }
+            _ => out.push(b),
+        }
+    }
+    out
+}
+
 /// Check if a path is a git repository
 pub fn is_git_repo(path: &Path) -> bool {
     path.join(".git").exists()
 }
 
 /// Create a snapshot before applying changes
-pub fn create_snapshot(db: &Database, session_id: &str, project_path: &Path) -> Result<Snapshot> {
+///
+/// `label` is an optional user-facing name shown alongside the timestamp.
+pub fn create_snapshot(
+    db: &Database,
+    session_id: &str,
+    project_path: &Path,
+    label: Option<&str>,
+) -> Result<Snapshot> {
+    create_snapshot_with_progress(db, session_id, project_path, label, |_| {})
+}
+
+/// Create a snapshot, reporting progress as files are backed up
+///
+/// Only file backups report progress; git snapshots are a single git call.
+pub fn create_snapshot_with_progress<F>(
+    db: &Database,
+    session_id: &str,
+    project_path: &Path,
+    label: Option<&str>,
+    mut on_progress: F,
+) -> Result<Snapshot>
+where
+    F: FnMut(SnapshotProgress),
+{
     // Security: Canonicalize path to prevent symlink attacks and traversal
     let canonical_path = project_path
         .canonicalize()
         .map_err(|_| Error::Other("Invalid or non-existent path".to_string()))?;
 
     if is_git_repo(&canonical_path) {
-        create_git_snapshot(db, session_id, &canonical_path)
+        create_git_snapshot(db, session_id, &canonical_path, label)
     } else {
-        create_file_backup_snapshot(db, session_id, &canonical_path)
+        create_file_backup_snapshot(db, session_id, &canonical_path, label, &mut on_progress)
     }
 }
 
-/// Collect all files in a directory (excluding hidden files and common ignore patterns)
-fn collect_project_files(project_path: &Path) -> Result<Vec<std::path::PathBuf>> {
-    let mut files = Vec::new();
+/// Names skipped when the project has no ignore file of its own
+const DEFAULT_EXCLUDES: &[&str] = &["node_modules", "target", "dist", "build", "__pycache__"];
+
+/// Ignore files at the project root whose rules replace `DEFAULT_EXCLUDES`
+const IGNORE_FILES: &[&str] = &[".gitignore", ".ignore"];
+
+/// Files found in a project directory
+#[derive(Debug, Default)]
+struct ProjectFiles {
+    /// Each file's absolute path and its path relative to the project root
+    files: Vec<(PathBuf, String)>,
+    /// Files skipped because their relative path isn't valid UTF-8, decoded lossily for display
+    non_utf8: Vec<String>,
+    /// Each symlink's absolute path and its path relative to the project root; links aren't
+    /// followed
+    symlinks: Vec<(PathBuf, String)>,
+}
 
-    fn visit_dir(dir: &Path, files: &mut Vec<std::path::PathBuf>) -> Result<()> {
-        if !dir.is_dir() {
-            return Ok(());
+/// Whether a symlink at `relative_path` pointing to `target` resolves inside the project
+///
+/// The target is resolved lexically against the link's directory. A `..`
+/// after a named component is refused, since the kernel would apply it to
+/// wherever that component leads rather than to its lexical parent.
+fn symlink_within_project(relative_path: &str, target: &str, project_path: &Path) -> bool {
+    use std::path::Component;
+
+    let link = Path::new(relative_path);
+    if relative_path.contains('\0') || !link.components().all(|c| matches!(c, Component::Normal(_)))
+    {
+        return false;
+    }
+    let mut named = false;
+    for component in Path::new(target).components() {
+        match component {
+            Component::Normal(_) => named = true,
+            Component::ParentDir if named => return false,
+            _ => {}
         }
+    }
+    let Some(link_dir) = project_path.join(link).parent().map(Path::to_path_buf) else {
+        return false;
+    };
+    normalize_path_components(&link_dir.join(target))
+        .is_ok_and(|resolved| resolved.starts_with(project_path))
+}
 
-        let entries = fs::read_dir(dir).map_err(|e| Error::Other(format!("Failed to read dir: {e}")))?;
-
-        for entry in entries {
-            let entry = entry.map_err(|e| Error::Other(format!("Failed to read entry: {e}")))?;
-            let path = entry.path();
-            let file_name = entry.file_name();
-            let name = file_name.to_string_lossy();
-
-            // Skip hidden files, node_modules, target, etc.
-            if name.starts_with('.')
-                || name == "node_modules"
-                || name == "target"
-                || name == "dist"
-                || name == "build"
-                || name == "__pycache__"
-                || name == ".git"
-            {
+/// Read the targets of a project's symlinks, dropping links that lead outside it
+fn backup_symlinks(symlinks: &[(PathBuf, String)], project_path: &Path) -> HashMap<String, String> {
+    let mut backed_up = HashMap::new();
+    for (link_path, relative_path) in symlinks {
+        let target = match fs::read_link(link_path) {
+            Ok(target) => target,
+            Err(e) => {
+                tracing::warn!("Skipping unreadable symlink {}: {}", relative_path, e);
                 continue;
             }
+        };
+        let Some(target) = target.to_str() else {
+            tracing::warn!("Skipping symlink {} with a non-UTF-8 target", relative_path);
+            continue;
+        };
+        if !symlink_within_project(relative_path, target, project_path) {
+            tracing::warn!(
+                "Skipping symlink {} pointing outside the project: {}",
+                relative_path,
+                target
+            );
+            continue;
+        }
+        backed_up.insert(relative_path.clone(), target.to_string());
+    }
+    backed_up
+}
 
-            if path.is_dir() {
-                visit_dir(&path, files)?;
-            } else if path.is_file() {
-                files.push(path);
+/// Collect all files in a directory, excluding hidden files and ignored paths
+///
+/// `.gitignore` and `.ignore` rules are honored even outside a git
+/// repository. If the project root has neither, `DEFAULT_EXCLUDES` is used.
+/// Paths that aren't valid UTF-8 can't be stored in snapshot metadata without
+/// mangling them, so they are reported in `non_utf8` instead of collected.
+fn collect_project_files(project_path: &Path) -> Result<ProjectFiles> {
+    let has_ignore_file = IGNORE_FILES
+        .iter()
+        .any(|name| project_path.join(name).is_file());
+
+    let mut walker = ignore::WalkBuilder::new(project_path);
+    walker
+        .hidden(true)
+        .parents(false)
+        .git_global(false)
+        .git_exclude(false)
+        .require_git(false)
+        .follow_links(false);
+    if !has_ignore_file {
+        walker.filter_entry(|entry| {
+            entry.depth() == 0
+                || !DEFAULT_EXCLUDES.contains(&entry.file_name().to_string_lossy().as_ref())
+        });
+    }
+
+    let mut collected = ProjectFiles::default();
+    for entry in walker.build() {
+        match entry {
+            Ok(entry) if entry.path_is_symlink() || entry.path().is_file() => {
+                let Ok(relative) = entry.path().strip_prefix(project_path) else {
+                    continue;
+                };
+                match relative.to_str() {
+                    Some(relative) => {
+                        let relative = relative.to_string();
+                        if entry.path_is_symlink() {
+                            collected.symlinks.push((entry.into_path(), relative));
+                        } else {
+                            collected.files.push((entry.into_path(), relative));
+                        }
+                    }
+                    None => {
+                        let lossy = relative.to_string_lossy().into_owned();
+                        tracing::warn!("Skipping file with a non-UTF-8 path: {}", lossy);
+                        collected.non_utf8.push(lossy);
+                    }
+                }
             }
+            Ok(_) => {}
+            Err(e) => tracing::warn!(
+                "Skipping unreadable path while collecting project files: {}",
+                e
+            ),
         }
-        Ok(())
     }
+    collected.non_utf8.sort();
+    Ok(collected)
+}
 
-    visit_dir(project_path, &mut files)?;
-    Ok(files)
+/// Files in a project that file backups skip because their paths aren't valid UTF-8
+///
+/// The paths are decoded lossily, so they are for display only.
+pub fn find_non_utf8_paths(project_path: &Path) -> Result<Vec<String>> {
+    let canonical_path = project_path
+        .canonicalize()
+        .map_err(|_| Error::Other("Invalid or non-existent path".to_string()))?;
+    Ok(collect_project_files(&canonical_path)?.non_utf8)
+}
+
+/// Files larger than this are left out of file backups
+const MAX_BACKUP_FILE_SIZE: u64 = 1_000_000;
+
+/// Rough file backup throughput used for estimates: bytes read, hashed and compressed per
+/// millisecond
+const ESTIMATE_BYTES_PER_MS: u64 = 40_000;
+
+/// Rough per-file overhead used for estimates, in files per millisecond
+const ESTIMATE_FILES_PER_MS: u64 = 20;
+
+/// Expected size and duration of a file backup snapshot
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SnapshotEstimate {
+    /// Files that would be backed up
+    pub file_count: usize,
+    /// Their combined size
+    pub total_bytes: u64,
+    /// Rough time to create the snapshot, at least 1ms
+    pub estimated_ms: u64,
+}
+
+/// Estimate the cost of a file backup of `project_path` without creating it
+///
+/// Counts the files a backup would store in the database (same excludes,
+/// under the size cap). The time is a rough figure from total size and file
+/// count; git repositories are snapshotted with git and are usually faster.
+pub fn estimate_snapshot_cost(project_path: &Path) -> Result<SnapshotEstimate> {
+    let canonical_path = project_path
+        .canonicalize()
+        .map_err(|_| Error::Other("Invalid or non-existent path".to_string()))?;
+
+    let mut file_count = 0;
+    let mut total_bytes = 0;
+    for (path, _) in collect_project_files(&canonical_path)?.files {
+        let Ok(metadata) = fs::metadata(&path) else {
+            continue;
+        };
+        if metadata.len() <= MAX_BACKUP_FILE_SIZE {
+            file_count += 1;
+            total_bytes += metadata.len();
+        }
+    }
+
+    let estimated_ms =
+        total_bytes / ESTIMATE_BYTES_PER_MS + file_count as u64 / ESTIMATE_FILES_PER_MS;
+    Ok(SnapshotEstimate {
+        file_count,
+        total_bytes,
+        estimated_ms: estimated_ms.max(1),
+    })
 }
 
 /// Create a file backup snapshot for non-git directories
-fn create_file_backup_snapshot(db: &Database, session_id: &str, project_path: &Path) -> Result<Snapshot> {
-    let files = collect_project_files(project_path)?;
+fn create_file_backup_snapshot(
+    db: &Database,
+    session_id: &str,
+    project_path: &Path,
+    label: Option<&str>,
+    on_progress: &mut dyn FnMut(SnapshotProgress),
+) -> Result<Snapshot> {
+    let ProjectFiles {
+        files,
+        non_utf8,
+        symlinks,
+    } = collect_project_files(project_path)?;
+
+    // The id is chosen up front so progress events can name the snapshot
+    let snapshot_id = uuid::Uuid::new_v4().to_string();
+    let mut progress = ProgressReporter::new(&snapshot_id, files.len(), on_progress);
+
+    let backup_large_files = sidecar::backup_large_files_enabled(db)?;
+    let sidecar_dir = if backup_large_files {
+        Some(sidecar::sidecar_dir(db)?)
+    } else {
+        None
+    };
 
-    let mut backup_files: HashMap<String, String> = HashMap::new();
+    let mut file_hashes: HashMap<String, String> = HashMap::new();
+    let mut sidecars: HashMap<String, String> = HashMap::new();
+    let mut skipped_large: Vec<String> = Vec::new();
+    let mut modes: HashMap<String, u32> = HashMap::new();
+    let mut blobs: HashMap<String, Vec<u8>> = HashMap::new();
 
-    for file_path in &files {
-        // Only backup small files (< 1MB)
-        if let Ok(metadata) = fs::metadata(file_path) {
-            if metadata.len() > 1_000_000 {
-                continue;
+    for (index, (file_path, relative_path)) in files.iter().enumerate() {
+        progress.report(index);
+        let Ok(file_metadata) = fs::metadata(file_path) else {
+            continue;
+        };
+        let relative_path = relative_path.clone();
+
+        // Small files (< 1MB) go in the database; larger ones in sidecar files if enabled
+        if file_metadata.len() > MAX_BACKUP_FILE_SIZE {
+            let stored = match &sidecar_dir {
+                Some(dir) if file_metadata.len() <= sidecar::MAX_SIDECAR_FILE_SIZE => {
+                    match sidecar::store_sidecar_file(dir, file_path) {
+                        Ok(hash) => Some(hash),
+                        Err(e) => {
+                            tracing::warn!("Failed to back up large file {}: {}", relative_path, e);
+                            None
+                        }
+                    }
+                }
+                _ => None,
+            };
+            match stored {
+                Some(hash) => {
+                    sidecars.insert(relative_path.clone(), hash);
+                }
+                None => {
+                    skipped_large.push(relative_path);
+                    continue;
+                }
             }
+        } else if let Ok(contents) = fs::read(file_path) {
+            let hash = blob_hash(&contents);
+            file_hashes.insert(relative_path.clone(), hash.clone());
+            blobs.entry(hash).or_insert(contents);
+        } else {
+            continue;
         }
 
-        if let Ok(contents) = fs::read(file_path) {
-            let relative_path = file_path
-                .strip_prefix(project_path)
-                .map_err(|e| Error::Other(format!("Failed to get relative path: {e}")))?;
-
-            backup_files.insert(
-                relative_path.to_string_lossy().to_string(),
-                BASE64.encode(&contents),
-            );
+        if let Some(mode) = file_mode(&file_metadata) {
+            modes.insert(relative_path, mode);
         }
     }
 
+    progress.report(files.len());
+    skipped_large.sort();
+    let symlinks = backup_symlinks(&symlinks, project_path);
+
+    let file_count = file_hashes.len() + sidecars.len();
     let metadata = FileBackupMetadata {
-        files: backup_files.clone(),
-        description: format!("Backup of {} files", backup_files.len()),
+        version: FILE_BACKUP_METADATA_VERSION,
+        files: HashMap::new(),
+        blobs: file_hashes,
+        sidecars,
+        skipped_large,
+        skipped_non_utf8: non_utf8,
+        modes,
+        symlinks,
+        description: format!("Backup of {file_count} files"),
     };
 
     let metadata_json = serde_json::to_string(&metadata)
         .map_err(|e| Error::Other(format!("Failed to serialize metadata: {e}")))?;
 
-    let snapshot = Snapshot::new_file_backup(session_id, &metadata_json);
-    db.insert_snapshot(&snapshot)?;
-
-    // Cleanup: Keep only 10 most recent snapshots per session
-    match db.cleanup_old_snapshots(session_id, 10) {
-        Ok(count) => {
-            if count > 0 {
-                tracing::info!("Cleaned up {} old snapshots for session {}", count, session_id);
+    let snapshot = Snapshot {
+        id: snapshot_id.clone(),
+        ..Snapshot::new_file_backup(session_id, &metadata_json).with_label(label)
+    };
+    let blobs: Vec<(String, Vec<u8>)> = blobs.into_iter().collect();
+    db.insert_snapshot_with_blobs(&snapshot, &blobs)?;
+
+    cleanup_session_snapshots(db, session_id);
+    if backup_large_files {
+        match sidecar::prune_sidecar_files(db) {
+            Ok(count) if count > 0 => {
+                tracing::info!("Removed {} unreferenced sidecar files", count)
             }
-        }
-        Err(e) => {
-            tracing::warn!("Failed to cleanup old snapshots: {}", e);
+            Ok(_) => {}
+            Err(e) => tracing::warn!("Failed to prune sidecar files: {}", e),
         }
     }
 
     tracing::info!(
-        "Created file backup snapshot: {} ({} files)",
+        "Created file backup snapshot: {} ({} files, {} distinct)",
         snapshot.id,
-        backup_files.len()
+        file_count,
+        blobs.len()
     );
 
     Ok(snapshot)
 }
 
+/// Snapshots kept per session, from the settings of the project owning `session_id`
+///
+/// Sessions without a known project use `ProjectSettings::DEFAULT_SNAPSHOT_RETENTION`.
+fn snapshot_retention(db: &Database, session_id: &str) -> Result<usize> {
+    let project = match db.get_session_by_id(session_id)? {
+        Some(metadata) => db.get_project(&metadata.project_id)?,
+        None => None,
+    };
+    Ok(
+        project.map_or(ProjectSettings::DEFAULT_SNAPSHOT_RETENTION, |p| {
+            p.settings().effective_snapshot_retention()
+        }),
+    )
+}
+
+/// Delete a session's oldest snapshots beyond its project's retention count
+///
+/// Best effort: failures are logged, since the new snapshot is already saved.
+fn cleanup_session_snapshots(db: &Database, session_id: &str) {
+    let keep = match snapshot_retention(db, session_id) {
+        Ok(0) => return,
+        Ok(keep) => keep,
+        Err(e) => {
+            tracing::warn!(
+                "Failed to load snapshot retention for session {}: {}",
+                session_id,
+                e
+            );
+            ProjectSettings::DEFAULT_SNAPSHOT_RETENTION
+        }
+    };
+    match db.cleanup_old_snapshots(session_id, keep) {
+        Ok(count) => {
+            if count > 0 {
+                tracing::info!(
+                    "Cleaned up {} old snapshots for session {}",
+                    count,
+                    session_id
+                );
+            }
+        }
+        Err(e) => {
+            tracing::warn!("Failed to cleanup old snapshots: {}", e);
+        }
+    }
+}
+
 /// Create a git ghost commit snapshot
-fn create_git_snapshot(db: &Database, session_id: &str, project_path: &Path) -> Result<Snapshot> {
-    // Stash any uncommitted changes
+///
+/// Uncommitted changes are captured with `git stash create`, which writes a
+/// stash commit object without touching the working tree or the stash list,
+/// so later stashes can't shift what the snapshot points at. Untracked files
+/// aren't included. With no changes the snapshot is just HEAD.
+fn create_git_snapshot(
+    db: &Database,
+    session_id: &str,
+    project_path: &Path,
+    label: Option<&str>,
+) -> Result<Snapshot> {
     let stash_output = Command::new("git")
-        .args(["stash", "push", "-u", "-m", "codex-desktop-snapshot"])
+        .args(["stash", "create", "codex-desktop-snapshot"])
         .current_dir(project_path)
         .output()
-        .map_err(|e| Error::Git(format!("Failed to run git stash: {e}")))?;
-
-    let stash_created = String::from_utf8_lossy(&stash_output.stdout)
-        .contains("Saved working directory");
-
-    // Get the current HEAD or stash ref
-    let ref_name = if stash_created {
-        // Get the stash reference
-        let stash_list = Command::new("git")
-            .args(["stash", "list", "-1"])
-            .current_dir(project_path)
-            .output()
-            .map_err(|e| Error::Git(format!("Failed to get stash list: {e}")))?;
+        .map_err(|e| Error::Git(format!("Failed to run git stash create: {e}")))?;
+
+    if !stash_output.status.success() {
+        let stderr = String::from_utf8_lossy(&stash_output.stderr);
+        return Err(Error::Git(format!(
+            "Failed to create stash commit: {}",
+            stderr.trim()
+        )));
+    }
 
-        let stash_info = String::from_utf8_lossy(&stash_list.stdout);
-        if stash_info.contains("codex-desktop-snapshot") {
-            "stash@{0}".to_string()
-        } else {
-            get_current_head(project_path)?
-        }
+    let stash_sha = String::from_utf8_lossy(&stash_output.stdout)
+        .trim()
+        .to_string();
+    let (snapshot, ref_name) = if stash_sha.is_empty() {
+        let head = get_current_head(project_path)?;
+        (
+            Snapshot::new_git_ghost(session_id, &head).with_label(label),
+            head,
+        )
     } else {
-        get_current_head(project_path)?
+        validate_commit_sha(&stash_sha)?;
+        (
+            Snapshot::new_git_stash(session_id, &stash_sha).with_label(label),
+            stash_sha,
+        )
     };
-
-    let snapshot = Snapshot::new_git_ghost(session_id, &ref_name);
     db.insert_snapshot(&snapshot)?;
 
-    // Cleanup: Keep only 10 most recent snapshots per session
-    match db.cleanup_old_snapshots(session_id, 10) {
-        Ok(count) => {
-            if count > 0 {
-                tracing::info!("Cleaned up {} old snapshots for session {}", count, session_id);
+    // The ref is a convenience for git tooling, so failing to write it doesn't fail the snapshot
+    match refs::git_snapshot_refs_enabled(db) {
+        Ok(true) => {
+            if let Err(e) = refs::create_snapshot_ref(project_path, &snapshot.id, &ref_name) {
+                tracing::warn!(
+                    "Failed to create git ref for snapshot {}: {}",
+                    snapshot.id,
+                    e
+                );
             }
         }
-        Err(e) => {
-            tracing::warn!("Failed to cleanup old snapshots: {}", e);
-        }
+        Ok(false) => {}
+        Err(e) => tracing::warn!("Failed to read git snapshot ref setting: {}", e),
+    }
+
+    cleanup_session_snapshots(db, session_id);
+    match refs::prune_snapshot_refs(db, project_path) {
+        Ok(count) if count > 0 => tracing::info!("Removed {} stale snapshot refs", count),
+        Ok(_) => {}
+        Err(e) => tracing::warn!("Failed to prune snapshot refs: {}", e),
     }
 
     tracing::info!("Created git snapshot: {} -> {}", snapshot.id, ref_name);
@@ -469,7 +1112,37 @@ fn get_current_head(project_path: &Path) -> Result<String> {
 }
 
 /// Revert to a snapshot
-pub fn revert_to_snapshot(db: &Database, snapshot_id: &str, project_path: &Path) -> Result<()> {
+///
+/// `line_endings` only applies to file backup snapshots.
+pub fn revert_to_snapshot(
+    db: &Database,
+    snapshot_id: &str,
+    project_path: &Path,
+    line_endings: LineEndingMode,
+    git_mode: GitRevertMode,
+) -> Result<RevertReport> {
+    revert_to_snapshot_with_progress(
+        db,
+        snapshot_id,
+        project_path,
+        line_endings,
+        git_mode,
+        |_| {},
+    )
+}
+
+/// Revert to a snapshot, reporting progress as file backup contents are restored
+pub fn revert_to_snapshot_with_progress<F>(
+    db: &Database,
+    snapshot_id: &str,
+    project_path: &Path,
+    line_endings: LineEndingMode,
+    git_mode: GitRevertMode,
+    mut on_progress: F,
+) -> Result<RevertReport>
+where
+    F: FnMut(SnapshotProgress),
+{
     // Security: Canonicalize path to prevent symlink attacks and traversal
     let canonical_path = project_path
         .canonicalize()
@@ -480,8 +1153,14 @@ pub fn revert_to_snapshot(db: &Database, snapshot_id: &str, project_path: &Path)
         .ok_or_else(|| Error::SnapshotNotFound(snapshot_id.to_string()))?;
 
     match snapshot.snapshot_type.as_str() {
-        "git_ghost" => revert_git_snapshot(&snapshot, &canonical_path),
-        "file_backup" => revert_file_backup_snapshot(&snapshot, &canonical_path),
+        "git_ghost" => revert_git_snapshot(&snapshot, &canonical_path, git_mode),
+        "file_backup" => revert_file_backup_snapshot(
+            db,
+            &snapshot,
+            &canonical_path,
+            line_endings,
+            &mut on_progress,
+        ),
         _ => Err(Error::Other(format!(
             "Unknown snapshot type: {}",
             snapshot.snapshot_type
@@ -489,6 +1168,64 @@ pub fn revert_to_snapshot(db: &Database, snapshot_id: &str, project_path: &Path)
     }
 }
 
+/// What a reverted file is written from
+enum RestoreSource {
+    Bytes(Vec<u8>),
+    /// A sidecar file, copied without loading it into memory
+    Copy(PathBuf),
+}
+
+/// Recreate a backed-up symlink at `relative_path`, replacing whatever file or link is there now
+///
+/// `project_path` must be canonical. A link already at the path is only
+/// removed when every directory above it is a real directory in the project.
+#[cfg(unix)]
+fn restore_symlink(relative_path: &str, target: &str, project_path: &Path) -> Result<()> {
+    if !symlink_within_project(relative_path, target, project_path) {
+        return Err(Error::Other(format!(
+            "Symlink target '{target}' is outside the project"
+        )));
+    }
+
+    // validate_restore_path refuses a link at the path itself, which is exactly what gets replaced
+    if matches!(
+        validate_restore_path(relative_path, project_path),
+        Err(PathValidationError::SymbolicLink)
+    ) {
+        let parent_is_safe = match relative_path.rsplit_once(['/', '\\']) {
+            Some((parent, _)) => validate_restore_path(parent, project_path).is_ok(),
+            None => true,
+        };
+        let existing = project_path.join(relative_path);
+        if parent_is_safe
+            && fs::symlink_metadata(&existing).is_ok_and(|m| m.file_type().is_symlink())
+        {
+            fs::remove_file(&existing)?;
+        }
+    }
+
+    let validated_path = prepare_restore_path(relative_path, project_path)?;
+    match fs::symlink_metadata(validated_path.as_path()) {
+        Ok(existing) if existing.is_dir() => {
+            return Err(Error::Other(format!(
+                "'{relative_path}' is now a directory"
+            )));
+        }
+        Ok(_) => fs::remove_file(validated_path.as_path())?,
+        Err(_) => {}
+    }
+    std::os::unix::fs::symlink(target, validated_path.as_path())
+        .map_err(|e| Error::Other(format!("Failed to create symlink '{relative_path}': {e}")))
+}
+
+/// Symlinks can't be created without extra privileges on Windows, so they are left as they are
+#[cfg(not(unix))]
+fn restore_symlink(_relative_path: &str, _target: &str, _project_path: &Path) -> Result<()> {
+    Err(Error::Other(
+        "Restoring symlinks is only supported on Unix".to_string(),
+    ))
+}
+
 /// Revert to a file backup snapshot
 ///
 /// This function restores files from a backup snapshot with comprehensive security checks:
@@ -496,14 +1233,27 @@ pub fn revert_to_snapshot(db: &Database, snapshot_id: &str, project_path: &Path)
 /// - Null byte injection prevention
 /// - Symbolic link attack prevention
 /// - TOCTOU (Time-of-check to time-of-use) mitigation
-fn revert_file_backup_snapshot(snapshot: &Snapshot, project_path: &Path) -> Result<()> {
-    let metadata_str = snapshot
-        .metadata_json
-        .as_ref()
-        .ok_or_else(|| Error::Other("Missing metadata in file backup snapshot".to_string()))?;
-
-    let metadata: FileBackupMetadata = serde_json::from_str(metadata_str)
-        .map_err(|e| Error::Other(format!("Failed to parse file backup metadata: {e}")))?;
+///
+/// Files with mixed line endings are reported regardless of `line_endings`;
+/// binary files are always written unchanged. A blob missing from the
+/// database is an error, since the snapshot can't be fully restored.
+fn revert_file_backup_snapshot(
+    db: &Database,
+    snapshot: &Snapshot,
+    project_path: &Path,
+    line_endings: LineEndingMode,
+    on_progress: &mut dyn FnMut(SnapshotProgress),
+) -> Result<RevertReport> {
+    // Older metadata is upgraded on first use; a failed upgrade still reverts from the old shape
+    let migrated = migrate_snapshot_metadata(db, snapshot).unwrap_or_else(|e| {
+        tracing::warn!("Failed to migrate snapshot {} metadata: {}", snapshot.id, e);
+        None
+    });
+    let snapshot = migrated.as_ref().unwrap_or(snapshot);
+    let metadata = file_backup_metadata(snapshot)?;
+    let file_total = metadata.files.len() + metadata.blobs.len() + metadata.sidecars.len();
+    let total = file_total + metadata.symlinks.len();
+    let mut progress = ProgressReporter::new(&snapshot.id, total, on_progress);
 
     // Ensure project_path is canonical for all subsequent comparisons
     let canonical_project = project_path
@@ -512,8 +1262,15 @@ fn revert_file_backup_snapshot(snapshot: &Snapshot, project_path: &Path) -> Resu
 
     let mut restored_count = 0;
     let mut skipped_paths: Vec<String> = Vec::new();
+    let mut mixed_line_endings: Vec<String> = Vec::new();
+
+    for (index, (relative_path, stored)) in metadata
+        .stored_files()
+        .chain(metadata.sidecar_files())
+        .enumerate()
+    {
+        progress.report(index);
 
-    for (relative_path, base64_content) in &metadata.files {
         // Use the unified path validation function
         // This performs all security checks in one place
         let validated_path = match prepare_restore_path(relative_path, &canonical_project) {
@@ -530,10 +1287,26 @@ fn revert_file_backup_snapshot(snapshot: &Snapshot, project_path: &Path) -> Resu
             }
         };
 
-        // Decode the base64 content
-        let contents = BASE64
-            .decode(base64_content)
-            .map_err(|e| Error::Other(format!("Failed to decode file content for '{relative_path}': {e}")))?;
+        // Sidecar files are copied as-is rather than loaded, so line endings aren't touched
+        let source =
+            match stored {
+                StoredContent::Sidecar(hash) => RestoreSource::Copy(
+                    StoredContent::existing_sidecar(db, hash, &snapshot.id, relative_path)?,
+                ),
+                _ => {
+                    let contents = stored.load(db, &snapshot.id, relative_path)?;
+                    let is_text = is_text_content(&contents);
+                    if is_text && has_mixed_line_endings(&contents) {
+                        mixed_line_endings.push(relative_path.clone());
+                    }
+                    RestoreSource::Bytes(match line_endings {
+                        LineEndingMode::Native if is_text => {
+                            convert_line_endings(&contents, cfg!(windows))
+                        }
+                        _ => contents,
+                    })
+                }
+            };
 
         // Final symlink check right before writing (TOCTOU mitigation)
         // This minimizes the window between check and use
@@ -549,22 +1322,55 @@ fn revert_file_backup_snapshot(snapshot: &Snapshot, project_path: &Path) -> Resu
         }
 
         // Write the file using the validated path
-        fs::write(validated_path.as_path(), &contents)
-            .map_err(|e| Error::Other(format!("Failed to write file '{relative_path}': {e}")))?;
+        match source {
+            RestoreSource::Bytes(contents) => fs::write(validated_path.as_path(), contents),
+            RestoreSource::Copy(path) => fs::copy(path, validated_path.as_path()).map(|_| ()),
+        }
+        .map_err(|e| Error::Other(format!("Failed to write file '{relative_path}': {e}")))?;
+        if let Some(&mode) = metadata.modes.get(relative_path) {
+            set_file_mode(validated_path.as_path(), mode).map_err(|e| {
+                Error::Other(format!(
+                    "Failed to set permissions on '{relative_path}': {e}"
+                ))
+            })?;
+        }
 
         restored_count += 1;
     }
 
-    // Report any skipped files due to security issues
-    if !skipped_paths.is_empty() {
-        tracing::warn!(
-            "Snapshot {} restored with {} files skipped due to security violations: {:?}",
+    let mut symlinks: Vec<(&String, &String)> = metadata.symlinks.iter().collect();
+    symlinks.sort();
+    for (index, (relative_path, target)) in symlinks.into_iter().enumerate() {
+        progress.report(file_total + index);
+        match restore_symlink(relative_path, target, &canonical_project) {
+            Ok(()) => restored_count += 1,
+            Err(e) => {
+                tracing::warn!("Skipping symlink {}: {}", relative_path, e);
+                skipped_paths.push(relative_path.clone());
+            }
+        }
+    }
+    progress.report(total);
+
+    // Report any skipped files due to security issues
+    if !skipped_paths.is_empty() {
+        tracing::warn!(
+            "Snapshot {} restored with {} files skipped due to security violations: {:?}",
             snapshot.id,
             skipped_paths.len(),
             skipped_paths
         );
     }
 
+    if !mixed_line_endings.is_empty() {
+        tracing::warn!(
+            "Snapshot {} contains {} files with mixed line endings: {:?}",
+            snapshot.id,
+            mixed_line_endings.len(),
+            mixed_line_endings
+        );
+    }
+
     tracing::info!(
         "Reverted file backup snapshot: {} ({} files restored, {} skipped)",
         snapshot.id,
@@ -572,11 +1378,182 @@ fn revert_file_backup_snapshot(snapshot: &Snapshot, project_path: &Path) -> Resu
         skipped_paths.len()
     );
 
-    Ok(())
+    if !metadata.skipped_large.is_empty() {
+        tracing::warn!(
+            "Snapshot {} did not back up {} large files: {:?}",
+            snapshot.id,
+            metadata.skipped_large.len(),
+            metadata.skipped_large
+        );
+    }
+    if !metadata.skipped_non_utf8.is_empty() {
+        tracing::warn!(
+            "Snapshot {} did not back up {} files with non-UTF-8 paths: {:?}",
+            snapshot.id,
+            metadata.skipped_non_utf8.len(),
+            metadata.skipped_non_utf8
+        );
+    }
+
+    mixed_line_endings.sort();
+    let mut not_backed_up = metadata.skipped_large;
+    not_backed_up.extend(metadata.skipped_non_utf8);
+    not_backed_up.sort();
+    Ok(RevertReport {
+        restored: restored_count,
+        skipped: skipped_paths,
+        mixed_line_endings,
+        not_backed_up,
+        branch: None,
+        conflicts: Vec::new(),
+    })
+}
+
+/// Unified diff of one file, or a note for binary content
+fn unified_file_diff(path: &str, old: Option<&[u8]>, new: Option<&[u8]>) -> String {
+    let old_label = if old.is_some() {
+        format!("a/{path}")
+    } else {
+        "/dev/null".to_string()
+    };
+    let new_label = if new.is_some() {
+        format!("b/{path}")
+    } else {
+        "/dev/null".to_string()
+    };
+    let (Ok(old_text), Ok(new_text)) = (
+        std::str::from_utf8(old.unwrap_or_default()),
+        std::str::from_utf8(new.unwrap_or_default()),
+    ) else {
+        return format!("Binary files {old_label} and {new_label} differ\n");
+    };
+
+    let mut diff = format!("diff --git a/{path} b/{path}\n");
+    diff.push_str(
+        &similar::TextDiff::from_lines(old_text, new_text)
+            .unified_diff()
+            .header(&old_label, &new_label)
+            .to_string(),
+    );
+    diff
+}
+
+/// Unified diff from a snapshot to the current working tree
+///
+/// This is what reverting would undo. For file backups, files deleted since
+/// the snapshot show as full deletions and files created since (that a backup
+/// would have included) as full additions. Git snapshots use `git diff <sha>`.
+pub fn diff_snapshot(db: &Database, snapshot_id: &str, project_path: &Path) -> Result<String> {
+    let canonical_path = project_path
+        .canonicalize()
+        .map_err(|_| Error::Other("Invalid or non-existent path".to_string()))?;
+
+    let snapshot = db
+        .get_snapshot(snapshot_id)?
+        .ok_or_else(|| Error::SnapshotNotFound(snapshot_id.to_string()))?;
+
+    match snapshot.snapshot_type.as_str() {
+        "git_ghost" => {
+            let commit_ref = git_snapshot_ref(&snapshot)?;
+            let output = Command::new("git")
+                .args(["diff", &commit_ref, "--"])
+                .current_dir(&canonical_path)
+                .output()
+                .map_err(|e| Error::Git(format!("Failed to run git diff: {e}")))?;
+            if !output.status.success() {
+                let stderr = String::from_utf8_lossy(&output.stderr);
+                return Err(Error::Git(format!(
+                    "Failed to diff against {commit_ref}: {}",
+                    stderr.trim()
+                )));
+            }
+            Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+        }
+        "file_backup" => {
+            let metadata = file_backup_metadata(&snapshot)?;
+            let mut stored: HashMap<&String, StoredContent> = metadata.stored_files().collect();
+
+            let mut current: Vec<String> = collect_project_files(&canonical_path)?
+                .files
+                .into_iter()
+                .filter(|(path, _)| {
+                    fs::metadata(path).is_ok_and(|m| m.len() <= MAX_BACKUP_FILE_SIZE)
+                })
+                .map(|(_, relative)| relative)
+                .collect();
+            current.sort();
+
+            let mut paths: Vec<&String> = stored.keys().copied().collect();
+            paths.extend(current.iter().filter(|p| !stored.contains_key(p)));
+            paths.sort();
+
+            let mut diff = String::new();
+            for path in paths {
+                let old = match stored.remove(path) {
+                    Some(content) => Some(content.load(db, &snapshot.id, path)?),
+                    None => None,
+                };
+                // Only read paths that pass the same checks a revert would apply
+                let new = match validate_restore_path(path, &canonical_path) {
+                    Ok(validated) => fs::read(validated.as_path()).ok(),
+                    Err(_) => continue,
+                };
+                if old != new {
+                    diff.push_str(&unified_file_diff(path, old.as_deref(), new.as_deref()));
+                }
+            }
+            Ok(diff)
+        }
+        other => Err(Error::Other(format!("Unknown snapshot type: {other}"))),
+    }
+}
+
+/// Paths with unresolved merge conflicts in the working tree
+pub fn conflicted_files(project_path: &Path) -> Result<Vec<String>> {
+    let output = preview::git_output(
+        project_path,
+        &["diff", "--name-only", "--diff-filter=U", "-z"],
+    )?;
+    let mut files: Vec<String> = output
+        .split(|&b| b == 0)
+        .filter(|path| !path.is_empty())
+        .map(|path| String::from_utf8_lossy(path).into_owned())
+        .collect();
+    files.dedup();
+    Ok(files)
+}
+
+/// Report for a stash that failed to re-apply, if the failure was merge conflicts
+///
+/// Any other failure is returned as an error built from `stderr`.
+fn stash_conflict_report(
+    project_path: &Path,
+    snapshot: &Snapshot,
+    stderr: &[u8],
+    context: &str,
+) -> Result<RevertReport> {
+    let conflicts = conflicted_files(project_path).unwrap_or_default();
+    if conflicts.is_empty() {
+        let stderr = String::from_utf8_lossy(stderr);
+        return Err(Error::Git(format!("{context}: {stderr}")));
+    }
+    tracing::warn!(
+        "Reverting to snapshot {} left {} conflicted file(s)",
+        snapshot.id,
+        conflicts.len()
+    );
+    Ok(RevertReport {
+        conflicts,
+        ..RevertReport::default()
+    })
 }
 
 /// Revert to a git snapshot
-fn revert_git_snapshot(snapshot: &Snapshot, project_path: &Path) -> Result<()> {
+fn revert_git_snapshot(
+    snapshot: &Snapshot,
+    project_path: &Path,
+    mode: GitRevertMode,
+) -> Result<RevertReport> {
     // Security: Canonicalize path to prevent symlink attacks and traversal
     let canonical_path = project_path
         .canonicalize()
@@ -591,8 +1568,51 @@ fn revert_git_snapshot(snapshot: &Snapshot, project_path: &Path) -> Result<()> {
     let commit_sha = metadata["commit_sha"]
         .as_str()
         .ok_or_else(|| Error::Other("Missing commit_sha in snapshot".to_string()))?;
+    let is_stash = metadata["stash"].as_bool() == Some(true);
 
-    if commit_sha.starts_with("stash@") {
+    if mode == GitRevertMode::Branch {
+        let branch =
+            revert_git_snapshot_to_branch(snapshot, &canonical_path, commit_sha, is_stash)?;
+        tracing::info!(
+            "Checked out snapshot {} on new branch {}",
+            snapshot.id,
+            branch
+        );
+        return Ok(RevertReport {
+            branch: Some(branch),
+            ..RevertReport::default()
+        });
+    }
+
+    if is_stash {
+        // Security: Validate commit SHA to prevent command injection
+        validate_commit_sha(commit_sha)?;
+
+        // Go back to the commit the changes were made on, then re-apply them
+        let base = format!("{commit_sha}^1");
+        let output = Command::new("git")
+            .args(["reset", "--hard", &base])
+            .current_dir(&canonical_path)
+            .output()
+            .map_err(|e| Error::Git(format!("Failed to reset: {e}")))?;
+
+        if !output.status.success() {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            return Err(Error::Git(format!("Failed to reset to {base}: {stderr}")));
+        }
+
+        let output = Command::new("git")
+            .args(["stash", "apply", commit_sha])
+            .current_dir(&canonical_path)
+            .output()
+            .map_err(|e| Error::Git(format!("Failed to apply stash commit: {e}")))?;
+
+        if !output.status.success() {
+            let context = format!("Failed to apply stash commit {commit_sha}");
+            return stash_conflict_report(&canonical_path, snapshot, &output.stderr, &context);
+        }
+    } else if commit_sha.starts_with("stash@") {
+        // Legacy snapshots recorded a stash list position
         // Pop the stash - validate stash ref format
         if !commit_sha.starts_with("stash@{") || !commit_sha.ends_with('}') {
             return Err(Error::Other("Invalid stash reference format".to_string()));
@@ -604,9 +1624,14 @@ fn revert_git_snapshot(snapshot: &Snapshot, project_path: &Path) -> Result<()> {
             .output()
             .map_err(|e| Error::Git(format!("Failed to pop stash: {e}")))?;
 
+        // A conflicting pop keeps the stash entry, so nothing is lost
         if !output.status.success() {
-            let stderr = String::from_utf8_lossy(&output.stderr);
-            return Err(Error::Git(format!("Failed to pop stash: {stderr}")));
+            return stash_conflict_report(
+                &canonical_path,
+                snapshot,
+                &output.stderr,
+                "Failed to pop stash",
+            );
         }
     } else {
         // Security: Validate commit SHA to prevent command injection
@@ -627,7 +1652,84 @@ fn revert_git_snapshot(snapshot: &Snapshot, project_path: &Path) -> Result<()> {
 
     tracing::info!("Reverted to snapshot: {}", snapshot.id);
 
-    Ok(())
+    Ok(RevertReport::default())
+}
+
+/// Name of the branch a snapshot is checked out on, e.g. `codex-snapshot/20240501-093000-1a2b3c4d`
+fn snapshot_branch_name(snapshot: &Snapshot) -> String {
+    let timestamp = chrono::DateTime::from_timestamp(snapshot.created_at, 0)
+        .map(|t| t.format("%Y%m%d-%H%M%S").to_string())
+        .unwrap_or_else(|| snapshot.created_at.to_string());
+    let id: String = snapshot
+        .id
+        .chars()
+        .filter(|c| c.is_ascii_alphanumeric())
+        .take(8)
+        .collect();
+    format!("codex-snapshot/{timestamp}-{id}")
+}
+
+/// Check out a git snapshot on a new branch, returning the branch name
+///
+/// The current branch and its commits are untouched. Uncommitted changes to
+/// tracked files must be committed or stashed first, so they are neither
+/// lost nor carried onto the snapshot branch.
+fn revert_git_snapshot_to_branch(
+    snapshot: &Snapshot,
+    project_path: &Path,
+    commit_sha: &str,
+    is_stash: bool,
+) -> Result<String> {
+    let status = preview::git_output(
+        project_path,
+        &["status", "--porcelain", "--untracked-files=no"],
+    )?;
+    if !status.is_empty() {
+        return Err(Error::Git(
+            "Commit or stash your changes before reverting onto a new branch".to_string(),
+        ));
+    }
+
+    // Reverting the same snapshot again gets a numbered branch
+    let base_name = snapshot_branch_name(snapshot);
+    let branch = (1..=100)
+        .map(|n| {
+            if n == 1 {
+                base_name.clone()
+            } else {
+                format!("{base_name}-{n}")
+            }
+        })
+        .find(|name| {
+            let branch_ref = format!("refs/heads/{name}");
+            preview::git_output(
+                project_path,
+                &["rev-parse", "--verify", "--quiet", &branch_ref],
+            )
+            .is_err()
+        })
+        .ok_or_else(|| Error::Git(format!("Too many branches named {base_name}")))?;
+    crate::commands::projects::validate_branch_name(&branch)?;
+
+    if commit_sha.starts_with("stash@") {
+        // Legacy snapshots recorded a stash list position
+        if !commit_sha.starts_with("stash@{") || !commit_sha.ends_with('}') {
+            return Err(Error::Other("Invalid stash reference format".to_string()));
+        }
+        preview::git_output(project_path, &["stash", "branch", &branch, commit_sha])?;
+        return Ok(branch);
+    }
+
+    // Security: Validate commit SHA to prevent command injection
+    validate_commit_sha(commit_sha)?;
+    if is_stash {
+        let base = format!("{commit_sha}^1");
+        preview::git_output(project_path, &["checkout", "-q", "-b", &branch, &base])?;
+        preview::git_output(project_path, &["stash", "apply", commit_sha])?;
+    } else {
+        preview::git_output(project_path, &["checkout", "-q", "-b", &branch, commit_sha])?;
+    }
+    Ok(branch)
 }
 
 #[cfg(test)]
@@ -956,4 +2058,991 @@ mod tests {
             );
         }
     }
+
+    // ==================== line ending tests ====================
+
+    fn backup_snapshot(files: &[(&str, &[u8])]) -> Snapshot {
+        let metadata = FileBackupMetadata {
+            version: 0,
+            files: files
+                .iter()
+                .map(|(path, contents)| (path.to_string(), BASE64.encode(contents)))
+                .collect(),
+            blobs: HashMap::new(),
+            sidecars: HashMap::new(),
+            skipped_large: Vec::new(),
+            skipped_non_utf8: Vec::new(),
+            modes: HashMap::new(),
+            symlinks: HashMap::new(),
+            description: "test".to_string(),
+        };
+        Snapshot::new_file_backup("session", &serde_json::to_string(&metadata).unwrap())
+    }
+
+    fn test_db() -> (TempDir, Database) {
+        let temp_dir = TempDir::new().unwrap();
+        let db = Database::new(&temp_dir.path().join("test.db")).unwrap();
+        (temp_dir, db)
+    }
+
+    #[test]
+    fn test_revert_preserves_crlf_bytes_by_default() {
+        let temp_dir = create_test_dir();
+        let (_db_dir, db) = test_db();
+        let snapshot = backup_snapshot(&[("win.txt", b"one\r\ntwo\r\n")]);
+
+        let report = revert_file_backup_snapshot(
+            &db,
+            &snapshot,
+            temp_dir.path(),
+            LineEndingMode::Preserve,
+            &mut |_| {},
+        )
+        .unwrap();
+
+        assert_eq!(report.restored, 1);
+        assert!(report.mixed_line_endings.is_empty());
+        assert_eq!(
+            fs::read(temp_dir.path().join("win.txt")).unwrap(),
+            b"one\r\ntwo\r\n"
+        );
+    }
+
+    #[test]
+    fn test_revert_native_mode_converts_line_endings() {
+        let temp_dir = create_test_dir();
+        let (_db_dir, db) = test_db();
+        let binary: &[u8] = b"\x00\r\n\x01";
+        let snapshot = backup_snapshot(&[("win.txt", b"one\r\ntwo\r\n"), ("blob.bin", binary)]);
+
+        revert_file_backup_snapshot(
+            &db,
+            &snapshot,
+            temp_dir.path(),
+            LineEndingMode::Native,
+            &mut |_| {},
+        )
+        .unwrap();
+
+        let expected: &[u8] = if cfg!(windows) {
+            b"one\r\ntwo\r\n"
+        } else {
+            b"one\ntwo\n"
+        };
+        assert_eq!(fs::read(temp_dir.path().join("win.txt")).unwrap(), expected);
+        // Binary content is never rewritten
+        assert_eq!(fs::read(temp_dir.path().join("blob.bin")).unwrap(), binary);
+    }
+
+    #[test]
+    fn test_revert_reports_mixed_line_endings() {
+        let temp_dir = create_test_dir();
+        let (_db_dir, db) = test_db();
+        let snapshot = backup_snapshot(&[("mixed.txt", b"a\r\nb\nc\r\n"), ("unix.txt", b"a\nb\n")]);
+
+        let report = revert_file_backup_snapshot(
+            &db,
+            &snapshot,
+            temp_dir.path(),
+            LineEndingMode::Preserve,
+            &mut |_| {},
+        )
+        .unwrap();
+
+        assert_eq!(report.mixed_line_endings, vec!["mixed.txt".to_string()]);
+        assert_eq!(
+            fs::read(temp_dir.path().join("mixed.txt")).unwrap(),
+            b"a\r\nb\nc\r\n"
+        );
+    }
+
+    // ==================== blob storage tests ====================
+
+    #[test]
+    fn test_file_backups_share_unchanged_blobs() {
+        let project = create_test_dir();
+        let (_db_dir, db) = test_db();
+        fs::write(project.path().join("same.txt"), "unchanged").unwrap();
+        fs::write(project.path().join("copy.txt"), "unchanged").unwrap();
+        fs::write(project.path().join("edit.txt"), "v1").unwrap();
+
+        let first =
+            create_file_backup_snapshot(&db, "s1", project.path(), None, &mut |_| {}).unwrap();
+        fs::write(project.path().join("edit.txt"), "v2").unwrap();
+        let second =
+            create_file_backup_snapshot(&db, "s1", project.path(), None, &mut |_| {}).unwrap();
+
+        let metadata: FileBackupMetadata =
+            serde_json::from_str(second.metadata_json.as_deref().unwrap()).unwrap();
+        assert!(metadata.files.is_empty());
+        assert_eq!(metadata.blobs["same.txt"], metadata.blobs["copy.txt"]);
+
+        let shared = blob_hash(b"unchanged");
+        assert_eq!(db.get_snapshot_blob_refcount(&shared).unwrap(), Some(2));
+        assert_eq!(
+            db.get_snapshot_blob_refcount(&blob_hash(b"v1")).unwrap(),
+            Some(1)
+        );
+
+        // Dropping the older snapshot releases only what it alone referenced
+        assert_eq!(db.delete_snapshots(&[first.id]).unwrap(), 1);
+        assert_eq!(db.get_snapshot_blob_refcount(&shared).unwrap(), Some(1));
+        assert_eq!(
+            db.get_snapshot_blob_refcount(&blob_hash(b"v1")).unwrap(),
+            None
+        );
+
+        assert_eq!(db.cleanup_old_snapshots("s1", 0).unwrap(), 1);
+        assert_eq!(db.get_snapshot_blob_refcount(&shared).unwrap(), None);
+        assert_eq!(
+            db.get_snapshot_blob_refcount(&blob_hash(b"v2")).unwrap(),
+            None
+        );
+    }
+
+    #[test]
+    fn test_revert_restores_blob_contents() {
+        let project = create_test_dir();
+        let (_db_dir, db) = test_db();
+        fs::create_dir(project.path().join("src")).unwrap();
+        fs::write(project.path().join("src/main.rs"), "fn main() {}").unwrap();
+
+        let snapshot =
+            create_file_backup_snapshot(&db, "s1", project.path(), None, &mut |_| {}).unwrap();
+        fs::write(project.path().join("src/main.rs"), "broken").unwrap();
+
+        let report = revert_file_backup_snapshot(
+            &db,
+            &snapshot,
+            project.path(),
+            LineEndingMode::Preserve,
+            &mut |_| {},
+        )
+        .unwrap();
+        assert_eq!(report.restored, 1);
+        assert_eq!(
+            fs::read_to_string(project.path().join("src/main.rs")).unwrap(),
+            "fn main() {}"
+        );
+    }
+
+    #[test]
+    fn test_large_text_file_is_stored_compressed() {
+        let project = create_test_dir();
+        let (_db_dir, db) = test_db();
+        let line = "let value = compute(input, options).expect(\"value\");\n";
+        let contents = line.repeat(500_000 / line.len());
+        fs::write(project.path().join("big.rs"), &contents).unwrap();
+
+        let snapshot =
+            create_file_backup_snapshot(&db, "s1", project.path(), None, &mut |_| {}).unwrap();
+
+        let usage = db.get_snapshot_blob_usage().unwrap();
+        assert_eq!(usage.raw_bytes, contents.len() as u64);
+        let stored = usage.stored_bytes as usize + snapshot.metadata_json.as_deref().unwrap().len();
+        assert!(
+            stored * 10 < BASE64.encode(&contents).len(),
+            "stored {stored} bytes for {} bytes of text",
+            contents.len()
+        );
+
+        fs::write(project.path().join("big.rs"), "truncated").unwrap();
+        revert_file_backup_snapshot(
+            &db,
+            &snapshot,
+            project.path(),
+            LineEndingMode::Preserve,
+            &mut |_| {},
+        )
+        .unwrap();
+        assert_eq!(
+            fs::read_to_string(project.path().join("big.rs")).unwrap(),
+            contents
+        );
+    }
+
+    #[test]
+    fn test_diff_file_backup_against_working_tree() {
+        let project = create_test_dir();
+        let (_db_dir, db) = test_db();
+        fs::write(project.path().join("edited.txt"), "one\ntwo\n").unwrap();
+        fs::write(project.path().join("deleted.txt"), "gone\n").unwrap();
+        fs::write(project.path().join("same.txt"), "same\n").unwrap();
+        let snapshot =
+            create_file_backup_snapshot(&db, "s1", project.path(), None, &mut |_| {}).unwrap();
+
+        fs::write(project.path().join("edited.txt"), "one\n2\n").unwrap();
+        fs::remove_file(project.path().join("deleted.txt")).unwrap();
+        fs::write(project.path().join("added.txt"), "new\n").unwrap();
+
+        let diff = diff_snapshot(&db, &snapshot.id, project.path()).unwrap();
+        assert!(diff.contains("--- /dev/null\n+++ b/added.txt\n"), "{diff}");
+        assert!(diff.contains("+new\n"));
+        assert!(
+            diff.contains("--- a/deleted.txt\n+++ /dev/null\n"),
+            "{diff}"
+        );
+        assert!(diff.contains("-gone\n"));
+        assert!(diff.contains("-two\n+2\n"), "{diff}");
+        assert!(!diff.contains("same.txt"));
+        // Files are listed in path order
+        assert!(diff.find("added.txt") < diff.find("deleted.txt"));
+    }
+
+    #[test]
+    fn test_diff_git_snapshot() {
+        let repo = create_test_dir();
+        let git = |args: &[&str]| {
+            Command::new("git")
+                .args(["-c", "user.name=Test", "-c", "user.email=test@example.com"])
+                .args(args)
+                .current_dir(repo.path())
+                .status()
+                .map(|s| s.success())
+                .unwrap_or(false)
+        };
+        if !git(&["init", "-q"]) {
+            return;
+        }
+        fs::write(repo.path().join("a.txt"), "before\n").unwrap();
+        assert!(git(&["add", "."]));
+        assert!(git(&["commit", "-q", "-m", "init"]));
+        let head = get_current_head(repo.path()).unwrap();
+
+        let (_db_dir, db) = test_db();
+        let snapshot = Snapshot::new_git_ghost("s1", &head);
+        db.insert_snapshot(&snapshot).unwrap();
+        fs::write(repo.path().join("a.txt"), "after\n").unwrap();
+
+        let diff = diff_snapshot(&db, &snapshot.id, repo.path()).unwrap();
+        assert!(diff.contains("-before\n+after\n"), "{diff}");
+    }
+
+    #[test]
+    fn test_git_snapshot_survives_later_stashes() {
+        let repo = create_test_dir();
+        let git = |args: &[&str]| {
+            Command::new("git")
+                .args(["-c", "user.name=Test", "-c", "user.email=test@example.com"])
+                .args(args)
+                .current_dir(repo.path())
+                .status()
+                .map(|s| s.success())
+                .unwrap_or(false)
+        };
+        if !git(&["init", "-q"]) {
+            return;
+        }
+        fs::write(repo.path().join("a.txt"), "base\n").unwrap();
+        assert!(git(&["add", "."]));
+        assert!(git(&["commit", "-q", "-m", "init"]));
+
+        let (_db_dir, db) = test_db();
+        fs::write(repo.path().join("a.txt"), "first edit\n").unwrap();
+        let first = create_git_snapshot(&db, "s1", repo.path(), None).unwrap();
+        // Snapshotting leaves the working tree and stash list alone
+        assert_eq!(
+            fs::read_to_string(repo.path().join("a.txt")).unwrap(),
+            "first edit\n"
+        );
+        assert!(!git(&["rev-parse", "--verify", "--quiet", "refs/stash"]));
+
+        fs::write(repo.path().join("a.txt"), "second edit\n").unwrap();
+        let second = create_git_snapshot(&db, "s1", repo.path(), None).unwrap();
+        assert_ne!(
+            git_snapshot_ref(&first).unwrap(),
+            git_snapshot_ref(&second).unwrap()
+        );
+
+        // An unrelated stash push doesn't change what the older snapshot restores
+        fs::write(repo.path().join("a.txt"), "unrelated\n").unwrap();
+        assert!(git(&["stash", "push", "-q"]));
+
+        revert_to_snapshot(
+            &db,
+            &first.id,
+            repo.path(),
+            LineEndingMode::Preserve,
+            GitRevertMode::Reset,
+        )
+        .unwrap();
+        assert_eq!(
+            fs::read_to_string(repo.path().join("a.txt")).unwrap(),
+            "first edit\n"
+        );
+        revert_to_snapshot(
+            &db,
+            &second.id,
+            repo.path(),
+            LineEndingMode::Preserve,
+            GitRevertMode::Reset,
+        )
+        .unwrap();
+        assert_eq!(
+            fs::read_to_string(repo.path().join("a.txt")).unwrap(),
+            "second edit\n"
+        );
+    }
+
+    #[test]
+    fn test_conflicting_stash_pop_reports_conflicts() {
+        let repo = create_test_dir();
+        let git = |args: &[&str]| {
+            Command::new("git")
+                .args(["-c", "user.name=Test", "-c", "user.email=test@example.com"])
+                .args(args)
+                .current_dir(repo.path())
+                .status()
+                .map(|s| s.success())
+                .unwrap_or(false)
+        };
+        if !git(&["init", "-q"]) {
+            return;
+        }
+        fs::write(repo.path().join("a.txt"), "base\n").unwrap();
+        fs::write(repo.path().join("b.txt"), "untouched\n").unwrap();
+        assert!(git(&["add", "."]));
+        assert!(git(&["commit", "-q", "-m", "init"]));
+
+        // A legacy snapshot pointing at a stash list entry
+        fs::write(repo.path().join("a.txt"), "stashed edit\n").unwrap();
+        assert!(git(&["stash", "push", "-q"]));
+        let (_db_dir, db) = test_db();
+        let snapshot = Snapshot::new_git_ghost("s1", "stash@{0}");
+        db.insert_snapshot(&snapshot).unwrap();
+
+        // A later commit touching the same line makes the pop conflict
+        fs::write(repo.path().join("a.txt"), "committed edit\n").unwrap();
+        assert!(git(&["commit", "-q", "-am", "edit"]));
+
+        let report = revert_to_snapshot(
+            &db,
+            &snapshot.id,
+            repo.path(),
+            LineEndingMode::Preserve,
+            GitRevertMode::Reset,
+        )
+        .unwrap();
+        assert_eq!(report.conflicts, vec!["a.txt".to_string()]);
+        assert_eq!(
+            conflicted_files(repo.path()).unwrap(),
+            vec!["a.txt".to_string()]
+        );
+
+        // The conflict is left for the user, and the stash entry is kept
+        let content = fs::read_to_string(repo.path().join("a.txt")).unwrap();
+        assert!(
+            content.contains("<<<<<<<") && content.contains("stashed edit"),
+            "{content}"
+        );
+        assert!(git(&["rev-parse", "--verify", "--quiet", "refs/stash"]));
+    }
+
+    #[test]
+    fn test_git_snapshot_of_clean_tree_records_head() {
+        let repo = create_test_dir();
+        let git = |args: &[&str]| {
+            Command::new("git")
+                .args(["-c", "user.name=Test", "-c", "user.email=test@example.com"])
+                .args(args)
+                .current_dir(repo.path())
+                .status()
+                .map(|s| s.success())
+                .unwrap_or(false)
+        };
+        if !git(&["init", "-q"]) {
+            return;
+        }
+        fs::write(repo.path().join("a.txt"), "base\n").unwrap();
+        assert!(git(&["add", "."]));
+        assert!(git(&["commit", "-q", "-m", "init"]));
+
+        let (_db_dir, db) = test_db();
+        let snapshot = create_git_snapshot(&db, "s1", repo.path(), None).unwrap();
+        assert_eq!(
+            git_snapshot_ref(&snapshot).unwrap(),
+            get_current_head(repo.path()).unwrap()
+        );
+        assert!(!snapshot.metadata_json.unwrap().contains("stash"));
+    }
+
+    #[test]
+    fn test_revert_git_snapshot_onto_branch() {
+        let repo = create_test_dir();
+        let git = |args: &[&str]| {
+            Command::new("git")
+                .args(["-c", "user.name=Test", "-c", "user.email=test@example.com"])
+                .args(args)
+                .current_dir(repo.path())
+                .status()
+                .map(|s| s.success())
+                .unwrap_or(false)
+        };
+        if !git(&["init", "-q"]) {
+            return;
+        }
+        fs::write(repo.path().join("a.txt"), "base\n").unwrap();
+        assert!(git(&["add", "."]));
+        assert!(git(&["commit", "-q", "-m", "init"]));
+
+        let (_db_dir, db) = test_db();
+        let snapshot = create_git_snapshot(&db, "s1", repo.path(), None).unwrap();
+        let original_branch =
+            preview::git_output(repo.path(), &["symbolic-ref", "--short", "HEAD"]).unwrap();
+        fs::write(repo.path().join("a.txt"), "later work\n").unwrap();
+        assert!(git(&["commit", "-q", "-am", "later"]));
+        let later_head = get_current_head(repo.path()).unwrap();
+
+        // Uncommitted changes block the checkout rather than being carried along
+        fs::write(repo.path().join("a.txt"), "uncommitted\n").unwrap();
+        let dirty = revert_to_snapshot(
+            &db,
+            &snapshot.id,
+            repo.path(),
+            LineEndingMode::Preserve,
+            GitRevertMode::Branch,
+        );
+        assert!(dirty.is_err());
+        assert!(git(&["checkout", "-q", "--", "a.txt"]));
+
+        let report = revert_to_snapshot(
+            &db,
+            &snapshot.id,
+            repo.path(),
+            LineEndingMode::Preserve,
+            GitRevertMode::Branch,
+        )
+        .unwrap();
+        let branch = report.branch.unwrap();
+        assert_eq!(branch, snapshot_branch_name(&snapshot));
+        assert!(branch.starts_with("codex-snapshot/"));
+        assert_eq!(
+            fs::read_to_string(repo.path().join("a.txt")).unwrap(),
+            "base\n"
+        );
+        let current_branch =
+            preview::git_output(repo.path(), &["symbolic-ref", "--short", "HEAD"]).unwrap();
+        assert_eq!(String::from_utf8(current_branch).unwrap().trim(), branch);
+
+        // The original branch still has the later commit
+        let original_branch = String::from_utf8(original_branch).unwrap();
+        let original_head =
+            preview::git_output(repo.path(), &["rev-parse", original_branch.trim()]).unwrap();
+        assert_eq!(String::from_utf8(original_head).unwrap().trim(), later_head);
+
+        // Reverting the same snapshot again picks a fresh name
+        let again = revert_to_snapshot(
+            &db,
+            &snapshot.id,
+            repo.path(),
+            LineEndingMode::Preserve,
+            GitRevertMode::Branch,
+        )
+        .unwrap();
+        assert_eq!(again.branch, Some(format!("{branch}-2")));
+    }
+
+    #[test]
+    fn test_snapshot_retention_comes_from_project_settings() {
+        use crate::database::{Project, SessionMetadata};
+
+        let project_dir = create_test_dir();
+        fs::write(project_dir.path().join("a.txt"), "a").unwrap();
+        let (_db_dir, db) = test_db();
+        let mut project = Project::new(&project_dir.path().to_string_lossy());
+        let settings = |retention: Option<usize>| {
+            let settings = ProjectSettings {
+                snapshot_retention: retention,
+                ..ProjectSettings::default()
+            };
+            Some(serde_json::to_string(&settings).unwrap())
+        };
+        project.settings_json = settings(Some(2));
+        db.insert_project(&project).unwrap();
+        db.upsert_session_metadata(&SessionMetadata::new("s1", &project.id))
+            .unwrap();
+
+        let count = |db: &Database| db.get_snapshots_for_session("s1").unwrap().len();
+        for _ in 0..4 {
+            create_snapshot(&db, "s1", project_dir.path(), None).unwrap();
+        }
+        assert_eq!(count(&db), 2);
+
+        // 0 keeps every snapshot
+        db.update_project_settings(&project.id, settings(Some(0)).as_deref().unwrap())
+            .unwrap();
+        for _ in 0..3 {
+            create_snapshot(&db, "s1", project_dir.path(), None).unwrap();
+        }
+        assert_eq!(count(&db), 5);
+
+        // Unset falls back to the default on the next snapshot
+        db.update_project_settings(&project.id, settings(None).as_deref().unwrap())
+            .unwrap();
+        for _ in 0..ProjectSettings::DEFAULT_SNAPSHOT_RETENTION {
+            create_snapshot(&db, "s1", project_dir.path(), None).unwrap();
+        }
+        assert_eq!(count(&db), ProjectSettings::DEFAULT_SNAPSHOT_RETENTION);
+    }
+
+    #[test]
+    fn test_snapshot_label_is_stored() {
+        let project = create_test_dir();
+        let (_db_dir, db) = test_db();
+        fs::write(project.path().join("a.txt"), "a").unwrap();
+
+        let labeled =
+            create_snapshot(&db, "s1", project.path(), Some("  before refactor ")).unwrap();
+        let blank = create_snapshot(&db, "s1", project.path(), Some("   ")).unwrap();
+
+        let snapshots = db.get_snapshots_for_session("s1").unwrap();
+        let label_of = |id: &str| snapshots.iter().find(|s| s.id == id).unwrap().label.clone();
+        assert_eq!(label_of(&labeled.id).as_deref(), Some("before refactor"));
+        assert_eq!(label_of(&blank.id), None);
+    }
+
+    fn collected_names(project: &Path) -> Vec<String> {
+        let mut names: Vec<String> = collect_project_files(project)
+            .unwrap()
+            .files
+            .iter()
+            .map(|(_, relative)| relative.replace('\\', "/"))
+            .collect();
+        names.sort();
+        names
+    }
+
+    #[test]
+    fn test_estimate_snapshot_cost() {
+        let project = create_test_dir();
+        fs::write(project.path().join("a.txt"), "12345").unwrap();
+        fs::create_dir_all(project.path().join("src")).unwrap();
+        fs::write(project.path().join("src/lib.rs"), vec![b'x'; 100_000]).unwrap();
+        // Excluded directories and files over the size cap aren't counted
+        fs::create_dir_all(project.path().join("node_modules")).unwrap();
+        fs::write(project.path().join("node_modules/dep.js"), "x").unwrap();
+        fs::write(
+            project.path().join("huge.bin"),
+            vec![0u8; MAX_BACKUP_FILE_SIZE as usize + 1],
+        )
+        .unwrap();
+
+        let estimate = estimate_snapshot_cost(project.path()).unwrap();
+        assert_eq!(estimate.file_count, 2);
+        assert_eq!(estimate.total_bytes, 100_005);
+        assert!(estimate.estimated_ms > 0);
+
+        let empty = create_test_dir();
+        let estimate = estimate_snapshot_cost(empty.path()).unwrap();
+        assert_eq!((estimate.file_count, estimate.total_bytes), (0, 0));
+        assert_eq!(estimate.estimated_ms, 1);
+    }
+
+    #[test]
+    fn test_collect_project_files_honors_gitignore_outside_git() {
+        let project = create_test_dir();
+        fs::write(project.path().join(".gitignore"), "artifacts/\n*.log\n").unwrap();
+        fs::create_dir_all(project.path().join("artifacts")).unwrap();
+        fs::write(project.path().join("artifacts/huge.bin"), "x").unwrap();
+        fs::write(project.path().join("debug.log"), "x").unwrap();
+        fs::create_dir_all(project.path().join("build")).unwrap();
+        fs::write(project.path().join("build/out.txt"), "x").unwrap();
+        fs::write(project.path().join("main.rs"), "x").unwrap();
+
+        // The project's rules replace the built-in excludes
+        assert_eq!(
+            collected_names(project.path()),
+            vec!["build/out.txt", "main.rs"]
+        );
+    }
+
+    #[test]
+    fn test_collect_project_files_default_excludes_without_ignore_file() {
+        let project = create_test_dir();
+        fs::create_dir_all(project.path().join("node_modules/pkg")).unwrap();
+        fs::write(project.path().join("node_modules/pkg/index.js"), "x").unwrap();
+        fs::create_dir_all(project.path().join(".cache")).unwrap();
+        fs::write(project.path().join(".cache/data"), "x").unwrap();
+        fs::create_dir_all(project.path().join("src")).unwrap();
+        fs::write(project.path().join("src/lib.rs"), "x").unwrap();
+
+        assert_eq!(collected_names(project.path()), vec!["src/lib.rs"]);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_non_utf8_paths_are_reported_not_mangled() {
+        use std::ffi::OsStr;
+        use std::os::unix::ffi::OsStrExt;
+
+        let project = create_test_dir();
+        let (_db_dir, db) = test_db();
+        fs::write(project.path().join("ok.txt"), "fine").unwrap();
+        if fs::write(
+            project.path().join(OsStr::from_bytes(b"bad\xff.txt")),
+            "mangled",
+        )
+        .is_err()
+        {
+            // Some filesystems refuse non-UTF-8 names outright
+            return;
+        }
+
+        assert_eq!(
+            find_non_utf8_paths(project.path()).unwrap(),
+            vec!["bad\u{FFFD}.txt"]
+        );
+
+        let snapshot =
+            create_file_backup_snapshot(&db, "s1", project.path(), None, &mut |_| {}).unwrap();
+        let metadata = file_backup_metadata(&snapshot).unwrap();
+        assert_eq!(metadata.blobs.keys().collect::<Vec<_>>(), vec!["ok.txt"]);
+        assert_eq!(metadata.skipped_non_utf8, vec!["bad\u{FFFD}.txt"]);
+
+        let report = revert_file_backup_snapshot(
+            &db,
+            &snapshot,
+            project.path(),
+            LineEndingMode::Preserve,
+            &mut |_| {},
+        )
+        .unwrap();
+        assert_eq!(report.restored, 1);
+        assert_eq!(report.not_backed_up, vec!["bad\u{FFFD}.txt"]);
+        // No lossily named copy was written
+        assert!(!project.path().join("bad\u{FFFD}.txt").exists());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_revert_restores_file_mode() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let project = create_test_dir();
+        let (_db_dir, db) = test_db();
+        let script = project.path().join("run.sh");
+        fs::write(&script, "#!/bin/sh\necho hi\n").unwrap();
+        fs::set_permissions(&script, fs::Permissions::from_mode(0o755)).unwrap();
+
+        let snapshot =
+            create_file_backup_snapshot(&db, "s1", project.path(), None, &mut |_| {}).unwrap();
+        fs::remove_file(&script).unwrap();
+
+        revert_file_backup_snapshot(
+            &db,
+            &snapshot,
+            project.path(),
+            LineEndingMode::Preserve,
+            &mut |_| {},
+        )
+        .unwrap();
+        let mode = fs::metadata(&script).unwrap().permissions().mode() & 0o7777;
+        assert_eq!(mode, 0o755);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_symlinks_round_trip_as_links() {
+        use std::os::unix::fs::symlink;
+
+        let project = create_test_dir();
+        let (_db_dir, db) = test_db();
+        let root = project.path().canonicalize().unwrap();
+        fs::write(root.join("target.txt"), "hello").unwrap();
+        fs::create_dir_all(root.join("sub")).unwrap();
+        symlink("../target.txt", root.join("sub/link.txt")).unwrap();
+        symlink("/etc/hostname", root.join("outside")).unwrap();
+
+        let snapshot = create_file_backup_snapshot(&db, "s1", &root, None, &mut |_| {}).unwrap();
+        let metadata = file_backup_metadata(&snapshot).unwrap();
+        assert_eq!(
+            metadata.blobs.keys().collect::<Vec<_>>(),
+            vec!["target.txt"]
+        );
+        assert_eq!(
+            metadata.symlinks,
+            HashMap::from([("sub/link.txt".to_string(), "../target.txt".to_string())])
+        );
+
+        // The link is replaced by a regular file, which a revert turns back into the link
+        fs::remove_file(root.join("sub/link.txt")).unwrap();
+        fs::write(root.join("sub/link.txt"), "not a link").unwrap();
+        let report = revert_file_backup_snapshot(
+            &db,
+            &snapshot,
+            &root,
+            LineEndingMode::Preserve,
+            &mut |_| {},
+        )
+        .unwrap();
+        assert_eq!(report.restored, 2);
+        assert!(report.skipped.is_empty());
+        assert_eq!(
+            fs::read_link(root.join("sub/link.txt")).unwrap(),
+            Path::new("../target.txt")
+        );
+        assert_eq!(
+            fs::read_to_string(root.join("sub/link.txt")).unwrap(),
+            "hello"
+        );
+
+        // A link already in place is recreated rather than written through
+        revert_file_backup_snapshot(&db, &snapshot, &root, LineEndingMode::Preserve, &mut |_| {})
+            .unwrap();
+        assert_eq!(
+            fs::read_link(root.join("sub/link.txt")).unwrap(),
+            Path::new("../target.txt")
+        );
+        assert_eq!(
+            fs::read_link(root.join("outside")).unwrap(),
+            Path::new("/etc/hostname")
+        );
+    }
+
+    #[test]
+    fn test_symlink_within_project() {
+        let root = Path::new("/work/project");
+        assert!(symlink_within_project("a/link", "../b.txt", root));
+        assert!(symlink_within_project("link", "src/lib.rs", root));
+        assert!(symlink_within_project("link", "/work/project/src", root));
+        assert!(!symlink_within_project("link", "../other", root));
+        assert!(!symlink_within_project("a/link", "../../secret", root));
+        assert!(!symlink_within_project("link", "/etc/passwd", root));
+        assert!(!symlink_within_project("link", "src/../../x", root));
+        assert!(!symlink_within_project("../link", "x", root));
+    }
+
+    #[test]
+    fn test_file_backup_reports_progress() {
+        let project = create_test_dir();
+        let (_db_dir, db) = test_db();
+        for i in 0..250 {
+            fs::write(project.path().join(format!("file{i}.txt")), format!("{i}")).unwrap();
+        }
+
+        let mut created = Vec::new();
+        let snapshot =
+            create_snapshot_with_progress(&db, "s1", project.path(), None, |p| created.push(p))
+                .unwrap();
+        let counts: Vec<(usize, usize)> = created.iter().map(|p| (p.processed, p.total)).collect();
+        assert_eq!(counts, vec![(0, 250), (100, 250), (200, 250), (250, 250)]);
+        assert!(created.iter().all(|p| p.snapshot_id == snapshot.id));
+
+        let mut reverted = Vec::new();
+        revert_to_snapshot_with_progress(
+            &db,
+            &snapshot.id,
+            project.path(),
+            LineEndingMode::Preserve,
+            GitRevertMode::Reset,
+            |p| reverted.push(p),
+        )
+        .unwrap();
+        assert_eq!(
+            reverted.last().map(|p| (p.processed, p.total)),
+            Some((250, 250))
+        );
+        assert!(reverted.iter().all(|p| p.snapshot_id == snapshot.id));
+    }
+
+    #[test]
+    fn test_large_files_are_skipped_by_default() {
+        let project = create_test_dir();
+        let (_db_dir, db) = test_db();
+        let big = vec![b'x'; MAX_BACKUP_FILE_SIZE as usize + 1];
+        fs::write(project.path().join("big.bin"), &big).unwrap();
+        fs::write(project.path().join("small.txt"), "small").unwrap();
+
+        let snapshot =
+            create_file_backup_snapshot(&db, "s1", project.path(), None, &mut |_| {}).unwrap();
+        let metadata = file_backup_metadata(&snapshot).unwrap();
+        assert_eq!(metadata.skipped_large, vec!["big.bin"]);
+        assert!(metadata.sidecars.is_empty());
+
+        let report = revert_file_backup_snapshot(
+            &db,
+            &snapshot,
+            project.path(),
+            LineEndingMode::Preserve,
+            &mut |_| {},
+        )
+        .unwrap();
+        assert_eq!(report.restored, 1);
+        assert_eq!(report.not_backed_up, vec!["big.bin"]);
+    }
+
+    #[test]
+    fn test_large_files_round_trip_through_sidecar() {
+        let project = create_test_dir();
+        let (db_dir, db) = test_db();
+        db.set_setting(BACKUP_LARGE_FILES_SETTING, Some("true"))
+            .unwrap();
+        let big: Vec<u8> = (0..MAX_BACKUP_FILE_SIZE + 10)
+            .map(|i| (i % 251) as u8)
+            .collect();
+        fs::write(project.path().join("big.bin"), &big).unwrap();
+
+        let snapshot =
+            create_file_backup_snapshot(&db, "s1", project.path(), None, &mut |_| {}).unwrap();
+        let metadata = file_backup_metadata(&snapshot).unwrap();
+        assert!(metadata.skipped_large.is_empty());
+        let hash = &metadata.sidecars["big.bin"];
+        assert!(db_dir.path().join("snapshot-files").join(hash).is_file());
+        // Nothing large went into the database
+        assert_eq!(db.get_snapshot_blob_usage().unwrap().blobs, 0);
+
+        fs::write(project.path().join("big.bin"), "overwritten").unwrap();
+        let report = revert_file_backup_snapshot(
+            &db,
+            &snapshot,
+            project.path(),
+            LineEndingMode::Native,
+            &mut |_| {},
+        )
+        .unwrap();
+        assert_eq!(report.restored, 1);
+        assert!(report.not_backed_up.is_empty());
+        assert_eq!(fs::read(project.path().join("big.bin")).unwrap(), big);
+
+        // A missing sidecar file fails the revert instead of silently skipping
+        fs::remove_file(db_dir.path().join("snapshot-files").join(hash)).unwrap();
+        assert!(revert_file_backup_snapshot(
+            &db,
+            &snapshot,
+            project.path(),
+            LineEndingMode::Preserve,
+            &mut |_| {}
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn test_revert_migrates_legacy_metadata() {
+        let project = create_test_dir();
+        let (_db_dir, db) = test_db();
+        let legacy = backup_snapshot(&[("a.txt", b"legacy a"), ("b.txt", b"legacy b")]);
+        db.insert_snapshot(&legacy).unwrap();
+        assert_eq!(
+            db.get_file_backups_before_version(FILE_BACKUP_METADATA_VERSION)
+                .unwrap()
+                .len(),
+            1
+        );
+
+        let report = revert_file_backup_snapshot(
+            &db,
+            &legacy,
+            project.path(),
+            LineEndingMode::Preserve,
+            &mut |_| {},
+        )
+        .unwrap();
+        assert_eq!(report.restored, 2);
+        assert_eq!(fs::read(project.path().join("a.txt")).unwrap(), b"legacy a");
+
+        // The row now has the current shape, with contents moved into blobs
+        let stored = db.get_snapshot(&legacy.id).unwrap().unwrap();
+        let metadata = file_backup_metadata(&stored).unwrap();
+        assert_eq!(metadata.version, FILE_BACKUP_METADATA_VERSION);
+        assert!(metadata.files.is_empty());
+        assert_eq!(metadata.blobs["b.txt"], blob_hash(b"legacy b"));
+        assert_eq!(
+            db.get_snapshot_blob_refcount(&blob_hash(b"legacy a"))
+                .unwrap(),
+            Some(1)
+        );
+        assert!(db
+            .get_file_backups_before_version(FILE_BACKUP_METADATA_VERSION)
+            .unwrap()
+            .is_empty());
+
+        // Reverting the stale in-memory copy again works from the migrated row
+        fs::write(project.path().join("a.txt"), "changed").unwrap();
+        revert_file_backup_snapshot(
+            &db,
+            &stored,
+            project.path(),
+            LineEndingMode::Preserve,
+            &mut |_| {},
+        )
+        .unwrap();
+        assert_eq!(fs::read(project.path().join("a.txt")).unwrap(), b"legacy a");
+
+        // Deleting the snapshot releases the migrated blobs
+        db.delete_snapshots(std::slice::from_ref(&legacy.id))
+            .unwrap();
+        assert_eq!(
+            db.get_snapshot_blob_refcount(&blob_hash(b"legacy a"))
+                .unwrap(),
+            None
+        );
+    }
+
+    #[test]
+    fn test_current_metadata_is_not_migrated() {
+        let project = create_test_dir();
+        let (_db_dir, db) = test_db();
+        fs::write(project.path().join("a.txt"), "current").unwrap();
+        let snapshot =
+            create_file_backup_snapshot(&db, "s1", project.path(), None, &mut |_| {}).unwrap();
+        assert_eq!(
+            file_backup_metadata(&snapshot).unwrap().version,
+            FILE_BACKUP_METADATA_VERSION
+        );
+        assert!(migrate_snapshot_metadata(&db, &snapshot).unwrap().is_none());
+
+        db.insert_snapshot(&backup_snapshot(&[("old.txt", b"old")]))
+            .unwrap();
+        assert_eq!(migrate_legacy_snapshots(&db).unwrap(), 1);
+        assert_eq!(migrate_legacy_snapshots(&db).unwrap(), 0);
+
+        fs::write(project.path().join("a.txt"), "changed").unwrap();
+        let report = revert_file_backup_snapshot(
+            &db,
+            &snapshot,
+            project.path(),
+            LineEndingMode::Preserve,
+            &mut |_| {},
+        )
+        .unwrap();
+        assert_eq!(report.restored, 1);
+        assert_eq!(
+            fs::read_to_string(project.path().join("a.txt")).unwrap(),
+            "current"
+        );
+        assert_eq!(
+            db.get_snapshot(&snapshot.id)
+                .unwrap()
+                .unwrap()
+                .metadata_json,
+            snapshot.metadata_json
+        );
+    }
+
+    #[test]
+    fn test_revert_fails_on_missing_blob() {
+        let project = create_test_dir();
+        let (_db_dir, db) = test_db();
+        let metadata = FileBackupMetadata {
+            version: FILE_BACKUP_METADATA_VERSION,
+            files: HashMap::new(),
+            blobs: HashMap::from([("a.txt".to_string(), blob_hash(b"gone"))]),
+            sidecars: HashMap::new(),
+            skipped_large: Vec::new(),
+            skipped_non_utf8: Vec::new(),
+            modes: HashMap::new(),
+            symlinks: HashMap::new(),
+            description: "test".to_string(),
+        };
+        let snapshot = Snapshot::new_file_backup("s1", &serde_json::to_string(&metadata).unwrap());
+
+        assert!(revert_file_backup_snapshot(
+            &db,
+            &snapshot,
+            project.path(),
+            LineEndingMode::Preserve,
+            &mut |_| {}
+        )
+        .is_err());
+    }
 }
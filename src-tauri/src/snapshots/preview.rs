@@ -0,0 +1,258 @@
+//! Listing what a snapshot holds, without reverting to it
+
+use std::collections::HashMap;
+use std::path::Path;
+use std::process::Command;
+
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
+use serde::Serialize;
+
+use super::{file_backup_metadata, git_snapshot_ref, sidecar};
+use crate::database::{Database, Snapshot};
+use crate::{Error, Result};
+
+/// A file a snapshot would restore
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SnapshotFileEntry {
+    /// Path relative to the project root
+    pub path: String,
+    /// Size of the stored contents, or None if the snapshot removes the file
+    pub size: Option<u64>,
+    /// Whether the file exists in the project right now
+    pub exists_now: bool,
+}
+
+/// List the files a snapshot covers, sorted by path
+///
+/// For file backups these are the backed-up files; for git snapshots, the
+/// files changed by the snapshot commit (or stash).
+pub fn list_snapshot_files(
+    db: &Database,
+    snapshot_id: &str,
+    project_path: &Path,
+) -> Result<Vec<SnapshotFileEntry>> {
+    let canonical_path = project_path
+        .canonicalize()
+        .map_err(|_| Error::Other("Invalid or non-existent path".to_string()))?;
+
+    let snapshot = db
+        .get_snapshot(snapshot_id)?
+        .ok_or_else(|| Error::SnapshotNotFound(snapshot_id.to_string()))?;
+
+    let sizes = match snapshot.snapshot_type.as_str() {
+        "file_backup" => file_backup_sizes(db, &snapshot)?,
+        "git_ghost" => git_snapshot_sizes(&snapshot, &canonical_path)?,
+        other => return Err(Error::Other(format!("Unknown snapshot type: {other}"))),
+    };
+
+    let mut entries: Vec<SnapshotFileEntry> = sizes
+        .into_iter()
+        .map(|(path, size)| SnapshotFileEntry {
+            exists_now: canonical_path.join(&path).is_file(),
+            path,
+            size,
+        })
+        .collect();
+    entries.sort_by(|a, b| a.path.cmp(&b.path));
+    Ok(entries)
+}
+
+/// Sizes of the files stored in a file backup
+fn file_backup_sizes(db: &Database, snapshot: &Snapshot) -> Result<HashMap<String, Option<u64>>> {
+    let metadata = file_backup_metadata(snapshot)?;
+
+    let mut sizes = HashMap::new();
+    for (path, content) in &metadata.files {
+        let size = BASE64.decode(content).ok().map(|bytes| bytes.len() as u64);
+        sizes.insert(path.clone(), size);
+    }
+    for (path, hash) in &metadata.blobs {
+        sizes.insert(path.clone(), db.get_snapshot_blob_size(hash)?);
+    }
+    if !metadata.sidecars.is_empty() {
+        let dir = sidecar::sidecar_dir(db)?;
+        for (path, hash) in &metadata.sidecars {
+            let size = sidecar::sidecar_path(&dir, hash)
+                .ok()
+                .and_then(|p| std::fs::metadata(p).ok())
+                .map(|m| m.len());
+            sizes.insert(path.clone(), size);
+        }
+    }
+    Ok(sizes)
+}
+
+/// Run git and return stdout, failing on a non-zero exit
+pub(super) fn git_output(project_path: &Path, args: &[&str]) -> Result<Vec<u8>> {
+    let output = Command::new("git")
+        .args(args)
+        .current_dir(project_path)
+        .output()
+        .map_err(|e| Error::Git(format!("Failed to run git: {e}")))?;
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(Error::Git(format!(
+            "git {} failed: {}",
+            args.join(" "),
+            stderr.trim()
+        )));
+    }
+    Ok(output.stdout)
+}
+
+/// Files changed by a git snapshot's commit, with their size in that commit
+///
+/// Stashes are diffed against their first parent (the commit they were made
+/// on), like `git stash show`.
+fn git_snapshot_sizes(
+    snapshot: &Snapshot,
+    project_path: &Path,
+) -> Result<HashMap<String, Option<u64>>> {
+    let commit_ref = git_snapshot_ref(snapshot)?;
+
+    let commit = format!("{commit_ref}^{{commit}}");
+    let parent = format!("{commit_ref}^1");
+    let has_parent = Command::new("git")
+        .args([
+            "rev-parse",
+            "--verify",
+            "--quiet",
+            &format!("{parent}^{{commit}}"),
+        ])
+        .current_dir(project_path)
+        .output()
+        .map(|o| o.status.success())
+        .unwrap_or(false);
+
+    let names = if has_parent {
+        git_output(
+            project_path,
+            &[
+                "diff",
+                "--name-only",
+                "-z",
+                "--no-renames",
+                &parent,
+                &commit,
+                "--",
+            ],
+        )?
+    } else {
+        // A root commit adds every file it contains
+        git_output(
+            project_path,
+            &["ls-tree", "-r", "--name-only", "-z", &commit],
+        )?
+    };
+    let mut sizes: HashMap<String, Option<u64>> = names
+        .split(|&b| b == 0)
+        .filter(|name| !name.is_empty())
+        .map(|name| (String::from_utf8_lossy(name).into_owned(), None))
+        .collect();
+
+    // `<mode> blob <hash> <size>\t<path>` for every file in the commit
+    let tree = git_output(project_path, &["ls-tree", "-r", "-l", "-z", &commit])?;
+    for record in tree.split(|&b| b == 0).filter(|r| !r.is_empty()) {
+        let record = String::from_utf8_lossy(record);
+        let Some((info, path)) = record.split_once('\t') else {
+            continue;
+        };
+        if let Some(size) = sizes.get_mut(path) {
+            *size = info.split_whitespace().nth(3).and_then(|s| s.parse().ok());
+        }
+    }
+    Ok(sizes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::snapshots::create_file_backup_snapshot;
+    use std::fs;
+
+    fn test_db() -> (tempfile::TempDir, Database) {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let db = Database::new(&temp_dir.path().join("test.db")).unwrap();
+        (temp_dir, db)
+    }
+
+    fn git(dir: &Path, args: &[&str]) -> bool {
+        Command::new("git")
+            .args(["-c", "user.name=Test", "-c", "user.email=test@example.com"])
+            .args(args)
+            .current_dir(dir)
+            .status()
+            .map(|s| s.success())
+            .unwrap_or(false)
+    }
+
+    #[test]
+    fn test_preview_file_backup() {
+        let project = tempfile::tempdir().unwrap();
+        let (_db_dir, db) = test_db();
+        fs::write(project.path().join("keep.txt"), "12345").unwrap();
+        fs::write(project.path().join("doomed.txt"), "abc").unwrap();
+        let snapshot =
+            create_file_backup_snapshot(&db, "s1", project.path(), None, &mut |_| {}).unwrap();
+        fs::remove_file(project.path().join("doomed.txt")).unwrap();
+
+        let entries = list_snapshot_files(&db, &snapshot.id, project.path()).unwrap();
+        assert_eq!(
+            entries,
+            vec![
+                SnapshotFileEntry {
+                    path: "doomed.txt".to_string(),
+                    size: Some(3),
+                    exists_now: false,
+                },
+                SnapshotFileEntry {
+                    path: "keep.txt".to_string(),
+                    size: Some(5),
+                    exists_now: true,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_preview_git_snapshot() {
+        let repo = tempfile::tempdir().unwrap();
+        if !git(repo.path(), &["init", "-q"]) {
+            return;
+        }
+        fs::write(repo.path().join("a.txt"), "one\n").unwrap();
+        assert!(git(repo.path(), &["add", "."]));
+        assert!(git(repo.path(), &["commit", "-q", "-m", "init"]));
+        fs::write(repo.path().join("a.txt"), "one\ntwo\n").unwrap();
+        fs::write(repo.path().join("b.txt"), "new\n").unwrap();
+        assert!(git(repo.path(), &["add", "."]));
+        assert!(git(repo.path(), &["commit", "-q", "-m", "second"]));
+
+        let head = git_output(repo.path(), &["rev-parse", "HEAD"]).unwrap();
+        let head = String::from_utf8(head).unwrap();
+        let (_db_dir, db) = test_db();
+        let snapshot = Snapshot::new_git_ghost("s1", head.trim());
+        db.insert_snapshot(&snapshot).unwrap();
+        fs::remove_file(repo.path().join("b.txt")).unwrap();
+
+        let entries = list_snapshot_files(&db, &snapshot.id, repo.path()).unwrap();
+        let summary: Vec<(&str, Option<u64>, bool)> = entries
+            .iter()
+            .map(|e| (e.path.as_str(), e.size, e.exists_now))
+            .collect();
+        assert_eq!(
+            summary,
+            vec![("a.txt", Some(8), true), ("b.txt", Some(4), false)]
+        );
+    }
+
+    #[test]
+    fn test_preview_rejects_unsafe_refs() {
+        let project = tempfile::tempdir().unwrap();
+        let (_db_dir, db) = test_db();
+        let snapshot = Snapshot::new_git_ghost("s1", "--output=/tmp/x");
+        db.insert_snapshot(&snapshot).unwrap();
+        assert!(list_snapshot_files(&db, &snapshot.id, project.path()).is_err());
+    }
+}
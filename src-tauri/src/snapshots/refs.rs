@@ -0,0 +1,181 @@
+//! Git refs naming each git snapshot
+//!
+//! When the `snapshot_git_refs` setting is on, every git snapshot also gets a
+//! lightweight ref `refs/codex-snapshots/<id>` pointing at its commit, so it
+//! shows up in `git log --all` and other git tooling and isn't garbage
+//! collected while the snapshot exists. Refs whose snapshot has been deleted
+//! are pruned by the snapshot cleanup commands, and otherwise the next time
+//! a snapshot of the same repository is taken.
+
+use std::path::Path;
+
+use super::is_git_repo;
+use super::preview::git_output;
+use crate::database::Database;
+use crate::Result;
+
+/// `app_settings` key enabling a git ref per git snapshot ("true"/"false")
+pub const GIT_SNAPSHOT_REFS_SETTING: &str = "snapshot_git_refs";
+
+/// Namespace holding the snapshot refs, outside branches and tags
+const SNAPSHOT_REF_PREFIX: &str = "refs/codex-snapshots/";
+
+/// Whether git snapshots should get a ref under `refs/codex-snapshots/`
+pub fn git_snapshot_refs_enabled(db: &Database) -> Result<bool> {
+    Ok(db.get_setting(GIT_SNAPSHOT_REFS_SETTING)?.as_deref() == Some("true"))
+}
+
+/// Full name of the ref for a snapshot
+pub(super) fn snapshot_ref_name(snapshot_id: &str) -> String {
+    format!("{SNAPSHOT_REF_PREFIX}{snapshot_id}")
+}
+
+/// Point the snapshot's ref at `commit`, creating or moving it
+pub(super) fn create_snapshot_ref(
+    project_path: &Path,
+    snapshot_id: &str,
+    commit: &str,
+) -> Result<()> {
+    git_output(
+        project_path,
+        &["update-ref", &snapshot_ref_name(snapshot_id), commit],
+    )?;
+    Ok(())
+}
+
+/// Delete the refs in `project_path` whose snapshot no longer exists
+///
+/// Returns the number of refs removed.
+pub fn prune_snapshot_refs(db: &Database, project_path: &Path) -> Result<usize> {
+    let listed = git_output(
+        project_path,
+        &["for-each-ref", "--format=%(refname)", SNAPSHOT_REF_PREFIX],
+    )?;
+    let mut removed = 0;
+    for ref_name in String::from_utf8_lossy(&listed).lines() {
+        let Some(snapshot_id) = ref_name.strip_prefix(SNAPSHOT_REF_PREFIX) else {
+            continue;
+        };
+        if db.get_snapshot(snapshot_id)?.is_some() {
+            continue;
+        }
+        git_output(project_path, &["update-ref", "-d", ref_name])?;
+        removed += 1;
+    }
+    Ok(removed)
+}
+
+/// Prune stale snapshot refs in every project that is a git repository
+///
+/// Best effort: failures are logged per project. Returns the number of refs removed.
+pub fn prune_all_snapshot_refs(db: &Database) -> Result<usize> {
+    let mut removed = 0;
+    for project in db.get_all_projects()? {
+        let path = Path::new(&project.path);
+        if !is_git_repo(path) {
+            continue;
+        }
+        match prune_snapshot_refs(db, path) {
+            Ok(count) => removed += count,
+            Err(e) => tracing::warn!("Failed to prune snapshot refs in {}: {}", project.path, e),
+        }
+    }
+    Ok(removed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::{Project, SessionMetadata};
+
+    fn git(dir: &Path, args: &[&str]) -> bool {
+        std::process::Command::new("git")
+            .args(["-c", "user.name=Test", "-c", "user.email=test@example.com"])
+            .args(args)
+            .current_dir(dir)
+            .output()
+            .map(|o| o.status.success())
+            .unwrap_or(false)
+    }
+
+    fn resolve(repo: &Path, name: &str) -> Option<String> {
+        git_output(repo, &["rev-parse", "--verify", "--quiet", name])
+            .ok()
+            .map(|out| String::from_utf8_lossy(&out).trim().to_string())
+    }
+
+    #[test]
+    fn test_snapshot_ref_follows_snapshot_lifetime() {
+        let repo = tempfile::tempdir().unwrap();
+        if !git(repo.path(), &["init", "-q"]) {
+            return;
+        }
+        std::fs::write(repo.path().join("main.rs"), "fn main() {}\n").unwrap();
+        assert!(git(repo.path(), &["add", "."]));
+        assert!(git(repo.path(), &["commit", "-q", "-m", "init"]));
+
+        let db_dir = tempfile::tempdir().unwrap();
+        let db = Database::new(&db_dir.path().join("test.db")).unwrap();
+        db.insert_project(&Project::new(repo.path().to_str().unwrap()))
+            .unwrap();
+        let head = resolve(repo.path(), "HEAD").unwrap();
+
+        // Off by default
+        let untagged = crate::snapshots::create_snapshot(&db, "s1", repo.path(), None).unwrap();
+        assert_eq!(resolve(repo.path(), &snapshot_ref_name(&untagged.id)), None);
+
+        db.set_setting(GIT_SNAPSHOT_REFS_SETTING, Some("true"))
+            .unwrap();
+        let clean = crate::snapshots::create_snapshot(&db, "s1", repo.path(), None).unwrap();
+        assert_eq!(
+            resolve(repo.path(), &snapshot_ref_name(&clean.id)),
+            Some(head.clone())
+        );
+
+        std::fs::write(repo.path().join("main.rs"), "fn main() { run(); }\n").unwrap();
+        let dirty = crate::snapshots::create_snapshot(&db, "s1", repo.path(), None).unwrap();
+        let stash = resolve(repo.path(), &snapshot_ref_name(&dirty.id)).unwrap();
+        assert!(dirty.metadata_json.as_deref().unwrap().contains(&stash));
+        assert_ne!(stash, head);
+
+        // Deleting the snapshot lets its ref be pruned; the others stay
+        db.delete_snapshots(std::slice::from_ref(&clean.id))
+            .unwrap();
+        assert_eq!(prune_all_snapshot_refs(&db).unwrap(), 1);
+        assert_eq!(resolve(repo.path(), &snapshot_ref_name(&clean.id)), None);
+        assert_eq!(
+            resolve(repo.path(), &snapshot_ref_name(&dirty.id)),
+            Some(stash)
+        );
+        assert_eq!(prune_snapshot_refs(&db, repo.path()).unwrap(), 0);
+    }
+
+    #[test]
+    fn test_refs_of_permanently_deleted_sessions_are_pruned() {
+        let repo = tempfile::tempdir().unwrap();
+        if !git(repo.path(), &["init", "-q"]) {
+            return;
+        }
+        std::fs::write(repo.path().join("main.rs"), "fn main() {}\n").unwrap();
+        assert!(git(repo.path(), &["add", "."]));
+        assert!(git(repo.path(), &["commit", "-q", "-m", "init"]));
+
+        let db_dir = tempfile::tempdir().unwrap();
+        let db = Database::new(&db_dir.path().join("test.db")).unwrap();
+        let project = Project::new(repo.path().to_str().unwrap());
+        db.insert_project(&project).unwrap();
+        db.set_setting(GIT_SNAPSHOT_REFS_SETTING, Some("true"))
+            .unwrap();
+        for session_id in ["s1", "s2"] {
+            db.upsert_session_metadata(&SessionMetadata::new(session_id, &project.id))
+                .unwrap();
+        }
+        let doomed = crate::snapshots::create_snapshot(&db, "s1", repo.path(), None).unwrap();
+        let kept = crate::snapshots::create_snapshot(&db, "s2", repo.path(), None).unwrap();
+
+        assert_eq!(db.delete_sessions_metadata(&["s1".to_string()]).unwrap(), 1);
+        assert_eq!(prune_all_snapshot_refs(&db).unwrap(), 1);
+        assert_eq!(resolve(repo.path(), &snapshot_ref_name(&doomed.id)), None);
+        assert!(resolve(repo.path(), &snapshot_ref_name(&kept.id)).is_some());
+    }
+}
@@ -0,0 +1,210 @@
+//! Large file backups stored beside the database
+//!
+//! Files over `MAX_BACKUP_FILE_SIZE` are too big to keep in SQLite. When the
+//! `snapshot_backup_large_files` setting is on, file backups stream them into
+//! a `snapshot-files` directory next to the database instead, named by the
+//! SHA-256 of their contents so unchanged files are stored once. Sidecar
+//! files are not carried in snapshot archives or project bundles.
+
+use std::fs;
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+use sha2::{Digest, Sha256};
+
+use crate::database::Database;
+use crate::{Error, Result};
+
+/// `app_settings` key enabling sidecar backups of large files ("true"/"false")
+pub const BACKUP_LARGE_FILES_SETTING: &str = "snapshot_backup_large_files";
+
+/// Files larger than this are skipped even when large file backups are on
+pub(super) const MAX_SIDECAR_FILE_SIZE: u64 = 512 * 1024 * 1024;
+
+/// Unreferenced sidecar files younger than this are kept, since a snapshot
+/// being created may have stored them without inserting its row yet
+const PRUNE_GRACE_PERIOD: Duration = Duration::from_secs(60 * 60);
+
+/// Whether large files should be backed up to sidecar files
+pub fn backup_large_files_enabled(db: &Database) -> Result<bool> {
+    Ok(db.get_setting(BACKUP_LARGE_FILES_SETTING)?.as_deref() == Some("true"))
+}
+
+/// Directory holding sidecar files for `db`
+pub(super) fn sidecar_dir(db: &Database) -> Result<PathBuf> {
+    db.data_dir()
+        .map(|dir| dir.join("snapshot-files"))
+        .ok_or_else(|| Error::Other("Large file backups need an on-disk database".to_string()))
+}
+
+/// Whether `name` is a sidecar file name: a lowercase hex SHA-256
+fn is_sidecar_name(name: &str) -> bool {
+    name.len() == 64 && name.bytes().all(|b| matches!(b, b'0'..=b'9' | b'a'..=b'f'))
+}
+
+/// Path of the sidecar file for `hash`
+///
+/// Security: the hash comes from snapshot metadata, so it must not be able to
+/// name anything outside the sidecar directory.
+pub(super) fn sidecar_path(dir: &Path, hash: &str) -> Result<PathBuf> {
+    if !is_sidecar_name(hash) {
+        return Err(Error::Other(format!("Invalid sidecar file hash: {hash:?}")));
+    }
+    Ok(dir.join(hash))
+}
+
+/// Stream `source` into the sidecar directory, returning the hash it is stored under
+pub(super) fn store_sidecar_file(dir: &Path, source: &Path) -> Result<String> {
+    store_sidecar_contents(dir, &mut fs::File::open(source)?)
+}
+
+/// Stream `input` into the sidecar directory, returning the hash it is stored under
+///
+/// The contents are copied to a temporary name while hashing and then renamed
+/// into place, replacing any identical copy so its modification time is fresh.
+pub(super) fn store_sidecar_contents(dir: &Path, input: &mut dyn Read) -> Result<String> {
+    store_sidecar_contents_tracked(dir, input).map(|(hash, _)| hash)
+}
+
+/// Like `store_sidecar_contents`, also reporting whether the file is new
+///
+/// A new file is the caller's to remove if no snapshot ends up referencing it.
+pub(super) fn store_sidecar_contents_tracked(
+    dir: &Path,
+    input: &mut dyn Read,
+) -> Result<(String, bool)> {
+    fs::create_dir_all(dir)?;
+    let temp_path = dir.join(format!(".{}.tmp", uuid::Uuid::new_v4()));
+
+    let mut copy = || -> Result<String> {
+        let mut output = fs::File::create(&temp_path)?;
+        let mut hasher = Sha256::new();
+        let mut buf = vec![0u8; 64 * 1024];
+        loop {
+            let n = input.read(&mut buf)?;
+            if n == 0 {
+                break;
+            }
+            hasher.update(&buf[..n]);
+            output.write_all(&buf[..n])?;
+        }
+        output.sync_all()?;
+        Ok(format!("{:x}", hasher.finalize()))
+    };
+
+    let hash = match copy() {
+        Ok(hash) => hash,
+        Err(e) => {
+            let _ = fs::remove_file(&temp_path);
+            return Err(e);
+        }
+    };
+    let created = !dir.join(&hash).exists();
+    fs::rename(&temp_path, dir.join(&hash))?;
+    Ok((hash, created))
+}
+
+/// Remove sidecar files stored for a snapshot that was never inserted
+///
+/// Files a snapshot references after all are kept.
+pub(super) fn discard_sidecar_files(db: &Database, hashes: &[String]) -> Result<()> {
+    let dir = sidecar_dir(db)?;
+    let referenced = db.get_referenced_sidecar_hashes()?;
+    for hash in hashes.iter().filter(|hash| !referenced.contains(*hash)) {
+        if let Err(e) = fs::remove_file(sidecar_path(&dir, hash)?) {
+            tracing::warn!("Failed to remove sidecar file {}: {}", hash, e);
+        }
+    }
+    Ok(())
+}
+
+/// Delete sidecar files no snapshot references any more, returning how many were removed
+pub(super) fn prune_sidecar_files(db: &Database) -> Result<usize> {
+    let dir = sidecar_dir(db)?;
+    if !dir.is_dir() {
+        return Ok(0);
+    }
+
+    let referenced = db.get_referenced_sidecar_hashes()?;
+    let cutoff = SystemTime::now() - PRUNE_GRACE_PERIOD;
+
+    let mut removed = 0;
+    for entry in fs::read_dir(&dir)?.filter_map(|e| e.ok()) {
+        let name = entry.file_name().to_string_lossy().to_string();
+        if !is_sidecar_name(&name) || referenced.contains(&name) {
+            continue;
+        }
+        let recent = entry
+            .metadata()
+            .and_then(|m| m.modified())
+            .map_or(true, |modified| modified > cutoff);
+        if recent {
+            continue;
+        }
+        match fs::remove_file(entry.path()) {
+            Ok(()) => removed += 1,
+            Err(e) => tracing::warn!("Failed to remove sidecar file {}: {}", name, e),
+        }
+    }
+    Ok(removed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_store_sidecar_file_is_content_addressed() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let dir = temp_dir.path().join("snapshot-files");
+        let source = temp_dir.path().join("big.bin");
+        fs::write(&source, b"large contents").unwrap();
+
+        let hash = store_sidecar_file(&dir, &source).unwrap();
+        assert_eq!(hash, format!("{:x}", Sha256::digest(b"large contents")));
+        assert_eq!(
+            fs::read(sidecar_path(&dir, &hash).unwrap()).unwrap(),
+            b"large contents"
+        );
+
+        // Storing the same contents again leaves one file and no temporaries
+        assert_eq!(store_sidecar_file(&dir, &source).unwrap(), hash);
+        assert_eq!(fs::read_dir(&dir).unwrap().count(), 1);
+    }
+
+    #[test]
+    fn test_prune_removes_only_old_unreferenced_files() {
+        use crate::database::Snapshot;
+
+        let temp_dir = tempfile::tempdir().unwrap();
+        let db = Database::new(&temp_dir.path().join("test.db")).unwrap();
+        let dir = sidecar_dir(&db).unwrap();
+        fs::create_dir_all(&dir).unwrap();
+
+        let (kept, stale, fresh) = ("a".repeat(64), "b".repeat(64), "c".repeat(64));
+        let old = SystemTime::now() - 2 * PRUNE_GRACE_PERIOD;
+        for name in [&kept, &stale] {
+            let file = fs::File::create(dir.join(name)).unwrap();
+            file.set_modified(old).unwrap();
+        }
+        fs::write(dir.join(&fresh), "").unwrap();
+        let metadata =
+            serde_json::json!({ "blobs": {}, "sidecars": { "big.bin": kept }, "description": "" });
+        db.insert_snapshot(&Snapshot::new_file_backup("s1", &metadata.to_string()))
+            .unwrap();
+
+        assert_eq!(prune_sidecar_files(&db).unwrap(), 1);
+        assert!(dir.join(&kept).exists());
+        assert!(!dir.join(&stale).exists());
+        assert!(dir.join(&fresh).exists());
+    }
+
+    #[test]
+    fn test_sidecar_path_rejects_traversal() {
+        let dir = Path::new("/data/snapshot-files");
+        assert!(sidecar_path(dir, "../codex-desktop.db").is_err());
+        assert!(sidecar_path(dir, &"A".repeat(64)).is_err());
+        assert!(sidecar_path(dir, &"a".repeat(64)).is_ok());
+    }
+}
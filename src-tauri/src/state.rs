@@ -1,12 +1,20 @@
 //! Application state management
 
+use std::collections::HashSet;
+use std::sync::atomic::AtomicBool;
 use std::sync::Arc;
 use tauri::{AppHandle, Emitter, Manager};
 use tokio::sync::RwLock;
 
-use crate::app_server::AppServerProcess;
-use crate::database::Database;
-use crate::Result;
+use crate::app_server::{
+    AccountCache, AppServerProcess, PendingApprovals, RecentEvents, RequestCanceller,
+};
+use crate::codex_import::SessionItemsCache;
+use crate::commands::app_server::CachedModels;
+use crate::database::{Database, DatabaseHealth};
+use crate::snapshots::SnapshotLocks;
+use crate::tasks::BackgroundTasks;
+use crate::{AppServerStartupInfo, Result};
 
 /// Global application state
 pub struct AppState {
@@ -18,6 +26,45 @@ pub struct AppState {
 
     /// Tauri app handle for emitting events
     pub app_handle: AppHandle,
+
+    /// Running background operations (streaming searches, etc.), cancellable by id
+    pub background_tasks: BackgroundTasks,
+
+    /// Projects with a snapshot being created or reverted
+    pub snapshot_locks: SnapshotLocks,
+
+    /// Canceller for the running app server's requests, usable while a request holds the server
+    /// lock
+    pub request_canceller: Arc<parking_lot::Mutex<Option<RequestCanceller>>>,
+
+    /// Unanswered approval requests of the running app server, readable without the server lock
+    pub pending_approvals: Arc<parking_lot::Mutex<Option<PendingApprovals>>>,
+
+    /// Recent notifications of the running app server, for replay to late subscribers
+    pub recent_events: Arc<parking_lot::Mutex<Option<RecentEvents>>>,
+
+    /// Ids of logins started with the running app server that haven't been cancelled or completed
+    pub pending_logins: Arc<parking_lot::Mutex<HashSet<String>>>,
+
+    /// Whether raw app-server traffic is mirrored to the debug console (off by default)
+    pub ipc_debug: Arc<AtomicBool>,
+
+    /// Account info from the most recent `account/read` call
+    pub account_cache: AccountCache,
+
+    /// Parsed messages of the thread being paged through with `get_thread_items`
+    pub session_items_cache: SessionItemsCache,
+
+    /// Model list from the most recent `model/list` call, with the `account_cache`
+    /// generation it was read in; the list depends on the account, so it is
+    /// stale once the account cache is invalidated
+    pub model_cache: Arc<RwLock<Option<CachedModels>>>,
+
+    /// Why the last attempt to start the app server failed, cleared on success
+    pub last_startup_error: Arc<parking_lot::Mutex<Option<AppServerStartupInfo>>>,
+
+    /// Report of the most recent database health check, if one has finished
+    pub last_database_health: Arc<parking_lot::Mutex<Option<DatabaseHealth>>>,
 }
 
 impl AppState {
@@ -42,6 +89,18 @@ impl AppState {
             database,
             app_server: Arc::new(RwLock::new(None)),
             app_handle: app_handle.clone(),
+            background_tasks: BackgroundTasks::default(),
+            snapshot_locks: SnapshotLocks::default(),
+            request_canceller: Arc::new(parking_lot::Mutex::new(None)),
+            pending_approvals: Arc::new(parking_lot::Mutex::new(None)),
+            recent_events: Arc::new(parking_lot::Mutex::new(None)),
+            pending_logins: Arc::new(parking_lot::Mutex::new(HashSet::new())),
+            ipc_debug: Arc::new(AtomicBool::new(false)),
+            account_cache: AccountCache::default(),
+            session_items_cache: SessionItemsCache::default(),
+            model_cache: Arc::new(RwLock::new(None)),
+            last_startup_error: Arc::new(parking_lot::Mutex::new(None)),
+            last_database_health: Arc::new(parking_lot::Mutex::new(None)),
         })
     }
 
@@ -49,7 +108,26 @@ impl AppState {
     pub async fn start_app_server(&self) -> Result<()> {
         let mut server = self.app_server.write().await;
         if server.is_none() {
-            let process = AppServerProcess::spawn(self.app_handle.clone()).await?;
+            let process = match AppServerProcess::spawn(
+                self.app_handle.clone(),
+                self.ipc_debug.clone(),
+                self.account_cache.clone(),
+                self.pending_logins.clone(),
+            )
+            .await
+            {
+                Ok(process) => process,
+                Err(e) => {
+                    if let crate::Error::AppServerStartup(info) = &e {
+                        *self.last_startup_error.lock() = Some(info.clone());
+                    }
+                    return Err(e);
+                }
+            };
+            *self.last_startup_error.lock() = None;
+            *self.request_canceller.lock() = Some(process.request_canceller());
+            *self.pending_approvals.lock() = Some(process.pending_approvals());
+            *self.recent_events.lock() = Some(process.recent_events());
             *server = Some(process);
             tracing::info!("App server started");
         }
@@ -59,6 +137,13 @@ impl AppState {
     /// Stop the app server process
     pub async fn stop_app_server(&self) -> Result<()> {
         let mut server = self.app_server.write().await;
+        *self.request_canceller.lock() = None;
+        *self.pending_approvals.lock() = None;
+        *self.recent_events.lock() = None;
+        // Pending logins die with the server that started them, and the
+        // account and model list are read again from the next one
+        self.pending_logins.lock().clear();
+        self.account_cache.invalidate();
         if let Some(mut process) = server.take() {
             // Check if process is still running before trying to shutdown
             if process.is_running() {
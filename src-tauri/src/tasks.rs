@@ -0,0 +1,212 @@
+//! Registry of background operations
+//!
+//! Long-running work (streaming searches, snapshots, watchers) registers here
+//! so the UI can list what is running and ask for it to stop. Cancellation is
+//! cooperative: the task polls its handle and exits when asked.
+
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+use serde::Serialize;
+
+/// A running background operation
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BackgroundTaskInfo {
+    pub id: String,
+    /// What kind of work this is, e.g. `search`
+    pub kind: String,
+    /// Human-readable detail, such as the search query
+    pub description: Option<String>,
+    /// Unix timestamp when the task started
+    pub started_at: i64,
+    /// Whether the task checks for cancellation
+    pub cancellable: bool,
+    pub cancel_requested: bool,
+}
+
+struct TaskEntry {
+    info: BackgroundTaskInfo,
+    cancelled: Arc<AtomicBool>,
+}
+
+/// Shared set of running background tasks
+#[derive(Clone, Default)]
+pub struct BackgroundTasks {
+    inner: Arc<parking_lot::Mutex<HashMap<String, TaskEntry>>>,
+}
+
+impl BackgroundTasks {
+    /// Register a task; it stays listed until the returned handle is dropped
+    ///
+    /// The task gets a random id, drawn again if a caller-chosen id already
+    /// took it.
+    pub fn register(
+        &self,
+        kind: &str,
+        description: Option<String>,
+        cancellable: bool,
+    ) -> TaskHandle {
+        loop {
+            let id = uuid::Uuid::new_v4().to_string();
+            if let Some(handle) = self.register_with_id(&id, kind, description.clone(), cancellable)
+            {
+                return handle;
+            }
+        }
+    }
+
+    /// Register a task under an id chosen by the caller
+    ///
+    /// Lets the UI know the id before the task emits anything. Returns None if
+    /// a running task already has the id.
+    pub fn register_with_id(
+        &self,
+        id: &str,
+        kind: &str,
+        description: Option<String>,
+        cancellable: bool,
+    ) -> Option<TaskHandle> {
+        let id = id.to_string();
+        let cancelled = Arc::new(AtomicBool::new(false));
+        let mut tasks = self.inner.lock();
+        if tasks.contains_key(&id) {
+            return None;
+        }
+        tasks.insert(
+            id.clone(),
+            TaskEntry {
+                info: BackgroundTaskInfo {
+                    id: id.clone(),
+                    kind: kind.to_string(),
+                    description,
+                    started_at: chrono::Utc::now().timestamp(),
+                    cancellable,
+                    cancel_requested: false,
+                },
+                cancelled: cancelled.clone(),
+            },
+        );
+        Some(TaskHandle {
+            id,
+            cancelled,
+            tasks: self.clone(),
+        })
+    }
+
+    /// Running tasks, oldest first
+    pub fn list(&self) -> Vec<BackgroundTaskInfo> {
+        let mut tasks: Vec<BackgroundTaskInfo> = self
+            .inner
+            .lock()
+            .values()
+            .map(|entry| BackgroundTaskInfo {
+                cancel_requested: entry.cancelled.load(Ordering::SeqCst),
+                ..entry.info.clone()
+            })
+            .collect();
+        tasks.sort_by(|a, b| {
+            a.started_at
+                .cmp(&b.started_at)
+                .then_with(|| a.id.cmp(&b.id))
+        });
+        tasks
+    }
+
+    /// Ask a task to stop
+    ///
+    /// Returns false if the task is unknown, already finished, or doesn't
+    /// support cancellation.
+    pub fn cancel(&self, id: &str) -> bool {
+        match self.inner.lock().get(id) {
+            Some(entry) if entry.info.cancellable => {
+                entry.cancelled.store(true, Ordering::SeqCst);
+                true
+            }
+            _ => false,
+        }
+    }
+}
+
+/// Registration of one running task; unregisters it when dropped
+pub struct TaskHandle {
+    id: String,
+    cancelled: Arc<AtomicBool>,
+    tasks: BackgroundTasks,
+}
+
+impl TaskHandle {
+    pub fn id(&self) -> &str {
+        &self.id
+    }
+
+    /// Flag set when cancellation is requested, for code that polls an `AtomicBool`
+    pub fn cancel_flag(&self) -> &AtomicBool {
+        &self.cancelled
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::SeqCst)
+    }
+}
+
+impl Drop for TaskHandle {
+    fn drop(&mut self) {
+        self.tasks.inner.lock().remove(&self.id);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_register_list_and_cancel() {
+        let tasks = BackgroundTasks::default();
+        let handle = tasks.register("search", Some("query".to_string()), true);
+        let fixed = tasks.register("watcher", None, false);
+
+        let listed = tasks.list();
+        assert_eq!(listed.len(), 2);
+        let search = listed.iter().find(|t| t.id == handle.id()).unwrap();
+        assert_eq!(search.kind, "search");
+        assert!(!search.cancel_requested);
+
+        assert!(tasks.cancel(handle.id()));
+        assert!(handle.is_cancelled());
+        assert!(tasks
+            .list()
+            .iter()
+            .any(|t| t.id == handle.id() && t.cancel_requested));
+
+        // Tasks that don't poll for cancellation can't be cancelled
+        assert!(!tasks.cancel(fixed.id()));
+        assert!(!fixed.is_cancelled());
+
+        // Finished tasks drop out of the list
+        let id = handle.id().to_string();
+        drop(handle);
+        assert!(!tasks.cancel(&id));
+        assert_eq!(tasks.list().len(), 1);
+    }
+
+    #[test]
+    fn test_caller_chosen_ids_are_unique_while_running() {
+        let tasks = BackgroundTasks::default();
+        let handle = tasks
+            .register_with_id("search-1", "search", None, true)
+            .unwrap();
+        assert_eq!(handle.id(), "search-1");
+        assert!(tasks
+            .register_with_id("search-1", "search", None, true)
+            .is_none());
+        assert!(tasks.cancel("search-1"));
+
+        // The id is free again once the task finishes
+        drop(handle);
+        assert!(tasks
+            .register_with_id("search-1", "search", None, true)
+            .is_some_and(|h| !h.is_cancelled()));
+    }
+}
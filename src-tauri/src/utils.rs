@@ -1,19 +1,29 @@
 //! Utility functions for codex-desktop
 
+use crate::{Error, PathValidationError, Result};
 use std::path::Path;
-use crate::Result;
 
 /// Validate and canonicalize a path, preventing traversal attacks
 ///
 /// This function ensures that a path is valid, exists, and is canonicalized
 /// to prevent symlink attacks and path traversal vulnerabilities.
 pub fn validate_and_canonicalize_path(path: &str) -> Result<std::path::PathBuf> {
+    if path.contains('\0') {
+        return Err(Error::path_validation(path, PathValidationError::NullByte));
+    }
+    if path.is_empty() {
+        return Err(Error::path_validation(
+            path,
+            PathValidationError::InvalidCharacters,
+        ));
+    }
+
     let project_path = Path::new(path);
 
     // Canonicalize to resolve symlinks and traversal
     let canonical_path = project_path
         .canonicalize()
-        .map_err(|_| crate::Error::InvalidPath(format!("Invalid or non-existent path: {path}")))?;
+        .map_err(|_| Error::path_validation(path, PathValidationError::NotFound))?;
 
     // Ensure it's absolute (canonicalize returns absolute path)
     if !canonical_path.is_absolute() {
@@ -23,6 +33,100 @@ pub fn validate_and_canonicalize_path(path: &str) -> Result<std::path::PathBuf>
     Ok(canonical_path)
 }
 
+/// Join a relative path onto a project root, ensuring the result stays inside it
+///
+/// The root is canonicalized, the relative path must not be absolute or contain
+/// `..`, and the joined path is canonicalized (so it must exist) and checked to
+/// still be under the root after symlink resolution.
+pub fn safe_join(root: &str, relative_path: &str) -> Result<std::path::PathBuf> {
+    let canonical_root = validate_and_canonicalize_path(root)?;
+
+    let relative = Path::new(relative_path);
+    if relative_path.contains('\0') {
+        return Err(Error::path_validation(
+            relative_path,
+            PathValidationError::NullByte,
+        ));
+    }
+    if relative_path.is_empty() {
+        return Err(Error::path_validation(
+            relative_path,
+            PathValidationError::InvalidCharacters,
+        ));
+    }
+    if relative.is_absolute() {
+        return Err(Error::path_validation(
+            relative_path,
+            PathValidationError::AbsolutePath,
+        ));
+    }
+    if relative
+        .components()
+        .any(|c| matches!(c, std::path::Component::ParentDir))
+    {
+        return Err(Error::path_validation(
+            relative_path,
+            PathValidationError::ParentTraversal,
+        ));
+    }
+
+    let joined = canonical_root
+        .join(relative)
+        .canonicalize()
+        .map_err(|_| Error::path_validation(relative_path, PathValidationError::NotFound))?;
+
+    if !joined.starts_with(&canonical_root) {
+        return Err(Error::path_validation(
+            relative_path,
+            PathValidationError::DirectoryEscape,
+        ));
+    }
+
+    Ok(joined)
+}
+
+/// Resolve a file path the app is about to write, such as an export destination
+///
+/// The path must be absolute with an existing parent directory, and must not
+/// name a directory or a symlink. Returns the path under the canonical parent.
+pub fn resolve_output_file(dest_path: &str) -> Result<std::path::PathBuf> {
+    let dest = Path::new(dest_path);
+    if dest_path.contains('\0') {
+        return Err(Error::path_validation(
+            dest_path,
+            PathValidationError::NullByte,
+        ));
+    }
+    if !dest.is_absolute() {
+        return Err(Error::InvalidPath(format!(
+            "Destination must be an absolute path: {dest_path}"
+        )));
+    }
+
+    let file_name = dest
+        .file_name()
+        .ok_or_else(|| Error::InvalidPath(format!("Destination has no file name: {dest_path}")))?;
+    let parent = dest
+        .parent()
+        .ok_or_else(|| Error::InvalidPath(format!("Invalid destination: {dest_path}")))?;
+    let canonical_parent = parent.canonicalize().map_err(|_| {
+        Error::InvalidPath(format!(
+            "Destination directory does not exist: {}",
+            parent.display()
+        ))
+    })?;
+
+    let resolved = canonical_parent.join(file_name);
+    if resolved.is_dir() || resolved.is_symlink() {
+        return Err(Error::InvalidPath(format!(
+            "Destination is not a regular file: {}",
+            resolved.display()
+        )));
+    }
+
+    Ok(resolved)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -49,4 +153,88 @@ mod tests {
         // This will fail because the path doesn't exist
         assert!(result.is_err() || result.unwrap().is_absolute());
     }
+
+    #[test]
+    fn test_safe_join_valid() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        std::fs::write(temp_dir.path().join("file.txt"), "hi").unwrap();
+        let root = temp_dir.path().to_str().unwrap();
+
+        let joined = safe_join(root, "file.txt").unwrap();
+        assert!(joined.ends_with("file.txt"));
+    }
+
+    #[test]
+    fn test_safe_join_rejects_escapes() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let root = temp_dir.path().to_str().unwrap();
+
+        assert!(safe_join(root, "../etc/passwd").is_err());
+        assert!(safe_join(root, "/etc/passwd").is_err());
+        assert!(safe_join(root, "").is_err());
+        assert!(safe_join(root, "missing.txt").is_err());
+    }
+
+    #[test]
+    fn test_validate_and_canonicalize_path_structured_errors() {
+        let kind = |path: &str| {
+            validate_and_canonicalize_path(path)
+                .unwrap_err()
+                .path_validation_kind()
+        };
+
+        assert_eq!(
+            kind("/nonexistent/path/that/does/not/exist"),
+            Some(PathValidationError::NotFound)
+        );
+        assert_eq!(kind("/tmp\0evil"), Some(PathValidationError::NullByte));
+        assert_eq!(kind(""), Some(PathValidationError::InvalidCharacters));
+    }
+
+    #[test]
+    fn test_safe_join_structured_errors() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let root = temp_dir.path().to_str().unwrap();
+        let kind = |relative: &str| {
+            safe_join(root, relative)
+                .unwrap_err()
+                .path_validation_kind()
+        };
+
+        assert_eq!(
+            kind("../etc/passwd"),
+            Some(PathValidationError::ParentTraversal)
+        );
+        assert_eq!(kind("/etc/passwd"), Some(PathValidationError::AbsolutePath));
+        assert_eq!(kind("a\0b"), Some(PathValidationError::NullByte));
+        assert_eq!(kind(""), Some(PathValidationError::InvalidCharacters));
+        assert_eq!(kind("missing.txt"), Some(PathValidationError::NotFound));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_safe_join_symlink_escape_is_directory_escape() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let outside = tempfile::tempdir().unwrap();
+        std::fs::write(outside.path().join("secret.txt"), "x").unwrap();
+        std::os::unix::fs::symlink(outside.path(), temp_dir.path().join("link")).unwrap();
+
+        let err = safe_join(temp_dir.path().to_str().unwrap(), "link/secret.txt").unwrap_err();
+        assert_eq!(
+            err.path_validation_kind(),
+            Some(PathValidationError::DirectoryEscape)
+        );
+    }
+
+    #[test]
+    fn test_path_validation_error_serializes_kind() {
+        let err = Error::path_validation("link", PathValidationError::SymbolicLink);
+        let value = serde_json::to_value(&err).unwrap();
+
+        assert_eq!(value["pathError"], "symbolic_link");
+        assert!(value["message"]
+            .as_str()
+            .unwrap()
+            .contains("Symbolic links"));
+    }
 }
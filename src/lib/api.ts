@@ -69,6 +69,12 @@ export interface SessionMetadata {
   tasksJson: string | null
 }
 
+export interface SessionPage {
+  sessions: SessionMetadata[]
+  /** Number of sessions across all pages */
+  total: number
+}
+
 export interface GitInfo {
   isGitRepo: boolean
   branch: string | null
@@ -169,6 +175,11 @@ export interface AccountInfo {
   requiresOpenaiAuth: boolean
 }
 
+export interface CachedAccountInfo extends AccountInfo {
+  fetchedAt: number
+  isStale: boolean
+}
+
 export interface RateLimitWindow {
   usedPercent: number
   windowDurationMins?: number | null
@@ -211,6 +222,8 @@ export interface Model {
   supportedReasoningEfforts: ReasoningEffortOption[]
   defaultReasoningEffort: string
   isDefault: boolean
+  /** Maximum context length in tokens, when the server reports it */
+  contextWindow?: number
 }
 
 export interface ModelListResponse {
@@ -345,8 +358,14 @@ export const projectApi = {
 // ==================== Session API ====================
 
 export const sessionApi = {
-  list: (projectId: string) =>
-    invoke<SessionMetadata[]>('list_sessions', { projectId }),
+  list: async (projectId: string) =>
+    (await invoke<SessionPage>('list_sessions', { projectId })).sessions,
+
+  listPage: (projectId: string, limit?: number, offset?: number) =>
+    invoke<SessionPage>('list_sessions', { projectId, limit, offset }),
+
+  listArchived: (projectId: string) =>
+    invoke<SessionMetadata[]>('list_archived_sessions', { projectId }),
 
   get: (sessionId: string) =>
     invoke<SessionMetadata | null>('get_session', { sessionId }),
@@ -389,6 +408,12 @@ export const sessionApi = {
   delete: (sessionId: string) =>
     invoke<void>('delete_session', { sessionId }),
 
+  bulkArchive: (sessionIds: string[], archived: boolean) =>
+    invoke<number>('bulk_archive_sessions', { sessionIds, archived }),
+
+  bulkDelete: (sessionIds: string[], permanent?: boolean) =>
+    invoke<number>('bulk_delete_sessions', { sessionIds, permanent }),
+
   /**
    * Search sessions across all projects with relevance scoring
    * Results are sorted by relevance score (descending):
@@ -522,6 +547,9 @@ export const serverApi = {
 
   getAccountInfo: () => invoke<AccountInfo>('get_account_info'),
 
+  getAccountInfoCached: (forceRefresh?: boolean) =>
+    invoke<CachedAccountInfo>('get_account_info_cached', { forceRefresh }),
+
   getAccountRateLimits: () => invoke<AccountRateLimitsResponse>('get_account_rate_limits'),
 
   startLogin: (loginType: 'chatgpt' | 'apiKey' = 'chatgpt', apiKey?: string) =>